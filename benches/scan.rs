@@ -0,0 +1,36 @@
+use std::io::Cursor;
+use std::path::PathBuf;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use pt::base::{lock_image, ImageInfo, TarImage};
+use pt::builder::TarBuilder;
+use pt::perf;
+
+/// 生成含 n 个小文件的测试归档
+fn fixture(n: usize) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("pt_bench_{}.tar", n));
+    if !path.exists() {
+        let mut builder = TarBuilder::new(Cursor::new(Vec::new()));
+        for i in 0..n {
+            builder.append_data(&format!("dir{}/file{}.txt", i % 64, i), 0o644, 0, &[b'x'; 700]).unwrap();
+        }
+        std::fs::write(&path, builder.finish().unwrap().into_inner()).unwrap();
+    }
+    path
+}
+
+fn bench_scan(c: &mut Criterion) {
+    let path = fixture(10_000);
+    let img = TarImage::open(path.to_str().unwrap()).unwrap();
+    let mut img = lock_image(&img).unwrap();
+
+    let before = perf::snapshot();
+    img.scan().unwrap();
+    eprintln!("one scan: {:?}", perf::snapshot().since(&before));
+
+    c.bench_function("scan 10k entries", |b| b.iter(|| img.scan().unwrap()));
+    c.bench_function("build_index 10k entries", |b| b.iter(|| img.build_index().unwrap()));
+}
+
+criterion_group!(benches, bench_scan);
+criterion_main!(benches);
@@ -0,0 +1,49 @@
+/* pt C 接口，对应 src/ffi.rs；以 `--features ffi --crate-type cdylib` 构建 */
+#ifndef PT_H
+#define PT_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* 打开的镜像 */
+typedef struct PtImage PtImage;
+
+/* 由 pt_next_entry 返回的条目 */
+typedef struct PtEntry PtEntry;
+
+/* 当前线程最近一次错误的描述，没有错误时返回 NULL */
+const char *pt_last_error(void);
+
+/* 打开 tar 镜像，失败时返回 NULL */
+PtImage *pt_open(const char *path);
+
+/* 按归档顺序返回下一个条目，到达末尾或出错时返回 NULL */
+PtEntry *pt_next_entry(PtImage *image);
+
+/* 条目路径，pt_entry_free 后失效 */
+const char *pt_entry_path(const PtEntry *entry);
+
+/* 数据区大小 */
+uint64_t pt_entry_size(const PtEntry *entry);
+
+/* header 中的 typeflag，如 '0'、'5' */
+char pt_entry_type(const PtEntry *entry);
+
+/* 顺序读取数据区，返回读取的字节数，0 表示读完，-1 表示出错 */
+intptr_t pt_entry_read(PtEntry *entry, uint8_t *buf, size_t len);
+
+/* 释放条目 */
+void pt_entry_free(PtEntry *entry);
+
+/* 关闭镜像 */
+void pt_close(PtImage *image);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* PT_H */
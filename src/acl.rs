@@ -0,0 +1,158 @@
+use std::fmt;
+use std::io;
+use crate::base::TarFile;
+
+/// 访问 ACL 在 PAX 记录中的键
+pub const ACL_ACCESS_KEY: &str = "SCHILY.acl.access";
+/// 目录默认 ACL 在 PAX 记录中的键
+pub const ACL_DEFAULT_KEY: &str = "SCHILY.acl.default";
+
+/// ACL 条目的作用对象
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AclTag {
+    UserObj,
+    /// 命名用户：名称以及（如果记录中带有）数字 id
+    User { name: String, id: Option<u32> },
+    GroupObj,
+    Group { name: String, id: Option<u32> },
+    Mask,
+    Other,
+}
+
+/// 单条 ACL 记录，perms 为 rwx 位（r=4, w=2, x=1）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AclEntry {
+    pub tag: AclTag,
+    pub perms: u8,
+}
+
+/// POSIX.1e ACL
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Acl {
+    pub entries: Vec<AclEntry>,
+}
+
+fn invalid(entry: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("invalid acl entry: {:?}", entry))
+}
+
+fn parse_perms(s: &str, entry: &str) -> io::Result<u8> {
+    if s.len() != 3 {
+        return Err(invalid(entry));
+    }
+    let mut perms = 0;
+    for (c, (want, bit)) in s.chars().zip([('r', 4), ('w', 2), ('x', 1)]) {
+        match c {
+            '-' => {}
+            c if c == want => perms |= bit,
+            _ => return Err(invalid(entry)),
+        }
+    }
+    Ok(perms)
+}
+
+/// 解析 star 格式的 ACL 文本："user::rwx,user:alice:r-x:1000,group::r--,mask::r-x,other::---"
+///
+/// 条目之间可用 ',' 或换行分隔，第四个字段（可选）为 star 记录的数字 id
+pub fn parse_acl(text: &str) -> io::Result<Acl> {
+    let mut acl = Acl::default();
+    for entry in text.split([',', '\n']).map(str::trim).filter(|e| !e.is_empty()) {
+        let fields: Vec<&str> = entry.split(':').collect();
+        if fields.len() < 3 || fields.len() > 4 {
+            return Err(invalid(entry));
+        }
+        let (kind, qualifier, perms) = (fields[0], fields[1], fields[2]);
+        let id = match fields.get(3) {
+            Some(id) => Some(id.parse().map_err(|_| invalid(entry))?),
+            None => qualifier.parse().ok(),
+        };
+        let tag = match (kind, qualifier.is_empty()) {
+            ("user" | "u", true) => AclTag::UserObj,
+            ("user" | "u", false) => AclTag::User { name: qualifier.to_string(), id },
+            ("group" | "g", true) => AclTag::GroupObj,
+            ("group" | "g", false) => AclTag::Group { name: qualifier.to_string(), id },
+            ("mask" | "m", _) => AclTag::Mask,
+            ("other" | "o", _) => AclTag::Other,
+            _ => return Err(invalid(entry)),
+        };
+        acl.entries.push(AclEntry { tag, perms: parse_perms(perms, entry)? });
+    }
+    Ok(acl)
+}
+
+impl fmt::Display for Acl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, e) in self.entries.iter().enumerate() {
+            if i > 0 {
+                f.write_str(",")?;
+            }
+            let perms: String = [(4, 'r'), (2, 'w'), (1, 'x')]
+                .iter()
+                .map(|&(bit, c)| if e.perms & bit != 0 { c } else { '-' })
+                .collect();
+            match &e.tag {
+                AclTag::UserObj => write!(f, "user::{}", perms)?,
+                AclTag::User { name, .. } => write!(f, "user:{}:{}", name, perms)?,
+                AclTag::GroupObj => write!(f, "group::{}", perms)?,
+                AclTag::Group { name, .. } => write!(f, "group:{}:{}", name, perms)?,
+                AclTag::Mask => write!(f, "mask::{}", perms)?,
+                AclTag::Other => write!(f, "other::{}", perms)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Acl {
+    /// 编码为 Linux `system.posix_acl_*` xattr 的二进制格式；lookup 用于解析没有数字 id 的命名用户/组
+    pub fn to_linux_xattr(
+        &self,
+        lookup_uid: impl Fn(&str) -> Option<u32>,
+        lookup_gid: impl Fn(&str) -> Option<u32>,
+    ) -> io::Result<Vec<u8>> {
+        const UNDEFINED_ID: u32 = u32::MAX;
+        let unresolved = |name: &str| {
+            io::Error::new(io::ErrorKind::NotFound, format!("cannot resolve acl qualifier {:?}", name))
+        };
+        let mut raw = Vec::with_capacity(self.entries.len());
+        for e in &self.entries {
+            let (tag, id): (u16, u32) = match &e.tag {
+                AclTag::UserObj => (0x01, UNDEFINED_ID),
+                AclTag::User { name, id } => (0x02, id.or_else(|| lookup_uid(name)).ok_or_else(|| unresolved(name))?),
+                AclTag::GroupObj => (0x04, UNDEFINED_ID),
+                AclTag::Group { name, id } => (0x08, id.or_else(|| lookup_gid(name)).ok_or_else(|| unresolved(name))?),
+                AclTag::Mask => (0x10, UNDEFINED_ID),
+                AclTag::Other => (0x20, UNDEFINED_ID),
+            };
+            raw.push((tag, e.perms as u16, id));
+        }
+        // 内核要求条目按 tag、id 排序
+        raw.sort();
+        let mut out = 2u32.to_le_bytes().to_vec();
+        for (tag, perm, id) in raw {
+            out.extend_from_slice(&tag.to_le_bytes());
+            out.extend_from_slice(&perm.to_le_bytes());
+            out.extend_from_slice(&id.to_le_bytes());
+        }
+        Ok(out)
+    }
+}
+
+impl TarFile {
+    fn acl_record(&self, key: &str) -> io::Result<Option<Acl>> {
+        match self.pax_records().get(key) {
+            Some(v) => Ok(Some(parse_acl(&String::from_utf8_lossy(v))?)),
+            None => Ok(None),
+        }
+    }
+
+    /// PAX `SCHILY.acl.access` 记录
+    pub fn acl_access(&self) -> io::Result<Option<Acl>> {
+        self.acl_record(ACL_ACCESS_KEY)
+    }
+
+    /// PAX `SCHILY.acl.default` 记录（仅目录）
+    pub fn acl_default(&self) -> io::Result<Option<Acl>> {
+        self.acl_record(ACL_DEFAULT_KEY)
+    }
+}
@@ -0,0 +1,64 @@
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{ready, Context, Poll};
+use tokio::io::{AsyncRead, ReadBuf};
+use tokio::task::JoinHandle;
+use crate::base::TarFile;
+
+/// 每次在阻塞线程池中读取的最大字节数
+const CHUNK: usize = 64 * 1024;
+
+/// 条目数据区的异步 reader；读取放到 `spawn_blocking` 线程中执行，需要在 tokio 运行时内使用
+pub struct AsyncBody {
+    file: Arc<TarFile>,
+    pos: u64,
+    /// 已读出但尚未交给调用方的数据
+    buf: Vec<u8>,
+    buf_pos: usize,
+    pending: Option<JoinHandle<io::Result<Vec<u8>>>>,
+}
+
+impl AsyncBody {
+    pub fn new(file: TarFile) -> Self {
+        AsyncBody { file: Arc::new(file), pos: 0, buf: Vec::new(), buf_pos: 0, pending: None }
+    }
+}
+
+impl AsyncRead for AsyncBody {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, out: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        if this.buf_pos == this.buf.len() {
+            if this.pending.is_none() {
+                if this.pos >= this.file.get_size() || out.remaining() == 0 {
+                    return Poll::Ready(Ok(()));
+                }
+                let (file, pos, len) = (this.file.clone(), this.pos, out.remaining().min(CHUNK));
+                this.pending = Some(tokio::task::spawn_blocking(move || {
+                    let mut data = vec![0u8; len];
+                    let n = file.read_body_at(pos, &mut data)?;
+                    data.truncate(n);
+                    Ok(data)
+                }));
+            }
+            let handle = this.pending.as_mut().expect("pending read");
+            let result = ready!(Pin::new(handle).poll(cx));
+            this.pending = None;
+            this.buf = result.map_err(io::Error::other)??;
+            this.buf_pos = 0;
+            this.pos += this.buf.len() as u64;
+        }
+        let n = out.remaining().min(this.buf.len() - this.buf_pos);
+        out.put_slice(&this.buf[this.buf_pos..this.buf_pos + n]);
+        this.buf_pos += n;
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl TarFile {
+    /// 转成异步 reader，可直接用于 `tokio::io::copy` 或 HTTP 响应体
+    pub fn into_async_read(self) -> AsyncBody {
+        AsyncBody::new(self)
+    }
+}
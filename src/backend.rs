@@ -0,0 +1,275 @@
+//! `TarImage` 读取数据的来源
+//!
+//! 解析只依赖按偏移读取与总长度，实现 `Backend` 即可在内存、mmap、HTTP 或对象存储上
+//! 复用同一套解析；`TarImage::open` 使用 `FileBackend`。
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use crate::base::TarImage;
+
+/// 可按偏移随机读取的只读数据源，会被多个线程同时读取
+pub trait Backend: Send + Sync {
+    /// 在 offset 处读取到 buf，返回读取的字节数，0 表示已到末尾；可以少于 buf 长度
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize>;
+
+    /// 数据总长
+    fn len(&self) -> io::Result<u64>;
+
+    fn is_empty(&self) -> io::Result<bool> {
+        Ok(self.len()? == 0)
+    }
+
+    /// 打开一份独立的句柄供其它线程使用；返回 None 表示直接共享当前实例即可
+    fn reopen(&self) -> io::Result<Option<Arc<dyn Backend>>> {
+        Ok(None)
+    }
+
+    /// 对应的本地文件路径，`TarImage::edit_header` 与 `repair_checksum` 写回时使用
+    fn local_path(&self) -> Option<&Path> {
+        None
+    }
+}
+
+/// 本地文件
+pub struct FileBackend {
+    file: File,
+    path: Option<PathBuf>,
+}
+
+impl FileBackend {
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref();
+        Ok(FileBackend { file: File::open(path)?, path: Some(path.to_path_buf()) })
+    }
+
+    /// 使用已打开的文件；没有路径，因此不能 reopen 或 edit_header
+    pub fn new(file: File) -> Self {
+        FileBackend { file, path: None }
+    }
+}
+
+/// 在 offset 处读取，不改变文件句柄的读写位置，多个读取方可共用同一个句柄
+#[cfg(unix)]
+pub(crate) fn read_file_at(file: &File, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+    std::os::unix::fs::FileExt::read_at(file, buf, offset)
+}
+
+/// Windows 上 seek_read 会移动句柄位置，但所有读取都显式给出偏移，不受影响
+#[cfg(windows)]
+pub(crate) fn read_file_at(file: &File, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+    std::os::windows::fs::FileExt::seek_read(file, buf, offset)
+}
+
+/// 没有定位读取的平台上共用句柄的位置，seek 与 read 之间加锁
+#[cfg(not(any(unix, windows)))]
+pub(crate) fn read_file_at(file: &File, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+    use std::io::{Read, Seek, SeekFrom};
+    static POSITION: Mutex<()> = Mutex::new(());
+    let _guard = POSITION.lock().map_err(|_| io::Error::other("file position lock poisoned"))?;
+    let mut file = file;
+    file.seek(SeekFrom::Start(offset))?;
+    file.read(buf)
+}
+
+impl Backend for FileBackend {
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        read_file_at(&self.file, buf, offset)
+    }
+
+    fn len(&self) -> io::Result<u64> {
+        Ok(self.file.metadata()?.len())
+    }
+
+    fn reopen(&self) -> io::Result<Option<Arc<dyn Backend>>> {
+        match &self.path {
+            Some(path) => Ok(Some(Arc::new(FileBackend::open(path)?))),
+            None => Ok(None),
+        }
+    }
+
+    fn local_path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+}
+
+/// 内存中的归档
+pub struct MemoryBackend {
+    data: Arc<[u8]>,
+}
+
+impl MemoryBackend {
+    pub fn new<D: Into<Arc<[u8]>>>(data: D) -> Self {
+        MemoryBackend { data: data.into() }
+    }
+}
+
+impl Backend for MemoryBackend {
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        let start = usize::try_from(offset).unwrap_or(usize::MAX).min(self.data.len());
+        let n = buf.len().min(self.data.len() - start);
+        buf[..n].copy_from_slice(&self.data[start..start + n]);
+        Ok(n)
+    }
+
+    fn len(&self) -> io::Result<u64> {
+        Ok(self.data.len() as u64)
+    }
+}
+
+/// 按块缓存的 backend：读取以 block_size 为单位向 inner 取数，最近最少使用的块先被淘汰，
+/// 用于 HTTP、对象存储等每次请求代价高的数据源。一次读取中连续未命中的块合并成一次请求
+pub struct BlockCache<B: Backend> {
+    inner: B,
+    block_size: u64,
+    max_blocks: usize,
+    state: Mutex<CacheState>,
+}
+
+#[derive(Default)]
+struct CacheState {
+    /// 块号 -> (数据, 最近使用的序号)
+    blocks: HashMap<u64, (Arc<[u8]>, u64)>,
+    /// 最近使用的序号 -> 块号，最小的最先淘汰
+    recency: BTreeMap<u64, u64>,
+    tick: u64,
+    hits: u64,
+    misses: u64,
+}
+
+impl CacheState {
+    fn touch(&mut self, index: u64) -> Option<Arc<[u8]>> {
+        let tick = self.tick;
+        let (data, used) = self.blocks.get_mut(&index)?;
+        self.recency.remove(used);
+        *used = tick;
+        self.recency.insert(tick, index);
+        self.tick += 1;
+        Some(data.clone())
+    }
+
+    fn insert(&mut self, index: u64, data: Arc<[u8]>, max_blocks: usize) {
+        while self.blocks.len() >= max_blocks {
+            let Some((_, oldest)) = self.recency.pop_first() else { break };
+            self.blocks.remove(&oldest);
+        }
+        self.recency.insert(self.tick, index);
+        self.blocks.insert(index, (data, self.tick));
+        self.tick += 1;
+    }
+}
+
+/// 循环读取直到填满 buf 或到达末尾
+fn read_full<B: Backend + ?Sized>(backend: &B, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+    let mut done = 0;
+    while done < buf.len() {
+        match backend.read_at(&mut buf[done..], offset + done as u64) {
+            Ok(0) => break,
+            Ok(n) => done += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(done)
+}
+
+impl<B: Backend> BlockCache<B> {
+    /// block_size 为每块字节数，capacity 为缓存总字节数上限（至少保留一块）
+    pub fn new(inner: B, block_size: usize, capacity: usize) -> Self {
+        let block_size = block_size.max(512);
+        BlockCache {
+            inner,
+            block_size: block_size as u64,
+            max_blocks: (capacity / block_size).max(1),
+            state: Mutex::new(CacheState::default()),
+        }
+    }
+
+    /// (命中块数, 未命中块数)
+    pub fn stats(&self) -> (u64, u64) {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        (state.hits, state.misses)
+    }
+
+    /// 取 [first, last] 范围内的块，未命中的连续块一次取回
+    fn blocks(&self, first: u64, last: u64, len: u64) -> io::Result<Vec<Arc<[u8]>>> {
+        let mut out = Vec::with_capacity((last - first + 1) as usize);
+        let mut index = first;
+        while index <= last {
+            let mut end = index;
+            {
+                let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+                if let Some(data) = state.touch(index) {
+                    state.hits += 1;
+                    out.push(data);
+                    index += 1;
+                    continue;
+                }
+                while end < last && !state.blocks.contains_key(&(end + 1)) {
+                    end += 1;
+                }
+            }
+            let start = index * self.block_size;
+            let stop = ((end + 1) * self.block_size).min(len);
+            let mut buf = vec![0u8; stop.saturating_sub(start) as usize];
+            let n = read_full(&self.inner, &mut buf, start)?;
+            buf.truncate(n);
+            let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+            for (i, chunk) in buf.chunks(self.block_size as usize).enumerate() {
+                let data: Arc<[u8]> = chunk.into();
+                state.misses += 1;
+                state.insert(index + i as u64, data.clone(), self.max_blocks);
+                out.push(data);
+            }
+            if n == 0 {
+                break;
+            }
+            index = end + 1;
+        }
+        Ok(out)
+    }
+}
+
+impl<B: Backend> Backend for BlockCache<B> {
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        let len = self.inner.len()?;
+        if offset >= len || buf.is_empty() {
+            return Ok(0);
+        }
+        let end = (offset + buf.len() as u64).min(len);
+        let first = offset / self.block_size;
+        let blocks = self.blocks(first, (end - 1) / self.block_size, len)?;
+        let mut done = 0;
+        for (i, block) in blocks.iter().enumerate() {
+            let block_start = (first + i as u64) * self.block_size;
+            let from = (offset + done as u64 - block_start) as usize;
+            if from >= block.len() {
+                break;
+            }
+            let n = (block.len() - from).min(buf.len() - done);
+            buf[done..done + n].copy_from_slice(&block[from..from + n]);
+            done += n;
+            if done == buf.len() {
+                break;
+            }
+        }
+        Ok(done)
+    }
+
+    fn len(&self) -> io::Result<u64> {
+        self.inner.len()
+    }
+
+    fn local_path(&self) -> Option<&Path> {
+        self.inner.local_path()
+    }
+}
+
+impl TarImage {
+    /// 在任意 backend 上打开镜像，name 用于错误信息与 `get_path`
+    pub fn from_backend<B: Backend + 'static>(backend: B, name: &str) -> io::Result<Arc<Mutex<TarImage>>> {
+        Ok(Arc::new(Mutex::new(TarImage::with_backend(Arc::new(backend), name)?)))
+    }
+}
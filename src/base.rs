@@ -1,6 +1,14 @@
-use std::{fs::File, io::{self, Read, Seek, SeekFrom}, sync::{Arc, Mutex}};
-use crate::tar::{TarHeader, read_tar_header, TarFileType};
+use std::{collections::BTreeMap, ffi::OsString, io::{self, Read, Seek, SeekFrom, Write}, sync::{Arc, Mutex, MutexGuard}, time::Duration};
+use crate::backend::{Backend, FileBackend};
+use crate::cancel::CancelToken;
+use crate::error::PtError;
+use crate::limits::{self, Limits};
+use crate::path::{normalize_path, os_from_bytes, strip_absolute};
+use crate::perf;
+use crate::pax::{merge_globals, parse_pax_records, pax_u64, PaxRecords};
+use crate::tar::{block_align, field_bytes, parse_gnu_sparse_block, write_checksum, SparseChunk, TarHeader, read_tar_header, TarFileType, TypeFlag};
 use std::any::Any;
+use std::ops::ControlFlow;
 
 /// 文件信息行为抽象，继承 Read + Seek
 pub trait FileInfo: Read + Seek + Any {
@@ -17,31 +25,164 @@ pub trait ImageInfo: Sized + Read + Seek {
     fn read_img_at(&mut self, offset: u64, size: u64) -> io::Result<(Vec<u8>, u64)>;
     fn get_file_at(&mut self, offset: u64) -> io::Result<(Box<dyn FileInfo>,u64)>;
     /// 遍历所有条目，并在每个条目上调用回调
+    ///
+    /// 回调严格按条目在归档中出现的顺序调用（偏移递增），同名条目会各出现一次；
+    /// 需要按路径排序时使用 `TarImage::for_each_entry_ordered`
     fn for_each_entry<F>(&mut self, callback: F) -> io::Result<()>
     where
         F: FnMut(Box<dyn FileInfo>) -> io::Result<()>;
 }
 
-/// Tar 镜像实现，只保存路径
+/// Tar 镜像实现，数据通过 backend 按偏移读取
 #[derive(Clone)]
 pub struct TarImage {
-    file: Arc<File>,
+    backend: Arc<dyn Backend>,
     path: String,
+    /// 镜像在底层文件中的起始偏移，嵌套归档时不为 0
+    base: u64,
     size: u64,
-    last_link_name : String,
+    keep_absolute_paths: bool,
+    /// Read / Seek 使用的读取位置，相对于 base
+    pos: u64,
+    readahead: Readahead,
+    retry: RetryPolicy,
+    cancel: Option<CancelToken>,
+    limits: Limits,
+    strict: bool,
+    /// 已读到的 PAX 全局 header：'g' header 的偏移 -> 其后生效的全部全局记录
+    globals: Arc<BTreeMap<u64, PaxRecords>>,
+    /// 是否已遍历整个归档，globals 中包含了所有 'g' header
+    globals_complete: bool,
+    /// [0, globals_scanned) 内的 'g' header 都已记录在 globals 中
+    globals_scanned: u64,
+}
+
+/// PAX 全局记录中描述单个条目的键，不作为后续条目的默认值
+const ENTRY_ONLY_KEYS: &[&str] = &["path", "linkpath", "size"];
+
+/// 默认预读块大小
+pub const DEFAULT_READAHEAD: usize = 1024 * 1024;
+
+/// 顺序扫描 header 时的预读缓冲；克隆得到的是同样大小的空缓冲，避免每个条目复制一份数据
+struct Readahead {
+    capacity: usize,
+    start: u64,
+    data: Vec<u8>,
+}
+
+impl Readahead {
+    fn new(capacity: usize) -> Self {
+        Readahead { capacity, start: 0, data: Vec::new() }
+    }
+
+    fn get(&self, offset: u64, size: u64) -> Option<&[u8]> {
+        let begin = offset.checked_sub(self.start)? as usize;
+        self.data.get(begin..begin.checked_add(size as usize)?)
+    }
+}
+
+impl Clone for Readahead {
+    fn clone(&self) -> Self {
+        Readahead::new(self.capacity)
+    }
+}
+
+/// 底层读取遇到暂时性错误（网络文件系统超时、Windows 上被杀毒软件锁定等）时的重试策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// 最多重试次数，0 表示不重试
+    pub max_retries: u32,
+    /// 第一次重试前的等待时间，之后每次翻倍
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy { max_retries: 0, initial_backoff: Duration::from_millis(100), max_backoff: Duration::from_secs(10) }
+    }
+}
+
+impl RetryPolicy {
+    /// 第 attempt 次重试（从 0 开始）前的等待时间
+    fn backoff(&self, attempt: u32) -> Duration {
+        self.initial_backoff.saturating_mul(1u32 << attempt.min(16)).min(self.max_backoff)
+    }
+}
+
+/// 读取条目数据区用的只读视图：共享文件句柄，按偏移读取，不需要锁住镜像
+#[derive(Clone)]
+struct ImageView {
+    backend: Arc<dyn Backend>,
+    base: u64,
+    retry: RetryPolicy,
+}
+
+impl ImageView {
+    /// 读满 buf，数据不足时返回 UnexpectedEof
+    fn read_exact_at(&self, offset: u64, buf: &mut [u8]) -> io::Result<()> {
+        let n = read_full_at(&*self.backend, buf, self.base + offset, &self.retry)?;
+        perf::add_bytes_read(n as u64);
+        if n != buf.len() {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "not enough data"));
+        }
+        Ok(())
+    }
+}
+
+/// 可重试的错误：超时、资源忙以及 Windows 的共享 / 锁冲突
+fn is_transient(e: &io::Error) -> bool {
+    if matches!(e.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut) {
+        return true;
+    }
+    #[cfg(windows)]
+    const CODES: &[i32] = &[32, 33]; // ERROR_SHARING_VIOLATION, ERROR_LOCK_VIOLATION
+    #[cfg(unix)]
+    const CODES: &[i32] = &[libc::EBUSY, libc::ETIMEDOUT];
+    #[cfg(not(any(unix, windows)))]
+    const CODES: &[i32] = &[];
+    e.raw_os_error().is_some_and(|code| CODES.contains(&code))
+}
+
+/// 循环读取直到填满 buf 或到达文件末尾，返回读取的字节数；暂时性错误按 retry 重试
+fn read_full_at(backend: &dyn Backend, buf: &mut [u8], offset: u64, retry: &RetryPolicy) -> io::Result<usize> {
+    let mut done = 0;
+    let mut attempt = 0;
+    while done < buf.len() {
+        perf::add_syscalls(1);
+        match backend.read_at(&mut buf[done..], offset + done as u64) {
+            Ok(0) => break,
+            Ok(n) => done += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) if attempt < retry.max_retries && is_transient(&e) => {
+                std::thread::sleep(retry.backoff(attempt));
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(done)
 }
 
 impl Read for TarImage {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        let mut file = self.file.as_ref().try_clone()?;
-        file.read(buf)
+        let len = (buf.len() as u64).min(self.size.saturating_sub(self.pos)) as usize;
+        perf::add_syscalls(1);
+        let n = self.backend.read_at(&mut buf[..len], self.base + self.pos)?;
+        self.pos += n as u64;
+        Ok(n)
     }
 }
 
 impl Seek for TarImage {
     fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
-        let mut file = self.file.as_ref().try_clone()?;
-        file.seek(pos)
+        let new_pos = match pos {
+            SeekFrom::Start(n) => Some(n),
+            SeekFrom::End(n) => self.size.checked_add_signed(n),
+            SeekFrom::Current(n) => self.pos.checked_add_signed(n),
+        };
+        self.pos = new_pos.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "invalid seek to a negative position"))?;
+        Ok(self.pos)
     }
 }
 
@@ -49,18 +190,370 @@ impl TarImage {
     pub fn get_path(&self) -> String {
         self.path.clone()
     }
+
+    /// 是否保留条目路径中的绝对前缀（'/'、盘符），相当于 GNU tar 的 `-P`；默认去掉
+    pub fn set_keep_absolute_paths(&mut self, keep: bool) {
+        self.keep_absolute_paths = keep;
+    }
+
+    pub fn keep_absolute_paths(&self) -> bool {
+        self.keep_absolute_paths
+    }
+
+    /// 严格模式：校验每个 header 的 magic、version 与数字字段（见 `TarHeader::validate_strict`），
+    /// 不符合时返回 `PtError::MalformedHeader`。默认关闭，以便读取 v7 等没有 magic 的旧格式
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+
+    pub fn strict(&self) -> bool {
+        self.strict
+    }
+
+    /// 设置底层读取的重试策略，默认不重试
+    pub fn set_retry_policy(&mut self, policy: RetryPolicy) {
+        self.retry = policy;
+    }
+
+    pub fn retry_policy(&self) -> RetryPolicy {
+        self.retry
+    }
+
+    /// 设置取消标记，遍历、建立索引与解包在每个条目之间检查，取消后返回 `PtError::Cancelled`
+    pub fn set_cancel_token(&mut self, token: CancelToken) {
+        self.cancel = Some(token);
+    }
+
+    pub fn cancel_token(&self) -> Option<&CancelToken> {
+        self.cancel.as_ref()
+    }
+
+    /// 设置资源上限，遍历时检查，超出时返回 `PtError::LimitExceeded`；默认不限制
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
+    }
+
+    pub fn limits(&self) -> Limits {
+        self.limits
+    }
+
+    /// 归档末尾生效的 PAX 全局记录（如 `comment`），按 POSIX 合并所有 'g' header：后出现的同名记录
+    /// 覆盖之前的，值为空的记录删除该键。第一次调用时遍历全部 header（不读取数据）
+    pub fn pax_globals(&mut self) -> io::Result<PaxRecords> {
+        if !self.globals_complete {
+            self.for_each_entry(|_| Ok(()))?;
+        }
+        Ok(self.globals.values().next_back().cloned().unwrap_or_default())
+    }
+
+    /// offset 之前最近的 'g' header 所确定的全局记录
+    fn globals_before(&self, offset: u64) -> Option<&PaxRecords> {
+        self.globals.range(..offset).next_back().map(|(_, records)| records)
+    }
+
+    /// 记录 offset 处 'g' header 的内容，与之前生效的全局记录合并
+    fn add_globals(&mut self, offset: u64, records: PaxRecords) {
+        let mut merged = self.globals_before(offset).cloned().unwrap_or_default();
+        merge_globals(&mut merged, records);
+        Arc::make_mut(&mut self.globals).insert(offset, merged);
+    }
+
+    /// 随机访问 offset 处的条目前，顺序读取其之前尚未遍历的 header（不读数据），
+    /// 使条目得到的全局记录与 `for_each_entry` 一致；之前的 header 损坏时停在该处
+    fn resolve_globals(&mut self, offset: u64) {
+        let mut off = self.globals_scanned;
+        while !self.globals_complete && off < offset {
+            match read_file_header(self, off).and_then(|entry| entry.map(|(file, n)| Ok((try_into_tarfile(file)?, n))).transpose()) {
+                Ok(Some((file, n))) => off += n + block_align(file.get_size()),
+                Ok(None) => self.globals_complete = true,
+                Err(_) => break,
+            }
+        }
+        self.globals_scanned = self.globals_scanned.max(off);
+    }
+
+    /// 已遍历完整个归档时返回全部 'g' header 的记录，供索引保存
+    pub(crate) fn complete_globals(&self) -> Option<Arc<BTreeMap<u64, PaxRecords>>> {
+        self.globals_complete.then(|| self.globals.clone())
+    }
+
+    /// 使用索引保存的 'g' header 记录，之后随机访问无需再顺序读取之前的 header
+    pub(crate) fn adopt_globals(&mut self, globals: &Arc<BTreeMap<u64, PaxRecords>>) {
+        if !self.globals_complete {
+            self.globals = globals.clone();
+            self.globals_complete = true;
+        }
+    }
+
+    /// offset 处的条目，到达归档末尾时返回 None；下一个条目位于 `get_end_offset()`，可不经回调逐个遍历
+    pub fn entry_at(&mut self, offset: u64) -> io::Result<Option<Box<TarFile>>> {
+        if offset >= self.size {
+            return Ok(None);
+        }
+        self.resolve_globals(offset);
+        read_file_header(self, offset)?.map(|(file, _)| try_into_tarfile(file)).transpose()
+    }
+
+    /// 就地修改 offset 处条目（`get_offset()`）的主 header，重新计算 checksum 后写回底层文件，返回修改后的 header
+    ///
+    /// 用于修改 mode / uid / gid / mtime 等不影响布局的字段，不允许修改 size 与 typeflag；
+    /// 条目 PAX 记录中的同名字段仍会覆盖 header 中的值
+    pub fn edit_header<F>(&mut self, offset: u64, edit: F) -> io::Result<TarHeader>
+    where
+        F: FnOnce(&mut TarHeader) -> io::Result<()>,
+    {
+        let file = self.entry_at(offset)?
+            .ok_or_else(|| io::Error::new(io::ErrorKind::UnexpectedEof, format!("no entry at offset {}", offset)))?;
+        let mut hdr = *file.get_header();
+        edit(&mut hdr)?;
+        if hdr.size != file.get_header().size || hdr.typeflag != file.get_header().typeflag {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "edit_header cannot change size or typeflag"));
+        }
+        let mut block = hdr.to_bytes();
+        // to_bytes 会给空 magic 补上 ustar，保持原 header 的格式
+        block[257..265].copy_from_slice(&[&hdr.magic[..], &hdr.version[..]].concat());
+        write_checksum(&mut block);
+        // 旧式 GNU 稀疏条目的主 header 在扩展块之前
+        self.write_block(file.get_data_offset() - file.sparse_ext_len() - 512, &block)?;
+        Ok(TarHeader::from_bytes(&block))
+    }
+
+    /// 重新计算 offset 处 header 块的 checksum 并就地写回，用于修复位翻转或外部工具改动后
+    /// checksum 不再匹配的 header；offset 为该 header 块自身的偏移（PAX、GNU 长名块同样适用）
+    ///
+    /// 返回是否做了修改，checksum 本来正确时不写入；全零块不是 header，返回 InvalidInput
+    pub fn repair_checksum(&mut self, offset: u64) -> io::Result<bool> {
+        if !offset.is_multiple_of(512) {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("offset {} is not block aligned", offset)));
+        }
+        let (data, _) = self.read_img_at(offset, 512)?;
+        let mut block = [0u8; 512];
+        block.copy_from_slice(&data);
+        let hdr = TarHeader::from_bytes(&block);
+        if hdr.is_zero_block() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("no header at offset {}", offset)));
+        }
+        if hdr.crc_ok() {
+            return Ok(false);
+        }
+        write_checksum(&mut block);
+        self.write_block(offset, &block)?;
+        Ok(true)
+    }
+
+    /// 把一个 512 字节块写回底层文件的 offset 处，并丢弃预读缓冲
+    fn write_block(&mut self, offset: u64, block: &[u8; 512]) -> io::Result<()> {
+        let path = self.backend.local_path().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::Unsupported, format!("{} is not backed by a local file", self.path))
+        })?;
+        let mut out = std::fs::OpenOptions::new().write(true).open(path)?;
+        out.seek(SeekFrom::Start(self.base + offset))?;
+        out.write_all(block)?;
+        out.flush()?;
+        self.readahead.data.clear();
+        Ok(())
+    }
+
+    /// 设置顺序扫描 header 时的预读块大小（字节），0 表示关闭预读
+    pub fn set_readahead(&mut self, bytes: usize) {
+        self.readahead = Readahead::new(bytes);
+    }
+
+    pub fn readahead(&self) -> usize {
+        self.readahead.capacity
+    }
+
+    /// 经过预读缓冲读取元数据块；不小于预读块的读取直接访问文件
+    fn read_meta_at(&mut self, offset: u64, size: u64) -> io::Result<(Vec<u8>, u64)> {
+        if size >= self.readahead.capacity as u64 {
+            return self.read_img_at(offset, size);
+        }
+        if let Some(hit) = self.readahead.get(offset, size) {
+            perf::add_cache_hit();
+            return Ok((hit.to_vec(), size));
+        }
+        let len = (self.readahead.capacity as u64).min(self.size.saturating_sub(offset)).max(size);
+        let mut data = std::mem::take(&mut self.readahead.data);
+        data.resize(len as usize, 0);
+        let n = read_full_at(&*self.backend, &mut data, self.base + offset, &self.retry)?;
+        perf::add_bytes_read(n as u64);
+        data.truncate(n);
+        self.readahead.start = offset;
+        self.readahead.data = data;
+        match self.readahead.get(offset, size) {
+            Some(buf) => Ok((buf.to_vec(), size)),
+            None => Err(io::Error::new(io::ErrorKind::UnexpectedEof, "not enough data")),
+        }
+    }
+
+    fn view(&self) -> ImageView {
+        ImageView { backend: self.backend.clone(), base: self.base, retry: self.retry }
+    }
+
+    /// 重新打开底层数据源得到独立的句柄，其余设置不变；供多线程读取使用，
+    /// backend 不支持时共享原来的句柄
+    pub fn reopen(&self) -> io::Result<TarImage> {
+        let backend = self.backend.reopen()?.unwrap_or_else(|| self.backend.clone());
+        Ok(TarImage { backend, ..self.clone() })
+    }
+
+    /// 底层本地文件的路径，非本地 backend 为 None
+    pub(crate) fn local_path(&self) -> Option<&std::path::Path> {
+        self.backend.local_path()
+    }
+
+    pub(crate) fn with_backend(backend: Arc<dyn Backend>, name: &str) -> io::Result<TarImage> {
+        let size = backend.len()?;
+        Ok(TarImage {
+            backend,
+            path: name.to_string(),
+            base: 0,
+            size,
+            keep_absolute_paths: false,
+            pos: 0,
+            readahead: Readahead::new(DEFAULT_READAHEAD),
+            retry: RetryPolicy::default(),
+            cancel: None,
+            limits: Limits::default(),
+            strict: false,
+            globals: Arc::default(),
+            globals_complete: false,
+            globals_scanned: 0,
+        })
+    }
+
+    /// 在另一个 backend 上打开镜像，沿用 self 的路径处理、重试、取消、资源上限与严格模式设置
+    pub(crate) fn sibling(&self, backend: Arc<dyn Backend>, name: &str) -> io::Result<TarImage> {
+        let size = backend.len()?;
+        Ok(TarImage {
+            backend,
+            path: name.to_string(),
+            base: 0,
+            size,
+            pos: 0,
+            readahead: Readahead::new(self.readahead.capacity),
+            globals: Arc::default(),
+            globals_complete: false,
+            globals_scanned: 0,
+            ..self.clone()
+        })
+    }
+
+    /// 把镜像中 [offset, offset + len) 的原始字节复制到 writer
+    pub fn copy_range_to<W: Write>(&mut self, offset: u64, len: u64, writer: &mut W) -> io::Result<u64> {
+        const CHUNK: u64 = 1024 * 1024;
+        let mut done = 0u64;
+        while done < len {
+            let (buf, n) = self.read_img_at(offset + done, CHUNK.min(len - done))?;
+            writer.write_all(&buf)?;
+            done += n;
+        }
+        Ok(done)
+    }
+
+    /// 遍历条目的公共实现；回调返回 Break 时结束遍历。读取 offset 处的条目失败时调用 on_error，
+    /// 其返回值为继续读取的偏移，None 表示结束遍历
+    pub(crate) fn walk_entries<F, E>(&mut self, mut callback: F, mut on_error: E) -> io::Result<()>
+    where
+        F: FnMut(Box<dyn FileInfo>) -> io::Result<ControlFlow<()>>,
+        E: FnMut(&mut TarImage, u64, io::Error) -> io::Result<Option<u64>>,
+    {
+        let mut off: u64 = 0;
+        let mut entries: u64 = 0;
+        let mut total: u64 = 0;
+        while off < self.size {
+            if let Some(token) = &self.cancel {
+                token.check()?;
+            }
+            let (file, n) = match read_file_header(self, off) {
+                Ok(Some(entry)) => entry,
+                Ok(None) => {
+                    self.globals_complete = true;
+                    break;
+                }
+                Err(e) => match on_error(self, off, e)? {
+                    Some(next) => {
+                        off = next;
+                        continue;
+                    }
+                    None => break,
+                },
+            };
+            let tar_file = try_into_tarfile(file)?;
+            entries += 1;
+            total = total.saturating_add(tar_file.get_size());
+            limits::check("entry count", entries, self.limits.max_entries)?;
+            limits::check("total size", total, self.limits.max_total_size)?;
+            off += n + block_align(tar_file.get_size());
+            self.globals_scanned = self.globals_scanned.max(off);
+            if callback(tar_file)?.is_break() {
+                break;
+            }
+        }
+        if off >= self.size {
+            self.globals_complete = true;
+        }
+        Ok(())
+    }
+
+    /// 与 `for_each_entry` 相同，但回调可以控制遍历：返回 `Break(())` 立即结束，
+    /// 返回 `Continue(Action::SkipSiblings)` 跳过其后与当前条目同目录的条目（含其子孙），
+    /// 直到遇到目录之外的条目。被跳过的条目仍要读取 header，但不读取数据、不调用回调
+    pub fn for_each_entry_ctrl<F>(&mut self, mut callback: F) -> io::Result<()>
+    where
+        F: FnMut(Box<TarFile>) -> io::Result<ControlFlow<(), Action>>,
+    {
+        let mut skipping: Option<String> = None;
+        self.walk_entries(|file| {
+            let tar_file = try_into_tarfile(file)?;
+            let path = tar_file.get_path();
+            let path = normalize_path(&path);
+            if let Some(parent) = &skipping {
+                if path.starts_with(parent.as_str()) {
+                    return Ok(ControlFlow::Continue(()));
+                }
+                skipping = None;
+            }
+            let parent = match path.rfind('/') {
+                Some(i) => path[..=i].to_string(),
+                None => String::new(),
+            };
+            match callback(tar_file)? {
+                ControlFlow::Break(()) => return Ok(ControlFlow::Break(())),
+                ControlFlow::Continue(Action::SkipSiblings) => skipping = Some(parent),
+                ControlFlow::Continue(Action::Continue) => {}
+            }
+            Ok(ControlFlow::Continue(()))
+        }, header_error)
+    }
+}
+
+/// `for_each_entry_ctrl` 回调在继续遍历时的选择
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Action {
+    /// 继续下一个条目
+    #[default]
+    Continue,
+    /// 跳过当前条目所在目录中余下的条目
+    SkipSiblings,
+}
+
+/// 遍历时读取 header 失败：库内错误原样返回，便于调用方区分，其它错误附上偏移
+fn header_error(_: &mut TarImage, off: u64, e: io::Error) -> io::Result<Option<u64>> {
+    Err(match crate::error::as_pt_error(&e) {
+        Some(_) => e,
+        None => io::Error::new(e.kind(), format!("Error reading file header at offset {}: {}", off, e)),
+    })
+}
+
+/// 锁定 open 返回的镜像句柄
+pub fn lock_image(img: &Arc<Mutex<TarImage>>) -> io::Result<MutexGuard<'_, TarImage>> {
+    img.lock().map_err(|_| io::Error::other("Failed to lock TarImage"))
 }
 
 impl ImageInfo for TarImage {
     fn open(path: &str) -> io::Result<Arc<Mutex<Self>>> {
-        let file = Arc::new(File::open(path)?);
-        let size = file.metadata()?.len();
-        Ok(Arc::new(Mutex::new(TarImage {
-            file,
-            path: path.to_string(),
-            size,
-            last_link_name: String::new(),
-        })))
+        TarImage::from_backend(FileBackend::open(path)?, path)
     }
 
     fn get_size(&self) -> io::Result<u64> {
@@ -68,10 +561,17 @@ impl ImageInfo for TarImage {
     }
 
     fn read_img_at(&mut self, offset: u64, size: u64) -> io::Result<(Vec<u8>, u64)> {
-        let mut file = self.file.as_ref().try_clone()?;
-        file.seek(SeekFrom::Start(offset))?;
+        // size 可能来自损坏的 header，分配之前先确认镜像中放得下
+        if size > self.size.saturating_sub(offset) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("read of {} bytes at offset {} exceeds image size {}", size, offset, self.size),
+            ));
+        }
         let mut buf = vec![0u8; size as usize];
-        let n = file.read(&mut buf)?;
+        let n = read_full_at(&*self.backend, &mut buf, self.base + offset, &self.retry)?;
+        perf::add_allocation();
+        perf::add_bytes_read(n as u64);
         if n != size as usize {
             return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "not enough data"));
         }
@@ -79,59 +579,28 @@ impl ImageInfo for TarImage {
     }
 
     fn get_file_at(&mut self, offset: u64) -> io::Result<(Box<dyn FileInfo>,u64)> {
-        match read_file_header(self, offset) {
-            Ok(file_res) => {
-                return Ok(file_res);
-            },
-            Err(e) => {
-                return Err(e);
-            }
-        };
+        self.resolve_globals(offset);
+        read_file_header(self, offset)?
+            .ok_or_else(|| io::Error::new(io::ErrorKind::UnexpectedEof, "end of archive"))
     }
 
     fn for_each_entry<F>(&mut self, mut callback: F) -> io::Result<()>
     where
         F: FnMut(Box<dyn FileInfo>) -> io::Result<()>,
     {
-        let mut off: u64 = 0;
-        while off < self.size {
-            match read_file_header(self, off) {
-                Ok((file,n)) => {
-                    let tar_file = try_into_tarfile(file)?;
-                    let mut body_size = tar_file.header.get_size();
-                    body_size = if (body_size % 512) == 0 {
-                        body_size
-                    } else {
-                        ((body_size / 512) + 1) *512
-                    };
-                    if tar_file.header.get_type_flag() == 'K' {
-                        off += tar_file.header_size;
-                    } else {
-                        off += n + body_size;
-                    }
-                    if tar_file.header.get_type_flag() != 'K' {
-                        callback(tar_file)?;
-                    }
-                },
-                Err(e) => {
-                    eprintln!("Error reading file header: {}", e);
-                    return Err(e);
-                }
-            };
-        }
-        Ok(())
+        self.walk_entries(|file| callback(file).map(ControlFlow::Continue), header_error)
     }
 }
 
 /// 从 TarImage 读取 header 并返回 (header, total_header_size)
-pub fn tar_hdr_read_internal(img_info: &mut TarImage, offset: u64) -> io::Result<(TarHeader, u64)> {
+pub(crate) fn tar_hdr_read_internal(img_info: &mut TarImage, offset: u64) -> io::Result<(TarHeader, u64)> {
     const BLOCK_SIZE: u64 = 512;
     let mut header_size: u64 = 0;
     let mut num_zero_blocks: u32 = 0;
 
     loop {
         // 读取一个 512 字节块
-        let (buf, n) = img_info.read_img_at(offset + header_size, BLOCK_SIZE)
+        let (buf, n) = img_info.read_meta_at(offset + header_size, BLOCK_SIZE)
             .map_err(|e| io::Error::new(e.kind(), format!("Error reading image at offset {}: {}", offset + header_size, e)))?;
         if n < BLOCK_SIZE {
             return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "not enough data"));
@@ -140,9 +609,10 @@ pub fn tar_hdr_read_internal(img_info: &mut TarImage, offset: u64) -> io::Result
         // 解析 tar header
         let hdr   = unsafe { read_tar_header(&buf)? };
         header_size += BLOCK_SIZE;
+        perf::add_blocks_read(1);
 
-        // 检测全零块 (EOF)
-        if hdr.get_name().is_empty() {
+        // 检测全零块 (EOF)，只有整个块都为 0 才算
+        if hdr.is_zero_block() {
             num_zero_blocks += 1;
             if num_zero_blocks >= 2 {
                 // 两个全零块表示真正的 EOF，返回 size = 0
@@ -157,107 +627,227 @@ pub fn tar_hdr_read_internal(img_info: &mut TarImage, offset: u64) -> io::Result
         if !hdr.crc_ok() {
             return Err(io::Error::new(io::ErrorKind::InvalidData, "tar header checksum error"));
         }
+        if img_info.strict {
+            hdr.validate_strict()
+                .map_err(|reason| PtError::MalformedHeader { offset: offset + header_size - BLOCK_SIZE, reason })?;
+        }
 
         // 成功解析到有效 header，返回 header 和已读取的大小
+        perf::add_header_parsed();
         return Ok((hdr, header_size));
     }
 }
 
-fn read_file_header(img_info :&mut TarImage, offset:u64) -> io::Result<(Box<dyn FileInfo>, u64)> {
+/// 读取 GNU 'L' / 'K' 扩展块中保存的长名称
+fn read_long_name(img_info: &mut TarImage, offset: u64, size: u64) -> io::Result<Vec<u8>> {
+    let (buf, _) = img_info.read_meta_at(offset, size)?;
+    Ok(field_bytes(&buf).to_vec())
+}
+
+/// 无法识别的厂商扩展 header（'A'..'Z' 中不表示独立条目的类型），原样附加到其后的条目
+pub(crate) fn is_vendor_extension(flag: char) -> bool {
+    flag.is_ascii_uppercase() && !matches!(flag, 'D' | 'K' | 'L' | 'M' | 'S' | 'V' | 'X')
+}
+
+/// 条目前附带的厂商扩展记录
+#[derive(Debug, Clone)]
+pub struct VendorRecord {
+    pub type_flag: char,
+    /// 扩展 header 自身的偏移
+    pub offset: u64,
+    pub data: Vec<u8>,
+}
+
+/// 读取 offset 处的条目，遇到归档结束标记（两个全零块）时返回 None
+///
+/// 条目之前的 GNU 'L' / 'K' 扩展 header 会被一并读取，其内容作为该条目的长名称 / 长链接名；
+/// PAX 'x' 扩展 header（以及 Solaris / star 格式相同的 'X'）的记录附加到该条目上，
+/// 其中 path / linkpath / size 覆盖 header 中的字段；
+/// 无法识别的厂商扩展 header 同样归入该条目，原始复制时随条目一起保留；
+/// PAX 全局 header 'g' 不是条目，其记录作为之后条目的默认值合并进来，条目自身的记录优先。
+/// 条目之前的 'g' header 同样计入条目的原始字节，归档末尾只剩 'g' header 时返回 None
+fn read_file_header(img_info :&mut TarImage, offset:u64) -> io::Result<Option<(Box<dyn FileInfo>, u64)>> {
     let mut current_offset = offset;
-    let (mut hdr, mut n) = tar_hdr_read_internal(img_info, offset)?;
-    current_offset += n;
-    if hdr.get_type_flag() == 'L' {
-        let sz = hdr.get_size();
-        let blocks = (sz / 512) + if (sz % 512) != 0 { 1 } else { 0 };
-        let extension_size = blocks * 512;
-        current_offset += extension_size;
-        (hdr, n)  = tar_hdr_read_internal(img_info, current_offset)?;
+    let mut long_name = Vec::new();
+    let mut long_link = Vec::new();
+    let mut pax = PaxRecords::new();
+    let mut vendor = Vec::new();
+    // 开头连续的 'g' header 所占的字节数，以及之后是否读到了属于条目的扩展 header
+    let mut globals_len = 0u64;
+    let mut extended = false;
+    let hdr = loop {
+        let (hdr, n) = tar_hdr_read_internal(img_info, current_offset)?;
+        if n == 0 {
+            if extended {
+                return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "archive ends after extension header"));
+            }
+            return Ok(None);
+        }
         current_offset += n;
-    }
+        if matches!(hdr.get_type_flag(), 'x' | 'X' | 'g' | 'L' | 'K') || is_vendor_extension(hdr.get_type_flag()) {
+            limits::check("extension header size", hdr.get_size(), img_info.limits.max_pax_size)?;
+        }
+        extended |= hdr.get_type_flag() != 'g';
+        match hdr.get_type_flag() {
+            'g' => {
+                let (data, _) = img_info.read_meta_at(current_offset, hdr.get_size())?;
+                img_info.add_globals(current_offset - n, parse_pax_records(&data)?);
+                if !extended {
+                    globals_len = current_offset - offset + block_align(hdr.get_size());
+                }
+            }
+            'L' => long_name = read_long_name(img_info, current_offset, hdr.get_size())?,
+            'K' => long_link = read_long_name(img_info, current_offset, hdr.get_size())?,
+            'x' | 'X' => {
+                let (data, _) = img_info.read_meta_at(current_offset, hdr.get_size())?;
+                pax.extend(parse_pax_records(&data)?);
+            }
+            flag if is_vendor_extension(flag) => {
+                let (data, _) = img_info.read_meta_at(current_offset, hdr.get_size())?;
+                vendor.push(VendorRecord { type_flag: flag, offset: current_offset - n, data });
+            }
+            _ => break hdr,
+        }
+        current_offset += block_align(hdr.get_size());
+    };
 
-    if hdr.get_type_flag() == 'K' {
-        let sz = hdr.get_size();
-        let blocks = (sz / 512) + if (sz % 512) != 0 { 1 } else { 0 };
-        let extension_size = blocks * 512;
-        current_offset += extension_size;
+    // 旧式 GNU 稀疏 header 放不下的映射在其后的扩展块中继续，这些块属于 header，数据区在其后
+    let mut sparse = Vec::new();
+    let mut sparse_ext_len = 0u64;
+    if hdr.get_type_flag() == 'S' {
+        let (map, mut extended) = hdr.get_gnu_sparse_map();
+        sparse = map;
+        while extended {
+            sparse_ext_len += 512;
+            limits::check("sparse map size", sparse_ext_len, img_info.limits.max_pax_size)?;
+            let (block, _) = img_info.read_meta_at(current_offset, 512)?;
+            let block = <[u8; 512]>::try_from(block.as_slice())
+                .map_err(|_| io::Error::new(io::ErrorKind::UnexpectedEof, "archive ends inside sparse map"))?;
+            let (more, next) = parse_gnu_sparse_block(&block);
+            sparse.extend(more);
+            extended = next;
+            current_offset += 512;
+        }
     }
 
-    n = current_offset - offset; // 计算 header 大小
+    let n = current_offset - offset; // 计算 header 大小
+
+    if let Some(globals) = img_info.globals_before(current_offset) {
+        // 全局记录是后续条目的默认值，条目自身的 'x' 记录优先
+        for (key, value) in globals {
+            if !ENTRY_ONLY_KEYS.contains(&key.as_str()) {
+                pax.entry(key.clone()).or_insert_with(|| value.clone());
+            }
+        }
+    }
 
     let mut tar_file = TarFile::new(Arc::new(img_info.clone().into()), hdr);
     tar_file.base_offset = offset;
+    tar_file.long_name = long_name;
+    tar_file.link = long_link;
+    if let Some(path) = pax.get("path") {
+        tar_file.long_name = path.clone();
+    }
+    if let Some(link) = pax.get("linkpath") {
+        tar_file.link = link.clone();
+    }
+    tar_file.size = pax_u64(&pax, "size").unwrap_or_else(|| hdr.get_size());
+    // 设备文件和 FIFO 没有数据块，忽略 size 字段中的任何值
+    if matches!(hdr.get_type_flag(), '3' | '4' | '6') {
+        tar_file.size = 0;
+    }
+    limits::check("entry size", tar_file.size, img_info.limits.max_entry_size)?;
+    tar_file.pax = pax;
+    tar_file.vendor = vendor;
+    tar_file.sparse = sparse;
+    tar_file.sparse_ext_len = sparse_ext_len;
+    tar_file.globals_len = globals_len;
+    tar_file.keep_absolute = img_info.keep_absolute_paths;
     if hdr.get_type_flag() == '5' {
         tar_file.file_type = TarFileType::Directory as i32;
     } else if hdr.get_type_flag() == '1' {
         tar_file.file_type = TarFileType::SymbolicLink as i32;
-        if img_info.last_link_name != "" {
-            tar_file.link = img_info.last_link_name.clone();
-        }
-    } else if hdr.get_type_flag() == 'K' {
-        img_info.last_link_name = hdr.get_link_name();
+    } else if hdr.get_type_flag() == '3' {
+        tar_file.file_type = TarFileType::CharacterDevice as i32;
+    } else if hdr.get_type_flag() == '4' {
+        tar_file.file_type = TarFileType::BlockDevice as i32;
+    } else if hdr.get_type_flag() == '6' {
+        tar_file.file_type = TarFileType::Fifo as i32;
     }
-    if n == 0 {
-        return Err(io::Error::new(io::ErrorKind::InvalidData, "tar header size is zero"));
+    if crate::whiteout::classify(&tar_file.get_path()).is_some() {
+        tar_file.file_type = TarFileType::Whiteout as i32;
     }
     tar_file.header_size = n;
-    Ok((Box::new(tar_file),n))
+    Ok(Some((Box::new(tar_file),n)))
 }
 
 
 /// Tar 文件片段结构，包含镜像引用、起始偏移和结束偏移
 #[derive(Clone)]
 pub struct TarFile {
-    image: Arc<Mutex<TarImage>>,
+    pub(crate) image: Arc<Mutex<TarImage>>,
+    /// 读取数据区时使用，各线程可同时读取，不经过 image 的锁
+    view: ImageView,
     header : TarHeader,
     base_offset: u64,
     pos: u64,
     file_type: i32,
-    link : String,
+    link : Vec<u8>,
+    long_name: Vec<u8>,
     header_size: u64,
+    keep_absolute: bool,
+    size: u64,
+    pax: PaxRecords,
+    vendor: Vec<VendorRecord>,
+    sparse: Vec<SparseChunk>,
+    /// 主 header 之后旧式 GNU 稀疏扩展块的总字节数
+    sparse_ext_len: u64,
+    /// 开头 'g' header 所占的字节数；它们不属于条目，丢弃条目时仍需保留
+    globals_len: u64,
 }
 
 impl TarFile {
     pub fn new(image: Arc<Mutex<TarImage>>, hdr: TarHeader) -> Self {
+        let view = image.lock().unwrap_or_else(|e| e.into_inner()).view();
         TarFile {
             image,
+            view,
             header: hdr,
             base_offset: 0,
             pos: 0,
             file_type: -1,
-            link: String::new(),
+            link: Vec::new(),
+            long_name: Vec::new(),
             header_size: 0,
+            keep_absolute: false,
+            size: hdr.get_size(),
+            pax: PaxRecords::new(),
+            vendor: Vec::new(),
+            sparse: Vec::new(),
+            sparse_ext_len: 0,
+            globals_len: 0,
         }
     }
 }
 
+/// 读写位置相对于条目数据区起点，只读到数据区末尾，不含 header 与块填充
 impl Read for TarFile {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        let mut img = self.image.try_lock().map_err(|_| {
-            io::Error::new(io::ErrorKind::Other, "Failed to lock TarImage")
-        })?;
-        if self.pos >= self.header.get_size() {
-            return Ok(0);
-        }
-        img.seek(SeekFrom::Start(self.pos))?;
-        Ok(img.read(buf).map(|n| {
-            self.pos += n as u64;
-            n
-        })?)
+        let n = self.read_body_at(self.pos, buf)?;
+        self.pos += n as u64;
+        Ok(n)
     }
 }
 
 impl Seek for TarFile {
     fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
-        let mut img = self.image.try_lock().map_err(|_| {
-            io::Error::new(io::ErrorKind::Other, "Failed to lock TarImage")
-        })?;
         let new_pos = match pos {
-            SeekFrom::Start(n) => SeekFrom::Start(self.base_offset + n),
-            SeekFrom::End(n) => SeekFrom::End(n),
-            SeekFrom::Current(n) => SeekFrom::Current(n),
+            SeekFrom::Start(n) => Some(n),
+            SeekFrom::End(n) => self.size.checked_add_signed(n),
+            SeekFrom::Current(n) => self.pos.checked_add_signed(n),
         };
-        Ok(img.seek(new_pos)?)
+        self.pos = new_pos.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "invalid seek to a negative position"))?;
+        Ok(self.pos)
     }
 }
 
@@ -273,17 +863,194 @@ impl FileInfo for TarFile {
 
 impl TarFile {
     pub fn get_name(&self) -> String {
-        self.header.get_name()
+        if !self.long_name.is_empty() {
+            String::from_utf8_lossy(&self.long_name).into_owned()
+        } else {
+            self.header.get_name()
+        }
+    }
+    /// 完整路径：GNU 长名称，或 ustar 的 prefix + name；非 UTF-8 字节按替换字符显示
+    pub fn get_path(&self) -> String {
+        String::from_utf8_lossy(&self.get_path_bytes()).into_owned()
     }
+    /// 完整路径的原始字节，不做任何编码转换；除非镜像设置了保留绝对路径，否则去掉绝对前缀
+    pub fn get_path_bytes(&self) -> Vec<u8> {
+        let path = if !self.long_name.is_empty() {
+            self.long_name.clone()
+        } else {
+            self.header.get_full_path_bytes()
+        };
+        if self.keep_absolute {
+            path
+        } else {
+            strip_absolute(&path).to_vec()
+        }
+    }
+    /// 完整路径转成系统路径，Unix 上保留非 UTF-8 字节（见 `path::os_from_bytes`）
+    pub fn get_path_os(&self) -> OsString {
+        os_from_bytes(&self.get_path_bytes())
+    }
+    /// 数据区大小，PAX size 记录优先于 header 字段
     pub fn get_size(&self) -> u64 {
-        self.header.get_size()
+        self.size
+    }
+    /// 条目附带的 PAX 扩展记录
+    pub fn pax_records(&self) -> &PaxRecords {
+        &self.pax
+    }
+
+    /// 条目前无法识别的厂商扩展记录，按出现顺序排列
+    pub fn vendor_records(&self) -> &[VendorRecord] {
+        &self.vendor
+    }
+    /// 旧式 GNU 稀疏文件（'S'）的稀疏映射，按原文件中的偏移排列；其它条目为空
+    pub fn sparse_map(&self) -> &[SparseChunk] {
+        &self.sparse
+    }
+
+    /// 主 header 之后旧式 GNU 稀疏扩展块的总字节数，原始复制 header 时需要一并复制
+    pub(crate) fn sparse_ext_len(&self) -> u64 {
+        self.sparse_ext_len
+    }
+
+    /// 展开空洞后的文件大小：稀疏文件取 header 的 realsize 字段，其它条目与 `get_size` 相同
+    pub fn real_size(&self) -> u64 {
+        if self.get_type_flag() == 'S' {
+            self.header.get_gnu_realsize()
+        } else {
+            self.size
+        }
     }
     pub fn get_type_flag(&self) -> char {
         self.header.get_type_flag()
     }
+
+    pub fn type_flag(&self) -> TypeFlag {
+        self.header.type_flag()
+    }
+    /// 条目类型，取值为 `TarFileType` 的 u32 表示，未识别的类型为 -1
+    pub fn get_file_type(&self) -> i32 {
+        self.file_type
+    }
     pub fn get_offset(&self) -> u64 {
         self.base_offset
     }
+    pub fn get_header(&self) -> &TarHeader {
+        &self.header
+    }
+    /// 数据区在镜像中的起始偏移（跳过 header 及其扩展块）
+    pub fn get_data_offset(&self) -> u64 {
+        self.base_offset + self.header_size
+    }
+    /// 链接目标，优先使用 GNU 'K' 长链接名
+    pub fn get_link_name(&self) -> String {
+        String::from_utf8_lossy(&self.get_link_name_bytes()).into_owned()
+    }
+    pub fn get_link_name_bytes(&self) -> Vec<u8> {
+        if !self.link.is_empty() {
+            self.link.clone()
+        } else {
+            self.header.get_link_name_bytes()
+        }
+    }
+
+    /// 条目（含扩展 header 与数据填充）在镜像中的结束偏移
+    pub fn get_end_offset(&self) -> u64 {
+        self.get_data_offset() + block_align(self.size)
+    }
+
+    /// 把整个条目（扩展 header、header、数据及填充）的原始字节复制到 writer
+    pub fn copy_raw_to<W: Write>(&self, writer: &mut W) -> io::Result<u64> {
+        self.copy_range(self.base_offset, self.get_end_offset() - self.base_offset, writer)
+    }
+
+    /// 把条目开头的 'g' header 原样复制到 writer，丢弃或重写条目时使用
+    pub(crate) fn copy_globals_to<W: Write>(&self, writer: &mut W) -> io::Result<u64> {
+        self.copy_range(self.base_offset, self.globals_len, writer)
+    }
+
+    /// 把数据区（不含填充）写到 writer，返回写出的字节数，即 `get_size()`；
+    /// 与读取位置无关，总是从数据区开头复制。归档在数据区内被截断时返回 UnexpectedEof
+    pub fn copy_to<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<u64> {
+        self.copy_range(self.get_data_offset(), self.size, writer)
+    }
+
+    /// 按块把镜像中 [start, start + len) 的字节复制到 writer
+    pub(crate) fn copy_range<W: Write + ?Sized>(&self, start: u64, len: u64, writer: &mut W) -> io::Result<u64> {
+        const CHUNK: u64 = 1024 * 1024;
+        let mut buf = vec![0u8; CHUNK.min(len) as usize];
+        let mut done = 0u64;
+        while done < len {
+            let n = CHUNK.min(len - done) as usize;
+            self.view.read_exact_at(start + done, &mut buf[..n])?;
+            writer.write_all(&buf[..n])?;
+            done += n as u64;
+        }
+        Ok(done)
+    }
+
+    /// 把条目数据区当作一个嵌套的 tar 镜像打开，与外层共用文件句柄，不复制数据
+    pub fn open_nested(&self) -> io::Result<Arc<Mutex<TarImage>>> {
+        let img = lock_image(&self.image)?;
+        let mut nested = img.clone();
+        nested.base = img.base + self.get_data_offset();
+        nested.size = self.size;
+        nested.path = format!("{}:{}", img.path, self.get_path());
+        Ok(Arc::new(Mutex::new(nested)))
+    }
+
+    /// 从数据区 pos 处读取，读取长度不会超出条目大小；不加锁，可从多个线程同时调用
+    pub(crate) fn read_body_at(&self, pos: u64, buf: &mut [u8]) -> io::Result<usize> {
+        let size = self.size;
+        if pos >= size {
+            return Ok(0);
+        }
+        let len = (buf.len() as u64).min(size - pos) as usize;
+        self.view.read_exact_at(self.get_data_offset() + pos, &mut buf[..len])?;
+        Ok(len)
+    }
+
+    /// 读出整个数据区，缓冲区按 `get_size()` 一次分配
+    pub fn read_to_vec(&self) -> io::Result<Vec<u8>> {
+        let size = usize::try_from(self.size)
+            .map_err(|_| io::Error::new(io::ErrorKind::OutOfMemory, "entry too large to read into memory"))?;
+        let mut out = vec![0u8; size];
+        let mut done = 0;
+        while done < size {
+            let n = self.read_body_at(done as u64, &mut out[done..])?;
+            if n == 0 {
+                return Err(io::Error::new(io::ErrorKind::UnexpectedEof, format!("entry {} is truncated", self.get_path())));
+            }
+            done += n;
+        }
+        Ok(out)
+    }
+
+    /// 读出整个数据区并按 UTF-8 解码，非 UTF-8 内容返回 InvalidData
+    pub fn read_to_string(&self) -> io::Result<String> {
+        String::from_utf8(self.read_to_vec()?)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("entry {} is not UTF-8: {}", self.get_path(), e)))
+    }
+}
+
+/// 只读取条目数据区的 reader
+pub struct BodyReader<'a> {
+    file: &'a TarFile,
+    pos: u64,
+}
+
+impl<'a> BodyReader<'a> {
+    pub fn new(file: &'a TarFile) -> Self {
+        BodyReader { file, pos: 0 }
+    }
+}
+
+impl Read for BodyReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.file.read_body_at(self.pos, buf)?;
+        self.pos += n as u64;
+        Ok(n)
+    }
 }
 
 pub fn try_into_tarfile(b: Box<dyn FileInfo>) -> io::Result<Box<TarFile>> {
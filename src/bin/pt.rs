@@ -0,0 +1,420 @@
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::{Arc, Mutex};
+
+use pt::base::{lock_image, ImageInfo, TarImage};
+use pt::builder::{SymlinkPolicy, TarBuilder};
+use pt::checksum::DigestAlgorithm;
+use pt::compress::{decoder, Compression};
+use pt::extract::{extract_all_report, unpack_stream, ExtractAction, ExtractOptions, OverwritePolicy};
+use pt::manifest::Manifest;
+use pt::repack::{transform_copy_paths, NameTransform};
+use pt::search::{ContentPattern, NamePattern};
+use pt::update::update_archive;
+use pt::verify::verify;
+
+const USAGE: &str = "usage:
+    pt list [--json] [-v] <image.tar>  (-v: like tar -tv)
+    pt extract [-k | --skip-old-files] [-U] [--dry-run] [--sandbox] <image.tar> [-C <dir>]
+                                    (image '-' streams from stdin)
+    pt create [--deterministic] [-h] [--exclude <glob>]... [--tarignore] <out.tar> <path>...
+    pt update <image.tar> <path>...
+    pt verify <image.tar> [--manifest <manifest.json>]
+    pt manifest <image.tar>         (sha256 manifest as JSON on stdout)
+    pt compare <image.tar> <dir>    (like tar -d)
+    pt stats <image.tar>
+    pt find [-E] <pattern> <image.tar>
+                                    (glob such as '**/*.so', or regex with -E)
+    pt grep [-E] <pattern> <image.tar>
+                                    (path:line:text; -E needs feature `regex`)
+    pt repack [--strip <prefix>] [--prefix <dir>] [--transform <s/re/repl/>]... <src.tar> <dst.tar>
+    pt mount <image.tar> <dir>      (feature `fuse`)
+
+options:
+    -P    keep leading '/' and drive letters in entry paths; extract allows
+          '..' and paths outside the target directory
+    --strict
+          reject headers without a ustar/GNU magic and version or with
+          malformed numeric fields
+    --deterministic
+          create: zero uid/gid, clamp mtimes to $SOURCE_DATE_EPOCH (or 0) and
+          pad the archive to 10 KiB records for byte-identical output
+    -k, --keep-old-files
+          extract: fail instead of replacing existing files
+    --skip-old-files
+          extract: leave existing files alone
+    -U, --unlink-first
+          extract: remove existing files before writing them
+    --dry-run
+          extract: print what would be created, replaced or skipped without
+          touching the disk
+    --sandbox
+          extract: resolve every path relative to the target directory handle
+          without following any symlink (Unix only)
+    -h, --dereference
+          create: archive the files symlinks point to instead of the links
+    --exclude <glob>
+          create: skip paths matching the glob; without '/' it matches names
+          at any depth (e.g. `target/`, `*.o`)
+    --tarignore
+          create: also read exclude globs from `.tarignore` in each directory
+    --strip <prefix>, --prefix <dir>
+          repack: remove a leading prefix such as `./` from entry paths and
+          hard link targets, or move entries under <dir>
+    --transform <s/re/repl/[gi]>
+          repack: sed-style rename of paths and link targets (feature `regex`);
+          renames apply in the order given";
+
+/// 所有子命令共用的选项
+#[derive(Default)]
+struct Flags {
+    keep_absolute: bool,
+    strict: bool,
+}
+
+impl Flags {
+    /// 取出通用选项，返回剩余参数
+    fn parse(args: Vec<String>) -> (Flags, Vec<String>) {
+        let mut flags = Flags::default();
+        let mut rest = Vec::new();
+        for arg in args {
+            match arg.as_str() {
+                "-P" => flags.keep_absolute = true,
+                "--strict" => flags.strict = true,
+                _ => rest.push(arg),
+            }
+        }
+        (flags, rest)
+    }
+
+    fn open(&self, image: &str) -> io::Result<Arc<Mutex<TarImage>>> {
+        let img = TarImage::open(image)?;
+        let mut guard = lock_image(&img)?;
+        guard.set_keep_absolute_paths(self.keep_absolute);
+        guard.set_strict(self.strict);
+        drop(guard);
+        Ok(img)
+    }
+}
+
+fn usage_error() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, USAGE)
+}
+
+/// 原样写到 stdout，保留名字中的非 UTF-8 字节；Windows 控制台只接受 UTF-8，按替换字符转换
+fn print_bytes(bytes: &[u8]) -> io::Result<()> {
+    #[cfg(windows)]
+    return io::stdout().lock().write_all(String::from_utf8_lossy(bytes).as_bytes());
+    #[cfg(not(windows))]
+    io::stdout().lock().write_all(bytes)
+}
+
+fn cmd_list(flags: &Flags, args: &[String]) -> io::Result<()> {
+    let (mut json, mut verbose, mut image) = (false, false, None);
+    for arg in args {
+        match arg.as_str() {
+            "--json" => json = true,
+            "-v" | "--verbose" => verbose = true,
+            _ if image.is_none() => image = Some(arg),
+            _ => return Err(usage_error()),
+        }
+    }
+    let img = flags.open(image.ok_or_else(usage_error)?)?;
+    if json {
+        println!("{}", lock_image(&img)?.list_json()?);
+        return Ok(());
+    }
+    let outcome = lock_image(&img)?.scan()?;
+    for meta in &outcome.entries {
+        let mut line = if verbose {
+            meta.long_format_bytes()
+        } else {
+            let mut line = format!("{} {} ", meta.type_flag, meta.size).into_bytes();
+            line.extend_from_slice(meta.path_bytes());
+            line
+        };
+        line.push(b'\n');
+        print_bytes(&line)?;
+    }
+    for w in &outcome.warnings {
+        eprintln!("pt: warning: {} (offset {}): {}", w.path, w.offset, w.message);
+    }
+    Ok(())
+}
+
+fn cmd_extract(flags: &Flags, args: &[String]) -> io::Result<()> {
+    // 与 GNU tar 一致，-P 同时关闭路径穿越检查
+    let mut options = ExtractOptions { allow_unsafe_paths: flags.keep_absolute, ..Default::default() };
+    let (mut image, mut dest) = (None, PathBuf::from("."));
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "-k" | "--keep-old-files" => options.overwrite = OverwritePolicy::Error,
+            "--skip-old-files" => options.overwrite = OverwritePolicy::Skip,
+            "-U" | "--unlink-first" => options.unlink_first = true,
+            "--dry-run" => options.dry_run = true,
+            "--sandbox" => options.sandboxed = true,
+            "-C" => dest = PathBuf::from(iter.next().ok_or_else(usage_error)?),
+            _ if image.is_none() => image = Some(arg),
+            _ => return Err(usage_error()),
+        }
+    }
+    let image = image.ok_or_else(usage_error)?;
+    if image == "-" {
+        if options.dry_run {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "--dry-run needs a seekable image, not stdin"));
+        }
+        // 从标准输入流式解包，按开头的魔数自动解压
+        let mut stdin = BufReader::new(io::stdin().lock());
+        let compression = Compression::sniff(stdin.fill_buf()?);
+        return unpack_stream(decoder(stdin, compression)?, &dest, &options);
+    }
+    let img = flags.open(image)?;
+    let mut img = lock_image(&img)?;
+    let report = extract_all_report(&mut img, &dest, &options)?;
+    if options.dry_run {
+        for record in &report {
+            let action = match record.action {
+                ExtractAction::Create => "create",
+                ExtractAction::Overwrite => "replace",
+                ExtractAction::Skip => "skip",
+            };
+            println!("{} {}", action, record.target.display());
+        }
+    }
+    Ok(())
+}
+
+fn cmd_create(args: &[String]) -> io::Result<()> {
+    let mut deterministic = false;
+    let mut dereference = false;
+    let mut tarignore = false;
+    let mut exclude = Vec::new();
+    let mut rest = Vec::new();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--deterministic" => deterministic = true,
+            "-h" | "--dereference" => dereference = true,
+            "--tarignore" => tarignore = true,
+            "--exclude" => exclude.push(iter.next().ok_or_else(usage_error)?.as_str()),
+            _ => match arg.strip_prefix("--exclude=") {
+                Some(pattern) => exclude.push(pattern),
+                None => rest.push(arg),
+            },
+        }
+    }
+    let args = rest;
+    let [out, inputs @ ..] = &args[..] else { return Err(usage_error()) };
+    if inputs.is_empty() {
+        return Err(usage_error());
+    }
+    let mut builder = TarBuilder::new(BufWriter::new(File::create(out)?));
+    if deterministic {
+        builder = builder.deterministic();
+    }
+    if dereference {
+        builder = builder.symlinks(SymlinkPolicy::Follow);
+    }
+    builder = builder.exclude(&exclude).tarignore(tarignore);
+    for input in inputs {
+        let name = input.trim_start_matches("./").trim_start_matches('/');
+        let path = Path::new(input);
+        let md = if dereference { fs::metadata(path)? } else { fs::symlink_metadata(path)? };
+        if md.is_dir() {
+            builder.append_dir_all(path, name)?;
+        } else {
+            builder.append_path(name, path)?;
+        }
+    }
+    builder.finish()?;
+    Ok(())
+}
+
+fn cmd_update(args: &[String]) -> io::Result<()> {
+    let [image, inputs @ ..] = args else { return Err(usage_error()) };
+    if inputs.is_empty() {
+        return Err(usage_error());
+    }
+    let inputs: Vec<(&str, &Path)> = inputs.iter()
+        .map(|input| (input.trim_start_matches("./").trim_start_matches('/'), Path::new(input)))
+        .collect();
+    let appended = update_archive(image, &inputs)?;
+    println!("{} entries appended", appended);
+    Ok(())
+}
+
+fn cmd_manifest(flags: &Flags, args: &[String]) -> io::Result<()> {
+    let [image] = args else { return Err(usage_error()) };
+    let img = flags.open(image)?;
+    let manifest = lock_image(&img)?.manifest(DigestAlgorithm::Sha256)?;
+    println!("{}", String::from_utf8_lossy(&manifest.to_bytes()));
+    Ok(())
+}
+
+fn cmd_verify_manifest(img: &Arc<Mutex<TarImage>>, manifest: &str) -> io::Result<()> {
+    let manifest = Manifest::parse(&fs::read(manifest)?)?;
+    let report = lock_image(img)?.verify_manifest(&manifest)?;
+    for path in &report.missing {
+        println!("missing: {}", path);
+    }
+    for path in &report.unexpected {
+        println!("unexpected: {}", path);
+    }
+    for (path, field) in &report.mismatched {
+        println!("mismatch: {} ({})", path, field);
+    }
+    if report.is_ok() {
+        Ok(())
+    } else {
+        Err(io::Error::new(io::ErrorKind::InvalidData, "archive does not match manifest"))
+    }
+}
+
+fn cmd_verify(flags: &Flags, args: &[String]) -> io::Result<()> {
+    let (image, manifest) = match args {
+        [image] => (image, None),
+        [image, flag, manifest] if flag == "--manifest" => (image, Some(manifest)),
+        _ => return Err(usage_error()),
+    };
+    let img = flags.open(image)?;
+    if let Some(manifest) = manifest {
+        return cmd_verify_manifest(&img, manifest);
+    }
+    let report = verify(&mut *lock_image(&img)?)?;
+    println!("{} entries, {} data bytes, {} checksummed", report.entries, report.data_bytes, report.checksummed);
+    for path in &report.truncated {
+        println!("truncated: {}", path);
+    }
+    for (path, key) in &report.checksum_mismatches {
+        println!("checksum mismatch: {} ({})", path, key);
+    }
+    if report.is_ok() {
+        Ok(())
+    } else if report.truncated.is_empty() {
+        Err(io::Error::new(io::ErrorKind::InvalidData, "archive content does not match recorded checksums"))
+    } else {
+        Err(io::Error::new(io::ErrorKind::InvalidData, "archive is truncated"))
+    }
+}
+
+fn cmd_compare(flags: &Flags, args: &[String]) -> io::Result<()> {
+    let [image, dir] = args else { return Err(usage_error()) };
+    let img = flags.open(image)?;
+    let report = lock_image(&img)?.compare_with(Path::new(dir))?;
+    for path in &report.missing {
+        println!("missing: {}", path);
+    }
+    for difference in &report.differences {
+        println!("differs: {} {:?}", difference.path, difference.changes);
+    }
+    if report.is_empty() {
+        Ok(())
+    } else {
+        Err(io::Error::new(io::ErrorKind::InvalidData, "archive differs from the filesystem"))
+    }
+}
+
+fn cmd_stats(flags: &Flags, args: &[String]) -> io::Result<()> {
+    let [image] = args else { return Err(usage_error()) };
+    let img = flags.open(image)?;
+    let stats = lock_image(&img)?.stats()?;
+    println!("{} entries, {} data bytes, {} header bytes, {} padding bytes",
+        stats.entries, stats.stored_bytes, stats.header_bytes, stats.padding_bytes);
+    for (flag, count) in &stats.by_type {
+        println!("type {:?}: {}", flag, count);
+    }
+    for (path, size) in &stats.largest {
+        println!("largest: {} {}", size, path);
+    }
+    for (path, depth) in &stats.deepest {
+        println!("deepest: {} {}", depth, path);
+    }
+    Ok(())
+}
+
+fn cmd_find(flags: &Flags, args: &[String]) -> io::Result<()> {
+    let (pattern, image) = match args {
+        [pattern, image] => (NamePattern::glob(pattern), image),
+        #[cfg(feature = "regex")]
+        [flag, pattern, image] if flag == "-E" => (NamePattern::regex(pattern)?, image),
+        _ => return Err(usage_error()),
+    };
+    let img = flags.open(image)?;
+    for meta in lock_image(&img)?.find(&pattern)? {
+        println!("{} {} {}", meta.type_flag, meta.size, meta.path);
+    }
+    Ok(())
+}
+
+fn cmd_grep(flags: &Flags, args: &[String]) -> io::Result<()> {
+    let (pattern, image) = match args {
+        [pattern, image] => (ContentPattern::literal(pattern), image),
+        #[cfg(feature = "regex")]
+        [flag, pattern, image] if flag == "-E" => (ContentPattern::regex(pattern)?, image),
+        _ => return Err(usage_error()),
+    };
+    let img = flags.open(image)?;
+    for hit in lock_image(&img)?.search_content(&pattern)? {
+        println!("{}:{}:{}", hit.path, hit.line_number, hit.line);
+    }
+    Ok(())
+}
+
+fn cmd_repack(args: &[String]) -> io::Result<()> {
+    let mut transforms = Vec::new();
+    let mut rest = Vec::new();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--strip" => transforms.push(NameTransform::StripPrefix(iter.next().ok_or_else(usage_error)?.clone())),
+            "--prefix" => transforms.push(NameTransform::Prefix(iter.next().ok_or_else(usage_error)?.clone())),
+            #[cfg(feature = "regex")]
+            "--transform" => transforms.push(NameTransform::sed(iter.next().ok_or_else(usage_error)?)?),
+            _ => rest.push(arg),
+        }
+    }
+    let [src, dst] = &rest[..] else { return Err(usage_error()) };
+    let renamed = transform_copy_paths(src, dst, &transforms)?;
+    eprintln!("{} entries renamed", renamed);
+    Ok(())
+}
+
+#[cfg(all(unix, feature = "fuse"))]
+fn cmd_mount(flags: &Flags, args: &[String]) -> io::Result<()> {
+    let [image, dir] = args else { return Err(usage_error()) };
+    pt::fuse::mount(flags.open(image)?, dir)
+}
+
+fn main() -> ExitCode {
+    let (flags, args) = Flags::parse(std::env::args().skip(1).collect());
+    let Some((cmd, rest)) = args.split_first() else {
+        eprintln!("{}", USAGE);
+        return ExitCode::FAILURE;
+    };
+    let result = match cmd.as_str() {
+        "list" => cmd_list(&flags, rest),
+        "extract" => cmd_extract(&flags, rest),
+        "create" => cmd_create(rest),
+        "update" => cmd_update(rest),
+        "verify" => cmd_verify(&flags, rest),
+        "manifest" => cmd_manifest(&flags, rest),
+        "compare" => cmd_compare(&flags, rest),
+        "stats" => cmd_stats(&flags, rest),
+        "find" => cmd_find(&flags, rest),
+        "grep" => cmd_grep(&flags, rest),
+        "repack" => cmd_repack(rest),
+        #[cfg(all(unix, feature = "fuse"))]
+        "mount" => cmd_mount(&flags, rest),
+        _ => Err(usage_error()),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("pt: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
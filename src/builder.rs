@@ -0,0 +1,626 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use crate::filter::ExcludePattern;
+use crate::meta::unix_secs;
+use crate::pax::pax_record;
+use crate::tar::{write_checksum, TarHeader, TypeFlag};
+
+/// 构造 header 所需的字段
+#[derive(Clone, Copy)]
+struct HeaderFields<'a> {
+    path: &'a str,
+    type_flag: u8,
+    mode: u32,
+    uid: u64,
+    gid: u64,
+    /// 属主的用户名 / 组名，为空时不写
+    uname: &'a str,
+    gname: &'a str,
+    /// 修改时间的秒数，早于 1970 年时为负
+    mtime: i64,
+    size: u64,
+    link_name: &'a str,
+    /// 设备文件的主 / 次设备号
+    device: (u32, u32),
+}
+
+/// 写出时使用的 header 格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HeaderFormat {
+    /// Unix V7：没有 magic、prefix 与用户名，路径与链接名最长 100 字节
+    V7,
+    /// POSIX ustar：长路径拆分到 prefix，仍放不下时报错
+    Ustar,
+    /// GNU：超长的路径 / 链接名写成 'L' / 'K' 扩展 header
+    Gnu,
+    /// ustar 放不下的字段写成 PAX 'x' 扩展 header，其余与 ustar 相同
+    #[default]
+    Pax,
+}
+
+/// 从文件系统追加时对符号链接的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SymlinkPolicy {
+    /// 按链接本身保存，不跟随
+    #[default]
+    Preserve,
+    /// 跟随链接，保存目标的内容（相当于 GNU tar 的 `-h`）；目标不存在的链接仍按链接保存
+    Follow,
+    /// 按链接本身保存，遇到目标不存在的链接时报错
+    DenyDangling,
+}
+
+/// 目录遍历时读取的忽略文件名
+pub const TARIGNORE: &str = ".tarignore";
+
+/// ustar 八进制字段能表示的上限（size / mtime 为 11 位，uid / gid 为 7 位）
+const MAX_OCTAL_11: u64 = 0o77777777777;
+const MAX_OCTAL_7: u64 = 0o7777777;
+
+/// 截断到 max 字节以内，不拆开 UTF-8 字符
+pub(crate) fn truncate(s: &str, max: usize) -> &str {
+    let mut end = s.len().min(max);
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    &s[..end]
+}
+
+fn too_long(what: &str, value: &str, format: HeaderFormat) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, format!("{} too long for {:?} header: {}", what, format, value))
+}
+
+/// 扩展 header 及其数据块
+pub(crate) fn extension_block(type_flag: TypeFlag, name: &str, data: &[u8], mtime: i64, gnu: bool) -> io::Result<Vec<u8>> {
+    let mut hdr = TarHeader::new(type_flag);
+    hdr.set_path(truncate(name, 100))?;
+    hdr.set_mode(0o644)?;
+    hdr.set_uid(0)?;
+    hdr.set_gid(0)?;
+    hdr.set_size(data.len() as u64)?;
+    hdr.set_mtime(mtime.clamp(0, MAX_OCTAL_11 as i64))?;
+    if gnu {
+        hdr.magic = *b"ustar ";
+        hdr.version = *b" \0";
+    }
+    let mut out = hdr.to_bytes().to_vec();
+    out.extend_from_slice(data);
+    out.resize(out.len() + padding(data.len() as u64), 0);
+    Ok(out)
+}
+
+/// GNU 'L' / 'K' 长名称 header 及其数据块
+pub(crate) fn gnu_long_block(flag: TypeFlag, value: &str) -> io::Result<Vec<u8>> {
+    let mut data = value.as_bytes().to_vec();
+    data.push(0);
+    extension_block(flag, "././@LongLink", &data, 0, true)
+}
+
+/// 按格式生成 header：返回 (扩展 header 字节, 主 header 块)
+fn encode_header(fields: &HeaderFields, format: HeaderFormat) -> io::Result<(Vec<u8>, [u8; 512])> {
+    let mut hdr = TarHeader::new(TypeFlag::from_byte(fields.type_flag));
+    let mut ext = Vec::new();
+    let mut records = Vec::new();
+
+    match format {
+        HeaderFormat::V7 if fields.path.len() > 100 => return Err(too_long("path", fields.path, format)),
+        HeaderFormat::V7 => hdr.set_path(fields.path)?,
+        HeaderFormat::Ustar => hdr.set_path(fields.path).map_err(|_| too_long("path", fields.path, format))?,
+        HeaderFormat::Gnu if fields.path.len() > 100 => {
+            ext.extend(gnu_long_block(TypeFlag::GnuLongName, fields.path)?);
+            hdr.set_path(truncate(fields.path, 100))?;
+        }
+        HeaderFormat::Gnu => hdr.set_path(fields.path)?,
+        HeaderFormat::Pax => {
+            if hdr.set_path(fields.path).is_err() {
+                records.extend(pax_record("path", fields.path.as_bytes()));
+                hdr.set_path(truncate(fields.path, 100))?;
+            }
+        }
+    }
+
+    if fields.link_name.len() > 100 {
+        match format {
+            HeaderFormat::Gnu => ext.extend(gnu_long_block(TypeFlag::GnuLongLink, fields.link_name)?),
+            HeaderFormat::Pax => records.extend(pax_record("linkpath", fields.link_name.as_bytes())),
+            _ => return Err(too_long("link name", fields.link_name, format)),
+        }
+        hdr.set_link_name(truncate(fields.link_name, 100))?;
+    } else {
+        hdr.set_link_name(fields.link_name)?;
+    }
+
+    // PAX 记录中的数值覆盖 header 字段，header 中只写能表示的部分
+    let mut number = |key: &str, value: u64, max: u64| -> io::Result<u64> {
+        if value <= max {
+            return Ok(value);
+        }
+        match format {
+            HeaderFormat::Pax => {
+                records.extend(pax_record(key, value.to_string().as_bytes()));
+                Ok(if key == "size" { value } else { 0 })
+            }
+            // GNU 的数字字段用 base-256 编码
+            HeaderFormat::Gnu => Ok(value),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidInput, format!("{} {} too large for {:?} header", key, value, format))),
+        }
+    };
+    let uid = number("uid", fields.uid, MAX_OCTAL_7)?;
+    let gid = number("gid", fields.gid, MAX_OCTAL_7)?;
+    let size = number("size", fields.size, MAX_OCTAL_11)?;
+    // 早于 1970 年或超出 11 位八进制的 mtime，GNU 用 base-256，PAX 写记录
+    let mtime = match format {
+        _ if (0..=MAX_OCTAL_11 as i64).contains(&fields.mtime) => fields.mtime,
+        HeaderFormat::Pax => {
+            records.extend(pax_record("mtime", fields.mtime.to_string().as_bytes()));
+            0
+        }
+        HeaderFormat::Gnu => fields.mtime,
+        _ => return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("mtime {} out of range for {:?} header", fields.mtime, format))),
+    };
+    hdr.set_mode(fields.mode)?;
+    if matches!(fields.type_flag, b'3' | b'4') {
+        hdr.set_device(fields.device.0, fields.device.1)?;
+    }
+    hdr.set_uid(uid)?;
+    hdr.set_gid(gid)?;
+    // V7 没有用户名字段；放不下 32 字节时 PAX 写记录，其它格式截断
+    if format != HeaderFormat::V7 {
+        for (key, value) in [("uname", fields.uname), ("gname", fields.gname)] {
+            if value.len() > 32 && format == HeaderFormat::Pax {
+                records.extend(pax_record(key, value.as_bytes()));
+            }
+            let value = truncate(value, 32);
+            if key == "uname" { hdr.set_uname(value)? } else { hdr.set_gname(value)? }
+        }
+    }
+    hdr.set_size(size)?;
+    hdr.set_mtime(mtime)?;
+
+    if !records.is_empty() {
+        let base = fields.path.trim_end_matches('/').rsplit('/').next().unwrap_or("");
+        ext.extend(extension_block(TypeFlag::PaxExtended, &format!("PaxHeaders/{}", base), &records, fields.mtime, false)?);
+    }
+
+    if format == HeaderFormat::Gnu {
+        hdr.magic = *b"ustar ";
+        hdr.version = *b" \0";
+    }
+    let mut block = hdr.to_bytes();
+    if format == HeaderFormat::V7 {
+        block[257..265].fill(0);
+        write_checksum(&mut block);
+    }
+    Ok((ext, block))
+}
+
+/// 文件系统元数据中的权限位与修改时间（秒，可早于 1970 年）
+pub(crate) fn mode_and_mtime(md: &fs::Metadata) -> (u32, i64) {
+    let mtime = md.modified().map_or(0, unix_secs);
+    #[cfg(unix)]
+    let mode = std::os::unix::fs::PermissionsExt::mode(&md.permissions()) & 0o7777;
+    #[cfg(not(unix))]
+    let mode = if md.is_dir() { 0o755 } else { 0o644 };
+    (mode, mtime)
+}
+
+/// 文件系统元数据中的属主 uid / gid，非 Unix 平台为 0
+fn owner_ids(md: &fs::Metadata) -> (u64, u64) {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        (md.uid() as u64, md.gid() as u64)
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = md;
+        (0, 0)
+    }
+}
+
+/// FIFO 与设备文件的 typeflag 及设备号
+#[cfg(unix)]
+fn special_file(md: &fs::Metadata) -> Option<(u8, (u32, u32))> {
+    use std::os::unix::fs::{FileTypeExt, MetadataExt};
+    let file_type = md.file_type();
+    let device = || {
+        let rdev = md.rdev() as libc::dev_t;
+        (libc::major(rdev) as u32, libc::minor(rdev) as u32)
+    };
+    if file_type.is_fifo() {
+        Some((b'6', (0, 0)))
+    } else if file_type.is_char_device() {
+        Some((b'3', device()))
+    } else if file_type.is_block_device() {
+        Some((b'4', device()))
+    } else {
+        None
+    }
+}
+
+#[cfg(not(unix))]
+fn special_file(_md: &fs::Metadata) -> Option<(u8, (u32, u32))> {
+    None
+}
+
+#[cfg(unix)]
+fn is_socket(file_type: &fs::FileType) -> bool {
+    std::os::unix::fs::FileTypeExt::is_socket(file_type)
+}
+
+#[cfg(not(unix))]
+fn is_socket(_file_type: &fs::FileType) -> bool {
+    false
+}
+
+fn padding(size: u64) -> usize {
+    ((512 - size % 512) % 512) as usize
+}
+
+/// 在内存中构造单个完整条目（header + 按块对齐的数据）
+#[derive(Debug, Clone)]
+pub struct EntryBuilder {
+    path: String,
+    type_flag: u8,
+    mode: u32,
+    uid: u64,
+    gid: u64,
+    mtime: i64,
+    link_name: String,
+    device: (u32, u32),
+    data: Vec<u8>,
+}
+
+impl EntryBuilder {
+    pub fn new(path: &str, type_flag: u8) -> Self {
+        EntryBuilder {
+            path: path.to_string(),
+            type_flag,
+            mode: 0o644,
+            uid: 0,
+            gid: 0,
+            mtime: 0,
+            link_name: String::new(),
+            device: (0, 0),
+            data: Vec::new(),
+        }
+    }
+
+    pub fn file(path: &str, data: &[u8]) -> Self {
+        EntryBuilder::new(path, b'0').data(data)
+    }
+
+    pub fn dir(path: &str) -> Self {
+        EntryBuilder::new(path, b'5').mode(0o755)
+    }
+
+    pub fn symlink(path: &str, target: &str) -> Self {
+        EntryBuilder::new(path, b'2').mode(0o777).link_name(target)
+    }
+
+    pub fn mode(mut self, mode: u32) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    pub fn owner(mut self, uid: u64, gid: u64) -> Self {
+        self.uid = uid;
+        self.gid = gid;
+        self
+    }
+
+    /// 修改时间的秒数，早于 1970 年时为负
+    pub fn mtime(mut self, mtime: i64) -> Self {
+        self.mtime = mtime;
+        self
+    }
+
+    pub fn link_name(mut self, target: &str) -> Self {
+        self.link_name = target.to_string();
+        self
+    }
+
+    /// 字符 / 块设备的主、次设备号
+    pub fn device(mut self, major: u32, minor: u32) -> Self {
+        self.device = (major, minor);
+        self
+    }
+
+    pub fn data(mut self, data: &[u8]) -> Self {
+        self.data = data.to_vec();
+        self
+    }
+
+    fn fields(&self) -> HeaderFields<'_> {
+        HeaderFields {
+            path: &self.path,
+            type_flag: self.type_flag,
+            mode: self.mode,
+            uid: self.uid,
+            gid: self.gid,
+            uname: "",
+            gname: "",
+            mtime: self.mtime,
+            size: self.data.len() as u64,
+            link_name: &self.link_name,
+            device: self.device,
+        }
+    }
+
+    /// 只生成主 header 块（默认格式，不含扩展 header）
+    pub fn header(&self) -> io::Result<[u8; 512]> {
+        Ok(encode_header(&self.fields(), HeaderFormat::default())?.1)
+    }
+
+    /// 按默认格式生成完整条目字节
+    pub fn build(&self) -> io::Result<Vec<u8>> {
+        self.build_as(HeaderFormat::default())
+    }
+
+    /// 按指定格式生成完整条目字节（扩展 header + header + 数据）
+    pub fn build_as(&self, format: HeaderFormat) -> io::Result<Vec<u8>> {
+        let (ext, header) = encode_header(&self.fields(), format)?;
+        let mut out = Vec::with_capacity(ext.len() + 512 + self.data.len() + 511);
+        out.extend_from_slice(&ext);
+        out.extend_from_slice(&header);
+        out.extend_from_slice(&self.data);
+        out.resize(out.len() + padding(self.data.len() as u64), 0);
+        Ok(out)
+    }
+}
+
+/// 可复现模式下归档总长对齐的记录大小（blocking factor 20，与 GNU tar 默认值相同）
+pub const RECORD_SIZE: u64 = 20 * 512;
+
+/// 环境变量 `SOURCE_DATE_EPOCH` 中的时间戳
+pub fn source_date_epoch() -> Option<i64> {
+    std::env::var("SOURCE_DATE_EPOCH").ok()?.trim().parse().ok()
+}
+
+/// `append_dir_all` 遍历中的状态
+struct Walk {
+    /// 跟随符号链接时已进入的目录（规范化路径），用于检测循环
+    ancestors: Vec<PathBuf>,
+    /// (规则所在目录相对 src_dir 的路径, 模式)
+    rules: Vec<(String, Vec<ExcludePattern>)>,
+}
+
+/// 顺序写出 tar 归档
+pub struct TarBuilder<W: Write> {
+    writer: W,
+    format: HeaderFormat,
+    /// 可复现模式下 mtime 的上限
+    deterministic: Option<i64>,
+    symlinks: SymlinkPolicy,
+    exclude: Vec<ExcludePattern>,
+    tarignore: bool,
+    written: u64,
+    /// 已查过的用户名 / 组名，只在 Unix 上使用
+    #[cfg_attr(not(unix), allow(dead_code))]
+    user_names: HashMap<u64, String>,
+    #[cfg_attr(not(unix), allow(dead_code))]
+    group_names: HashMap<u64, String>,
+}
+
+impl<W: Write> TarBuilder<W> {
+    pub fn new(writer: W) -> Self {
+        Self::with_format(writer, HeaderFormat::default())
+    }
+
+    pub fn with_format(writer: W, format: HeaderFormat) -> Self {
+        TarBuilder { writer, format, deterministic: None, symlinks: SymlinkPolicy::default(),
+            exclude: Vec::new(), tarignore: false, written: 0, user_names: HashMap::new(), group_names: HashMap::new() }
+    }
+
+    /// 可复现模式：每个 header 的 uid / gid 置 0、用户名 / 组名清空，mtime 不晚于 `SOURCE_DATE_EPOCH`（未设置时为 0），
+    /// 归档总长补齐到 `RECORD_SIZE` 的倍数；`append_dir_all` 总是按名称排序遍历，与 read_dir 的顺序无关，
+    /// 其它条目的顺序由调用方决定
+    pub fn deterministic(self) -> Self {
+        self.deterministic_at(source_date_epoch().unwrap_or(0))
+    }
+
+    /// 与 `deterministic` 相同，mtime 上限由调用方指定
+    pub fn deterministic_at(mut self, max_mtime: i64) -> Self {
+        self.deterministic = Some(max_mtime);
+        self
+    }
+
+    /// `append_path` 与 `append_dir_all` 对符号链接的处理方式
+    pub fn symlinks(mut self, policy: SymlinkPolicy) -> Self {
+        self.symlinks = policy;
+        self
+    }
+
+    /// `append_dir_all` 跳过匹配任一模式的路径（目录连同其内容一起跳过）；
+    /// 不含 '/' 的模式匹配任意层级的文件名（如 `target/`、`*.o`），含 '/' 的模式相对 src_dir 匹配
+    pub fn exclude<S: AsRef<str>>(mut self, patterns: &[S]) -> Self {
+        self.exclude.extend(patterns.iter().filter_map(|p| ExcludePattern::new(p.as_ref())));
+        self
+    }
+
+    /// `append_dir_all` 读取各目录下的 `.tarignore`，其中的模式作用于该目录及其子目录
+    pub fn tarignore(mut self, enabled: bool) -> Self {
+        self.tarignore = enabled;
+        self
+    }
+
+    /// 按符号链接策略取得 fs_path 的元数据
+    fn entry_metadata(&self, fs_path: &Path) -> io::Result<fs::Metadata> {
+        let md = fs::symlink_metadata(fs_path)?;
+        if !md.file_type().is_symlink() || self.symlinks == SymlinkPolicy::Preserve {
+            return Ok(md);
+        }
+        match (fs::metadata(fs_path), self.symlinks) {
+            (Ok(target), SymlinkPolicy::Follow) => Ok(target),
+            (Ok(_), _) => Ok(md),
+            (Err(e), SymlinkPolicy::DenyDangling) if e.kind() == io::ErrorKind::NotFound => Err(io::Error::new(
+                io::ErrorKind::NotFound, format!("dangling symlink: {}", fs_path.display()))),
+            (Err(e), _) if e.kind() == io::ErrorKind::NotFound => Ok(md),
+            (Err(e), _) => Err(e),
+        }
+    }
+
+    fn write(&mut self, data: &[u8]) -> io::Result<()> {
+        self.writer.write_all(data)?;
+        self.written += data.len() as u64;
+        Ok(())
+    }
+
+    fn append_entry(&mut self, fields: &HeaderFields, body: &mut dyn Read) -> io::Result<()> {
+        let mut fields = *fields;
+        if let Some(max_mtime) = self.deterministic {
+            fields.uid = 0;
+            fields.gid = 0;
+            fields.uname = "";
+            fields.gname = "";
+            fields.mtime = fields.mtime.min(max_mtime);
+        }
+        let (ext, header) = encode_header(&fields, self.format)?;
+        self.write(&ext)?;
+        self.write(&header)?;
+        let n = io::copy(&mut body.take(fields.size), &mut self.writer)?;
+        self.written += n;
+        if n != fields.size {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, format!("short body for {}", fields.path)));
+        }
+        self.write(&vec![0u8; padding(n)])
+    }
+
+    /// 追加由 `EntryBuilder` 构造的条目
+    pub fn append(&mut self, entry: &EntryBuilder) -> io::Result<()> {
+        self.append_entry(&entry.fields(), &mut &entry.data[..])
+    }
+
+    /// 追加普通文件
+    pub fn append_data(&mut self, path: &str, mode: u32, mtime: i64, data: &[u8]) -> io::Result<()> {
+        let fields = HeaderFields { path, type_flag: b'0', mode, uid: 0, gid: 0, uname: "", gname: "", mtime, size: data.len() as u64, link_name: "", device: (0, 0) };
+        self.append_entry(&fields, &mut &data[..])
+    }
+
+    /// 追加目录，路径统一以 '/' 结尾
+    pub fn append_dir(&mut self, path: &str, mode: u32, mtime: i64) -> io::Result<()> {
+        let path = if path.ends_with('/') { path.to_string() } else { format!("{}/", path) };
+        let fields = HeaderFields { path: &path, type_flag: b'5', mode, uid: 0, gid: 0, uname: "", gname: "", mtime, size: 0, link_name: "", device: (0, 0) };
+        self.append_entry(&fields, &mut io::empty())
+    }
+
+    /// 追加符号链接
+    pub fn append_symlink(&mut self, path: &str, target: &str, mtime: i64) -> io::Result<()> {
+        let fields = HeaderFields { path, type_flag: b'2', mode: 0o777, uid: 0, gid: 0, uname: "", gname: "", mtime, size: 0, link_name: target, device: (0, 0) };
+        self.append_entry(&fields, &mut io::empty())
+    }
+
+    /// 按文件系统上的类型追加单个路径（不递归），符号链接按 `symlinks` 设置的策略处理；
+    /// 记录源文件的 uid / gid 及对应的用户名 / 组名（Unix）
+    pub fn append_path(&mut self, archive_path: &str, fs_path: &Path) -> io::Result<()> {
+        let md = self.entry_metadata(fs_path)?;
+        let (mode, mtime) = mode_and_mtime(&md);
+        let (uid, gid) = owner_ids(&md);
+        let (uname, gname) = self.owner_names(uid, gid);
+        let fields = HeaderFields { path: archive_path, type_flag: b'0', mode, uid, gid, uname: &uname, gname: &gname,
+            mtime, size: 0, link_name: "", device: (0, 0) };
+
+        if md.file_type().is_symlink() {
+            let target = fs::read_link(fs_path)?.to_string_lossy().into_owned();
+            let fields = HeaderFields { type_flag: b'2', mode: 0o777, link_name: &target, ..fields };
+            self.append_entry(&fields, &mut io::empty())
+        } else if md.is_dir() {
+            let path = if archive_path.ends_with('/') { archive_path.to_string() } else { format!("{}/", archive_path) };
+            self.append_entry(&HeaderFields { path: &path, type_flag: b'5', ..fields }, &mut io::empty())
+        } else if let Some((type_flag, device)) = special_file(&md) {
+            self.append_entry(&HeaderFields { type_flag, device, ..fields }, &mut io::empty())
+        } else {
+            self.append_entry(&HeaderFields { size: md.len(), ..fields }, &mut File::open(fs_path)?)
+        }
+    }
+
+    /// uid / gid 对应的用户名 / 组名，查询结果缓存在 builder 中
+    fn owner_names(&mut self, uid: u64, gid: u64) -> (String, String) {
+        #[cfg(unix)]
+        {
+            let uname = self.user_names.entry(uid)
+                .or_insert_with(|| crate::sys::user_name(uid as u32).unwrap_or_default()).clone();
+            let gname = self.group_names.entry(gid)
+                .or_insert_with(|| crate::sys::group_name(gid as u32).unwrap_or_default()).clone();
+            (uname, gname)
+        }
+        #[cfg(not(unix))]
+        {
+            let _ = (uid, gid);
+            (String::new(), String::new())
+        }
+    }
+
+    /// 递归追加 src_dir 下的全部内容，归档内路径为 prefix/<相对路径>，同一目录下按名称排序
+    ///
+    /// prefix 非空时先追加 src_dir 本身对应的目录条目；空目录、FIFO 与设备文件按原类型保留，
+    /// socket 与匹配 `exclude` / `.tarignore` 的路径被跳过；符号链接按 `symlinks` 设置的策略处理，
+    /// 跟随时指向上层目录的链接视为循环并报错
+    pub fn append_dir_all(&mut self, src_dir: &Path, prefix: &str) -> io::Result<()> {
+        let prefix = prefix.trim_end_matches('/');
+        if !prefix.is_empty() {
+            self.append_path(prefix, src_dir)?;
+        }
+        let mut walk = Walk { ancestors: Vec::new(), rules: vec![(String::new(), self.exclude.clone())] };
+        self.append_tree(src_dir, prefix, "", &mut walk)
+    }
+
+    fn excluded(rules: &[(String, Vec<ExcludePattern>)], rel: &str, is_dir: bool) -> bool {
+        rules.iter().any(|(base, patterns)| {
+            let rel = if base.is_empty() { rel } else { &rel[base.len() + 1..] };
+            patterns.iter().any(|p| p.matches(rel, is_dir))
+        })
+    }
+
+    /// rel 为 dir 相对 src_dir 的路径
+    fn append_tree(&mut self, dir: &Path, prefix: &str, rel: &str, walk: &mut Walk) -> io::Result<()> {
+        if self.symlinks == SymlinkPolicy::Follow {
+            let real = fs::canonicalize(dir)?;
+            if walk.ancestors.contains(&real) {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, format!("symlink loop at {}", dir.display())));
+            }
+            walk.ancestors.push(real);
+        }
+        let ignore_file = dir.join(TARIGNORE);
+        let has_rules = self.tarignore && ignore_file.is_file();
+        if has_rules {
+            walk.rules.push((rel.to_string(), ExcludePattern::parse_list(&fs::read_to_string(ignore_file)?)));
+        }
+        let mut children: Vec<_> = fs::read_dir(dir)?.collect::<io::Result<_>>()?;
+        children.sort_by_key(|c| c.file_name());
+        for child in children {
+            let file_name = child.file_name().to_string_lossy().into_owned();
+            let name = if prefix.is_empty() { file_name.clone() } else { format!("{}/{}", prefix, file_name) };
+            let child_rel = if rel.is_empty() { file_name } else { format!("{}/{}", rel, file_name) };
+            let path = child.path();
+            let md = self.entry_metadata(&path)?;
+            if is_socket(&md.file_type()) || Self::excluded(&walk.rules, &child_rel, md.is_dir()) {
+                continue;
+            }
+            self.append_path(&name, &path)?;
+            if md.is_dir() {
+                self.append_tree(&path, &name, &child_rel, walk)?;
+            }
+        }
+        if has_rules {
+            walk.rules.pop();
+        }
+        if self.symlinks == SymlinkPolicy::Follow {
+            walk.ancestors.pop();
+        }
+        Ok(())
+    }
+
+    /// 写出结束标记并返回底层 writer
+    pub fn finish(mut self) -> io::Result<W> {
+        self.write(&[0u8; 1024])?;
+        if self.deterministic.is_some() {
+            let pad = self.written.next_multiple_of(RECORD_SIZE) - self.written;
+            self.write(&vec![0u8; pad as usize])?;
+        }
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
@@ -0,0 +1,32 @@
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use crate::error::PtError;
+
+/// 协作式取消标记，克隆得到的标记共享同一状态，可在其他线程调用 `cancel`
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken {
+    flag: Arc<AtomicBool>,
+}
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.flag.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.flag.load(Ordering::Relaxed)
+    }
+
+    /// 已取消时返回 `PtError::Cancelled`
+    pub fn check(&self) -> io::Result<()> {
+        if self.is_cancelled() {
+            return Err(PtError::Cancelled.into());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,193 @@
+use std::collections::BTreeMap;
+use std::io::{self, Read};
+use sha2::{Digest, Sha256, Sha512};
+use crate::base::{try_into_tarfile, BodyReader, ImageInfo, TarFile, TarImage};
+use crate::pax::PaxRecords;
+
+/// 条目内容摘要算法
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DigestAlgorithm {
+    Sha256,
+    Sha512,
+}
+
+impl DigestAlgorithm {
+    /// 小写名称，如 "sha256"
+    pub fn name(&self) -> &'static str {
+        match self {
+            DigestAlgorithm::Sha256 => "sha256",
+            DigestAlgorithm::Sha512 => "sha512",
+        }
+    }
+
+    /// `name` 的逆操作，不区分大小写
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "sha256" => Some(DigestAlgorithm::Sha256),
+            "sha512" => Some(DigestAlgorithm::Sha512),
+            _ => None,
+        }
+    }
+}
+
+/// 摘要的小写十六进制表示
+pub fn to_hex(digest: &[u8]) -> String {
+    digest.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn hash_reader<D: Digest, R: Read>(mut reader: R) -> io::Result<Vec<u8>> {
+    let mut hasher = D::new();
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        let n = reader.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(hasher.finalize().to_vec())
+}
+
+/// PAX 记录中声明的数据区摘要
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BodyDigest {
+    Crc32(u32),
+    Sha256([u8; 32]),
+}
+
+/// 数据区完整性校验结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IntegrityStatus {
+    /// 条目没有可识别的摘要记录
+    Unchecked,
+    /// 所有摘要都与内容一致
+    Ok,
+    /// 与内容不一致的记录键
+    Mismatch(String),
+}
+
+fn parse_hex(s: &str) -> Option<Vec<u8>> {
+    let s = s.trim();
+    let s = s.strip_prefix("0x").unwrap_or(s);
+    if !s.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..s.len()).step_by(2).map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok()).collect()
+}
+
+/// 识别摘要记录：键的最后一段为 `crc32` 或 `sha256`（如 `SCHILY.crc32`），值为十六进制
+pub fn body_digests(records: &PaxRecords) -> Vec<(String, BodyDigest)> {
+    let mut out = Vec::new();
+    for (key, value) in records {
+        let kind = key.rsplit('.').next().unwrap_or("").to_ascii_lowercase();
+        let Some(bytes) = std::str::from_utf8(value).ok().and_then(parse_hex) else { continue };
+        let digest = match (kind.as_str(), bytes.len()) {
+            ("crc32", 4) => BodyDigest::Crc32(u32::from_be_bytes(bytes.try_into().unwrap())),
+            ("sha256", 32) => BodyDigest::Sha256(bytes.try_into().unwrap()),
+            _ => continue,
+        };
+        out.push((key.clone(), digest));
+    }
+    out
+}
+
+/// 边读边计算摘要，读到末尾时与声明的值比较，不一致时返回 InvalidData
+pub struct VerifyingReader<R: Read> {
+    inner: R,
+    digests: Vec<(String, BodyDigest)>,
+    crc: crc32fast::Hasher,
+    sha: Sha256,
+    finished: bool,
+}
+
+impl<R: Read> VerifyingReader<R> {
+    pub fn new(inner: R, digests: Vec<(String, BodyDigest)>) -> Self {
+        VerifyingReader { inner, digests, crc: crc32fast::Hasher::new(), sha: Sha256::new(), finished: false }
+    }
+
+    /// 与声明的摘要逐一比较
+    fn status(&mut self) -> IntegrityStatus {
+        if self.digests.is_empty() {
+            return IntegrityStatus::Unchecked;
+        }
+        let crc = self.crc.clone().finalize();
+        let sha: [u8; 32] = self.sha.clone().finalize().into();
+        for (key, digest) in &self.digests {
+            let ok = match digest {
+                BodyDigest::Crc32(want) => *want == crc,
+                BodyDigest::Sha256(want) => *want == sha,
+            };
+            if !ok {
+                return IntegrityStatus::Mismatch(key.clone());
+            }
+        }
+        IntegrityStatus::Ok
+    }
+}
+
+impl<R: Read> Read for VerifyingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.crc.update(&buf[..n]);
+        self.sha.update(&buf[..n]);
+        if n == 0 && !buf.is_empty() && !self.finished {
+            self.finished = true;
+            if let IntegrityStatus::Mismatch(key) = self.status() {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, format!("body does not match {}", key)));
+            }
+        }
+        Ok(n)
+    }
+}
+
+impl TarFile {
+    /// 流式计算数据区摘要
+    pub fn digest(&self, algo: DigestAlgorithm) -> io::Result<Vec<u8>> {
+        let reader = BodyReader::new(self);
+        match algo {
+            DigestAlgorithm::Sha256 => hash_reader::<Sha256, _>(reader),
+            DigestAlgorithm::Sha512 => hash_reader::<Sha512, _>(reader),
+        }
+    }
+
+    /// 条目 PAX 记录中声明的数据区摘要
+    pub fn body_digests(&self) -> Vec<(String, BodyDigest)> {
+        body_digests(self.pax_records())
+    }
+
+    /// 读取数据区时校验摘要的 reader
+    pub fn verified_reader(&self) -> VerifyingReader<BodyReader<'_>> {
+        VerifyingReader::new(BodyReader::new(self), self.body_digests())
+    }
+
+    /// 读取整个数据区并与声明的摘要比较
+    pub fn verify_body(&self) -> io::Result<IntegrityStatus> {
+        let digests = self.body_digests();
+        if digests.is_empty() {
+            return Ok(IntegrityStatus::Unchecked);
+        }
+        let mut reader = VerifyingReader::new(BodyReader::new(self), digests);
+        // 由调用方取结果，读到末尾时不报错
+        reader.finished = true;
+        io::copy(&mut reader, &mut io::sink())?;
+        Ok(reader.status())
+    }
+}
+
+impl TarImage {
+    /// 计算所有普通文件的内容摘要，路径 -> 摘要，同名条目以最后一个为准
+    pub fn digest_manifest(&mut self, algo: DigestAlgorithm) -> io::Result<BTreeMap<String, Vec<u8>>> {
+        let mut manifest = BTreeMap::new();
+        self.for_each_entry(|file| {
+            let tar_file = try_into_tarfile(file)?;
+            let meta = tar_file.meta();
+            if meta.is_file() {
+                manifest.insert(meta.path, tar_file.digest(algo)?);
+            } else {
+                manifest.remove(&meta.path);
+            }
+            Ok(())
+        })?;
+        Ok(manifest)
+    }
+}
@@ -0,0 +1,78 @@
+use std::collections::HashMap;
+use std::io;
+use unicode_normalization::UnicodeNormalization;
+use crate::error::PtError;
+
+/// 两个不同的归档路径在大小写不敏感的文件系统上落到同一目标时的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CollisionPolicy {
+    /// 返回 PtError::NameCollision
+    Error,
+    /// 给后出现的条目加 `~N` 后缀
+    Rename,
+    /// 后出现的条目覆盖先出现的（与普通 tar 解包一致）
+    LastWins,
+}
+
+/// 按文件系统的比较规则折叠路径：NFC 规范化后转小写
+pub fn fold_path(path: &str) -> String {
+    path.nfc().collect::<String>().to_lowercase()
+}
+
+/// 单次解包过程中的路径冲突检测
+pub(crate) struct CollisionTracker {
+    policy: CollisionPolicy,
+    enabled: bool,
+    /// 折叠后的路径 -> 实际使用的路径
+    seen: HashMap<String, String>,
+    /// 重命名过的路径前缀：(原路径, 新路径)，用于改写其子条目
+    renamed: Vec<(String, String)>,
+}
+
+impl CollisionTracker {
+    pub(crate) fn new(policy: CollisionPolicy, case_insensitive: bool) -> Self {
+        CollisionTracker { policy, enabled: case_insensitive, seen: HashMap::new(), renamed: Vec::new() }
+    }
+
+    /// 返回条目实际应写入的相对路径
+    pub(crate) fn resolve(&mut self, path: &str) -> io::Result<String> {
+        if !self.enabled {
+            return Ok(path.to_string());
+        }
+        let path = path.trim_end_matches('/');
+        let mut actual = path.to_string();
+        for (from, to) in self.renamed.iter().rev() {
+            if let Some(rest) = path.strip_prefix(from.as_str()).filter(|r| r.starts_with('/')) {
+                actual = format!("{}{}", to, rest);
+                break;
+            }
+        }
+
+        let key = fold_path(&actual);
+        match self.seen.get(&key) {
+            // 完全相同的路径重复出现是正常的 tar 语义
+            Some(existing) if *existing != actual => match self.policy {
+                CollisionPolicy::LastWins => {}
+                CollisionPolicy::Error => {
+                    return Err(PtError::NameCollision { path: path.to_string(), existing: existing.clone() }.into());
+                }
+                CollisionPolicy::Rename => {
+                    let mut n = 1;
+                    let renamed = loop {
+                        let candidate = format!("{}~{}", actual, n);
+                        if !self.seen.contains_key(&fold_path(&candidate)) {
+                            break candidate;
+                        }
+                        n += 1;
+                    };
+                    self.renamed.push((actual.clone(), renamed.clone()));
+                    self.seen.insert(fold_path(&renamed), renamed.clone());
+                    return Ok(renamed);
+                }
+            },
+            _ => {}
+        }
+        self.seen.insert(key, actual.clone());
+        Ok(actual)
+    }
+}
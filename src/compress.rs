@@ -0,0 +1,64 @@
+use std::io::{self, Read};
+use crate::base::{BodyReader, TarFile};
+
+/// 条目数据区的压缩格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    Gzip,
+    Zstd,
+    Xz,
+}
+
+impl Compression {
+    /// 根据开头的魔数判断压缩格式
+    pub fn sniff(magic: &[u8]) -> Self {
+        if magic.starts_with(&[0x1f, 0x8b]) {
+            Compression::Gzip
+        } else if magic.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+            Compression::Zstd
+        } else if magic.starts_with(&[0xfd, b'7', b'z', b'X', b'Z', 0x00]) {
+            Compression::Xz
+        } else {
+            Compression::None
+        }
+    }
+}
+
+#[allow(dead_code)]
+fn unsupported(name: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::Unsupported, format!("{} support is not enabled (feature `{}`)", name, name))
+}
+
+/// 对任意 reader 套上与 compression 对应的解码器
+pub fn decoder<'a, R: Read + 'a>(reader: R, compression: Compression) -> io::Result<Box<dyn Read + 'a>> {
+    match compression {
+        Compression::None => Ok(Box::new(reader)),
+        #[cfg(feature = "gzip")]
+        Compression::Gzip => Ok(Box::new(flate2::read::MultiGzDecoder::new(reader))),
+        #[cfg(not(feature = "gzip"))]
+        Compression::Gzip => Err(unsupported("gzip")),
+        #[cfg(feature = "zstd")]
+        Compression::Zstd => Ok(Box::new(zstd::stream::read::Decoder::new(reader)?)),
+        #[cfg(not(feature = "zstd"))]
+        Compression::Zstd => Err(unsupported("zstd")),
+        #[cfg(feature = "xz")]
+        Compression::Xz => Ok(Box::new(xz2::read::XzDecoder::new_multi_decoder(reader))),
+        #[cfg(not(feature = "xz"))]
+        Compression::Xz => Err(unsupported("xz")),
+    }
+}
+
+impl TarFile {
+    /// 探测条目自身内容的压缩格式
+    pub fn compression(&self) -> io::Result<Compression> {
+        let mut magic = [0u8; 6];
+        let n = self.read_body_at(0, &mut magic)?;
+        Ok(Compression::sniff(&magic[..n]))
+    }
+
+    /// 返回解压后的内容 reader；未压缩的条目直接返回原始数据
+    pub fn decompressed_reader(&self) -> io::Result<Box<dyn Read + '_>> {
+        decoder(BodyReader::new(self), self.compression()?)
+    }
+}
@@ -0,0 +1,236 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use sha2::{Digest, Sha256};
+use crate::base::{lock_image, try_into_tarfile, ImageInfo, TarFile, TarImage};
+use crate::meta::EntryMeta;
+use crate::path::sanitize_path;
+
+/// 条目之间的差异项
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change {
+    Type,
+    Size,
+    Mode,
+    Mtime,
+    LinkName,
+    Content,
+}
+
+/// 两个归档中同一路径的条目比较结果
+#[derive(Debug, Clone)]
+pub struct ModifiedEntry {
+    pub path: String,
+    pub old: EntryMeta,
+    pub new: EntryMeta,
+    pub changes: Vec<Change>,
+}
+
+/// 归档比较结果，各列表按路径排序
+#[derive(Debug, Clone, Default)]
+pub struct ArchiveDiff {
+    pub added: Vec<EntryMeta>,
+    pub removed: Vec<EntryMeta>,
+    pub modified: Vec<ModifiedEntry>,
+}
+
+impl ArchiveDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.modified.is_empty()
+    }
+}
+
+/// 与磁盘比较时一个条目的差异
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiskDifference {
+    pub path: String,
+    pub changes: Vec<Change>,
+}
+
+/// `compare_with` 的结果，各列表按路径排序
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DiskDiff {
+    /// 磁盘上不存在的条目路径
+    pub missing: Vec<String>,
+    pub differences: Vec<DiskDifference>,
+}
+
+impl DiskDiff {
+    pub fn is_empty(&self) -> bool {
+        self.missing.is_empty() && self.differences.is_empty()
+    }
+}
+
+struct Snapshot {
+    meta: EntryMeta,
+    hash: Option<[u8; 32]>,
+}
+
+/// 计算条目数据区的 SHA-256
+pub(crate) fn sha256_body(file: &TarFile) -> io::Result<[u8; 32]> {
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 64 * 1024];
+    let mut pos = 0u64;
+    loop {
+        let n = file.read_body_at(pos, &mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+        pos += n as u64;
+    }
+    Ok(hasher.finalize().into())
+}
+
+/// 计算磁盘文件内容的 SHA-256
+fn sha256_file(path: &Path) -> io::Result<[u8; 32]> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut fs::File::open(path)?, &mut hasher)?;
+    Ok(hasher.finalize().into())
+}
+
+/// 磁盘上的文件类型是否与条目类型一致；硬链接按普通文件比较
+fn same_kind(meta: &EntryMeta, file_type: fs::FileType) -> bool {
+    match meta.type_flag {
+        '0' | '\0' | '7' | '1' => file_type.is_file(),
+        '5' | 'D' => file_type.is_dir(),
+        '2' => file_type.is_symlink(),
+        #[cfg(unix)]
+        '3' => std::os::unix::fs::FileTypeExt::is_char_device(&file_type),
+        #[cfg(unix)]
+        '4' => std::os::unix::fs::FileTypeExt::is_block_device(&file_type),
+        #[cfg(unix)]
+        '6' => std::os::unix::fs::FileTypeExt::is_fifo(&file_type),
+        _ => true,
+    }
+}
+
+fn compare_disk(file: &TarFile, meta: &EntryMeta, target: &Path, check_content: bool) -> io::Result<Vec<Change>> {
+    let md = fs::symlink_metadata(target)?;
+    if !same_kind(meta, md.file_type()) {
+        return Ok(vec![Change::Type]);
+    }
+    let mut changes = Vec::new();
+    let is_file = md.is_file();
+    let is_symlink = md.file_type().is_symlink();
+    // 硬链接条目没有数据区，大小与内容以磁盘上的文件为准
+    let has_body = meta.type_flag != '1';
+    if is_file && has_body && md.len() != meta.size {
+        changes.push(Change::Size);
+    }
+    #[cfg(unix)]
+    if !is_symlink && std::os::unix::fs::PermissionsExt::mode(&md.permissions()) & 0o7777 != meta.mode & 0o7777 {
+        changes.push(Change::Mode);
+    }
+    let mtime = md.modified().map_or(0, crate::meta::unix_secs);
+    if !is_symlink && mtime != meta.mtime {
+        changes.push(Change::Mtime);
+    }
+    if is_symlink && fs::read_link(target)?.to_string_lossy() != meta.link_name {
+        changes.push(Change::LinkName);
+    }
+    if check_content && is_file && has_body && !changes.contains(&Change::Size) && sha256_file(target)? != sha256_body(file)? {
+        changes.push(Change::Content);
+    }
+    Ok(changes)
+}
+
+impl TarImage {
+    /// 相当于 `tar -d`：逐条目与 dir 下对应路径比较类型、大小、权限、修改时间、链接目标与内容
+    pub fn compare_with(&mut self, dir: &Path) -> io::Result<DiskDiff> {
+        self.compare_with_options(dir, true)
+    }
+
+    /// 与 `compare_with` 相同，check_content 为 false 时不读取内容计算哈希
+    pub fn compare_with_options(&mut self, dir: &Path, check_content: bool) -> io::Result<DiskDiff> {
+        let mut entries = BTreeMap::new();
+        self.for_each_entry(|file| {
+            let tar_file = try_into_tarfile(file)?;
+            let meta = tar_file.meta();
+            entries.insert(sanitize_path(&meta.path)?, (tar_file, meta));
+            Ok(())
+        })?;
+        let mut result = DiskDiff::default();
+        for (path, (file, meta)) in entries {
+            let target = path.split('/').filter(|c| !c.is_empty()).fold(dir.to_path_buf(), |p, c| p.join(c));
+            match compare_disk(&file, &meta, &target, check_content) {
+                Ok(changes) if changes.is_empty() => {}
+                Ok(changes) => result.differences.push(DiskDifference { path: meta.path, changes }),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => result.missing.push(meta.path),
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(result)
+    }
+}
+
+/// 收集镜像中的所有条目，同名条目以最后出现的为准（与 tar 解包语义一致）
+fn snapshot(img: &mut TarImage) -> io::Result<BTreeMap<String, Snapshot>> {
+    let mut entries = BTreeMap::new();
+    img.for_each_entry(|file| {
+        let tar_file = try_into_tarfile(file)?;
+        let meta = tar_file.meta();
+        let hash = if meta.is_file() { Some(sha256_body(&tar_file)?) } else { None };
+        entries.insert(meta.path.clone(), Snapshot { meta, hash });
+        Ok(())
+    })?;
+    Ok(entries)
+}
+
+fn compare(old: &Snapshot, new: &Snapshot) -> Vec<Change> {
+    let mut changes = Vec::new();
+    if old.meta.type_flag != new.meta.type_flag {
+        changes.push(Change::Type);
+    }
+    if old.meta.size != new.meta.size {
+        changes.push(Change::Size);
+    }
+    if old.meta.mode != new.meta.mode {
+        changes.push(Change::Mode);
+    }
+    if old.meta.mtime != new.meta.mtime {
+        changes.push(Change::Mtime);
+    }
+    if old.meta.link_name != new.meta.link_name {
+        changes.push(Change::LinkName);
+    }
+    if old.hash != new.hash {
+        changes.push(Change::Content);
+    }
+    changes
+}
+
+/// 逐条目比较两个镜像（路径、大小、权限、修改时间、内容哈希）
+pub fn diff(a: &mut TarImage, b: &mut TarImage) -> io::Result<ArchiveDiff> {
+    let old = snapshot(a)?;
+    let mut new = snapshot(b)?;
+    let mut result = ArchiveDiff::default();
+
+    for (path, old_entry) in old {
+        match new.remove(&path) {
+            Some(new_entry) => {
+                let changes = compare(&old_entry, &new_entry);
+                if !changes.is_empty() {
+                    result.modified.push(ModifiedEntry {
+                        path,
+                        old: old_entry.meta,
+                        new: new_entry.meta,
+                        changes,
+                    });
+                }
+            }
+            None => result.removed.push(old_entry.meta),
+        }
+    }
+    result.added = new.into_values().map(|s| s.meta).collect();
+    Ok(result)
+}
+
+/// 按路径打开两个镜像并比较
+pub fn diff_paths(a: &str, b: &str) -> io::Result<ArchiveDiff> {
+    let a = TarImage::open(a)?;
+    let b = TarImage::open(b)?;
+    let result = diff(&mut *lock_image(&a)?, &mut *lock_image(&b)?);
+    result
+}
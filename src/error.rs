@@ -0,0 +1,62 @@
+use std::fmt;
+use std::io;
+
+/// 库内可区分的错误类型，统一包装在 io::Error 中返回，可通过 `get_ref()` / `downcast` 取出
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PtError {
+    /// 条目路径包含 Windows 保留的设备名（CON、NUL、COM1 等）
+    ReservedName { path: String, component: String },
+    /// 两个不同的条目路径在大小写不敏感的文件系统上指向同一目标
+    NameCollision { path: String, existing: String },
+    /// 条目路径是绝对路径、含 ".."，或经由符号链接指向解包目录之外
+    UnsafePath { path: String },
+    /// 超出 `Limits` 中设置的资源上限
+    LimitExceeded { limit: &'static str, value: u64, max: u64 },
+    /// 操作被 `CancelToken` 取消
+    Cancelled,
+    /// 严格模式下 offset 处的 header 不符合 ustar 格式
+    MalformedHeader { offset: u64, reason: String },
+}
+
+impl fmt::Display for PtError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PtError::ReservedName { path, component } => {
+                write!(f, "entry {} uses reserved device name {}", path, component)
+            }
+            PtError::NameCollision { path, existing } => {
+                write!(f, "entry {} collides with {} on a case-insensitive filesystem", path, existing)
+            }
+            PtError::UnsafePath { path } => write!(f, "entry {} escapes the extraction directory", path),
+            PtError::LimitExceeded { limit, value, max } => write!(f, "{} limit exceeded: {} > {}", limit, value, max),
+            PtError::Cancelled => write!(f, "operation cancelled"),
+            PtError::MalformedHeader { offset, reason } => write!(f, "malformed header at offset {}: {}", offset, reason),
+        }
+    }
+}
+
+impl std::error::Error for PtError {}
+
+impl PtError {
+    fn kind(&self) -> io::ErrorKind {
+        match self {
+            PtError::ReservedName { .. } => io::ErrorKind::InvalidInput,
+            PtError::NameCollision { .. } => io::ErrorKind::AlreadyExists,
+            PtError::UnsafePath { .. } => io::ErrorKind::InvalidInput,
+            PtError::LimitExceeded { .. } => io::ErrorKind::InvalidData,
+            PtError::Cancelled => io::ErrorKind::Interrupted,
+            PtError::MalformedHeader { .. } => io::ErrorKind::InvalidData,
+        }
+    }
+}
+
+impl From<PtError> for io::Error {
+    fn from(e: PtError) -> Self {
+        io::Error::new(e.kind(), e)
+    }
+}
+
+/// 从 io::Error 中取出 PtError
+pub fn as_pt_error(e: &io::Error) -> Option<&PtError> {
+    e.get_ref().and_then(|inner| inner.downcast_ref::<PtError>())
+}
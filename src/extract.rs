@@ -0,0 +1,960 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use crate::base::{is_vendor_extension, lock_image, try_into_tarfile, ImageInfo, TarFile, TarImage};
+use crate::cancel::CancelToken;
+use crate::error::PtError;
+use crate::idmap::IdMap;
+use crate::limits::{self, Limits};
+use crate::collision::{CollisionPolicy, CollisionTracker};
+use crate::meta::{EntryMeta, ImplicitDirs};
+use crate::path::{os_from_bytes, sanitize_path, sanitize_path_bytes};
+use crate::pax::{merge_globals, parse_pax_records, pax_record, pax_u64, PaxRecords};
+use crate::progress::{NoProgress, Progress};
+#[cfg(unix)]
+use crate::sandbox::Sandbox;
+use crate::tar::{block_align, parse_gnu_sparse_block, TarHeader, TypeFlag};
+
+/// 把条目数据区完整写入 writer，返回写入的字节数
+pub(crate) fn copy_body<W: Write>(file: &TarFile, writer: &mut W) -> io::Result<u64> {
+    let mut buf = vec![0u8; 64 * 1024];
+    let mut pos = 0u64;
+    loop {
+        let n = file.read_body_at(pos, &mut buf)?;
+        if n == 0 {
+            break;
+        }
+        writer.write_all(&buf[..n])?;
+        pos += n as u64;
+    }
+    Ok(pos)
+}
+
+/// 写出普通文件的内容：旧式 GNU 稀疏文件（'S'）的各段写到映射中的偏移，其余部分留作空洞，
+/// 最后把文件长度设为 realsize
+fn write_file_body(file: &TarFile, out: &mut fs::File) -> io::Result<()> {
+    if file.get_type_flag() != 'S' {
+        return copy_body(file, out).map(drop);
+    }
+    let mut buf = vec![0u8; 64 * 1024];
+    let mut pos = 0u64;
+    for chunk in file.sparse_map() {
+        out.seek(SeekFrom::Start(chunk.offset))?;
+        let end = pos.checked_add(chunk.length)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "sparse map length overflows"))?;
+        while pos < end {
+            let want = buf.len().min((end - pos) as usize);
+            let n = file.read_body_at(pos, &mut buf[..want])?;
+            if n == 0 {
+                return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "sparse map exceeds stored data"));
+            }
+            out.write_all(&buf[..n])?;
+            pos += n as u64;
+        }
+    }
+    out.set_len(file.real_size())
+}
+
+/// 解包目标已存在时的处理方式；已存在的目录遇到目录条目时总是合并，不受影响
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OverwritePolicy {
+    /// 替换已存在的文件（与普通 tar 解包一致）
+    #[default]
+    Overwrite,
+    /// 保留已存在的文件，跳过该条目（类似 `tar --skip-old-files`）
+    Skip,
+    /// 返回 AlreadyExists（类似 `tar -k`）
+    Error,
+}
+
+/// 解包时对一个条目采取的动作
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExtractAction {
+    /// 目标不存在，新建
+    Create,
+    /// 目标已存在，被替换；目录为合并并更新属性
+    Overwrite,
+    /// 目标已存在，按 `OverwritePolicy::Skip` 保留
+    Skip,
+}
+
+/// 解包报告中的一项
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExtractRecord {
+    /// 相对 dest 的路径，已经过大小写冲突改名
+    pub path: String,
+    pub target: PathBuf,
+    pub type_flag: char,
+    pub action: ExtractAction,
+}
+
+/// 解包选项
+#[derive(Debug, Clone)]
+pub struct ExtractOptions {
+    /// 按 header 中的 mode 设置文件和目录权限（含 setuid/setgid/sticky 位，仅 Unix）
+    pub preserve_permissions: bool,
+    /// 以 root 运行时恢复属主（类似 `tar -p --same-owner`），非 root 时忽略
+    pub preserve_ownership: bool,
+    /// 恢复属主时只使用数字 uid/gid，不按 uname/gname 查找本机账户
+    pub numeric_owner: bool,
+    /// 用 setxattr 恢复 PAX `SCHILY.xattr.*` 记录中的扩展属性（仅 Linux）
+    pub preserve_xattrs: bool,
+    /// 恢复 PAX `SCHILY.acl.*` 记录中的 POSIX ACL（仅 Linux）
+    pub preserve_acls: bool,
+    /// 目标文件系统是否大小写不敏感，默认 Windows / macOS 为 true
+    pub case_insensitive: bool,
+    /// 大小写不敏感时路径冲突的处理方式
+    pub collision_policy: CollisionPolicy,
+    /// 不检查路径穿越：允许绝对路径、".." 以及经由符号链接写到目标目录之外（类似 `tar -P`）
+    pub allow_unsafe_paths: bool,
+    /// 恢复修改时间与 PAX `atime`，PAX 记录带小数时精确到纳秒（Unix 下使用 utimensat）
+    pub preserve_times: bool,
+    /// 恢复属主前按映射表转换 uid/gid（如为 user namespace 整体平移 100000），
+    /// 同时映射 ACL 中的数字 id；设置后 uname/gname 不再用于查找本机账户
+    pub id_map: Option<IdMap>,
+    /// 为归档中没有记录的上级目录合成条目：按 0755 创建并使用引出它的条目的属主，
+    /// 而不是由 umask 决定权限；已存在的目录不受影响
+    pub implicit_dirs: bool,
+    /// 目标已存在时的处理方式，包括本次解包中先写出的同名条目
+    pub overwrite: OverwritePolicy,
+    /// 替换已存在的文件前先删除它（类似 `tar -U`）：不会写穿硬链接，也能替换只读文件；
+    /// 已存在的空目录会被删除，非空目录返回错误
+    pub unlink_first: bool,
+    /// 只计算每个条目会被如何处理，不创建、修改或删除任何文件；结果见 `extract_all_report`
+    pub dry_run: bool,
+    /// 沙箱模式（仅 Unix）：dest 只打开一次，条目路径相对该目录句柄逐级解析，从不跟随符号链接，
+    /// 属性也通过句柄设置，并发替换目录也无法让写入逃出 dest。比默认检查更严格：dest 中
+    /// 已有的符号链接（即使指向 dest 之内）也不能作为上级目录，且忽略 `allow_unsafe_paths`
+    pub sandboxed: bool,
+    /// `unpack_stream` 的资源上限，边暂存边检查；从镜像解包时以镜像的 `set_limits` 为准
+    pub limits: Limits,
+    /// `unpack_stream` 的取消标记，每个条目及每块数据前检查；从镜像解包时以镜像的 `set_cancel_token` 为准
+    pub cancel: Option<CancelToken>,
+}
+
+impl Default for ExtractOptions {
+    fn default() -> Self {
+        ExtractOptions {
+            preserve_permissions: true,
+            preserve_ownership: false,
+            numeric_owner: false,
+            preserve_xattrs: false,
+            preserve_acls: false,
+            case_insensitive: cfg!(any(windows, target_os = "macos")),
+            collision_policy: CollisionPolicy::Error,
+            allow_unsafe_paths: false,
+            preserve_times: false,
+            id_map: None,
+            implicit_dirs: false,
+            overwrite: OverwritePolicy::Overwrite,
+            unlink_first: false,
+            dry_run: false,
+            sandboxed: false,
+            limits: Limits::default(),
+            cancel: None,
+        }
+    }
+}
+
+/// 恢复属主：优先按 uname/gname 查找本机账户，查不到时回退到数字 id
+#[cfg(unix)]
+fn set_owner(target: &Path, meta: &EntryMeta, options: &ExtractOptions) -> io::Result<()> {
+    match owner_ids(meta, options)? {
+        Some((uid, gid)) => crate::sys::lchown(target, uid, gid),
+        None => Ok(()),
+    }
+}
+
+/// 要恢复的 (uid, gid)；不恢复属主时为 None
+#[cfg(unix)]
+fn owner_ids(meta: &EntryMeta, options: &ExtractOptions) -> io::Result<Option<(u32, u32)>> {
+    use crate::sys;
+    if !options.preserve_ownership || !sys::is_root() {
+        return Ok(None);
+    }
+    if let Some(map) = &options.id_map {
+        return Ok(Some((map_id(meta.uid, |id| map.map_uid(id))?, map_id(meta.gid, |id| map.map_gid(id))?)));
+    }
+    let by_name = |name: &str, lookup: fn(&str) -> Option<u32>| {
+        if options.numeric_owner || name.is_empty() { None } else { lookup(name) }
+    };
+    let uid = by_name(&meta.uname, sys::lookup_uid).unwrap_or(meta.uid as u32);
+    let gid = by_name(&meta.gname, sys::lookup_gid).unwrap_or(meta.gid as u32);
+    Ok(Some((uid, gid)))
+}
+
+/// 归档中的 id 超出 u32 时视为未映射
+#[cfg(unix)]
+fn map_id(id: u64, map: impl Fn(u32) -> io::Result<u32>) -> io::Result<u32> {
+    let id = u32::try_from(id).map_err(|_| io::Error::new(io::ErrorKind::InvalidData, format!("id {} is not mapped", id)))?;
+    map(id)
+}
+
+#[cfg(unix)]
+fn set_xattrs(target: &Path, meta: &EntryMeta, options: &ExtractOptions) -> io::Result<()> {
+    if !options.preserve_xattrs {
+        return Ok(());
+    }
+    for (name, value) in &meta.xattrs {
+        crate::sys::set_xattr(target, name, value)
+            .map_err(|e| io::Error::new(e.kind(), format!("setxattr {} on {}: {}", name, target.display(), e)))?;
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn set_acls(target: &Path, file: &TarFile, options: &ExtractOptions) -> io::Result<()> {
+    for (name, value) in acl_xattrs(file, options)? {
+        crate::sys::set_xattr(target, name, &value)
+            .map_err(|e| io::Error::new(e.kind(), format!("set acl on {}: {}", target.display(), e)))?;
+    }
+    Ok(())
+}
+
+/// 要写入的 ACL 扩展属性 (名字, Linux 二进制格式的值)；不恢复 ACL 时为空
+#[cfg(unix)]
+fn acl_xattrs(file: &TarFile, options: &ExtractOptions) -> io::Result<Vec<(&'static str, Vec<u8>)>> {
+    use crate::sys;
+    if !options.preserve_acls {
+        return Ok(Vec::new());
+    }
+    let mut out = Vec::new();
+    let records = [("system.posix_acl_access", file.acl_access()?), ("system.posix_acl_default", file.acl_default()?)];
+    for (name, acl) in records {
+        if let Some(mut acl) = acl {
+            if let Some(map) = &options.id_map {
+                acl = map.map_acl(&acl)?;
+            }
+            out.push((name, acl.to_linux_xattr(sys::lookup_uid, sys::lookup_gid)?));
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(not(unix))]
+fn set_acls(_target: &Path, _file: &TarFile, _options: &ExtractOptions) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn set_xattrs(_target: &Path, _meta: &EntryMeta, _options: &ExtractOptions) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn set_owner(_target: &Path, _meta: &EntryMeta, _options: &ExtractOptions) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(unix)]
+fn set_times(target: &Path, meta: &EntryMeta, options: &ExtractOptions) -> io::Result<()> {
+    if !options.preserve_times {
+        return Ok(());
+    }
+    crate::sys::set_times(target, meta.accessed(), meta.modified())
+        .map_err(|e| io::Error::new(e.kind(), format!("set times on {}: {}", target.display(), e)))
+}
+
+/// 只能通过文件句柄设置时间，目录与符号链接跳过
+#[cfg(not(unix))]
+fn set_times(target: &Path, meta: &EntryMeta, options: &ExtractOptions) -> io::Result<()> {
+    if !options.preserve_times || !fs::symlink_metadata(target)?.is_file() {
+        return Ok(());
+    }
+    let mut times = fs::FileTimes::new().set_modified(meta.modified());
+    if let Some(accessed) = meta.accessed() {
+        times = times.set_accessed(accessed);
+    }
+    fs::OpenOptions::new().write(true).open(target)?.set_times(times)
+}
+
+#[cfg(unix)]
+fn set_mode(target: &Path, mode: u32) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(target, fs::Permissions::from_mode(mode & 0o7777))
+}
+
+#[cfg(not(unix))]
+fn set_mode(_target: &Path, _mode: u32) -> io::Result<()> {
+    Ok(())
+}
+
+/// 计算条目在 dest 下的目标路径，逐段拼接以保证分隔符统一（`\\?\` 路径不会自动转换 '/'）
+fn entry_target(dest: &Path, path: &[u8]) -> io::Result<PathBuf> {
+    #[cfg(windows)]
+    {
+        let path = String::from_utf8_lossy(path);
+        if let Some(component) = crate::path::find_reserved_windows_name(&path) {
+            return Err(PtError::ReservedName { path: path.to_string(), component: component.to_string() }.into());
+        }
+    }
+    let mut target = dest.to_path_buf();
+    for component in path.split(|&b| b == b'/').filter(|c| !c.is_empty()) {
+        target.push(os_from_bytes(component));
+    }
+    Ok(target)
+}
+
+/// 检查 target 在 dest 下的各级父目录，已存在的符号链接必须解析到 dest 之内
+fn guard_symlinks(dest: &Path, target: &Path, path: &str) -> io::Result<()> {
+    let escape = || io::Error::from(PtError::UnsafePath { path: path.to_string() });
+    let root = fs::canonicalize(dest)?;
+    let Some(parent) = target.parent() else { return Ok(()) };
+    let rel = parent.strip_prefix(dest).map_err(|_| escape())?;
+    let mut current = dest.to_path_buf();
+    for component in rel.components() {
+        current.push(component);
+        let Ok(md) = fs::symlink_metadata(&current) else { break };
+        if !md.file_type().is_symlink() {
+            continue;
+        }
+        // 悬空链接无法判断最终指向，一并拒绝
+        let resolved = fs::canonicalize(&current).map_err(|_| escape())?;
+        if !resolved.starts_with(&root) {
+            return Err(escape());
+        }
+    }
+    Ok(())
+}
+
+/// 把单个条目落盘到 dest 下；目录的权限与时间需要在其内容写完后再设置，因此返回给调用方延后处理
+pub(crate) fn extract_entry(file: &TarFile, dest: &Path, rel_path: &[u8], options: &ExtractOptions) -> io::Result<Option<(PathBuf, EntryMeta)>> {
+    let meta = file.meta();
+    let target = entry_target(dest, rel_path)?;
+    if !options.allow_unsafe_paths {
+        guard_symlinks(dest, &target, &String::from_utf8_lossy(rel_path))?;
+        // 目标本身是符号链接时先删除，避免写入或设置权限时跟随链接
+        if fs::symlink_metadata(&target).is_ok_and(|m| m.file_type().is_symlink()) {
+            fs::remove_file(&target)?;
+        }
+    }
+    match meta.type_flag {
+        // 'D' 为 GNU 增量备份中的目录条目
+        '5' | 'D' => {
+            fs::create_dir_all(&target)?;
+            set_owner(&target, &meta, options)?;
+            set_xattrs(&target, &meta, options)?;
+            set_acls(&target, file, options)?;
+            if options.preserve_permissions || options.preserve_times {
+                return Ok(Some((target, meta)));
+            }
+        }
+        '0' | '\0' | '7' | 'S' => {
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            let mut out = fs::File::create(&target)?;
+            write_file_body(file, &mut out)?;
+            drop(out);
+            // chown 会清除 setuid/setgid 位和 security.capability，必须在其它属性之前
+            set_owner(&target, &meta, options)?;
+            set_xattrs(&target, &meta, options)?;
+            if options.preserve_permissions {
+                set_mode(&target, meta.mode)?;
+            }
+            // 设置 ACL 会改写 mode 的组权限位，放在 chmod 之后
+            set_acls(&target, file, options)?;
+            set_times(&target, &meta, options)?;
+        }
+        '1' => {
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            let _ = fs::remove_file(&target);
+            let source = if options.allow_unsafe_paths {
+                entry_target(dest, meta.link_name_bytes())?
+            } else {
+                let source = entry_target(dest, &sanitize_path_bytes(meta.link_name_bytes())?)?;
+                guard_symlinks(dest, &source, &meta.link_name)?;
+                source
+            };
+            fs::hard_link(source, &target)?;
+        }
+        '2' => {
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            let _ = fs::remove_file(&target);
+            #[cfg(unix)]
+            std::os::unix::fs::symlink(os_from_bytes(meta.link_name_bytes()), &target)?;
+            #[cfg(windows)]
+            std::os::windows::fs::symlink_file(&meta.link_name, &target)?;
+            set_owner(&target, &meta, options)?;
+            set_times(&target, &meta, options)?;
+        }
+        // 设备节点只在以 root 运行时创建，否则跳过
+        #[cfg(unix)]
+        '3' | '4' if crate::sys::is_root() => {
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            let _ = fs::remove_file(&target);
+            crate::sys::mknod(&target, meta.type_flag == '4', meta.mode, meta.devmajor, meta.devminor)?;
+            set_owner(&target, &meta, options)?;
+            set_times(&target, &meta, options)?;
+        }
+        #[cfg(unix)]
+        '6' => {
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            let _ = fs::remove_file(&target);
+            crate::sys::mkfifo(&target, meta.mode)?;
+            set_owner(&target, &meta, options)?;
+            if options.preserve_permissions {
+                set_mode(&target, meta.mode)?;
+            }
+            set_times(&target, &meta, options)?;
+        }
+        // 其他类型暂不处理
+        _ => {}
+    }
+    Ok(None)
+}
+
+/// 沙箱模式下落盘单个条目，与 `extract_entry` 的处理相同，但路径经 `Sandbox` 解析、属性通过句柄设置；
+/// 返回目录的权限与时间是否需要延后设置
+#[cfg(unix)]
+fn extract_entry_sandboxed(file: &TarFile, sandbox: &Sandbox, rel_path: &[u8], options: &ExtractOptions) -> io::Result<bool> {
+    use std::os::fd::AsFd;
+    use crate::sandbox::{fchmod, fchown, fsetxattr, futimens};
+    let meta = file.meta();
+    let display = String::from_utf8_lossy(rel_path);
+    // chown 必须在其它属性之前，ACL 在 chmod 之后，原因见 `extract_entry`
+    let owner = |fd: std::os::fd::BorrowedFd| match owner_ids(&meta, options)? {
+        Some((uid, gid)) => fchown(&fd, uid, gid),
+        None => Ok(()),
+    };
+    let xattrs = |fd: std::os::fd::BorrowedFd| -> io::Result<()> {
+        if options.preserve_xattrs {
+            for (name, value) in &meta.xattrs {
+                fsetxattr(&fd, name, value)
+                    .map_err(|e| io::Error::new(e.kind(), format!("setxattr {} on {}: {}", name, display, e)))?;
+            }
+        }
+        Ok(())
+    };
+    let acls = |fd: std::os::fd::BorrowedFd| -> io::Result<()> {
+        for (name, value) in acl_xattrs(file, options)? {
+            fsetxattr(&fd, name, &value).map_err(|e| io::Error::new(e.kind(), format!("set acl on {}: {}", display, e)))?;
+        }
+        Ok(())
+    };
+    let slot_owner = |slot: &crate::sandbox::Slot| match owner_ids(&meta, options)? {
+        Some((uid, gid)) => slot.lchown(uid, gid),
+        None => Ok(()),
+    };
+    match meta.type_flag {
+        '5' | 'D' => {
+            let dir = sandbox.create_dir(rel_path)?;
+            owner(dir.as_fd())?;
+            xattrs(dir.as_fd())?;
+            acls(dir.as_fd())?;
+            return Ok(options.preserve_permissions || options.preserve_times);
+        }
+        '0' | '\0' | '7' | 'S' => {
+            let mut out = sandbox.create_file(rel_path)?;
+            write_file_body(file, &mut out)?;
+            owner(out.as_fd())?;
+            xattrs(out.as_fd())?;
+            if options.preserve_permissions {
+                fchmod(&out, meta.mode)?;
+            }
+            acls(out.as_fd())?;
+            if options.preserve_times {
+                futimens(&out, meta.accessed(), meta.modified())?;
+            }
+        }
+        '1' => sandbox.hard_link(sanitize_path_bytes(meta.link_name_bytes())?, rel_path)?,
+        '2' => {
+            let slot = sandbox.symlink(meta.link_name_bytes(), rel_path)?;
+            slot_owner(&slot)?;
+            if options.preserve_times {
+                slot.set_times(meta.accessed(), meta.modified())?;
+            }
+        }
+        '3' | '4' if crate::sys::is_root() => {
+            let kind = if meta.type_flag == '4' { libc::S_IFBLK } else { libc::S_IFCHR };
+            let slot = sandbox.mknod(rel_path, kind, meta.mode, libc::makedev(meta.devmajor as _, meta.devminor as _))?;
+            slot_owner(&slot)?;
+            if options.preserve_times {
+                slot.set_times(meta.accessed(), meta.modified())?;
+            }
+        }
+        '6' => {
+            let slot = sandbox.mknod(rel_path, libc::S_IFIFO, meta.mode, 0)?;
+            slot_owner(&slot)?;
+            if options.preserve_permissions {
+                fchmod(&sandbox.open_fifo(&slot)?, meta.mode)?;
+            }
+            if options.preserve_times {
+                slot.set_times(meta.accessed(), meta.modified())?;
+            }
+        }
+        _ => {}
+    }
+    Ok(false)
+}
+
+/// 把镜像中的所有条目解包到 dest 目录
+pub fn extract_all(img: &mut TarImage, dest: &Path) -> io::Result<()> {
+    extract_all_with(img, dest, &ExtractOptions::default())
+}
+
+/// 按 options 把镜像中的所有条目解包到 dest 目录
+pub fn extract_all_with(img: &mut TarImage, dest: &Path, options: &ExtractOptions) -> io::Result<()> {
+    extract_all_with_progress(img, dest, options, &mut NoProgress)
+}
+
+/// 与 `extract_all_with` 相同，每解包完一个条目报告一次进度
+pub fn extract_all_with_progress(
+    img: &mut TarImage,
+    dest: &Path,
+    options: &ExtractOptions,
+    progress: &mut dyn Progress,
+) -> io::Result<()> {
+    extract_all_inner(img, dest, options, progress).map(drop)
+}
+
+/// 与 `extract_all_with` 相同，并按归档顺序返回对每个写出（或将要写出）的条目采取的动作；
+/// 配合 `dry_run` 可预览解包结果
+pub fn extract_all_report(img: &mut TarImage, dest: &Path, options: &ExtractOptions) -> io::Result<Vec<ExtractRecord>> {
+    extract_all_inner(img, dest, options, &mut NoProgress)
+}
+
+fn extract_all_inner(
+    img: &mut TarImage,
+    dest: &Path,
+    options: &ExtractOptions,
+    progress: &mut dyn Progress,
+) -> io::Result<Vec<ExtractRecord>> {
+    if !options.dry_run {
+        fs::create_dir_all(dest)?;
+    }
+    // Windows 下使用扩展长度路径，避免深层目录超出 MAX_PATH
+    #[cfg(windows)]
+    let dest = &crate::path::to_extended_length(dest)?;
+    let mut unpacker = Unpacker::new(dest, options)?;
+    img.for_each_entry_with_progress(progress, |file| unpacker.entry(&*try_into_tarfile(file)?))?;
+    unpacker.finish()
+}
+
+/// 条目是否会在磁盘上产生文件，与 `extract_entry` 中处理的类型一致
+fn writes_to_disk(type_flag: char) -> bool {
+    match type_flag {
+        '5' | 'D' | '0' | '\0' | '7' | 'S' | '1' | '2' => true,
+        #[cfg(unix)]
+        '3' | '4' => crate::sys::is_root(),
+        #[cfg(unix)]
+        '6' => true,
+        _ => false,
+    }
+}
+
+/// 删除已存在的 target，目录只在为空时删除
+fn unlink(target: &Path) -> io::Result<()> {
+    if fs::symlink_metadata(target)?.is_dir() {
+        fs::remove_dir(target)
+    } else {
+        fs::remove_file(target)
+    }
+}
+
+/// 逐条解包的状态：大小写冲突检测、延后设置属性的目录与解包报告
+struct Unpacker<'a> {
+    dest: &'a Path,
+    options: &'a ExtractOptions,
+    dirs: Vec<(PathBuf, EntryMeta)>,
+    collisions: CollisionTracker,
+    implicit: ImplicitDirs,
+    report: Vec<ExtractRecord>,
+    /// dry run 时已"写出"的目标及其是否为目录，让后出现的同名条目看到它们
+    planned: HashMap<PathBuf, bool>,
+    /// 沙箱模式下打开的 dest；此时 `dirs` 中保存的是相对 dest 的路径
+    #[cfg(unix)]
+    sandbox: Option<Sandbox>,
+}
+
+impl<'a> Unpacker<'a> {
+    fn new(dest: &'a Path, options: &'a ExtractOptions) -> io::Result<Self> {
+        #[cfg(not(unix))]
+        if options.sandboxed {
+            return Err(io::Error::new(io::ErrorKind::Unsupported, "sandboxed extraction is only supported on Unix"));
+        }
+        let collisions = CollisionTracker::new(options.collision_policy, options.case_insensitive);
+        Ok(Unpacker {
+            dest,
+            options,
+            dirs: Vec::new(),
+            collisions,
+            implicit: ImplicitDirs::default(),
+            report: Vec::new(),
+            planned: HashMap::new(),
+            #[cfg(unix)]
+            sandbox: if options.sandboxed && !options.dry_run { Some(Sandbox::open(dest)?) } else { None },
+        })
+    }
+
+    /// 路径穿越检查；dry run 时 dest 可能还不存在，其下也就不会有符号链接
+    fn guard(&self, target: &Path, rel_path: &str) -> io::Result<()> {
+        if self.options.allow_unsafe_paths || (self.options.dry_run && !self.dest.exists()) {
+            return Ok(());
+        }
+        guard_symlinks(self.dest, target, rel_path)
+    }
+
+    /// 按 `OverwritePolicy` 决定对 target 的动作并记入报告
+    fn plan(&mut self, rel_path: &str, target: &Path, type_flag: char) -> io::Result<ExtractAction> {
+        let existing = match fs::symlink_metadata(target) {
+            Ok(md) => Some(md.is_dir()),
+            Err(_) => self.planned.get(target).copied(),
+        };
+        let is_dir = matches!(type_flag, '5' | 'D');
+        let action = match existing {
+            None => ExtractAction::Create,
+            Some(true) if is_dir => ExtractAction::Overwrite,
+            Some(_) => match self.options.overwrite {
+                OverwritePolicy::Overwrite => ExtractAction::Overwrite,
+                OverwritePolicy::Skip => ExtractAction::Skip,
+                OverwritePolicy::Error => return Err(io::Error::new(
+                    io::ErrorKind::AlreadyExists, format!("{} already exists", target.display()))),
+            },
+        };
+        if self.options.dry_run && action != ExtractAction::Skip {
+            self.planned.insert(target.to_path_buf(), is_dir);
+        }
+        self.report.push(ExtractRecord { path: rel_path.to_string(), target: target.to_path_buf(), type_flag, action });
+        Ok(action)
+    }
+
+    fn entry(&mut self, tar_file: &TarFile) -> io::Result<()> {
+        let path = tar_file.get_path();
+        let path = if self.options.allow_unsafe_paths { path } else { sanitize_path(&path)? };
+        let rel_path = self.collisions.resolve(&path)?;
+        // 非 UTF-8 的名字按原始字节落盘；冲突处理改了名时使用改后的名字
+        let raw = tar_file.get_path_bytes();
+        let raw_path = match std::str::from_utf8(&raw) {
+            Err(_) if rel_path == path && self.options.allow_unsafe_paths => Some(raw),
+            Err(_) if rel_path == path => Some(sanitize_path_bytes(&raw)?),
+            _ => None,
+        };
+        let disk_path = raw_path.as_deref().unwrap_or(rel_path.as_bytes());
+        if self.options.implicit_dirs {
+            let meta = EntryMeta { path: rel_path.clone(), ..tar_file.meta() };
+            for dir in self.implicit.missing(&meta) {
+                self.implicit_dir(&dir)?;
+            }
+        }
+        let type_flag = tar_file.get_type_flag();
+        if !writes_to_disk(type_flag) {
+            return Ok(());
+        }
+        let target = entry_target(self.dest, disk_path)?;
+        if self.options.dry_run {
+            self.guard(&target, &rel_path)?;
+        }
+        match self.plan(&rel_path, &target, type_flag)? {
+            _ if self.options.dry_run => return Ok(()),
+            ExtractAction::Skip => return Ok(()),
+            ExtractAction::Overwrite if self.options.unlink_first && !matches!(type_flag, '5' | 'D') => {
+                self.unlink(disk_path, &target)?;
+            }
+            _ => {}
+        }
+        #[cfg(unix)]
+        if let Some(sandbox) = &self.sandbox {
+            if extract_entry_sandboxed(tar_file, sandbox, disk_path, self.options)? {
+                self.dirs.push((PathBuf::from(os_from_bytes(disk_path)), tar_file.meta()));
+            }
+            return Ok(());
+        }
+        self.dirs.extend(extract_entry(tar_file, self.dest, disk_path, self.options)?);
+        Ok(())
+    }
+
+    /// `unlink_first` 时删除已存在的目标
+    fn unlink(&self, rel_path: &[u8], target: &Path) -> io::Result<()> {
+        #[cfg(unix)]
+        if let Some(sandbox) = &self.sandbox {
+            return sandbox.slot(rel_path)?.remove();
+        }
+        #[cfg(not(unix))]
+        let _ = rel_path;
+        unlink(target)
+    }
+
+    /// 创建合成的上级目录；权限立即设置，不参与 `finish` 的延后处理，以免覆盖之后出现的显式条目
+    fn implicit_dir(&mut self, dir: &EntryMeta) -> io::Result<()> {
+        let rel_path = dir.path.trim_end_matches('/');
+        let target = entry_target(self.dest, rel_path.as_bytes())?;
+        if fs::symlink_metadata(&target).is_ok() || self.planned.contains_key(&target) {
+            return Ok(());
+        }
+        self.guard(&target, rel_path)?;
+        self.plan(&dir.path, &target, '5')?;
+        if self.options.dry_run {
+            return Ok(());
+        }
+        #[cfg(unix)]
+        if let Some(sandbox) = &self.sandbox {
+            let fd = sandbox.create_dir(rel_path)?;
+            if let Some((uid, gid)) = owner_ids(dir, self.options)? {
+                crate::sandbox::fchown(&fd, uid, gid)?;
+            }
+            if self.options.preserve_permissions {
+                crate::sandbox::fchmod(&fd, dir.mode)?;
+            }
+            return Ok(());
+        }
+        fs::create_dir_all(&target)?;
+        set_owner(&target, dir, self.options)?;
+        if self.options.preserve_permissions {
+            set_mode(&target, dir.mode)?;
+        }
+        Ok(())
+    }
+
+    /// 由深到浅设置目录权限与时间，避免只读目录影响后续写入、写入内容改变目录的 mtime
+    fn finish(self) -> io::Result<Vec<ExtractRecord>> {
+        #[cfg(unix)]
+        if let Some(sandbox) = &self.sandbox {
+            for (dir, meta) in self.dirs.iter().rev() {
+                let fd = sandbox.open_dir(std::os::unix::ffi::OsStrExt::as_bytes(dir.as_os_str()))?;
+                if self.options.preserve_permissions {
+                    crate::sandbox::fchmod(&fd, meta.mode)?;
+                }
+                if self.options.preserve_times {
+                    crate::sandbox::futimens(&fd, meta.accessed(), meta.modified())?;
+                }
+            }
+            return Ok(self.report);
+        }
+        for (dir, meta) in self.dirs.iter().rev() {
+            if self.options.preserve_permissions {
+                set_mode(dir, meta.mode)?;
+            }
+            set_times(dir, meta, self.options)?;
+        }
+        Ok(self.report)
+    }
+}
+
+/// 流式解包时暂存当前条目（header 与数据区）的临时文件，drop 时删除
+struct Spool {
+    path: PathBuf,
+    file: fs::File,
+}
+
+impl Spool {
+    fn new() -> io::Result<Self> {
+        static NEXT: AtomicU64 = AtomicU64::new(0);
+        let name = format!(".pt-spool-{}-{}", std::process::id(), NEXT.fetch_add(1, Ordering::Relaxed));
+        let path = std::env::temp_dir().join(name);
+        let file = fs::OpenOptions::new().read(true).write(true).create_new(true).open(&path)?;
+        Ok(Spool { path, file })
+    }
+
+    /// 用 headers 与 reader 中 body_len 字节的数据区替换暂存内容，并补上结束标记；
+    /// 每复制一块数据前检查 cancel
+    fn fill<R: Read>(&mut self, headers: &[u8], reader: &mut R, body_len: u64, cancel: Option<&CancelToken>) -> io::Result<()> {
+        self.file.set_len(0)?;
+        self.file.seek(SeekFrom::Start(0))?;
+        self.file.write_all(headers)?;
+        let mut buf = vec![0u8; 64 * 1024];
+        let mut left = body_len;
+        while left > 0 {
+            if let Some(token) = cancel {
+                token.check()?;
+            }
+            let want = left.min(buf.len() as u64) as usize;
+            let n = match reader.read(&mut buf[..want]) {
+                Ok(0) => return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "stream ends inside entry data")),
+                Ok(n) => n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+            self.file.write_all(&buf[..n])?;
+            left -= n as u64;
+        }
+        self.file.write_all(&[0u8; 1024])?;
+        self.file.flush()
+    }
+}
+
+impl Drop for Spool {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// 读取一个 512 字节块，流在块边界处结束时返回 false
+fn read_block<R: Read>(reader: &mut R, block: &mut [u8; 512]) -> io::Result<bool> {
+    let mut done = 0;
+    while done < block.len() {
+        match reader.read(&mut block[done..]) {
+            Ok(0) if done == 0 => return Ok(false),
+            Ok(0) => return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "stream ends inside a header block")),
+            Ok(n) => done += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(true)
+}
+
+/// 从流中读出下一个条目的全部 header 块（含扩展 header 及其数据），返回 (headers, 数据区长度)；
+/// 扩展 header 按 `max_pax_size` 检查后才读入。到达结束标记或流结束时返回 None
+fn read_stream_headers<R: Read>(reader: &mut R, globals: &mut PaxRecords, limits: &Limits) -> io::Result<Option<(Vec<u8>, u64)>> {
+    let mut headers = Vec::new();
+    let mut pax = PaxRecords::new();
+    let mut block = [0u8; 512];
+    let mut zero_blocks = 0;
+    loop {
+        if !read_block(reader, &mut block)? {
+            if headers.is_empty() {
+                return Ok(None);
+            }
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "archive ends after extension header"));
+        }
+        let hdr = TarHeader::from_bytes(&block);
+        if hdr.is_zero_block() {
+            zero_blocks += 1;
+            if zero_blocks >= 2 {
+                return Ok(None);
+            }
+            continue;
+        }
+        if !hdr.crc_ok() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "tar header checksum error"));
+        }
+        headers.extend_from_slice(&block);
+        let flag = hdr.get_type_flag();
+        if !(matches!(flag, 'x' | 'X' | 'g' | 'L' | 'K') || is_vendor_extension(flag)) {
+            let size = match flag {
+                // 设备文件和 FIFO 没有数据块
+                '3' | '4' | '6' => 0,
+                _ => pax_u64(&pax, "size").unwrap_or_else(|| hdr.get_size()),
+            };
+            // 旧式 GNU 稀疏 header 之后的扩展块属于 header
+            if flag == 'S' {
+                let (_, mut extended) = hdr.get_gnu_sparse_map();
+                while extended {
+                    if !read_block(reader, &mut block)? {
+                        return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "stream ends inside sparse map"));
+                    }
+                    headers.extend_from_slice(&block);
+                    extended = parse_gnu_sparse_block(&block).1;
+                }
+            }
+            return Ok(Some((headers, size)));
+        }
+        limits::check("extension header size", hdr.get_size(), limits.max_pax_size)?;
+        let len = block_align(hdr.get_size());
+        let start = headers.len();
+        let n = reader.take(len).read_to_end(&mut headers)?;
+        if n as u64 != len {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "stream ends inside extension header"));
+        }
+        let records = &headers[start..start + hdr.get_size() as usize];
+        if matches!(flag, 'x' | 'X') {
+            pax.extend(parse_pax_records(records)?);
+        } else if flag == 'g' {
+            // 'g' 不属于当前条目，合并进全局记录后丢弃
+            merge_globals(globals, parse_pax_records(records)?);
+            headers.truncate(start - 512);
+        }
+    }
+}
+
+/// 从只能顺序读取的 reader（如管道、网络流）一次性解包到 dest，不需要 seek；
+/// 每次只把当前条目暂存到临时目录下的文件中，读到结束标记后丢弃流中剩余的数据
+///
+/// 压缩的流需要先套上 `compress::decoder`
+pub fn unpack_stream<R: Read>(mut reader: R, dest: &Path, options: &ExtractOptions) -> io::Result<()> {
+    if !options.dry_run {
+        fs::create_dir_all(dest)?;
+    }
+    #[cfg(windows)]
+    let dest = &crate::path::to_extended_length(dest)?;
+    let mut spool = Spool::new()?;
+    let mut unpacker = Unpacker::new(dest, options)?;
+    let mut globals = PaxRecords::new();
+    let cancel = options.cancel.as_ref();
+    let (mut entries, mut total) = (0u64, 0u64);
+    loop {
+        if let Some(token) = cancel {
+            token.check()?;
+        }
+        let Some((mut headers, size)) = read_stream_headers(&mut reader, &mut globals, &options.limits)? else {
+            break;
+        };
+        entries += 1;
+        total = total.saturating_add(size);
+        limits::check("entry count", entries, options.limits.max_entries)?;
+        limits::check("entry size", size, options.limits.max_entry_size)?;
+        limits::check("total size", total, options.limits.max_total_size)?;
+        // 之前的 'g' header 合并成一个放在条目前，使全局记录在各暂存条目上继续生效
+        if !globals.is_empty() {
+            let records: Vec<u8> = globals.iter().flat_map(|(key, value)| pax_record(key, value)).collect();
+            headers.splice(0..0, crate::builder::extension_block(TypeFlag::PaxGlobal, "pax_global_header", &records, 0, false)?);
+        }
+        spool.fill(&headers, &mut reader, block_align(size), cancel)?;
+        let img = TarImage::open(&spool.path.to_string_lossy())?;
+        let mut img = lock_image(&img)?;
+        img.set_keep_absolute_paths(options.allow_unsafe_paths);
+        img.set_limits(options.limits);
+        if let Some(token) = cancel {
+            img.set_cancel_token(token.clone());
+        }
+        let file = img.entry_at(0)?
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "spooled entry is empty"))?;
+        drop(img);
+        unpacker.entry(&file)?;
+    }
+    io::copy(&mut reader, &mut io::sink())?;
+    unpacker.finish().map(drop)
+}
+
+fn not_found(path: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, format!("entry not found: {}", path))
+}
+
+/// 按条目元数据设置文件的修改时间与权限
+fn restore_metadata(target: &Path, meta: &EntryMeta) -> io::Result<()> {
+    let out = fs::OpenOptions::new().write(true).open(target)?;
+    out.set_modified(meta.modified())?;
+    set_mode(target, meta.mode)
+}
+
+/// 把普通文件条目写到 target（完整的目标文件路径），并恢复 mtime 与权限
+pub fn unpack_entry(file: &TarFile, target: &Path) -> io::Result<EntryMeta> {
+    let meta = file.meta();
+    if !meta.is_file() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("entry is not a regular file: {}", meta.path),
+        ));
+    }
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut out = fs::File::create(target)?;
+    copy_body(file, &mut out)?;
+    drop(out);
+    restore_metadata(target, &meta)?;
+    Ok(meta)
+}
+
+/// 按路径取出单个条目写到 target
+pub fn extract_file(img: &mut TarImage, path: &str, target: &Path) -> io::Result<EntryMeta> {
+    let file = img.find_entry(path)?.ok_or_else(|| not_found(path))?;
+    unpack_entry(&file, target)
+}
+
+/// 按路径取出单个条目写入 writer，返回写入的字节数
+pub fn extract_file_to<W: Write>(img: &mut TarImage, path: &str, writer: &mut W) -> io::Result<u64> {
+    let file = img.find_entry(path)?.ok_or_else(|| not_found(path))?;
+    copy_body(&file, writer)
+}
@@ -0,0 +1,152 @@
+//! C 接口，对应的头文件为 `include/pt.h`
+//!
+//! 所有函数出错时返回 NULL 或 -1，错误信息通过 `pt_last_error` 取得。
+
+use std::cell::RefCell;
+use std::ffi::{c_char, CStr, CString};
+use std::io::{self, Read};
+use std::ptr;
+use std::sync::{Arc, Mutex};
+use crate::base::{lock_image, ImageInfo, TarFile, TarImage};
+
+/// 打开的镜像及下一个条目的偏移
+pub struct PtImage {
+    img: Arc<Mutex<TarImage>>,
+    offset: u64,
+}
+
+/// 由 `pt_next_entry` 返回的条目
+pub struct PtEntry {
+    file: Box<TarFile>,
+    path: CString,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_error(e: &io::Error) {
+    let msg = CString::new(e.to_string().replace('\0', "?")).unwrap_or_default();
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(msg));
+}
+
+/// 当前线程最近一次错误的描述，没有错误时返回 NULL；在下一次出错前有效
+#[no_mangle]
+pub extern "C" fn pt_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_ref().map_or(ptr::null(), |msg| msg.as_ptr()))
+}
+
+/// 打开 tar 镜像，失败时返回 NULL
+///
+/// # Safety
+/// `path` 必须是以 '\0' 结尾的有效字符串
+#[no_mangle]
+pub unsafe extern "C" fn pt_open(path: *const c_char) -> *mut PtImage {
+    if path.is_null() {
+        set_error(&io::Error::new(io::ErrorKind::InvalidInput, "path is NULL"));
+        return ptr::null_mut();
+    }
+    let path = CStr::from_ptr(path).to_string_lossy();
+    match TarImage::open(&path) {
+        Ok(img) => Box::into_raw(Box::new(PtImage { img, offset: 0 })),
+        Err(e) => {
+            set_error(&e);
+            ptr::null_mut()
+        }
+    }
+}
+
+fn next_entry(image: &mut PtImage) -> io::Result<Option<PtEntry>> {
+    let Some(file) = lock_image(&image.img)?.entry_at(image.offset)? else { return Ok(None) };
+    image.offset = file.get_end_offset();
+    let path = CString::new(file.get_path().replace('\0', "?")).unwrap_or_default();
+    Ok(Some(PtEntry { file, path }))
+}
+
+/// 按归档顺序返回下一个条目，到达末尾或出错时返回 NULL（出错时 `pt_last_error` 非 NULL）
+///
+/// # Safety
+/// `image` 必须是 `pt_open` 返回且尚未关闭的指针
+#[no_mangle]
+pub unsafe extern "C" fn pt_next_entry(image: *mut PtImage) -> *mut PtEntry {
+    let Some(image) = image.as_mut() else { return ptr::null_mut() };
+    LAST_ERROR.with(|last| *last.borrow_mut() = None);
+    match next_entry(image) {
+        Ok(Some(entry)) => Box::into_raw(Box::new(entry)),
+        Ok(None) => ptr::null_mut(),
+        Err(e) => {
+            set_error(&e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// 条目路径，由条目持有，`pt_entry_free` 后失效
+///
+/// # Safety
+/// `entry` 必须是 `pt_next_entry` 返回且尚未释放的指针
+#[no_mangle]
+pub unsafe extern "C" fn pt_entry_path(entry: *const PtEntry) -> *const c_char {
+    entry.as_ref().map_or(ptr::null(), |e| e.path.as_ptr())
+}
+
+/// 数据区大小
+///
+/// # Safety
+/// 同 `pt_entry_path`
+#[no_mangle]
+pub unsafe extern "C" fn pt_entry_size(entry: *const PtEntry) -> u64 {
+    entry.as_ref().map_or(0, |e| e.file.get_size())
+}
+
+/// header 中的 typeflag，如 '0'、'5'
+///
+/// # Safety
+/// 同 `pt_entry_path`
+#[no_mangle]
+pub unsafe extern "C" fn pt_entry_type(entry: *const PtEntry) -> c_char {
+    entry.as_ref().map_or(0, |e| e.file.get_header().typeflag as c_char)
+}
+
+/// 顺序读取数据区，返回读取的字节数，0 表示读完，-1 表示出错
+///
+/// # Safety
+/// `entry` 同 `pt_entry_path`；`buf` 至少可写 `len` 字节
+#[no_mangle]
+pub unsafe extern "C" fn pt_entry_read(entry: *mut PtEntry, buf: *mut u8, len: usize) -> isize {
+    let Some(entry) = entry.as_mut() else { return -1 };
+    if buf.is_null() {
+        set_error(&io::Error::new(io::ErrorKind::InvalidInput, "buffer is NULL"));
+        return -1;
+    }
+    let buf = std::slice::from_raw_parts_mut(buf, len);
+    match entry.file.read(buf) {
+        Ok(n) => n as isize,
+        Err(e) => {
+            set_error(&e);
+            -1
+        }
+    }
+}
+
+/// 释放条目
+///
+/// # Safety
+/// `entry` 为 NULL 或 `pt_next_entry` 返回且尚未释放的指针
+#[no_mangle]
+pub unsafe extern "C" fn pt_entry_free(entry: *mut PtEntry) {
+    if !entry.is_null() {
+        drop(Box::from_raw(entry));
+    }
+}
+
+/// 关闭镜像；已取得的条目仍可继续读取，需各自释放
+///
+/// # Safety
+/// `image` 为 NULL 或 `pt_open` 返回且尚未关闭的指针
+#[no_mangle]
+pub unsafe extern "C" fn pt_close(image: *mut PtImage) {
+    if !image.is_null() {
+        drop(Box::from_raw(image));
+    }
+}
@@ -0,0 +1,131 @@
+use std::io;
+use crate::base::{try_into_tarfile, FileInfo, ImageInfo, TarImage};
+use crate::path::normalize_path;
+
+/// 单段通配匹配：`*` 任意字符（不跨 '/'），`?` 单个字符，`[abc]` / `[a-z]` / `[!a]` 字符集
+fn match_segment(pat: &[char], s: &[char]) -> bool {
+    match pat.first() {
+        None => s.is_empty(),
+        Some('*') => (0..=s.len()).any(|i| match_segment(&pat[1..], &s[i..])),
+        Some('?') => !s.is_empty() && match_segment(&pat[1..], &s[1..]),
+        Some('[') => {
+            let Some(close) = pat.iter().skip(2).position(|&c| c == ']').map(|i| i + 2) else {
+                // 没有闭合的 '[' 按普通字符处理
+                return s.first() == Some(&'[') && match_segment(&pat[1..], &s[1..]);
+            };
+            let Some(&c) = s.first() else { return false };
+            let (negate, set) = match pat[1] {
+                '!' | '^' => (true, &pat[2..close]),
+                _ => (false, &pat[1..close]),
+            };
+            let mut hit = false;
+            let mut i = 0;
+            while i < set.len() {
+                if i + 2 < set.len() && set[i + 1] == '-' {
+                    hit |= set[i] <= c && c <= set[i + 2];
+                    i += 3;
+                } else {
+                    hit |= set[i] == c;
+                    i += 1;
+                }
+            }
+            hit != negate && match_segment(&pat[close + 1..], &s[1..])
+        }
+        Some(&p) => s.first() == Some(&p) && match_segment(&pat[1..], &s[1..]),
+    }
+}
+
+fn match_segments(pat: &[Vec<char>], path: &[Vec<char>]) -> bool {
+    match pat.first() {
+        None => path.is_empty(),
+        // `**` 匹配零个或多个路径段
+        Some(p) if p.len() == 2 && p[0] == '*' && p[1] == '*' => {
+            (0..=path.len()).any(|i| match_segments(&pat[1..], &path[i..]))
+        }
+        Some(p) => !path.is_empty() && match_segment(p, &path[0]) && match_segments(&pat[1..], &path[1..]),
+    }
+}
+
+fn split(path: &str) -> Vec<Vec<char>> {
+    normalize_path(path).split('/').map(|s| s.chars().collect()).collect()
+}
+
+/// glob 模式集合，任一模式匹配即视为命中
+#[derive(Debug, Clone)]
+pub struct EntryFilter {
+    patterns: Vec<Vec<Vec<char>>>,
+}
+
+impl EntryFilter {
+    pub fn new<S: AsRef<str>>(patterns: &[S]) -> Self {
+        EntryFilter {
+            patterns: patterns.iter().map(|p| split(p.as_ref())).collect(),
+        }
+    }
+
+    pub fn matches(&self, path: &str) -> bool {
+        let path = split(path);
+        self.patterns.iter().any(|p| match_segments(p, &path))
+    }
+}
+
+/// 归档时的排除规则，语法与 `.gitignore` 相近：
+/// 不含 '/' 的模式匹配任意层级的文件名，含 '/' 的模式相对规则所在目录匹配，结尾的 '/' 表示只匹配目录
+#[derive(Debug, Clone)]
+pub(crate) struct ExcludePattern {
+    pattern: Vec<Vec<char>>,
+    anchored: bool,
+    dir_only: bool,
+}
+
+impl ExcludePattern {
+    /// 空模式返回 None
+    pub(crate) fn new(pattern: &str) -> Option<Self> {
+        let dir_only = pattern.ends_with('/');
+        let anchored = pattern.trim_end_matches('/').contains('/');
+        if normalize_path(pattern).is_empty() {
+            return None;
+        }
+        Some(ExcludePattern { pattern: split(pattern), anchored, dir_only })
+    }
+
+    /// 解析 `.tarignore` 的内容：每行一个模式，忽略空行与 '#' 开头的注释
+    pub(crate) fn parse_list(text: &str) -> Vec<Self> {
+        text.lines()
+            .map(str::trim)
+            .filter(|line| !line.starts_with('#'))
+            .filter_map(ExcludePattern::new)
+            .collect()
+    }
+
+    /// rel 为相对规则所在目录的路径
+    pub(crate) fn matches(&self, rel: &str, is_dir: bool) -> bool {
+        if self.dir_only && !is_dir {
+            return false;
+        }
+        let path = split(rel);
+        if self.anchored {
+            match_segments(&self.pattern, &path)
+        } else {
+            path.last().is_some_and(|name| match_segments(&self.pattern, std::slice::from_ref(name)))
+        }
+    }
+}
+
+impl TarImage {
+    /// 只对路径匹配任一 glob 模式（如 `usr/lib/**/*.so`）的条目调用回调
+    pub fn for_each_entry_matching<S, F>(&mut self, patterns: &[S], mut callback: F) -> io::Result<()>
+    where
+        S: AsRef<str>,
+        F: FnMut(Box<dyn FileInfo>) -> io::Result<()>,
+    {
+        let filter = EntryFilter::new(patterns);
+        self.for_each_entry(|file| {
+            let tar_file = try_into_tarfile(file)?;
+            if filter.matches(&tar_file.get_path()) {
+                callback(tar_file)?;
+            }
+            Ok(())
+        })
+    }
+}
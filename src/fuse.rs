@@ -0,0 +1,185 @@
+use std::ffi::OsStr;
+use std::io;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, UNIX_EPOCH};
+use fuser::{
+    BackgroundSession, Config, Errno, FileAttr, FileHandle, FileType, Filesystem, Generation, INodeNo, LockOwner,
+    MountOption, OpenFlags, ReplyAttr, ReplyData, ReplyDirectory, ReplyEntry, Request,
+};
+use crate::base::{lock_image, try_into_tarfile, ImageInfo, TarImage};
+use crate::meta::EntryMeta;
+use crate::tree::{NodeId, TarTree};
+
+/// 归档只读，属性可以长期缓存
+const TTL: Duration = Duration::from_secs(3600);
+
+/// 以只读文件系统的形式提供归档内容；inode 为目录树节点编号 + 1
+pub struct TarFs {
+    img: Arc<Mutex<TarImage>>,
+    tree: TarTree,
+}
+
+fn ino_of(id: NodeId) -> INodeNo {
+    INodeNo(id.index() as u64 + 1)
+}
+
+impl TarFs {
+    /// 扫描一次归档建立目录树
+    pub fn new(img: Arc<Mutex<TarImage>>) -> io::Result<Self> {
+        let tree = lock_image(&img)?.tree()?;
+        Ok(TarFs { img, tree })
+    }
+
+    fn node_of(&self, ino: INodeNo) -> Option<NodeId> {
+        self.tree.id((ino.0 as usize).checked_sub(1)?)
+    }
+
+    /// 硬链接指向目标条目，取目标的元数据
+    fn resolve<'a>(&'a self, meta: &'a EntryMeta) -> &'a EntryMeta {
+        if meta.type_flag != '1' {
+            return meta;
+        }
+        self.tree.lookup(&meta.link_name)
+            .and_then(|id| self.tree.node(id).meta())
+            .filter(|target| target.type_flag != '1')
+            .unwrap_or(meta)
+    }
+
+    fn attr(&self, id: NodeId) -> FileAttr {
+        let node = self.tree.node(id);
+        let meta = node.meta().map(|m| self.resolve(m));
+        let kind = match meta.map(|m| m.type_flag) {
+            _ if node.is_dir() => FileType::Directory,
+            Some('2') => FileType::Symlink,
+            Some('3') => FileType::CharDevice,
+            Some('4') => FileType::BlockDevice,
+            Some('6') => FileType::NamedPipe,
+            _ => FileType::RegularFile,
+        };
+        let size = match (kind, meta) {
+            (FileType::Symlink, Some(m)) => m.link_name.len() as u64,
+            (FileType::RegularFile, Some(m)) => m.size,
+            _ => 0,
+        };
+        let mtime = meta.map_or(UNIX_EPOCH, |m| m.modified());
+        let default_mode = if kind == FileType::Directory { 0o755 } else { 0o644 };
+        FileAttr {
+            ino: ino_of(id),
+            size,
+            blocks: size.div_ceil(512),
+            atime: mtime,
+            mtime,
+            ctime: mtime,
+            crtime: mtime,
+            kind,
+            perm: meta.map_or(default_mode, |m| m.mode & 0o7777) as u16,
+            nlink: if kind == FileType::Directory { 2 } else { 1 },
+            uid: meta.map_or(0, |m| m.uid as u32),
+            gid: meta.map_or(0, |m| m.gid as u32),
+            rdev: meta.filter(|m| m.is_device()).map_or(0, |m| libc::makedev(m.devmajor, m.devminor) as u32),
+            flags: 0,
+            blksize: 512,
+        }
+    }
+
+    fn read_at(&self, meta: &EntryMeta, offset: u64, size: u32) -> io::Result<Vec<u8>> {
+        let file = try_into_tarfile(lock_image(&self.img)?.get_file_at(meta.offset)?.0)?;
+        let mut buf = vec![0u8; (size as u64).min(meta.size.saturating_sub(offset)) as usize];
+        let mut done = 0;
+        while done < buf.len() {
+            let n = file.read_body_at(offset + done as u64, &mut buf[done..])?;
+            if n == 0 {
+                break;
+            }
+            done += n;
+        }
+        buf.truncate(done);
+        Ok(buf)
+    }
+}
+
+impl Filesystem for TarFs {
+    fn lookup(&self, _req: &Request, parent: INodeNo, name: &OsStr, reply: ReplyEntry) {
+        let child = self.node_of(parent)
+            .zip(name.to_str())
+            .and_then(|(parent, name)| self.tree.children(parent).find(|(n, _)| *n == name))
+            .map(|(_, id)| id);
+        match child {
+            Some(id) => reply.entry(&TTL, &self.attr(id), Generation(0)),
+            None => reply.error(Errno::ENOENT),
+        }
+    }
+
+    fn getattr(&self, _req: &Request, ino: INodeNo, _fh: Option<FileHandle>, reply: ReplyAttr) {
+        match self.node_of(ino) {
+            Some(id) => reply.attr(&TTL, &self.attr(id)),
+            None => reply.error(Errno::ENOENT),
+        }
+    }
+
+    fn readlink(&self, _req: &Request, ino: INodeNo, reply: ReplyData) {
+        match self.node_of(ino).and_then(|id| self.tree.node(id).meta()) {
+            Some(meta) if meta.type_flag == '2' => reply.data(meta.link_name.as_bytes()),
+            _ => reply.error(Errno::EINVAL),
+        }
+    }
+
+    fn read(
+        &self,
+        _req: &Request,
+        ino: INodeNo,
+        _fh: FileHandle,
+        offset: u64,
+        size: u32,
+        _flags: OpenFlags,
+        _lock_owner: Option<LockOwner>,
+        reply: ReplyData,
+    ) {
+        let Some(meta) = self.node_of(ino).and_then(|id| self.tree.node(id).meta()) else {
+            return reply.error(Errno::ENOENT);
+        };
+        match self.read_at(self.resolve(meta), offset, size) {
+            Ok(data) => reply.data(&data),
+            Err(_) => reply.error(Errno::EIO),
+        }
+    }
+
+    fn readdir(&self, _req: &Request, ino: INodeNo, _fh: FileHandle, offset: u64, mut reply: ReplyDirectory) {
+        let Some(id) = self.node_of(ino) else {
+            return reply.error(Errno::ENOENT);
+        };
+        let parent = self.tree.node(id).parent().unwrap_or(id);
+        let mut entries = vec![(ino, FileType::Directory, "."), (ino_of(parent), FileType::Directory, "..")];
+        entries.extend(self.tree.children(id).map(|(name, child)| (ino_of(child), self.attr(child).kind, name)));
+        for (i, (child, kind, name)) in entries.into_iter().enumerate().skip(offset as usize) {
+            // 偏移为下一项的序号
+            if reply.add(child, (i + 1) as u64, kind, name) {
+                break;
+            }
+        }
+        reply.ok();
+    }
+}
+
+fn config(img: &Arc<Mutex<TarImage>>) -> io::Result<Config> {
+    let mut config = Config::default();
+    config.mount_options = vec![
+        MountOption::RO,
+        MountOption::FSName(lock_image(img)?.get_path()),
+        MountOption::Subtype("pt".to_string()),
+    ];
+    Ok(config)
+}
+
+/// 只读挂载归档，阻塞到卸载为止
+pub fn mount<P: AsRef<Path>>(img: Arc<Mutex<TarImage>>, mountpoint: P) -> io::Result<()> {
+    let config = config(&img)?;
+    fuser::mount(TarFs::new(img)?, mountpoint, &config)
+}
+
+/// 在后台线程挂载，返回的句柄被丢弃时自动卸载
+pub fn spawn_mount<P: AsRef<Path>>(img: Arc<Mutex<TarImage>>, mountpoint: P) -> io::Result<BackgroundSession> {
+    let config = config(&img)?;
+    fuser::spawn_mount(TarFs::new(img)?, mountpoint, &config)
+}
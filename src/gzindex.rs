@@ -0,0 +1,295 @@
+//! 可随机访问的 .tar.gz
+//!
+//! deflate 流不能从中间开始解压，但多成员 gzip（bgzip、`pigz --independent` 以及
+//! `GzipIndexWriter` 的输出）的每个成员都能单独解压。`GzipIndex` 记录各成员的起点，
+//! 读取时从不超过目标偏移的最近起点开始解压；只有一个成员的普通 gzip 仍需从头解压。
+//! 索引可按 bgzip 的 `.gzi` 格式保存与读取。
+
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use flate2::bufread::{GzDecoder, MultiGzDecoder};
+use flate2::write::GzEncoder;
+use crate::backend::{Backend, BlockCache, FileBackend};
+use crate::base::TarImage;
+
+/// `open_gzip` 缓存解压结果的块大小与总容量
+const CACHE_BLOCK: usize = 256 * 1024;
+const CACHE_CAPACITY: usize = 32 * 1024 * 1024;
+
+/// 一个可以独立解压的位置
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Checkpoint {
+    /// 成员在压缩文件中的偏移
+    pub compressed: u64,
+    /// 对应的解压后偏移
+    pub uncompressed: u64,
+}
+
+/// gzip 成员索引
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GzipIndex {
+    /// 按偏移递增，第一个总是 (0, 0)
+    points: Vec<Checkpoint>,
+    /// 解压后的总长
+    len: u64,
+}
+
+/// 记录已消费字节数的 BufRead，用来找出成员的结束位置
+struct Counted<R> {
+    inner: R,
+    pos: u64,
+}
+
+impl<R: BufRead> Read for Counted<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+impl<R: BufRead> BufRead for Counted<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.inner.consume(amt);
+        self.pos += amt as u64;
+    }
+}
+
+/// 从 backend 的 offset 开始顺序读取
+struct BackendReader<'a> {
+    backend: &'a dyn Backend,
+    pos: u64,
+}
+
+impl Read for BackendReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.backend.read_at(buf, self.pos)?;
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+/// 依次解压每个成员，返回 (成员起点, 解压后总长)
+fn scan_members<R: Read>(reader: R) -> io::Result<(Vec<Checkpoint>, u64)> {
+    let mut input = Counted { inner: BufReader::new(reader), pos: 0 };
+    let mut points = Vec::new();
+    let mut len = 0;
+    while !input.fill_buf()?.is_empty() {
+        points.push(Checkpoint { compressed: input.pos, uncompressed: len });
+        len += io::copy(&mut GzDecoder::new(&mut input), &mut io::sink())?;
+    }
+    if points.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "empty gzip stream"));
+    }
+    Ok((points, len))
+}
+
+impl GzipIndex {
+    /// 完整解压一遍，记录每个成员的起点
+    pub fn build<R: Read>(reader: R) -> io::Result<Self> {
+        let (points, len) = scan_members(reader)?;
+        Ok(GzipIndex { points, len })
+    }
+
+    pub fn checkpoints(&self) -> &[Checkpoint] {
+        &self.points
+    }
+
+    /// 解压后的总长
+    pub fn uncompressed_len(&self) -> u64 {
+        self.len
+    }
+
+    /// 不超过 offset 的最近起点
+    fn checkpoint_for(&self, offset: u64) -> Checkpoint {
+        let i = self.points.partition_point(|p| p.uncompressed <= offset);
+        self.points[i.saturating_sub(1)]
+    }
+
+    /// 按 bgzip `.gzi` 格式写出：条目数，随后每项为 (压缩偏移, 解压偏移)，均为 u64 小端，省略开头的 (0, 0)
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let points = &self.points[1..];
+        let mut out = Vec::with_capacity(8 + points.len() * 16);
+        out.extend_from_slice(&(points.len() as u64).to_le_bytes());
+        for point in points {
+            out.extend_from_slice(&point.compressed.to_le_bytes());
+            out.extend_from_slice(&point.uncompressed.to_le_bytes());
+        }
+        fs::write(path, out)
+    }
+
+    /// 读取 `.gzi` 索引；文件中没有总长，通过解压 source 的最后一个成员得到
+    pub fn load<P: AsRef<Path>>(path: P, source: &dyn Backend) -> io::Result<Self> {
+        let data = fs::read(path)?;
+        let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, format!("invalid gzi index: {}", msg));
+        let count = data.get(..8).map(|b| u64::from_le_bytes(b.try_into().unwrap())).ok_or_else(|| invalid("truncated"))?;
+        if count.checked_mul(16).and_then(|n| n.checked_add(8)) != Some(data.len() as u64) {
+            return Err(invalid("length does not match entry count"));
+        }
+        let mut points = vec![Checkpoint { compressed: 0, uncompressed: 0 }];
+        for pair in data[8..].chunks_exact(16) {
+            let point = Checkpoint {
+                compressed: u64::from_le_bytes(pair[..8].try_into().unwrap()),
+                uncompressed: u64::from_le_bytes(pair[8..].try_into().unwrap()),
+            };
+            let last = points[points.len() - 1];
+            if point.compressed <= last.compressed || point.uncompressed < last.uncompressed {
+                return Err(invalid("offsets are not increasing"));
+            }
+            points.push(point);
+        }
+        let last = points[points.len() - 1];
+        let (_, tail) = scan_members(BackendReader { backend: source, pos: last.compressed })?;
+        Ok(GzipIndex { points, len: last.uncompressed + tail })
+    }
+}
+
+/// 按索引随机读取解压后的内容；每次读取都从最近的起点解压，通常再套一层 `BlockCache`
+pub struct GzipBackend<B: Backend> {
+    inner: B,
+    index: GzipIndex,
+}
+
+impl<B: Backend> GzipBackend<B> {
+    pub fn new(inner: B, index: GzipIndex) -> Self {
+        GzipBackend { inner, index }
+    }
+
+    pub fn index(&self) -> &GzipIndex {
+        &self.index
+    }
+}
+
+impl<B: Backend> Backend for GzipBackend<B> {
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        if offset >= self.index.len || buf.is_empty() {
+            return Ok(0);
+        }
+        let point = self.index.checkpoint_for(offset);
+        let source = BufReader::new(BackendReader { backend: &self.inner, pos: point.compressed });
+        let mut decoder = MultiGzDecoder::new(source);
+        let skip = offset - point.uncompressed;
+        if io::copy(&mut (&mut decoder).take(skip), &mut io::sink())? < skip {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "gzip stream shorter than its index"));
+        }
+        let mut done = 0;
+        while done < buf.len() {
+            match decoder.read(&mut buf[done..]) {
+                Ok(0) => break,
+                Ok(n) => done += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(done)
+    }
+
+    fn len(&self) -> io::Result<u64> {
+        Ok(self.index.len)
+    }
+}
+
+/// 计数的 writer，记录已写出的压缩字节数
+struct CountingWriter<W> {
+    inner: W,
+    written: u64,
+}
+
+impl<W: Write> Write for CountingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.written += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// 写出多成员 gzip：每 span 字节明文开始一个新成员并记录起点，
+/// 结果仍是标准 gzip，任何解压工具都能读取
+pub struct GzipIndexWriter<W: Write> {
+    encoder: Option<GzEncoder<CountingWriter<W>>>,
+    level: flate2::Compression,
+    span: u64,
+    /// 当前成员已写入的明文字节数
+    member_len: u64,
+    total: u64,
+    points: Vec<Checkpoint>,
+}
+
+impl<W: Write> GzipIndexWriter<W> {
+    pub fn new(inner: W, span: u64, level: flate2::Compression) -> Self {
+        GzipIndexWriter {
+            encoder: Some(GzEncoder::new(CountingWriter { inner, written: 0 }, level)),
+            level,
+            span: span.max(1),
+            member_len: 0,
+            total: 0,
+            points: vec![Checkpoint { compressed: 0, uncompressed: 0 }],
+        }
+    }
+
+    fn encoder(&mut self) -> &mut GzEncoder<CountingWriter<W>> {
+        self.encoder.as_mut().expect("GzipIndexWriter used after finish")
+    }
+
+    /// 结束当前成员并开始下一个
+    fn next_member(&mut self) -> io::Result<()> {
+        let out = self.encoder.take().expect("GzipIndexWriter used after finish").finish()?;
+        self.points.push(Checkpoint { compressed: out.written, uncompressed: self.total });
+        self.encoder = Some(GzEncoder::new(out, self.level));
+        self.member_len = 0;
+        Ok(())
+    }
+
+    /// 写出最后一个成员，返回内层 writer 与索引
+    pub fn finish(mut self) -> io::Result<(W, GzipIndex)> {
+        let out = self.encoder.take().expect("GzipIndexWriter used after finish").finish()?;
+        Ok((out.inner, GzipIndex { points: self.points, len: self.total }))
+    }
+}
+
+impl<W: Write> Write for GzipIndexWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        if self.member_len >= self.span {
+            self.next_member()?;
+        }
+        let room = (self.span - self.member_len).min(buf.len() as u64) as usize;
+        let n = self.encoder().write(&buf[..room])?;
+        self.member_len += n as u64;
+        self.total += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.encoder().flush()
+    }
+}
+
+impl TarImage {
+    /// 打开 .tar.gz 并随机读取：有同名 `.gzi` 索引时直接使用，否则解压一遍建立索引
+    pub fn open_gzip<P: AsRef<Path>>(path: P) -> io::Result<Arc<Mutex<TarImage>>> {
+        let path = path.as_ref();
+        let file = FileBackend::open(path)?;
+        let mut gzi = path.as_os_str().to_owned();
+        gzi.push(".gzi");
+        let index = match GzipIndex::load(&gzi, &file) {
+            Ok(index) => index,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => GzipIndex::build(BackendReader { backend: &file, pos: 0 })?,
+            Err(e) => return Err(e),
+        };
+        let backend = BlockCache::new(GzipBackend::new(file, index), CACHE_BLOCK, CACHE_CAPACITY);
+        TarImage::from_backend(backend, &path.to_string_lossy())
+    }
+}
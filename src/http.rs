@@ -0,0 +1,142 @@
+//! 通过 HTTP `Range:` 请求按需读取远程 tar
+//!
+//! 只支持明文 `http://`，每次读取使用一个新连接；服务器必须支持范围请求（返回 206）。
+//! 配合 `TarImage` 的预读缓冲，扫描 header 时每次请求约 1 MiB。
+
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use crate::backend::Backend;
+use crate::base::TarImage;
+
+/// 连接与读写的超时时间，超时按暂时性错误处理，可由 `RetryPolicy` 重试
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// 远程 tar
+pub struct HttpBackend {
+    /// host:port
+    authority: String,
+    host: String,
+    path: String,
+    len: u64,
+}
+
+fn invalid(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// 拆分 `http://host[:port]/path`
+fn parse_url(url: &str) -> io::Result<(String, String, String)> {
+    let rest = url.strip_prefix("http://").ok_or_else(|| {
+        io::Error::new(io::ErrorKind::Unsupported, format!("only http:// URLs are supported: {}", url))
+    })?;
+    let (authority, path) = match rest.find('/') {
+        Some(i) => (&rest[..i], &rest[i..]),
+        None => (rest, "/"),
+    };
+    if authority.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("URL has no host: {}", url)));
+    }
+    let host = authority.to_string();
+    let authority = if authority.contains(':') { host.clone() } else { format!("{}:80", authority) };
+    Ok((authority, host, path.to_string()))
+}
+
+/// 一次范围请求的响应：(Content-Range 中的总长, 数据)
+struct RangeResponse {
+    total: u64,
+    body: Vec<u8>,
+}
+
+impl HttpBackend {
+    /// 发出一次范围请求取得总长
+    pub fn open(url: &str) -> io::Result<Self> {
+        let (authority, host, path) = parse_url(url)?;
+        let mut backend = HttpBackend { authority, host, path, len: 0 };
+        backend.len = backend.get_range(0, 0)?.total;
+        Ok(backend)
+    }
+
+    /// 请求 [start, end] 闭区间
+    fn get_range(&self, start: u64, end: u64) -> io::Result<RangeResponse> {
+        let stream = TcpStream::connect(&self.authority)?;
+        stream.set_read_timeout(Some(TIMEOUT))?;
+        stream.set_write_timeout(Some(TIMEOUT))?;
+        let mut writer = &stream;
+        write!(writer, "GET {} HTTP/1.1\r\nHost: {}\r\nRange: bytes={}-{}\r\nConnection: close\r\nUser-Agent: pt\r\n\r\n",
+            self.path, self.host, start, end)?;
+        writer.flush()?;
+
+        let mut reader = BufReader::new(&stream);
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        let status = line.split_whitespace().nth(1).unwrap_or("");
+        match status {
+            "206" => {}
+            "200" => return Err(io::Error::new(io::ErrorKind::Unsupported, format!(
+                "server does not support range requests for {}", self.path))),
+            "404" => return Err(io::Error::new(io::ErrorKind::NotFound, format!("{} not found", self.path))),
+            _ => return Err(invalid(format!("unexpected HTTP status for {}: {}", self.path, line.trim_end()))),
+        }
+
+        let mut content_length = None;
+        let mut total = None;
+        loop {
+            line.clear();
+            if reader.read_line(&mut line)? == 0 {
+                return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "connection closed inside HTTP headers"));
+            }
+            let header = line.trim_end();
+            if header.is_empty() {
+                break;
+            }
+            let Some((name, value)) = header.split_once(':') else { continue };
+            let value = value.trim();
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.parse::<u64>().ok();
+            } else if name.eq_ignore_ascii_case("content-range") {
+                // bytes <start>-<end>/<total>
+                total = value.rsplit('/').next().and_then(|t| t.parse::<u64>().ok());
+            }
+        }
+        let total = total.ok_or_else(|| invalid(format!("missing Content-Range for {}", self.path)))?;
+        let mut body = Vec::new();
+        match content_length {
+            Some(len) => {
+                reader.take(len).read_to_end(&mut body)?;
+                if body.len() as u64 != len {
+                    return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "connection closed inside HTTP body"));
+                }
+            }
+            None => {
+                reader.read_to_end(&mut body)?;
+            }
+        }
+        Ok(RangeResponse { total, body })
+    }
+}
+
+impl Backend for HttpBackend {
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        if offset >= self.len || buf.is_empty() {
+            return Ok(0);
+        }
+        let end = (offset + buf.len() as u64).min(self.len) - 1;
+        let response = self.get_range(offset, end)?;
+        let n = response.body.len().min(buf.len());
+        buf[..n].copy_from_slice(&response.body[..n]);
+        Ok(n)
+    }
+
+    fn len(&self) -> io::Result<u64> {
+        Ok(self.len)
+    }
+}
+
+impl TarImage {
+    /// 通过范围请求打开远程 tar，只下载扫描与读取到的部分
+    pub fn open_url(url: &str) -> io::Result<Arc<Mutex<TarImage>>> {
+        TarImage::from_backend(HttpBackend::open(url)?, url)
+    }
+}
@@ -0,0 +1,87 @@
+//! 解包时的 uid / gid 映射，与 user namespace 的 `/proc/<pid>/uid_map` 语义相同
+
+use std::io;
+use crate::acl::{Acl, AclTag};
+
+/// 连续的一段 id：归档中的 [inner, inner + count) 映射到本机的 [outer, outer + count)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IdRange {
+    pub inner: u32,
+    pub outer: u32,
+    pub count: u32,
+}
+
+impl IdRange {
+    fn map(&self, id: u32) -> Option<u32> {
+        let delta = id.checked_sub(self.inner)?;
+        (delta < self.count).then(|| self.outer + delta)
+    }
+}
+
+/// uid 与 gid 的映射表；某一类没有任何区间时该类 id 原样使用，
+/// 有区间但 id 不落在任何区间内时报错（与内核拒绝未映射的 id 一致）
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IdMap {
+    pub uids: Vec<IdRange>,
+    pub gids: Vec<IdRange>,
+}
+
+/// 解析 `uid_map` 格式的文本：每行 "inner outer count"
+fn parse_ranges(text: &str) -> io::Result<Vec<IdRange>> {
+    let mut ranges = Vec::new();
+    for line in text.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        let invalid = || io::Error::new(io::ErrorKind::InvalidInput, format!("invalid id map line: {:?}", line));
+        let fields: Vec<u32> = line.split_whitespace()
+            .map(|f| f.parse().map_err(|_| invalid()))
+            .collect::<io::Result<_>>()?;
+        let [inner, outer, count] = fields[..] else { return Err(invalid()) };
+        if count == 0 || inner.checked_add(count - 1).is_none() || outer.checked_add(count - 1).is_none() {
+            return Err(invalid());
+        }
+        ranges.push(IdRange { inner, outer, count });
+    }
+    Ok(ranges)
+}
+
+impl IdMap {
+    /// 把 uid 与 gid 的 [0, count) 整体平移 offset，如 `IdMap::shift(100000, 65536)`
+    pub fn shift(offset: u32, count: u32) -> Self {
+        let range = vec![IdRange { inner: 0, outer: offset, count }];
+        IdMap { uids: range.clone(), gids: range }
+    }
+
+    /// 由 `uid_map` / `gid_map` 格式的文本构造
+    pub fn parse(uid_map: &str, gid_map: &str) -> io::Result<Self> {
+        Ok(IdMap { uids: parse_ranges(uid_map)?, gids: parse_ranges(gid_map)? })
+    }
+
+    fn map(ranges: &[IdRange], id: u32, what: &str) -> io::Result<u32> {
+        if ranges.is_empty() {
+            return Ok(id);
+        }
+        ranges.iter().find_map(|r| r.map(id)).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidData, format!("{} {} is not mapped", what, id))
+        })
+    }
+
+    pub fn map_uid(&self, uid: u32) -> io::Result<u32> {
+        Self::map(&self.uids, uid, "uid")
+    }
+
+    pub fn map_gid(&self, gid: u32) -> io::Result<u32> {
+        Self::map(&self.gids, gid, "gid")
+    }
+
+    /// 映射 ACL 中命名用户 / 组的数字 id；只有名称的条目保持不变
+    pub fn map_acl(&self, acl: &Acl) -> io::Result<Acl> {
+        let mut acl = acl.clone();
+        for entry in &mut acl.entries {
+            match &mut entry.tag {
+                AclTag::User { id: Some(id), .. } => *id = self.map_uid(*id)?,
+                AclTag::Group { id: Some(id), .. } => *id = self.map_gid(*id)?,
+                _ => {}
+            }
+        }
+        Ok(acl)
+    }
+}
@@ -0,0 +1,365 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use crate::base::{lock_image, try_into_tarfile, ImageInfo, TarImage};
+use crate::builder::{mode_and_mtime, EntryBuilder, TarBuilder};
+use crate::error::PtError;
+use crate::extract::{copy_body, extract_all};
+use crate::path::sanitize_path;
+
+/// GNU dumpdir 记录中的一项
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DumpDirEntry {
+    /// 'Y'：文件包含在本归档中
+    Included(String),
+    /// 'N'：文件未变化，存在于之前的归档中
+    Unchanged(String),
+    /// 'D'：子目录
+    Directory(String),
+    /// 'R' / 'T'：重命名的源与目标
+    Rename(String),
+    RenameTarget(String),
+    /// 'X'：临时目录名
+    Temp(String),
+}
+
+impl DumpDirEntry {
+    pub fn name(&self) -> &str {
+        match self {
+            DumpDirEntry::Included(s)
+            | DumpDirEntry::Unchanged(s)
+            | DumpDirEntry::Directory(s)
+            | DumpDirEntry::Rename(s)
+            | DumpDirEntry::RenameTarget(s)
+            | DumpDirEntry::Temp(s) => s,
+        }
+    }
+
+    /// dumpdir 中的控制字符
+    pub fn code(&self) -> u8 {
+        match self {
+            DumpDirEntry::Included(_) => b'Y',
+            DumpDirEntry::Unchanged(_) => b'N',
+            DumpDirEntry::Directory(_) => b'D',
+            DumpDirEntry::Rename(_) => b'R',
+            DumpDirEntry::RenameTarget(_) => b'T',
+            DumpDirEntry::Temp(_) => b'X',
+        }
+    }
+
+    /// 该项是否代表增量完成后目录中应存在的成员
+    pub fn is_member(&self) -> bool {
+        matches!(self, DumpDirEntry::Included(_) | DumpDirEntry::Unchanged(_) | DumpDirEntry::Directory(_))
+    }
+}
+
+/// 解析 'D' 条目的数据区：每项为 控制字符 + 名称 + '\0'，以空项结束
+pub fn parse_dumpdir(body: &[u8]) -> io::Result<Vec<DumpDirEntry>> {
+    let mut entries = Vec::new();
+    for item in body.split(|&b| b == 0) {
+        if item.is_empty() {
+            break;
+        }
+        let name = String::from_utf8_lossy(&item[1..]).into_owned();
+        let entry = match item[0] {
+            b'Y' => DumpDirEntry::Included(name),
+            b'N' => DumpDirEntry::Unchanged(name),
+            b'D' => DumpDirEntry::Directory(name),
+            b'R' => DumpDirEntry::Rename(name),
+            b'T' => DumpDirEntry::RenameTarget(name),
+            b'X' => DumpDirEntry::Temp(name),
+            c => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("unknown dumpdir control code: {:?}", c as char),
+                ));
+            }
+        };
+        entries.push(entry);
+    }
+    Ok(entries)
+}
+
+/// 生成 'D' 条目的数据区，与 `parse_dumpdir` 互逆
+pub fn encode_dumpdir(entries: &[DumpDirEntry]) -> Vec<u8> {
+    let mut out = Vec::new();
+    for entry in entries {
+        out.push(entry.code());
+        out.extend_from_slice(entry.name().as_bytes());
+        out.push(0);
+    }
+    out.push(0);
+    out
+}
+
+/// 快照文件中一个目录的记录
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirRecord {
+    pub nfs: bool,
+    /// 备份时目录的修改时间
+    pub mtime: Duration,
+    pub dev: u64,
+    pub ino: u64,
+    pub entries: Vec<DumpDirEntry>,
+}
+
+/// GNU `--listed-incremental` 快照文件（格式 2）
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Snapshot {
+    /// 生成该快照的备份开始时间，下一级备份只包含此后修改的文件
+    pub time: Duration,
+    /// 归档内目录路径（不含结尾的 '/'） -> 记录
+    pub dirs: BTreeMap<String, DirRecord>,
+}
+
+const SNAPSHOT_HEADER: &str = "GNU tar-1.35-2\n";
+
+fn snapshot_error(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("invalid snapshot file: {}", msg))
+}
+
+impl Snapshot {
+    /// 解析快照文件内容，只支持格式 2
+    pub fn parse(data: &[u8]) -> io::Result<Self> {
+        let newline = data.iter().position(|&b| b == b'\n').ok_or_else(|| snapshot_error("missing header line"))?;
+        let header = String::from_utf8_lossy(&data[..newline]);
+        if !header.starts_with("GNU tar-") || !header.ends_with("-2") {
+            return Err(snapshot_error(&format!("unsupported format {:?}", header)));
+        }
+        let mut fields = data[newline + 1..].split(|&b| b == 0);
+        let mut next = |what: &str| fields.next().ok_or_else(|| snapshot_error(&format!("missing {}", what)));
+        let number = |field: &[u8], what: &str| -> io::Result<u64> {
+            std::str::from_utf8(field).ok().and_then(|s| s.parse().ok()).ok_or_else(|| snapshot_error(&format!("bad {}", what)))
+        };
+        let time = Duration::new(number(next("time")?, "time")?, number(next("time")?, "time")? as u32);
+        let mut dirs = BTreeMap::new();
+        loop {
+            let nfs = match next("record") {
+                Ok(field) if !field.is_empty() => field == b"1",
+                // 文件以最后一条记录的两个 '\0' 结束
+                _ => break,
+            };
+            let mtime = Duration::new(number(next("mtime")?, "mtime")?, number(next("mtime")?, "mtime")? as u32);
+            let dev = number(next("dev")?, "dev")?;
+            let ino = number(next("ino")?, "ino")?;
+            let name = String::from_utf8_lossy(next("name")?).into_owned();
+            let mut body = Vec::new();
+            loop {
+                let item = next("dumpdir")?;
+                if item.is_empty() {
+                    break;
+                }
+                body.extend_from_slice(item);
+                body.push(0);
+            }
+            dirs.insert(name, DirRecord { nfs, mtime, dev, ino, entries: parse_dumpdir(&body)? });
+        }
+        Ok(Snapshot { time, dirs })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = SNAPSHOT_HEADER.as_bytes().to_vec();
+        out.extend(format!("{}\0{}\0", self.time.as_secs(), self.time.subsec_nanos()).into_bytes());
+        for (name, dir) in &self.dirs {
+            out.extend(format!("{}\0{}\0{}\0{}\0{}\0{}\0", dir.nfs as u8, dir.mtime.as_secs(), dir.mtime.subsec_nanos(),
+                dir.dev, dir.ino, name).into_bytes());
+            out.extend(encode_dumpdir(&dir.entries));
+        }
+        out
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        Snapshot::parse(&fs::read(path)?)
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, self.to_bytes())
+    }
+}
+
+fn since_epoch(t: io::Result<SystemTime>) -> Duration {
+    t.ok().and_then(|t| t.duration_since(UNIX_EPOCH).ok()).unwrap_or_default()
+}
+
+/// 判断文件是否变化所用的时间：mtime 与 ctime 中较晚者，这样 chmod、改名也会被包含
+fn change_time(md: &fs::Metadata) -> Duration {
+    let mtime = since_epoch(md.modified());
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        mtime.max(Duration::new(md.ctime().max(0) as u64, md.ctime_nsec().max(0) as u32))
+    }
+    #[cfg(not(unix))]
+    mtime
+}
+
+#[cfg(unix)]
+fn dev_ino(md: &fs::Metadata) -> (u64, u64) {
+    use std::os::unix::fs::MetadataExt;
+    (md.dev(), md.ino())
+}
+
+#[cfg(not(unix))]
+fn dev_ino(_md: &fs::Metadata) -> (u64, u64) {
+    (0, 0)
+}
+
+/// 一次增量备份的状态
+struct Backup<'a> {
+    /// 上一级快照，level 0 时为 None
+    previous: Option<&'a Snapshot>,
+    next: Snapshot,
+    entries: u64,
+}
+
+impl Backup<'_> {
+    /// 写出 name 及其下的内容；since 为 None 表示全部包含
+    fn walk<W: Write>(&mut self, builder: &mut TarBuilder<W>, name: &str, path: &Path, since: Option<Duration>) -> io::Result<()> {
+        let md = fs::symlink_metadata(path)?;
+        if !md.is_dir() {
+            if since.is_none_or(|t| change_time(&md) > t) {
+                builder.append_path(name, path)?;
+                self.entries += 1;
+            }
+            return Ok(());
+        }
+        // 上一级快照中没有的目录（新建或移动过来的）整体包含
+        let since = since.filter(|_| self.previous.is_some_and(|p| p.dirs.contains_key(name)));
+        let mut children: Vec<_> = fs::read_dir(path)?.collect::<io::Result<_>>()?;
+        children.sort_by_key(|c| c.file_name());
+        let mut dumpdir = Vec::new();
+        for child in &children {
+            let child_name = child.file_name().to_string_lossy().into_owned();
+            let child_md = fs::symlink_metadata(child.path())?;
+            if child_md.is_dir() {
+                dumpdir.push(DumpDirEntry::Directory(child_name));
+            } else if since.is_none_or(|t| change_time(&child_md) > t) {
+                dumpdir.push(DumpDirEntry::Included(child_name));
+            } else {
+                dumpdir.push(DumpDirEntry::Unchanged(child_name));
+            }
+        }
+        let (mode, mtime) = mode_and_mtime(&md);
+        let body = encode_dumpdir(&dumpdir);
+        builder.append(&EntryBuilder::new(&format!("{}/", name), b'D').mode(mode).mtime(mtime).data(&body))?;
+        self.entries += 1;
+        let (dev, ino) = dev_ino(&md);
+        self.next.dirs.insert(name.to_string(), DirRecord {
+            nfs: false,
+            mtime: since_epoch(md.modified()),
+            dev,
+            ino,
+            entries: dumpdir.clone(),
+        });
+        for (child, entry) in children.iter().zip(&dumpdir) {
+            let child_name = format!("{}/{}", name, entry.name());
+            match entry {
+                DumpDirEntry::Directory(_) => self.walk(builder, &child_name, &child.path(), since)?,
+                DumpDirEntry::Included(_) => {
+                    builder.append_path(&child_name, &child.path())?;
+                    self.entries += 1;
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 相当于 `tar --listed-incremental=<snapshot>`：snapshot 不存在时做完整备份（level 0），
+/// 否则只写出快照时间之后变化的文件（level 1）；每个目录写成带 dumpdir 的 'D' 条目，
+/// 恢复时据此删除已不存在的文件。完成后用本次备份的状态覆盖 snapshot，返回写出的条目数
+pub fn create_incremental<W: Write>(writer: W, inputs: &[(&str, &Path)], snapshot: &Path) -> io::Result<u64> {
+    let previous = match Snapshot::load(snapshot) {
+        Ok(previous) => Some(previous),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+        Err(e) => return Err(e),
+    };
+    let mut backup = Backup {
+        previous: previous.as_ref(),
+        next: Snapshot { time: since_epoch(Ok(SystemTime::now())), dirs: BTreeMap::new() },
+        entries: 0,
+    };
+    let mut builder = TarBuilder::new(writer);
+    for (name, path) in inputs {
+        let since = previous.as_ref().map(|p| p.time);
+        backup.walk(&mut builder, name.trim_end_matches('/'), path, since)?;
+    }
+    builder.finish()?;
+    backup.next.save(snapshot)?;
+    Ok(backup.entries)
+}
+
+/// 收集增量归档中的所有 dumpdir 记录：(目录路径, 记录项)
+fn collect_dumpdirs(img: &mut TarImage) -> io::Result<Vec<(String, Vec<DumpDirEntry>)>> {
+    let mut dirs = Vec::new();
+    img.for_each_entry(|file| {
+        let tar_file = try_into_tarfile(file)?;
+        if tar_file.get_type_flag() == 'D' {
+            let mut body = Vec::new();
+            copy_body(&tar_file, &mut body)?;
+            dirs.push((tar_file.meta().path, parse_dumpdir(&body)?));
+        }
+        Ok(())
+    })?;
+    Ok(dirs)
+}
+
+/// dumpdir 记录的目录在 dest 下的位置：路径须通过 `sanitize_path`，途经的已有路径中不能有符号链接
+fn dumpdir_target(dest: &Path, dir: &str) -> io::Result<PathBuf> {
+    let rel = sanitize_path(dir)?;
+    let mut current = dest.to_path_buf();
+    for component in rel.split('/').filter(|c| !c.is_empty()) {
+        current.push(component);
+        match fs::symlink_metadata(&current) {
+            Ok(md) if md.file_type().is_symlink() => return Err(PtError::UnsafePath { path: dir.to_string() }.into()),
+            Ok(_) => {}
+            Err(_) => break,
+        }
+    }
+    Ok(dest.join(rel))
+}
+
+/// 按 dumpdir 删除目录中已不存在于新备份里的成员
+fn apply_deletions(target: &Path, entries: &[DumpDirEntry]) -> io::Result<()> {
+    if !target.is_dir() {
+        return Ok(());
+    }
+    let keep: HashSet<&str> = entries.iter().filter(|e| e.is_member()).map(|e| e.name()).collect();
+    for child in fs::read_dir(target)? {
+        let child = child?;
+        let name = child.file_name();
+        if keep.contains(name.to_string_lossy().as_ref()) {
+            continue;
+        }
+        if child.file_type()?.is_dir() {
+            fs::remove_dir_all(child.path())?;
+        } else {
+            fs::remove_file(child.path())?;
+        }
+    }
+    Ok(())
+}
+
+/// 解包完整备份，再依次应用各个增量备份（包括 dumpdir 记录的删除）；
+/// 增量备份中任一 dumpdir 路径不安全时，在删除任何文件之前报错
+pub fn restore_chain(full: &str, incrementals: &[&str], dest: &Path) -> io::Result<()> {
+    let img = TarImage::open(full)?;
+    extract_all(&mut *lock_image(&img)?, dest)?;
+
+    for path in incrementals {
+        let img = TarImage::open(path)?;
+        let mut img = lock_image(&img)?;
+        let deletions = collect_dumpdirs(&mut img)?
+            .into_iter()
+            .map(|(dir, entries)| Ok((dumpdir_target(dest, &dir)?, entries)))
+            .collect::<io::Result<Vec<_>>>()?;
+        for (target, entries) in deletions {
+            apply_deletions(&target, &entries)?;
+        }
+        extract_all(&mut img, dest)?;
+    }
+    Ok(())
+}
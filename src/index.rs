@@ -0,0 +1,793 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::mem::size_of;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, UNIX_EPOCH};
+use sha2::{Digest, Sha256};
+use crate::backend::read_file_at;
+use crate::base::{try_into_tarfile, ImageInfo, TarFile, TarImage};
+use crate::meta::EntryMeta;
+use crate::pax::PaxRecords;
+use crate::path::{normalize_path, normalize_path_bytes};
+
+/// 索引的内存预算
+#[derive(Debug, Clone)]
+pub struct IndexBudget {
+    /// 允许索引占用的最大字节数（估算值）
+    pub max_bytes: usize,
+    /// 超出预算时写入磁盘侧车索引的目录，None 时使用系统临时目录
+    pub sidecar_dir: Option<PathBuf>,
+}
+
+/// 索引当前的存储形式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum IndexKind {
+    /// 完整元数据常驻内存
+    Full,
+    /// 只保存 (路径哈希, 偏移)，元数据按需重新读取 header
+    Compact,
+    /// (路径哈希, 偏移) 保存在磁盘侧车文件中
+    Sidecar,
+}
+
+/// 条目遍历顺序
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum EntryOrder {
+    /// 归档中的出现顺序（偏移递增）
+    #[default]
+    Archive,
+    /// 按路径字节序排列；同名条目保持归档中的相对顺序
+    Path,
+}
+
+/// 侧车文件中的一条记录：路径哈希 + header 偏移，各 8 字节小端
+const SLOT_SIZE: u64 = 16;
+
+#[derive(Debug)]
+struct SidecarFile {
+    path: PathBuf,
+    file: File,
+    slots: u64,
+}
+
+impl Drop for SidecarFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+#[derive(Debug)]
+enum Storage {
+    /// by_path 以规范化后的原始路径字节为键，只差在非 UTF-8 字节上的名字不会相互覆盖
+    Full { entries: Vec<EntryMeta>, by_path: HashMap<Vec<u8>, usize> },
+    /// 按哈希排序
+    Compact(Vec<(u64, u64)>),
+    Sidecar(SidecarFile),
+}
+
+/// 一次扫描得到的条目索引，按路径查找
+#[derive(Debug)]
+pub struct Index {
+    storage: Storage,
+    len: usize,
+    /// 建立索引时归档的指纹，`save` 写入文件，`load` 时据此判断索引是否过期
+    source: Option<Fingerprint>,
+    /// 归档中各 'g' header 偏移处生效的全局记录，打开条目时交给镜像，免去顺序读取之前的 header
+    globals: Arc<BTreeMap<u64, PaxRecords>>,
+}
+
+impl Default for Index {
+    fn default() -> Self {
+        Index { storage: Storage::Full { entries: Vec::new(), by_path: HashMap::new() }, len: 0, source: None, globals: Arc::default() }
+    }
+}
+
+/// 归档指纹：大小、本地文件的修改时间，以及首尾各 SAMPLE_SIZE 字节的 sha256
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Fingerprint {
+    size: u64,
+    mtime: Option<Duration>,
+    sample: [u8; 32],
+}
+
+/// 指纹采样的字节数
+const SAMPLE_SIZE: u64 = 64 * 1024;
+
+impl Fingerprint {
+    fn of(img: &mut TarImage) -> io::Result<Self> {
+        let size = img.get_size()?;
+        let mtime = match img.local_path() {
+            Some(path) => fs::metadata(path)?.modified()?.duration_since(UNIX_EPOCH).ok(),
+            None => None,
+        };
+        let mut sha = Sha256::new();
+        sha.update(size.to_le_bytes());
+        let head = SAMPLE_SIZE.min(size);
+        sha.update(img.read_img_at(0, head)?.0);
+        let tail = SAMPLE_SIZE.min(size - head);
+        sha.update(img.read_img_at(size - tail, tail)?.0);
+        Ok(Fingerprint { size, mtime, sample: sha.finalize().into() })
+    }
+
+    /// 两边都有修改时间时才比较修改时间，远程 backend 只比较大小与采样摘要
+    fn matches(&self, other: &Fingerprint) -> bool {
+        let mtime_ok = match (self.mtime, other.mtime) {
+            (Some(a), Some(b)) => a == b,
+            _ => true,
+        };
+        self.size == other.size && self.sample == other.sample && mtime_ok
+    }
+}
+
+/// 路径哈希（FNV-1a），结果与运行环境无关
+pub(crate) fn path_hash(path: &[u8]) -> u64 {
+    let mut h: u64 = 0xcbf29ce484222325;
+    for &b in normalize_path_bytes(path) {
+        h ^= b as u64;
+        h = h.wrapping_mul(0x100000001b3);
+    }
+    h
+}
+
+/// 估算一条元数据占用的内存
+fn meta_footprint(meta: &EntryMeta) -> usize {
+    size_of::<EntryMeta>()
+        + meta.path.capacity()
+        + meta.link_name.capacity()
+        + meta.raw_path.as_ref().map_or(0, Vec::capacity)
+        + meta.raw_link_name.as_ref().map_or(0, Vec::capacity)
+        + meta.uname.capacity()
+        + meta.gname.capacity()
+        + meta.xattrs.iter().map(|(k, v)| k.capacity() + v.capacity() + 64).sum::<usize>()
+}
+
+/// 哈希表中一项的开销：键、值以及控制字节，按 7/8 装载率折算
+fn map_slot_footprint(key: &[u8]) -> usize {
+    (size_of::<(Vec<u8>, usize)>() + 1) * 8 / 7 + key.len()
+}
+
+/// 按哈希排序 (哈希, 偏移) 表；稳定排序保证同哈希的条目仍按归档顺序排列
+fn compact_slots(mut slots: Vec<(u64, u64)>) -> Vec<(u64, u64)> {
+    slots.sort_by_key(|&(h, _)| h);
+    slots.shrink_to_fit();
+    slots
+}
+
+impl Index {
+    /// 扫描整个镜像建立索引，同名条目以最后出现的为准
+    pub fn build(img: &mut TarImage) -> io::Result<Self> {
+        let mut index = Index { source: Some(Fingerprint::of(img)?), ..Index::default() };
+        img.for_each_entry(|file| {
+            let tar_file = try_into_tarfile(file)?;
+            index.push(tar_file.meta());
+            Ok(())
+        })?;
+        index.globals = img.complete_globals().unwrap_or_default();
+        Ok(index)
+    }
+
+    /// 在内存预算内建立索引：超过预算时先退化为紧凑索引，仍然超出则写入磁盘侧车文件
+    pub fn build_with_budget(img: &mut TarImage, budget: &IndexBudget) -> io::Result<Self> {
+        let source = Some(Fingerprint::of(img)?);
+        let mut index = Index { source: source.clone(), ..Index::default() };
+        let mut used = 0usize;
+        let mut slots: Option<Vec<(u64, u64)>> = None;
+        img.for_each_entry(|file| {
+            let tar_file = try_into_tarfile(file)?;
+            let meta = tar_file.meta();
+            if let Some(slots) = slots.as_mut() {
+                slots.push((path_hash(meta.path_bytes()), meta.offset));
+                index.len += 1;
+            } else {
+                used += meta_footprint(&meta) + map_slot_footprint(meta.path_bytes());
+                index.push(meta);
+                if used > budget.max_bytes {
+                    slots = Some(index.take_slots());
+                }
+            }
+            Ok(())
+        })?;
+        let globals = img.complete_globals().unwrap_or_default();
+
+        let Some(slots) = slots else { return Ok(Index { globals, ..index }) };
+        let len = index.len;
+        let slots = compact_slots(slots);
+        if slots.capacity() * size_of::<(u64, u64)>() <= budget.max_bytes {
+            return Ok(Index { storage: Storage::Compact(slots), len, source, globals });
+        }
+        let dir = budget.sidecar_dir.clone().unwrap_or_else(std::env::temp_dir);
+        let sidecar = write_sidecar(&dir, slots)?;
+        Ok(Index { storage: Storage::Sidecar(sidecar), len, source, globals })
+    }
+
+    /// 把完整索引转成 (哈希, 偏移) 列表，供退化使用
+    fn take_slots(&mut self) -> Vec<(u64, u64)> {
+        let storage = std::mem::replace(&mut self.storage, Storage::Compact(Vec::new()));
+        match storage {
+            Storage::Full { entries, .. } => entries.iter().map(|m| (path_hash(m.path_bytes()), m.offset)).collect(),
+            Storage::Compact(slots) => slots,
+            Storage::Sidecar(_) => Vec::new(),
+        }
+    }
+
+    fn push(&mut self, meta: EntryMeta) {
+        if let Storage::Full { entries, by_path } = &mut self.storage {
+            by_path.insert(normalize_path_bytes(meta.path_bytes()).to_vec(), entries.len());
+            entries.push(meta);
+            self.len += 1;
+        }
+    }
+
+    pub fn kind(&self) -> IndexKind {
+        match self.storage {
+            Storage::Full { .. } => IndexKind::Full,
+            Storage::Compact(_) => IndexKind::Compact,
+            Storage::Sidecar(_) => IndexKind::Sidecar,
+        }
+    }
+
+    /// 索引在内存中的估算占用（字节）
+    pub fn memory_usage(&self) -> usize {
+        size_of::<Self>() + match &self.storage {
+            Storage::Full { entries, by_path } => {
+                entries.iter().map(meta_footprint).sum::<usize>()
+                    + (entries.capacity() - entries.len()) * size_of::<EntryMeta>()
+                    + by_path.keys().map(|k| map_slot_footprint(k)).sum::<usize>()
+            }
+            Storage::Compact(slots) => slots.capacity() * size_of::<(u64, u64)>(),
+            Storage::Sidecar(_) => size_of::<SidecarFile>(),
+        }
+    }
+
+    /// 按归档顺序排列的全部条目；只有完整索引才保存元数据，其它形式返回空切片
+    pub fn entries(&self) -> &[EntryMeta] {
+        match &self.storage {
+            Storage::Full { entries, .. } => entries,
+            _ => &[],
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// 按路径取常驻内存的元数据；紧凑和侧车索引请使用 `stat`
+    pub fn get(&self, path: &str) -> Option<&EntryMeta> {
+        self.get_bytes(path.as_bytes())
+    }
+
+    /// 与 `get` 相同，按原始字节查找，可查找非 UTF-8 的名字
+    pub fn get_bytes(&self, path: &[u8]) -> Option<&EntryMeta> {
+        match &self.storage {
+            Storage::Full { entries, by_path } => by_path.get(normalize_path_bytes(path)).map(|&i| &entries[i]),
+            _ => None,
+        }
+    }
+
+    /// 哈希相同的候选偏移，按归档顺序排列
+    fn candidates(&self, path: &[u8]) -> io::Result<Vec<u64>> {
+        let hash = path_hash(path);
+        match &self.storage {
+            Storage::Full { .. } => Ok(self.get_bytes(path).map(|m| m.offset).into_iter().collect()),
+            Storage::Compact(slots) => {
+                let start = slots.partition_point(|&(h, _)| h < hash);
+                Ok(slots[start..].iter().take_while(|&&(h, _)| h == hash).map(|&(_, off)| off).collect())
+            }
+            Storage::Sidecar(sidecar) => sidecar.candidates(hash),
+        }
+    }
+
+    /// 把建立索引时记录的全局记录交给 img；来源不明的索引（如反序列化得到）由 img 自行顺序读取
+    fn lend_globals(&self, img: &mut TarImage) {
+        if self.source.is_some() {
+            img.adopt_globals(&self.globals);
+        }
+    }
+
+    /// 按路径直接定位并打开条目，无需重新扫描
+    pub fn open_entry(&self, img: &mut TarImage, path: &str) -> io::Result<Option<Box<TarFile>>> {
+        self.open_entry_bytes(img, path.as_bytes())
+    }
+
+    /// 与 `open_entry` 相同，按原始字节比较路径
+    pub fn open_entry_bytes(&self, img: &mut TarImage, path: &[u8]) -> io::Result<Option<Box<TarFile>>> {
+        self.lend_globals(img);
+        let wanted = normalize_path_bytes(path);
+        // 哈希冲突时逐个比对路径，同名以最后一个为准
+        for offset in self.candidates(path)?.into_iter().rev() {
+            let file = try_into_tarfile(img.get_file_at(offset)?.0)?;
+            if normalize_path_bytes(&file.get_path_bytes()) == wanted {
+                return Ok(Some(file));
+            }
+        }
+        Ok(None)
+    }
+
+    /// 按路径取元数据，适用于所有索引形式
+    pub fn stat(&self, img: &mut TarImage, path: &str) -> io::Result<Option<EntryMeta>> {
+        if let Some(meta) = self.get(path) {
+            return Ok(Some(meta.clone()));
+        }
+        Ok(self.open_entry(img, path)?.map(|f| f.meta()))
+    }
+
+    /// 批量取元数据，结果与 paths 一一对应；非完整索引时按偏移顺序读取 header
+    pub fn stat_many<S: AsRef<str>>(&self, img: &mut TarImage, paths: &[S]) -> io::Result<Vec<Option<EntryMeta>>> {
+        let mut results = vec![None; paths.len()];
+        let mut pending = Vec::new();
+        for (i, path) in paths.iter().enumerate() {
+            match self.get(path.as_ref()) {
+                Some(meta) => results[i] = Some(meta.clone()),
+                None if self.kind() != IndexKind::Full => {
+                    pending.extend(self.candidates(path.as_ref().as_bytes())?.into_iter().map(|offset| (offset, i)));
+                }
+                None => {}
+            }
+        }
+        // 按偏移排序，同名多个候选时后出现的覆盖前面的
+        pending.sort_unstable();
+        self.lend_globals(img);
+        for (offset, i) in pending {
+            let meta = try_into_tarfile(img.get_file_at(offset)?.0)?.meta();
+            if normalize_path_bytes(meta.path_bytes()) == normalize_path_bytes(paths[i].as_ref().as_bytes()) {
+                results[i] = Some(meta);
+            }
+        }
+        Ok(results)
+    }
+}
+
+/// 序列化形式：完整索引保存元数据，紧凑与侧车索引保存 (路径哈希, 偏移)，反序列化后侧车索引变为紧凑索引
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum IndexRepr {
+    Full(Vec<EntryMeta>),
+    Compact(Vec<(u64, u64)>),
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Index {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let repr = match &self.storage {
+            Storage::Full { entries, .. } => IndexRepr::Full(entries.clone()),
+            Storage::Compact(slots) => IndexRepr::Compact(slots.clone()),
+            Storage::Sidecar(sidecar) => IndexRepr::Compact(
+                (0..sidecar.slots).map(|i| sidecar.slot(i)).collect::<io::Result<_>>().map_err(serde::ser::Error::custom)?,
+            ),
+        };
+        repr.serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Index {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(match IndexRepr::deserialize(deserializer)? {
+            IndexRepr::Full(entries) => {
+                let mut index = Index::default();
+                entries.into_iter().for_each(|meta| index.push(meta));
+                index
+            }
+            IndexRepr::Compact(slots) => {
+                let len = slots.len();
+                Index { storage: Storage::Compact(compact_slots(slots)), len, ..Index::default() }
+            }
+        })
+    }
+}
+
+/// 索引文件头
+const TOC_MAGIC: &[u8; 8] = b"PTTOC\0\0\x05";
+
+/// 索引文件的写出端，整数均为小端
+struct TocWriter {
+    buf: Vec<u8>,
+}
+
+impl TocWriter {
+    fn u8(&mut self, v: u8) {
+        self.buf.push(v);
+    }
+
+    fn u32(&mut self, v: u32) {
+        self.buf.extend_from_slice(&v.to_le_bytes());
+    }
+
+    fn u64(&mut self, v: u64) {
+        self.buf.extend_from_slice(&v.to_le_bytes());
+    }
+
+    fn bytes(&mut self, v: &[u8]) {
+        self.u64(v.len() as u64);
+        self.buf.extend_from_slice(v);
+    }
+
+    fn duration(&mut self, v: Option<Duration>) {
+        match v {
+            Some(d) => {
+                self.u8(1);
+                self.u64(d.as_secs());
+                self.u32(d.subsec_nanos());
+            }
+            None => self.u8(0),
+        }
+    }
+
+    fn timestamp(&mut self, v: Option<(i64, u32)>) {
+        match v {
+            Some((secs, nanos)) => {
+                self.u8(1);
+                self.u64(secs as u64);
+                self.u32(nanos);
+            }
+            None => self.u8(0),
+        }
+    }
+
+    fn raw(&mut self, v: Option<&[u8]>) {
+        match v {
+            Some(raw) => {
+                self.u8(1);
+                self.bytes(raw);
+            }
+            None => self.u8(0),
+        }
+    }
+
+    fn globals(&mut self, globals: &BTreeMap<u64, PaxRecords>) {
+        self.u64(globals.len() as u64);
+        for (offset, records) in globals {
+            self.u64(*offset);
+            self.u64(records.len() as u64);
+            for (key, value) in records {
+                self.bytes(key.as_bytes());
+                self.bytes(value);
+            }
+        }
+    }
+
+    fn meta(&mut self, meta: &EntryMeta) {
+        self.bytes(meta.path.as_bytes());
+        self.raw(meta.raw_path.as_deref());
+        self.u64(meta.size);
+        self.u32(meta.type_flag as u32);
+        self.u32(meta.mode);
+        self.u64(meta.uid);
+        self.u64(meta.gid);
+        self.bytes(meta.uname.as_bytes());
+        self.bytes(meta.gname.as_bytes());
+        self.u64(meta.mtime as u64);
+        self.u32(meta.mtime_nsec);
+        self.timestamp(meta.atime);
+        self.timestamp(meta.ctime);
+        self.bytes(meta.link_name.as_bytes());
+        self.raw(meta.raw_link_name.as_deref());
+        self.u32(meta.devmajor);
+        self.u32(meta.devminor);
+        self.u64(meta.offset);
+        self.u64(meta.data_offset);
+        self.u64(meta.xattrs.len() as u64);
+        for (key, value) in &meta.xattrs {
+            self.bytes(key.as_bytes());
+            self.bytes(value);
+        }
+    }
+}
+
+/// 索引文件的读取端，越界与非法内容均报 InvalidData
+struct TocReader<'a> {
+    data: &'a [u8],
+}
+
+fn bad_toc(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("invalid index file: {}", msg))
+}
+
+impl TocReader<'_> {
+    fn take(&mut self, n: u64) -> io::Result<&[u8]> {
+        if n > self.data.len() as u64 {
+            return Err(bad_toc("truncated"));
+        }
+        let (head, rest) = self.data.split_at(n as usize);
+        self.data = rest;
+        Ok(head)
+    }
+
+    fn u8(&mut self) -> io::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> io::Result<u64> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn bytes(&mut self) -> io::Result<Vec<u8>> {
+        let len = self.u64()?;
+        Ok(self.take(len)?.to_vec())
+    }
+
+    fn string(&mut self) -> io::Result<String> {
+        String::from_utf8(self.bytes()?).map_err(|_| bad_toc("string is not UTF-8"))
+    }
+
+    fn duration(&mut self) -> io::Result<Option<Duration>> {
+        match self.u8()? {
+            0 => Ok(None),
+            1 => {
+                let secs = self.u64()?;
+                let nanos = self.u32()?;
+                if nanos >= 1_000_000_000 {
+                    return Err(bad_toc("invalid nanoseconds"));
+                }
+                Ok(Some(Duration::new(secs, nanos)))
+            }
+            _ => Err(bad_toc("invalid time tag")),
+        }
+    }
+
+    /// 与 `duration` 的编码相同，秒数按 i64 解释
+    fn timestamp(&mut self) -> io::Result<Option<(i64, u32)>> {
+        Ok(self.duration()?.map(|d| (d.as_secs() as i64, d.subsec_nanos())))
+    }
+
+    fn raw(&mut self) -> io::Result<Option<Vec<u8>>> {
+        match self.u8()? {
+            0 => Ok(None),
+            1 => Ok(Some(self.bytes()?)),
+            _ => Err(bad_toc("invalid raw name tag")),
+        }
+    }
+
+    fn globals(&mut self) -> io::Result<BTreeMap<u64, PaxRecords>> {
+        let mut globals = BTreeMap::new();
+        for _ in 0..self.u64()? {
+            let offset = self.u64()?;
+            let mut records = PaxRecords::new();
+            for _ in 0..self.u64()? {
+                let key = self.string()?;
+                records.insert(key, self.bytes()?);
+            }
+            globals.insert(offset, records);
+        }
+        Ok(globals)
+    }
+
+    fn meta(&mut self) -> io::Result<EntryMeta> {
+        let path = self.string()?;
+        let raw_path = self.raw()?;
+        let size = self.u64()?;
+        let type_flag = char::from_u32(self.u32()?).ok_or_else(|| bad_toc("invalid type flag"))?;
+        let mode = self.u32()?;
+        let uid = self.u64()?;
+        let gid = self.u64()?;
+        let uname = self.string()?;
+        let gname = self.string()?;
+        let mtime = self.u64()? as i64;
+        let mtime_nsec = self.u32()?;
+        if mtime_nsec >= 1_000_000_000 {
+            return Err(bad_toc("invalid nanoseconds"));
+        }
+        let atime = self.timestamp()?;
+        let ctime = self.timestamp()?;
+        let link_name = self.string()?;
+        let raw_link_name = self.raw()?;
+        let devmajor = self.u32()?;
+        let devminor = self.u32()?;
+        let offset = self.u64()?;
+        let data_offset = self.u64()?;
+        let mut xattrs = std::collections::BTreeMap::new();
+        for _ in 0..self.u64()? {
+            let key = self.string()?;
+            xattrs.insert(key, self.bytes()?);
+        }
+        Ok(EntryMeta {
+            path, raw_path, size, type_flag, mode, uid, gid, uname, gname,
+            mtime, mtime_nsec,
+            atime, ctime, link_name, raw_link_name, devmajor, devminor, offset, data_offset, xattrs,
+        })
+    }
+}
+
+impl Index {
+    /// 把索引连同归档指纹写入 path，之后可用 `load` 跳过重新扫描；
+    /// 完整索引保存全部元数据，紧凑与侧车索引保存 (路径哈希, 偏移)
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let source = self.source.as_ref().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "index was not built from an archive")
+        })?;
+        let mut out = TocWriter { buf: TOC_MAGIC.to_vec() };
+        out.u64(source.size);
+        out.duration(source.mtime);
+        out.buf.extend_from_slice(&source.sample);
+        out.globals(&self.globals);
+        out.u64(self.len as u64);
+        let slots = match &self.storage {
+            Storage::Full { entries, .. } => {
+                out.u8(0);
+                entries.iter().for_each(|meta| out.meta(meta));
+                Vec::new()
+            }
+            Storage::Compact(slots) => slots.clone(),
+            Storage::Sidecar(sidecar) => (0..sidecar.slots).map(|i| sidecar.slot(i)).collect::<io::Result<_>>()?,
+        };
+        if self.kind() != IndexKind::Full {
+            out.u8(1);
+            for (hash, offset) in slots {
+                out.u64(hash);
+                out.u64(offset);
+            }
+        }
+        fs::write(path, out.buf)
+    }
+
+    /// 读取 `save` 写出的索引；与 img 的指纹不符（归档已被修改或不是同一个归档）时返回 InvalidData，
+    /// 调用方应重新 `build`。侧车索引读回后为紧凑索引
+    pub fn load<P: AsRef<Path>>(path: P, img: &mut TarImage) -> io::Result<Self> {
+        let data = fs::read(path)?;
+        if !data.starts_with(TOC_MAGIC) {
+            return Err(bad_toc("bad magic"));
+        }
+        let mut input = TocReader { data: &data[TOC_MAGIC.len()..] };
+        let size = input.u64()?;
+        let mtime = input.duration()?;
+        let sample = input.take(32)?.try_into().unwrap();
+        let saved = Fingerprint { size, mtime, sample };
+        let current = Fingerprint::of(img)?;
+        if !saved.matches(&current) {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("stale index for {}", img.get_path())));
+        }
+        let globals = input.globals()?;
+        let len = input.u64()?;
+        let mut index = match input.u8()? {
+            0 => {
+                let mut index = Index::default();
+                for _ in 0..len {
+                    index.push(input.meta()?);
+                }
+                index
+            }
+            1 => {
+                let mut slots = Vec::new();
+                for _ in 0..len {
+                    slots.push((input.u64()?, input.u64()?));
+                }
+                Index { storage: Storage::Compact(compact_slots(slots)), len: len as usize, ..Index::default() }
+            }
+            _ => return Err(bad_toc("unknown index kind")),
+        };
+        if !input.data.is_empty() {
+            return Err(bad_toc("trailing data"));
+        }
+        index.source = Some(current);
+        index.globals = Arc::new(globals);
+        Ok(index)
+    }
+}
+
+fn write_sidecar(dir: &Path, slots: Vec<(u64, u64)>) -> io::Result<SidecarFile> {
+    static COUNTER: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
+    let n = COUNTER.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    let path = dir.join(format!("pt-index-{}-{}.idx", std::process::id(), n));
+    let mut out = BufWriter::new(File::create(&path)?);
+    for (hash, offset) in &slots {
+        out.write_all(&hash.to_le_bytes())?;
+        out.write_all(&offset.to_le_bytes())?;
+    }
+    out.flush()?;
+    drop(out);
+    Ok(SidecarFile { file: File::open(&path)?, path, slots: slots.len() as u64 })
+}
+
+impl SidecarFile {
+    /// 定位读取，不依赖共享的文件位置，多个线程可同时查询
+    fn slot(&self, i: u64) -> io::Result<(u64, u64)> {
+        let mut buf = [0u8; SLOT_SIZE as usize];
+        let mut done = 0;
+        while done < buf.len() {
+            match read_file_at(&self.file, &mut buf[done..], i * SLOT_SIZE + done as u64) {
+                Ok(0) => return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "sidecar index truncated")),
+                Ok(n) => done += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        let hash = u64::from_le_bytes(buf[..8].try_into().unwrap());
+        let offset = u64::from_le_bytes(buf[8..].try_into().unwrap());
+        Ok((hash, offset))
+    }
+
+    fn candidates(&self, hash: u64) -> io::Result<Vec<u64>> {
+        // 二分查找第一个 >= hash 的位置
+        let (mut lo, mut hi) = (0, self.slots);
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            if self.slot(mid)?.0 < hash { lo = mid + 1 } else { hi = mid }
+        }
+        let mut out = Vec::new();
+        while lo < self.slots {
+            let (h, offset) = self.slot(lo)?;
+            if h != hash {
+                break;
+            }
+            out.push(offset);
+            lo += 1;
+        }
+        Ok(out)
+    }
+}
+
+impl TarImage {
+    pub fn build_index(&mut self) -> io::Result<Index> {
+        Index::build(self)
+    }
+
+    /// 按指定顺序遍历所有条目；Path 顺序先建立索引再按路径排序后逐个打开
+    pub fn for_each_entry_ordered<F>(&mut self, order: EntryOrder, mut callback: F) -> io::Result<()>
+    where
+        F: FnMut(Box<TarFile>) -> io::Result<()>,
+    {
+        if order == EntryOrder::Archive {
+            return self.for_each_entry(|file| callback(try_into_tarfile(file)?));
+        }
+        let index = Index::build(self)?;
+        let mut entries: Vec<_> = index.entries().iter().map(|m| (normalize_path(&m.path), m.offset)).collect();
+        // 稳定排序，同名条目仍按归档顺序
+        entries.sort_by(|a, b| a.0.cmp(b.0));
+        for (_, offset) in entries {
+            callback(try_into_tarfile(self.get_file_at(offset)?.0)?)?;
+        }
+        Ok(())
+    }
+
+    /// 单次扫描批量取元数据，结果与 paths 一一对应，同名条目以最后一个为准
+    pub fn stat_many<S: AsRef<str>>(&mut self, paths: &[S]) -> io::Result<Vec<Option<EntryMeta>>> {
+        let mut wanted: HashMap<&str, Vec<usize>> = HashMap::new();
+        for (i, path) in paths.iter().enumerate() {
+            wanted.entry(normalize_path(path.as_ref())).or_default().push(i);
+        }
+        let mut results = vec![None; paths.len()];
+        self.for_each_entry(|file| {
+            let tar_file = try_into_tarfile(file)?;
+            if let Some(slots) = wanted.get(normalize_path(&tar_file.get_path())) {
+                let meta = tar_file.meta();
+                for &i in slots {
+                    results[i] = Some(meta.clone());
+                }
+            }
+            Ok(())
+        })?;
+        Ok(results)
+    }
+
+    /// 顺序扫描查找条目，同名条目返回最后一个
+    pub fn find_entry(&mut self, path: &str) -> io::Result<Option<Box<TarFile>>> {
+        self.find_entry_bytes(path.as_bytes())
+    }
+
+    /// 与 `find_entry` 相同，按原始字节比较路径，可查找非 UTF-8 的名字
+    pub fn find_entry_bytes(&mut self, path: &[u8]) -> io::Result<Option<Box<TarFile>>> {
+        let wanted = normalize_path_bytes(path).to_vec();
+        let mut found = None;
+        self.for_each_entry(|file| {
+            let tar_file = try_into_tarfile(file)?;
+            if normalize_path_bytes(&tar_file.get_path_bytes()) == wanted {
+                found = Some(tar_file);
+            }
+            Ok(())
+        })?;
+        Ok(found)
+    }
+}
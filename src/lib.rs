@@ -1,2 +1,90 @@
 pub mod base;
-pub mod tar;
\ No newline at end of file
+pub mod backend;
+pub mod acl;
+pub mod tar;
+pub mod transform;
+pub mod tree;
+pub mod update;
+pub mod meta;
+pub mod diff;
+pub mod extract;
+pub mod incremental;
+pub mod merge;
+#[cfg(feature = "s3")]
+pub mod object_store;
+pub mod oci;
+pub mod overlay;
+pub mod builder;
+pub mod cancel;
+pub mod checksum;
+pub mod collision;
+pub mod verify;
+pub mod volume;
+pub mod filter;
+#[cfg(feature = "gzip")]
+pub mod gzindex;
+#[cfg(feature = "http")]
+pub mod http;
+pub mod idmap;
+#[cfg(all(unix, feature = "fuse"))]
+pub mod fuse;
+pub mod scan;
+pub mod search;
+pub mod stats;
+pub mod compress;
+pub mod index;
+pub mod limits;
+pub mod listing;
+pub mod manifest;
+pub mod mime;
+pub mod mode;
+pub mod nested;
+pub mod recover;
+pub mod repack;
+#[cfg(unix)]
+pub mod sandbox;
+pub mod error;
+pub mod path;
+#[cfg(feature = "rayon")]
+pub mod parallel;
+#[cfg(feature = "tokio")]
+pub mod async_io;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "python")]
+pub mod python;
+pub mod pax;
+pub mod perf;
+pub mod progress;
+pub mod whiteout;
+#[cfg(feature = "zstd")]
+pub mod zstd_seek;
+#[cfg(unix)]
+pub mod sys;
+
+pub use backend::{Backend, BlockCache, FileBackend, MemoryBackend};
+pub use base::{lock_image, try_into_tarfile, BodyReader, FileInfo, ImageInfo, RetryPolicy, TarFile, TarImage};
+pub use cancel::CancelToken;
+pub use checksum::DigestAlgorithm;
+pub use collision::CollisionPolicy;
+pub use compress::Compression;
+pub use error::PtError;
+pub use extract::{ExtractOptions, OverwritePolicy};
+pub use idmap::IdMap;
+pub use index::{EntryOrder, Index, IndexBudget};
+pub use limits::Limits;
+pub use merge::ConflictPolicy;
+pub use meta::EntryMeta;
+pub use mode::Mode;
+pub use progress::{Progress, ProgressInfo};
+pub use tar::{TarFileType, TarHeader, TypeFlag};
+pub use volume::Volumes;
+
+/// 常用类型与 trait，`use pt::prelude::*;` 即可使用
+pub mod prelude {
+    pub use crate::base::{lock_image, try_into_tarfile, FileInfo, ImageInfo, TarFile, TarImage};
+    pub use crate::error::PtError;
+    pub use crate::extract::ExtractOptions;
+    pub use crate::meta::EntryMeta;
+    pub use crate::tar::{TarHeader, TypeFlag};
+}
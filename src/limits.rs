@@ -0,0 +1,35 @@
+use std::io;
+use crate::error::PtError;
+
+/// 处理不可信归档时的资源上限，None 表示不限制
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Limits {
+    /// 一次遍历最多的条目数
+    pub max_entries: Option<u64>,
+    /// 单个条目数据区的最大字节数
+    pub max_entry_size: Option<u64>,
+    /// 一次遍历中所有条目数据区的总字节数
+    pub max_total_size: Option<u64>,
+    /// 单个扩展 header（PAX 'x'、Solaris 'X'、GNU 'L' / 'K'、厂商扩展）的最大字节数，超出时不会读入内存
+    pub max_pax_size: Option<u64>,
+}
+
+impl Limits {
+    /// 较保守的默认上限，适合处理来源不明的归档
+    pub fn untrusted() -> Self {
+        Limits {
+            max_entries: Some(1_000_000),
+            max_entry_size: Some(64 << 30),
+            max_total_size: Some(256 << 30),
+            max_pax_size: Some(1 << 20),
+        }
+    }
+}
+
+/// value 超过 max 时返回 `PtError::LimitExceeded`
+pub(crate) fn check(limit: &'static str, value: u64, max: Option<u64>) -> io::Result<()> {
+    match max {
+        Some(max) if value > max => Err(PtError::LimitExceeded { limit, value, max }.into()),
+        _ => Ok(()),
+    }
+}
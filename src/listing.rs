@@ -0,0 +1,131 @@
+use std::io;
+use serde_json::{json, Value};
+use crate::base::{try_into_tarfile, ImageInfo, TarImage};
+use crate::meta::{EntryMeta, ImplicitDirs};
+use crate::mode::Mode;
+use crate::tar::TypeFlag;
+
+/// 条目类型的可读名称
+pub(crate) fn type_name(type_flag: char) -> &'static str {
+    match TypeFlag::from_byte(type_flag as u8) {
+        TypeFlag::Regular | TypeFlag::Contiguous => "file",
+        TypeFlag::HardLink => "hardlink",
+        TypeFlag::Symlink => "symlink",
+        TypeFlag::CharDevice => "char",
+        TypeFlag::BlockDevice => "block",
+        TypeFlag::Directory | TypeFlag::GnuDumpDir => "dir",
+        TypeFlag::Fifo => "fifo",
+        _ => "other",
+    }
+}
+
+/// `tar -tv` 中的类型字符
+fn type_char(type_flag: char) -> char {
+    match TypeFlag::from_byte(type_flag as u8) {
+        TypeFlag::Regular | TypeFlag::Contiguous => '-',
+        TypeFlag::HardLink => 'h',
+        TypeFlag::Symlink => 'l',
+        TypeFlag::CharDevice => 'c',
+        TypeFlag::BlockDevice => 'b',
+        TypeFlag::Directory | TypeFlag::GnuDumpDir => 'd',
+        TypeFlag::Fifo => 'p',
+        _ => '?',
+    }
+}
+
+/// UTC 时间 "YYYY-MM-DD HH:MM"
+fn format_time(secs: i64) -> String {
+    let days = secs.div_euclid(86400);
+    let rem = secs.rem_euclid(86400);
+    // 由 1970-01-01 起的天数换算公历日期（Howard Hinnant 的 civil_from_days）
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02} {:02}:{:02}", year, month, day, rem / 3600, rem % 3600 / 60)
+}
+
+impl EntryMeta {
+    /// 与 `tar -tvf` 相同格式的一行：`drwxr-xr-x user/group 1234 2023-05-01 12:00 path -> target`；
+    /// 没有用户名时显示数字 id，时间按 UTC 显示
+    pub fn long_format(&self) -> String {
+        String::from_utf8_lossy(&self.long_format_bytes()).into_owned()
+    }
+
+    /// 与 `long_format` 相同，路径与链接目标保留原始字节
+    pub fn long_format_bytes(&self) -> Vec<u8> {
+        let owner = if self.uname.is_empty() { self.uid.to_string() } else { self.uname.clone() };
+        let group = if self.gname.is_empty() { self.gid.to_string() } else { self.gname.clone() };
+        let owner = format!("{}/{}", owner, group);
+        // GNU tar 把 "属主/属组 大小" 对齐到 19 列
+        let width = 19usize.saturating_sub(owner.len() + 1);
+        // 设备文件显示 "主,次" 设备号而不是大小
+        let size = if self.is_device() { format!("{},{}", self.devmajor, self.devminor) } else { self.size.to_string() };
+        let mut line = format!("{}{} {} {:>width$} {} ",
+            type_char(self.type_flag), Mode::new(self.mode).symbolic(), owner, size,
+            format_time(self.mtime), width = width).into_bytes();
+        line.extend_from_slice(self.path_bytes());
+        match TypeFlag::from_byte(self.type_flag as u8) {
+            TypeFlag::Symlink => line.extend_from_slice(b" -> "),
+            TypeFlag::HardLink => line.extend_from_slice(b" link to "),
+            _ => return line,
+        }
+        line.extend_from_slice(self.link_name_bytes());
+        line
+    }
+
+    /// 清单中的一项：path、size、type、mode、mtime、link_target、offset，设备文件另有 devmajor、devminor
+    pub fn to_json(&self) -> Value {
+        let mut value = json!({
+            "path": self.path,
+            "size": self.size,
+            "type": type_name(self.type_flag),
+            "mode": format!("{:04o}", self.mode & 0o7777),
+            "mtime": self.mtime,
+            "link_target": if self.link_name.is_empty() { Value::Null } else { Value::from(self.link_name.as_str()) },
+            "offset": self.offset,
+        });
+        if self.is_device() {
+            value["devmajor"] = Value::from(self.devmajor);
+            value["devminor"] = Value::from(self.devminor);
+        }
+        value
+    }
+}
+
+impl TarImage {
+    /// 按归档顺序列出所有条目，返回 JSON 数组
+    pub fn listing(&mut self) -> io::Result<Value> {
+        self.listing_with(false)
+    }
+
+    /// 与 `listing` 相同；implicit_dirs 为 true 时在条目之前补上归档中缺少的上级目录，
+    /// 这些项带 `"implicit": true`
+    pub fn listing_with(&mut self, implicit_dirs: bool) -> io::Result<Value> {
+        let mut entries = Vec::new();
+        let mut dirs = ImplicitDirs::default();
+        self.for_each_entry(|file| {
+            let meta = try_into_tarfile(file)?.meta();
+            if implicit_dirs {
+                for dir in dirs.missing(&meta) {
+                    let mut value = dir.to_json();
+                    value["implicit"] = Value::Bool(true);
+                    entries.push(value);
+                }
+            }
+            entries.push(meta.to_json());
+            Ok(())
+        })?;
+        Ok(Value::Array(entries))
+    }
+
+    /// `listing` 的紧凑 JSON 文本，便于交给 jq 等工具处理
+    pub fn list_json(&mut self) -> io::Result<String> {
+        Ok(self.listing()?.to_string())
+    }
+}
@@ -0,0 +1,175 @@
+//! 完整性清单：每个条目的路径、类型、大小与内容摘要
+//!
+//! `to_bytes` 的输出对同样的内容总是相同（JSON 键按字母序、条目按路径排序），
+//! 签名即对这段字节签名。启用 `sign` feature 后可用 ed25519 生成与校验分离的签名。
+
+use std::collections::BTreeMap;
+use std::io;
+use serde_json::{json, Value};
+use crate::base::{try_into_tarfile, ImageInfo, TarImage};
+use crate::checksum::{to_hex, DigestAlgorithm};
+use crate::listing::type_name;
+
+/// 清单格式版本
+const MANIFEST_VERSION: u64 = 1;
+
+/// 清单中的一项
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManifestEntry {
+    pub path: String,
+    /// 与 `pt list --json` 相同的类型名，如 "file"、"dir"、"symlink"
+    pub kind: String,
+    pub size: u64,
+    /// 链接目标，非链接时为空
+    pub link_target: String,
+    /// 普通文件内容摘要的十六进制，其它类型为 None
+    pub digest: Option<String>,
+}
+
+/// 归档的完整性清单，同名条目以最后一个为准
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Manifest {
+    pub algorithm: DigestAlgorithm,
+    pub entries: BTreeMap<String, ManifestEntry>,
+}
+
+/// 清单与归档不一致之处
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ManifestReport {
+    /// 清单中有、归档中没有的路径
+    pub missing: Vec<String>,
+    /// 归档中有、清单中没有的路径
+    pub unexpected: Vec<String>,
+    /// (路径, 不一致的字段)
+    pub mismatched: Vec<(String, String)>,
+}
+
+impl ManifestReport {
+    pub fn is_ok(&self) -> bool {
+        self.missing.is_empty() && self.unexpected.is_empty() && self.mismatched.is_empty()
+    }
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("invalid manifest: {}", msg))
+}
+
+impl ManifestEntry {
+    fn to_json(&self) -> Value {
+        json!({
+            "path": self.path,
+            "type": self.kind,
+            "size": self.size,
+            "link_target": if self.link_target.is_empty() { Value::Null } else { Value::from(self.link_target.as_str()) },
+            "digest": self.digest,
+        })
+    }
+
+    fn from_json(value: &Value) -> io::Result<Self> {
+        let field = |key: &str| value.get(key).ok_or_else(|| invalid(&format!("entry without {}", key)));
+        let string = |key: &str| -> io::Result<Option<String>> {
+            match field(key)? {
+                Value::Null => Ok(None),
+                Value::String(s) => Ok(Some(s.clone())),
+                _ => Err(invalid(&format!("{} is not a string", key))),
+            }
+        };
+        Ok(ManifestEntry {
+            path: string("path")?.ok_or_else(|| invalid("entry without path"))?,
+            kind: string("type")?.ok_or_else(|| invalid("entry without type"))?,
+            size: field("size")?.as_u64().ok_or_else(|| invalid("size is not a number"))?,
+            link_target: string("link_target")?.unwrap_or_default(),
+            digest: string("digest")?,
+        })
+    }
+}
+
+impl Manifest {
+    /// 序列化为 JSON 文本
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let entries: Vec<Value> = self.entries.values().map(ManifestEntry::to_json).collect();
+        json!({
+            "version": MANIFEST_VERSION,
+            "algorithm": self.algorithm.name(),
+            "entries": entries,
+        }).to_string().into_bytes()
+    }
+
+    /// 解析 `to_bytes` 的输出
+    pub fn parse(data: &[u8]) -> io::Result<Self> {
+        let value: Value = serde_json::from_slice(data).map_err(|e| invalid(&e.to_string()))?;
+        if value.get("version").and_then(Value::as_u64) != Some(MANIFEST_VERSION) {
+            return Err(invalid("unsupported version"));
+        }
+        let algorithm = value.get("algorithm").and_then(Value::as_str)
+            .and_then(DigestAlgorithm::from_name)
+            .ok_or_else(|| invalid("unknown algorithm"))?;
+        let mut entries = BTreeMap::new();
+        for item in value.get("entries").and_then(Value::as_array).ok_or_else(|| invalid("entries is not an array"))? {
+            let entry = ManifestEntry::from_json(item)?;
+            entries.insert(entry.path.clone(), entry);
+        }
+        Ok(Manifest { algorithm, entries })
+    }
+}
+
+#[cfg(feature = "sign")]
+impl Manifest {
+    /// 对 `to_bytes` 的输出做 ed25519 签名，返回 64 字节的分离签名
+    pub fn sign(&self, key: &ed25519_dalek::SigningKey) -> [u8; 64] {
+        use ed25519_dalek::Signer;
+        key.sign(&self.to_bytes()).to_bytes()
+    }
+
+    /// 校验 data 的签名后再解析；签名格式错误或不匹配时返回 InvalidData
+    pub fn parse_signed(data: &[u8], signature: &[u8], key: &ed25519_dalek::VerifyingKey) -> io::Result<Self> {
+        let signature = ed25519_dalek::Signature::from_slice(signature).map_err(|_| invalid("malformed signature"))?;
+        key.verify_strict(data, &signature).map_err(|_| invalid("signature mismatch"))?;
+        Self::parse(data)
+    }
+}
+
+impl TarImage {
+    /// 生成完整性清单，普通文件按 algo 计算内容摘要
+    pub fn manifest(&mut self, algo: DigestAlgorithm) -> io::Result<Manifest> {
+        let mut entries = BTreeMap::new();
+        self.for_each_entry(|file| {
+            let tar_file = try_into_tarfile(file)?;
+            let meta = tar_file.meta();
+            let digest = if meta.is_file() { Some(to_hex(&tar_file.digest(algo)?)) } else { None };
+            entries.insert(meta.path.clone(), ManifestEntry {
+                kind: type_name(meta.type_flag).to_string(),
+                size: meta.size,
+                link_target: meta.link_name,
+                digest,
+                path: meta.path,
+            });
+            Ok(())
+        })?;
+        Ok(Manifest { algorithm: algo, entries })
+    }
+
+    /// 按清单校验归档：逐项比较类型、大小、链接目标与内容摘要
+    pub fn verify_manifest(&mut self, manifest: &Manifest) -> io::Result<ManifestReport> {
+        let actual = self.manifest(manifest.algorithm)?;
+        let mut report = ManifestReport::default();
+        for (path, want) in &manifest.entries {
+            let Some(got) = actual.entries.get(path) else {
+                report.missing.push(path.clone());
+                continue;
+            };
+            let fields = [
+                ("type", want.kind != got.kind),
+                ("size", want.size != got.size),
+                ("link_target", want.link_target != got.link_target),
+                ("digest", want.digest != got.digest),
+            ];
+            if let Some((field, _)) = fields.iter().find(|(_, differs)| *differs) {
+                report.mismatched.push((path.clone(), field.to_string()));
+            }
+        }
+        report.unexpected = actual.entries.keys().filter(|p| !manifest.entries.contains_key(*p)).cloned().collect();
+        Ok(report)
+    }
+}
@@ -0,0 +1,161 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use crate::base::{lock_image, try_into_tarfile, ImageInfo, TarImage};
+use crate::diff::sha256_body;
+use crate::meta::EntryMeta;
+use crate::path::normalize_path;
+use crate::whiteout::{classify, Whiteout};
+
+/// 两个归档出现同一路径时的处理策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictPolicy {
+    /// 保留 a 中的条目
+    PreferA,
+    /// 保留 b 中的条目
+    PreferB,
+    /// 保留 mtime 较新的条目，相同时保留 b
+    NewerMtime,
+    /// 返回错误
+    Error,
+}
+
+struct RawEntry {
+    meta: EntryMeta,
+    hash: Option<[u8; 32]>,
+    /// 原始字节范围，包含扩展 header 与数据填充，不含开头的 'g' header
+    start: u64,
+    end: u64,
+}
+
+/// 条目在输入中的一次出现
+struct Slot {
+    path: String,
+    /// 这次出现之前的 'g' header 原始字节；条目被丢弃时照样输出，后续条目的全局记录才不变
+    globals: Vec<u8>,
+}
+
+impl RawEntry {
+    fn same_content(&self, other: &RawEntry) -> bool {
+        let (a, b) = (&self.meta, &other.meta);
+        a.type_flag == b.type_flag
+            && a.size == b.size
+            && a.mode == b.mode
+            && a.mtime == b.mtime
+            && a.link_name == b.link_name
+            && self.hash == other.hash
+    }
+}
+
+/// 按归档顺序收集条目的每次出现，同名条目以最后一个为准
+fn collect(img: &mut TarImage) -> io::Result<(Vec<Slot>, HashMap<String, RawEntry>)> {
+    let mut order = Vec::new();
+    let mut entries = HashMap::new();
+    img.for_each_entry(|file| {
+        let tar_file = try_into_tarfile(file)?;
+        let meta = tar_file.meta();
+        let hash = if meta.is_file() { Some(sha256_body(&tar_file)?) } else { None };
+        let mut globals = Vec::new();
+        tar_file.copy_globals_to(&mut globals)?;
+        let start = tar_file.get_offset() + globals.len() as u64;
+        order.push(Slot { path: meta.path.clone(), globals });
+        entries.insert(meta.path.clone(), RawEntry { hash, start, end: tar_file.get_end_offset(), meta });
+        Ok(())
+    })?;
+    Ok((order, entries))
+}
+
+/// 合并两个归档：先按 a 的顺序输出，再追加 b 独有的条目；条目以原始块复制，
+/// 'g' header 留在输入中原来的位置，不随条目一起丢弃
+pub fn merge<W: Write>(a: &mut TarImage, b: &mut TarImage, output: &mut W, policy: ConflictPolicy) -> io::Result<()> {
+    let (order_a, mut entries_a) = collect(a)?;
+    let (order_b, mut entries_b) = collect(b)?;
+
+    for slot in &order_a {
+        output.write_all(&slot.globals)?;
+        // 同名条目只在第一次出现的位置输出
+        let Some(ea) = entries_a.remove(&slot.path) else {
+            continue;
+        };
+        let path = &slot.path;
+        let from_b = match entries_b.remove(path) {
+            None => None,
+            Some(eb) if ea.same_content(&eb) => None,
+            Some(eb) => match policy {
+                ConflictPolicy::PreferA => None,
+                ConflictPolicy::PreferB => Some(eb),
+                ConflictPolicy::NewerMtime if ea.meta.mtime > eb.meta.mtime => None,
+                ConflictPolicy::NewerMtime => Some(eb),
+                ConflictPolicy::Error => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::AlreadyExists,
+                        format!("merge conflict: {}", path),
+                    ));
+                }
+            },
+        };
+        match from_b {
+            Some(eb) => b.copy_range_to(eb.start, eb.end - eb.start, output)?,
+            None => a.copy_range_to(ea.start, ea.end - ea.start, output)?,
+        };
+    }
+    for slot in &order_b {
+        output.write_all(&slot.globals)?;
+        if let Some(eb) = entries_b.remove(&slot.path) {
+            b.copy_range_to(eb.start, eb.end - eb.start, output)?;
+        }
+    }
+    // 归档结束标记：两个全零块
+    output.write_all(&[0u8; 1024])?;
+    output.flush()
+}
+
+/// 把上层镜像层叠加到下层之上（overlayfs 语义）：上层的白化标记删除下层对应条目，
+/// 同名条目以上层为准，白化文件本身不输出
+pub fn merge_layers<W: Write>(lower: &mut TarImage, upper: &mut TarImage, output: &mut W) -> io::Result<()> {
+    let mut whiteouts = Vec::new();
+    let mut upper_entries = Vec::new();
+    let mut shadowed = HashSet::new();
+    upper.for_each_entry(|file| {
+        let tar_file = try_into_tarfile(file)?;
+        let path = tar_file.get_path();
+        match classify(&path) {
+            Some(wh) => whiteouts.push(wh),
+            None => {
+                upper_entries.push((tar_file.get_offset(), tar_file.get_end_offset()));
+                shadowed.insert(normalize_path(&path).to_string());
+            }
+        }
+        Ok(())
+    })?;
+
+    let mut lower_entries = Vec::new();
+    lower.for_each_entry(|file| {
+        let tar_file = try_into_tarfile(file)?;
+        let path = tar_file.get_path();
+        let hidden = shadowed.contains(normalize_path(&path))
+            || whiteouts.iter().any(|wh: &Whiteout| wh.hides(&path));
+        if !hidden {
+            lower_entries.push((tar_file.get_offset(), tar_file.get_end_offset()));
+        }
+        Ok(())
+    })?;
+
+    for (start, end) in lower_entries {
+        lower.copy_range_to(start, end - start, output)?;
+    }
+    for (start, end) in upper_entries {
+        upper.copy_range_to(start, end - start, output)?;
+    }
+    output.write_all(&[0u8; 1024])?;
+    output.flush()
+}
+
+/// 按路径打开两个归档并合并写入 output 文件
+pub fn merge_paths(a: &str, b: &str, output: &str, policy: ConflictPolicy) -> io::Result<()> {
+    let a = TarImage::open(a)?;
+    let b = TarImage::open(b)?;
+    let mut out = BufWriter::new(File::create(output)?);
+    let result = merge(&mut *lock_image(&a)?, &mut *lock_image(&b)?, &mut out, policy);
+    result
+}
@@ -0,0 +1,237 @@
+use std::collections::{BTreeMap, HashSet};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use crate::base::TarFile;
+use crate::path::normalize_path;
+use crate::pax::{pax_timestamp, pax_u64, xattrs, PaxRecords};
+
+/// 合成目录的权限
+pub const IMPLICIT_DIR_MODE: u32 = 0o755;
+
+/// 条目元数据快照，不持有镜像句柄，可自由保存和比较
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EntryMeta {
+    /// 完整路径（GNU 长名称或 prefix + name）
+    pub path: String,
+    /// 路径不是合法 UTF-8 时（如 PAX `hdrcharset=BINARY` 的条目）的原始字节，此时 path 为其 lossy 转换
+    pub raw_path: Option<Vec<u8>>,
+    pub size: u64,
+    pub type_flag: char,
+    pub mode: u32,
+    pub uid: u64,
+    pub gid: u64,
+    pub uname: String,
+    pub gname: String,
+    /// 修改时间的秒数（向下取整），早于 1970 年时为负；PAX `mtime` 记录优先于 header
+    pub mtime: i64,
+    /// 修改时间在 mtime 之后的纳秒部分，总是非负，只有 PAX `mtime` 记录带小数时不为 0
+    pub mtime_nsec: u32,
+    /// PAX `atime` 记录的 (秒, 纳秒)，与 mtime / mtime_nsec 的表示相同，可早于 1970 年
+    pub atime: Option<(i64, u32)>,
+    /// PAX `ctime` 记录的 (秒, 纳秒)
+    pub ctime: Option<(i64, u32)>,
+    pub link_name: String,
+    /// 链接目标不是合法 UTF-8 时的原始字节
+    pub raw_link_name: Option<Vec<u8>>,
+    /// 字符与块设备的主设备号，其它类型为 0
+    pub devmajor: u32,
+    /// 字符与块设备的次设备号，其它类型为 0
+    pub devminor: u32,
+    /// header 在镜像中的起始偏移
+    pub offset: u64,
+    /// 数据区在镜像中的起始偏移
+    pub data_offset: u64,
+    /// 来自 PAX `SCHILY.xattr.*` 记录的扩展属性
+    pub xattrs: BTreeMap<String, Vec<u8>>,
+}
+
+/// 相对 UNIX 纪元 secs 秒（可为负）加 nanos 纳秒的时刻
+pub fn unix_time(secs: i64, nanos: u32) -> SystemTime {
+    let base = match u64::try_from(secs) {
+        Ok(secs) => UNIX_EPOCH + Duration::from_secs(secs),
+        Err(_) => UNIX_EPOCH - Duration::from_secs(secs.unsigned_abs()),
+    };
+    base + Duration::from_nanos(nanos as u64)
+}
+
+/// t 相对 UNIX 纪元的整秒数（向下取整），早于 1970 年时为负
+pub(crate) fn unix_secs(t: SystemTime) -> i64 {
+    match t.duration_since(UNIX_EPOCH) {
+        Ok(d) => i64::try_from(d.as_secs()).unwrap_or(i64::MAX),
+        Err(e) => {
+            let d = e.duration();
+            let secs = i64::try_from(d.as_secs()).unwrap_or(i64::MAX);
+            if d.subsec_nanos() > 0 { -secs - 1 } else { -secs }
+        }
+    }
+}
+
+/// bytes 不是合法 UTF-8 时原样返回
+fn non_utf8(bytes: Vec<u8>) -> Option<Vec<u8>> {
+    String::from_utf8(bytes).err().map(|e| e.into_bytes())
+}
+
+/// 设备号：star 的 PAX `SCHILY.devmajor` / `SCHILY.devminor` 记录优先于 header 字段
+fn device(records: &PaxRecords, key: &str, header: u32) -> u32 {
+    pax_u64(records, key).and_then(|v| u32::try_from(v).ok()).unwrap_or(header)
+}
+
+impl EntryMeta {
+    pub fn from_tar_file(file: &TarFile) -> Self {
+        let hdr = file.get_header();
+        let is_device = matches!(hdr.get_type_flag(), '3' | '4');
+        EntryMeta {
+            path: file.get_path(),
+            raw_path: non_utf8(file.get_path_bytes()),
+            size: file.get_size(),
+            type_flag: hdr.get_type_flag(),
+            mode: hdr.get_mode().bits(),
+            uid: file.uid(),
+            gid: file.gid(),
+            uname: hdr.get_uname(),
+            gname: hdr.get_gname(),
+            mtime: file.mtime_parts().0,
+            mtime_nsec: file.mtime_parts().1,
+            atime: file.atime(),
+            ctime: file.ctime(),
+            link_name: file.get_link_name(),
+            raw_link_name: non_utf8(file.get_link_name_bytes()),
+            devmajor: if is_device { device(file.pax_records(), "SCHILY.devmajor", hdr.get_devmajor()) } else { 0 },
+            devminor: if is_device { device(file.pax_records(), "SCHILY.devminor", hdr.get_devminor()) } else { 0 },
+            offset: file.get_offset(),
+            data_offset: file.get_data_offset(),
+            xattrs: xattrs(file.pax_records()),
+        }
+    }
+
+    /// 为归档中没有显式记录的上级目录 path 合成的条目：mode 0755，属主与修改时间取自引出它的条目 child。
+    /// 合成条目没有 header，offset 与 data_offset 沿用 child 的值
+    pub fn implicit_dir(path: &str, child: &EntryMeta) -> EntryMeta {
+        EntryMeta {
+            path: format!("{}/", normalize_path(path)),
+            raw_path: None,
+            size: 0,
+            type_flag: '5',
+            mode: IMPLICIT_DIR_MODE,
+            uid: child.uid,
+            gid: child.gid,
+            uname: child.uname.clone(),
+            gname: child.gname.clone(),
+            mtime: child.mtime,
+            mtime_nsec: child.mtime_nsec,
+            atime: None,
+            ctime: None,
+            link_name: String::new(),
+            raw_link_name: None,
+            devmajor: 0,
+            devminor: 0,
+            offset: child.offset,
+            data_offset: child.offset,
+            xattrs: BTreeMap::new(),
+        }
+    }
+
+    /// 路径的原始字节
+    pub fn path_bytes(&self) -> &[u8] {
+        self.raw_path.as_deref().unwrap_or(self.path.as_bytes())
+    }
+
+    /// 链接目标的原始字节
+    pub fn link_name_bytes(&self) -> &[u8] {
+        self.raw_link_name.as_deref().unwrap_or(self.link_name.as_bytes())
+    }
+
+    /// 精确到纳秒的修改时间（相对 UNIX 纪元）；早于 1970 年时为 0，完整的范围见 `modified`
+    pub fn mtime_precise(&self) -> Duration {
+        u64::try_from(self.mtime).map_or(Duration::ZERO, |secs| Duration::new(secs, self.mtime_nsec))
+    }
+
+    /// 修改时间，可早于 1970 年
+    pub fn modified(&self) -> SystemTime {
+        unix_time(self.mtime, self.mtime_nsec)
+    }
+
+    pub fn accessed(&self) -> Option<SystemTime> {
+        self.atime.map(|(secs, nanos)| unix_time(secs, nanos))
+    }
+
+    /// 状态变化时间（ctime）
+    pub fn changed(&self) -> Option<SystemTime> {
+        self.ctime.map(|(secs, nanos)| unix_time(secs, nanos))
+    }
+
+    pub fn is_dir(&self) -> bool {
+        self.type_flag == '5'
+    }
+
+    /// 字符或块设备
+    pub fn is_device(&self) -> bool {
+        matches!(self.type_flag, '3' | '4')
+    }
+
+    /// 普通文件（'0' 或旧格式的 '\0'）
+    pub fn is_file(&self) -> bool {
+        self.type_flag == '0' || self.type_flag == '\0'
+    }
+}
+
+impl TarFile {
+    pub fn meta(&self) -> EntryMeta {
+        EntryMeta::from_tar_file(self)
+    }
+
+    /// 修改时间：优先使用 PAX `mtime` 记录（可带纳秒），否则为 header 中的秒数；可早于 1970 年
+    pub fn mtime(&self) -> SystemTime {
+        let (secs, nanos) = self.mtime_parts();
+        unix_time(secs, nanos)
+    }
+
+    /// 修改时间的 (秒, 纳秒)，与 `EntryMeta` 的 mtime / mtime_nsec 相同
+    pub(crate) fn mtime_parts(&self) -> (i64, u32) {
+        pax_timestamp(self.pax_records(), "mtime").unwrap_or_else(|| (self.get_header().get_mtime(), 0))
+    }
+
+    /// 属主 uid：优先使用 PAX `uid` 记录，否则为 header 字段（八进制或 GNU base-256）
+    pub fn uid(&self) -> u64 {
+        pax_u64(self.pax_records(), "uid").unwrap_or_else(|| self.get_header().get_uid())
+    }
+
+    /// 属组 gid：优先使用 PAX `gid` 记录
+    pub fn gid(&self) -> u64 {
+        pax_u64(self.pax_records(), "gid").unwrap_or_else(|| self.get_header().get_gid())
+    }
+
+    /// PAX `atime` 记录中的访问时间 (秒, 纳秒)，可早于 1970 年
+    pub fn atime(&self) -> Option<(i64, u32)> {
+        pax_timestamp(self.pax_records(), "atime")
+    }
+
+    /// PAX `ctime` 记录中的状态变化时间 (秒, 纳秒)
+    pub fn ctime(&self) -> Option<(i64, u32)> {
+        pax_timestamp(self.pax_records(), "ctime")
+    }
+}
+
+/// 按归档顺序记录已出现的目录，为尚未出现的上级目录合成条目
+#[derive(Debug, Default)]
+pub(crate) struct ImplicitDirs {
+    seen: HashSet<String>,
+}
+
+impl ImplicitDirs {
+    /// meta 之前应补上的上级目录，由浅到深；目录条目本身也记为已出现
+    pub(crate) fn missing(&mut self, meta: &EntryMeta) -> Vec<EntryMeta> {
+        let path = normalize_path(&meta.path);
+        let mut out = Vec::new();
+        for (i, _) in path.match_indices('/') {
+            let dir = &path[..i];
+            if !dir.is_empty() && self.seen.insert(dir.to_string()) {
+                out.push(EntryMeta::implicit_dir(dir, meta));
+            }
+        }
+        if meta.is_dir() {
+            self.seen.insert(path.to_string());
+        }
+        out
+    }
+}
@@ -0,0 +1,75 @@
+use std::io;
+use crate::base::TarFile;
+
+/// 判断内容类型所需读取的字节数（ustar 魔数位于 257 偏移处）
+const SNIFF_LEN: usize = 512;
+
+/// 按魔数签名判断 MIME 类型：(偏移, 签名, MIME)
+const SIGNATURES: &[(usize, &[u8], &str)] = &[
+    (0, &[0x1f, 0x8b], "application/gzip"),
+    (0, &[0x28, 0xb5, 0x2f, 0xfd], "application/zstd"),
+    (0, &[0xfd, b'7', b'z', b'X', b'Z', 0x00], "application/x-xz"),
+    (0, b"BZh", "application/x-bzip2"),
+    (0, b"PK\x03\x04", "application/zip"),
+    (0, &[b'7', b'z', 0xbc, 0xaf, 0x27, 0x1c], "application/x-7z-compressed"),
+    (0, b"\x7fELF", "application/x-executable"),
+    (0, b"%PDF-", "application/pdf"),
+    (0, b"\x89PNG\r\n\x1a\n", "image/png"),
+    (0, &[0xff, 0xd8, 0xff], "image/jpeg"),
+    (0, b"GIF87a", "image/gif"),
+    (0, b"GIF89a", "image/gif"),
+    (0, b"SQLite format 3\0", "application/vnd.sqlite3"),
+    (0, b"#!", "text/x-shellscript"),
+    (0, b"<?xml", "application/xml"),
+    (257, b"ustar", "application/x-tar"),
+];
+
+/// 按内容开头的字节判断 MIME 类型，无法识别时返回 application/octet-stream
+pub fn sniff_mime(data: &[u8]) -> &'static str {
+    for &(offset, sig, mime) in SIGNATURES {
+        if data.len() >= offset + sig.len() && &data[offset..offset + sig.len()] == sig {
+            return mime;
+        }
+    }
+    if data.len() >= 12 && &data[0..4] == b"RIFF" && &data[8..12] == b"WEBP" {
+        return "image/webp";
+    }
+    let text = match std::str::from_utf8(data) {
+        Ok(s) => s,
+        // 截断处可能落在多字节字符中间
+        Err(e) if e.error_len().is_none() => std::str::from_utf8(&data[..e.valid_up_to()]).unwrap_or(""),
+        Err(_) => return "application/octet-stream",
+    };
+    if text.chars().any(|c| c.is_control() && !c.is_whitespace()) {
+        return "application/octet-stream";
+    }
+    let trimmed = text.trim_start();
+    if trimmed.starts_with('{') || trimmed.starts_with('[') {
+        "application/json"
+    } else if trimmed.as_bytes().get(..14).is_some_and(|b| b.eq_ignore_ascii_case(b"<!doctype html")) {
+        "text/html"
+    } else if data.is_empty() {
+        "application/x-empty"
+    } else {
+        "text/plain"
+    }
+}
+
+impl TarFile {
+    /// 读取数据区开头判断内容类型；目录等非普通文件返回 None
+    pub fn content_type(&self) -> io::Result<Option<&'static str>> {
+        if !self.meta().is_file() {
+            return Ok(None);
+        }
+        let mut buf = [0u8; SNIFF_LEN];
+        let mut len = 0;
+        while len < buf.len() {
+            let n = self.read_body_at(len as u64, &mut buf[len..])?;
+            if n == 0 {
+                break;
+            }
+            len += n;
+        }
+        Ok(Some(sniff_mime(&buf[..len])))
+    }
+}
@@ -0,0 +1,105 @@
+//! header mode 字段的类型化表示
+
+/// 一组读、写、执行权限
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Perms {
+    pub read: bool,
+    pub write: bool,
+    pub execute: bool,
+}
+
+impl Perms {
+    fn from_bits(bits: u32) -> Self {
+        Perms { read: bits & 0o4 != 0, write: bits & 0o2 != 0, execute: bits & 0o1 != 0 }
+    }
+}
+
+/// 条目的 mode：属主、属组、其他用户三组权限与 setuid/setgid/sticky 位。
+/// 保留 header 中的原始值，有的归档在高位带有 `S_IFREG` 等文件类型位
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Mode(u32);
+
+impl Mode {
+    pub const SETUID: u32 = 0o4000;
+    pub const SETGID: u32 = 0o2000;
+    pub const STICKY: u32 = 0o1000;
+
+    pub const fn new(bits: u32) -> Self {
+        Mode(bits)
+    }
+
+    /// header 中的原始值
+    pub const fn bits(self) -> u32 {
+        self.0
+    }
+
+    /// 去掉文件类型位后的权限部分（低 12 位）
+    pub const fn permissions(self) -> u32 {
+        self.0 & 0o7777
+    }
+
+    pub fn owner(self) -> Perms {
+        Perms::from_bits(self.0 >> 6)
+    }
+
+    pub fn group(self) -> Perms {
+        Perms::from_bits(self.0 >> 3)
+    }
+
+    pub fn other(self) -> Perms {
+        Perms::from_bits(self.0)
+    }
+
+    pub fn is_setuid(self) -> bool {
+        self.0 & Self::SETUID != 0
+    }
+
+    pub fn is_setgid(self) -> bool {
+        self.0 & Self::SETGID != 0
+    }
+
+    pub fn is_sticky(self) -> bool {
+        self.0 & Self::STICKY != 0
+    }
+
+    /// 任意一组带执行位
+    pub fn is_executable(self) -> bool {
+        self.0 & 0o111 != 0
+    }
+
+    /// `ls -l` 形式的九位权限，如 "rwxr-sr-t"；特殊位所在组无执行权限时用大写 S / T
+    pub fn symbolic(self) -> String {
+        let specials = [(self.owner(), self.is_setuid(), 's'), (self.group(), self.is_setgid(), 's'), (self.other(), self.is_sticky(), 't')];
+        let mut out = String::with_capacity(9);
+        for (perms, special, letter) in specials {
+            out.push(if perms.read { 'r' } else { '-' });
+            out.push(if perms.write { 'w' } else { '-' });
+            out.push(match (special, perms.execute) {
+                (true, true) => letter,
+                (true, false) => letter.to_ascii_uppercase(),
+                (false, true) => 'x',
+                (false, false) => '-',
+            });
+        }
+        out
+    }
+}
+
+impl From<u32> for Mode {
+    fn from(bits: u32) -> Self {
+        Mode(bits)
+    }
+}
+
+impl From<Mode> for u32 {
+    fn from(mode: Mode) -> Self {
+        mode.0
+    }
+}
+
+impl PartialEq<u32> for Mode {
+    fn eq(&self, other: &u32) -> bool {
+        self.0 == *other
+    }
+}
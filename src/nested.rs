@@ -0,0 +1,102 @@
+//! 嵌套归档：条目本身是 tar（可带压缩）时，递归遍历其中的条目
+//!
+//! 内层条目用虚拟路径表示，各层之间以 '!' 分隔，如 `outer.tar!inner.tar!etc/passwd`。
+
+use std::io::{self, Read};
+use std::sync::{Arc, Mutex};
+use crate::backend::MemoryBackend;
+use crate::base::{lock_image, try_into_tarfile, BodyReader, ImageInfo, TarFile, TarImage};
+use crate::compress::{decoder, Compression};
+use crate::error::PtError;
+
+/// 虚拟路径中各层之间的分隔符
+pub const NESTED_SEPARATOR: char = '!';
+
+/// 递归的最大层数，防止包含自身的归档无限展开
+pub const MAX_NESTING: usize = 8;
+
+/// 按名字判断是否为 tar 归档（不区分大小写）
+fn has_tar_name(path: &str) -> bool {
+    const SUFFIXES: &[&str] = &[".tar", ".tar.gz", ".tgz", ".tar.zst", ".tzst", ".tar.xz", ".txz"];
+    let path = path.to_ascii_lowercase();
+    SUFFIXES.iter().any(|suffix| path.ends_with(suffix))
+}
+
+/// 拼接虚拟路径
+pub fn nested_path(outer: &str, inner: &str) -> String {
+    format!("{}{}{}", outer, NESTED_SEPARATOR, inner)
+}
+
+impl TarFile {
+    /// 判断条目本身是否为 tar 归档，是则返回其压缩格式：必须是普通文件，且名字以 `.tar`、`.tgz`
+    /// 等结尾，或（解压后）开头是带 ustar 魔数的 header。未启用对应压缩 feature 的内容只按名字判断
+    pub fn nested_archive(&self) -> io::Result<Option<Compression>> {
+        if !self.meta().is_file() || self.get_size() == 0 {
+            return Ok(None);
+        }
+        let compression = self.compression()?;
+        if has_tar_name(&self.get_path()) {
+            return Ok(Some(compression));
+        }
+        let Ok(reader) = decoder(BodyReader::new(self), compression) else { return Ok(None) };
+        let mut block = Vec::with_capacity(512);
+        // 压缩内容损坏时视为不是归档
+        if reader.take(512).read_to_end(&mut block).is_err() || block.len() < 512 {
+            return Ok(None);
+        }
+        Ok((&block[257..262] == b"ustar").then_some(compression))
+    }
+
+    /// 把条目作为 tar 镜像打开：未压缩时与外层共用句柄，压缩时解压到内存；
+    /// 解压后的大小受外层 `Limits::max_entry_size` 限制
+    pub fn open_archive(&self, compression: Compression) -> io::Result<Arc<Mutex<TarImage>>> {
+        if compression == Compression::None {
+            return self.open_nested();
+        }
+        let outer = lock_image(&self.image)?.clone();
+        let max = outer.limits().max_entry_size;
+        let mut data = Vec::new();
+        let reader = decoder(BodyReader::new(self), compression)?;
+        reader.take(max.map_or(u64::MAX, |m| m.saturating_add(1))).read_to_end(&mut data)?;
+        if let Some(max) = max.filter(|&m| data.len() as u64 > m) {
+            return Err(PtError::LimitExceeded { limit: "max_entry_size", value: data.len() as u64, max }.into());
+        }
+        let name = format!("{}:{}", outer.get_path(), self.get_path());
+        Ok(Arc::new(Mutex::new(outer.sibling(Arc::new(MemoryBackend::new(data)), &name)?)))
+    }
+}
+
+/// 遍历 img 的条目，遇到嵌套归档时在回调之后进入其中
+fn walk(
+    img: &mut TarImage,
+    prefix: Option<&str>,
+    depth: usize,
+    callback: &mut dyn FnMut(&str, &TarFile) -> io::Result<()>,
+) -> io::Result<()> {
+    img.for_each_entry(|file| {
+        let tar_file = try_into_tarfile(file)?;
+        let path = match prefix {
+            Some(prefix) => nested_path(prefix, &tar_file.get_path()),
+            None => tar_file.get_path(),
+        };
+        callback(&path, &tar_file)?;
+        if depth < MAX_NESTING {
+            if let Some(compression) = tar_file.nested_archive()? {
+                let inner = tar_file.open_archive(compression)?;
+                walk(&mut *lock_image(&inner)?, Some(&path), depth + 1, callback)?;
+            }
+        }
+        Ok(())
+    })
+}
+
+impl TarImage {
+    /// 遍历所有条目，并深入本身是 tar 的条目（最多 `MAX_NESTING` 层）；回调参数为虚拟路径与条目，
+    /// 嵌套归档条目自身先于其中的条目回调
+    pub fn for_each_entry_recursive<F>(&mut self, mut callback: F) -> io::Result<()>
+    where
+        F: FnMut(&str, &TarFile) -> io::Result<()>,
+    {
+        walk(self, None, 0, &mut callback)
+    }
+}
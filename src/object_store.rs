@@ -0,0 +1,91 @@
+//! S3 等对象存储上的 tar，按需发出范围 GET
+//!
+//! 签名与传输由调用方提供的 fetch 函数完成（如 aws-sdk 的 `get_object().range(..)`），
+//! 本模块只负责把解析所需的读取转换成范围请求。
+
+use std::io;
+use std::sync::{Arc, Mutex};
+use crate::backend::Backend;
+use crate::base::TarImage;
+
+/// 一次范围 GET：对象的 [start, end] 闭区间，与 HTTP `Range: bytes=start-end` 相同
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RangeRequest<'a> {
+    pub bucket: &'a str,
+    pub key: &'a str,
+    pub start: u64,
+    pub end: u64,
+}
+
+/// 范围 GET 的结果
+#[derive(Debug, Clone, Default)]
+pub struct RangeResponse {
+    pub body: Vec<u8>,
+    /// 对象总长，取自 `Content-Range` 中 '/' 之后的部分
+    pub total_len: u64,
+}
+
+/// 调用方提供的范围请求函数
+pub type FetchFn = dyn Fn(&RangeRequest) -> io::Result<RangeResponse> + Send + Sync;
+
+/// 对象存储中的一个对象
+pub struct ObjectBackend {
+    bucket: String,
+    key: String,
+    len: u64,
+    fetch: Box<FetchFn>,
+}
+
+/// 拆分 `s3://bucket/key`
+pub fn parse_s3_url(url: &str) -> io::Result<(String, String)> {
+    let invalid = || io::Error::new(io::ErrorKind::InvalidInput, format!("expected s3://bucket/key: {}", url));
+    let rest = url.strip_prefix("s3://").ok_or_else(invalid)?;
+    let (bucket, key) = rest.split_once('/').ok_or_else(invalid)?;
+    if bucket.is_empty() || key.is_empty() {
+        return Err(invalid());
+    }
+    Ok((bucket.to_string(), key.to_string()))
+}
+
+impl ObjectBackend {
+    /// 先请求第一个字节取得对象总长
+    pub fn new<F>(bucket: &str, key: &str, fetch: F) -> io::Result<Self>
+    where
+        F: Fn(&RangeRequest) -> io::Result<RangeResponse> + Send + Sync + 'static,
+    {
+        let mut backend = ObjectBackend { bucket: bucket.to_string(), key: key.to_string(), len: 0, fetch: Box::new(fetch) };
+        backend.len = backend.get(0, 0)?.total_len;
+        Ok(backend)
+    }
+
+    fn get(&self, start: u64, end: u64) -> io::Result<RangeResponse> {
+        (self.fetch)(&RangeRequest { bucket: &self.bucket, key: &self.key, start, end })
+    }
+}
+
+impl Backend for ObjectBackend {
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        if offset >= self.len || buf.is_empty() {
+            return Ok(0);
+        }
+        let end = (offset + buf.len() as u64).min(self.len) - 1;
+        let response = self.get(offset, end)?;
+        let n = response.body.len().min(buf.len());
+        buf[..n].copy_from_slice(&response.body[..n]);
+        Ok(n)
+    }
+
+    fn len(&self) -> io::Result<u64> {
+        Ok(self.len)
+    }
+}
+
+impl TarImage {
+    /// 打开对象存储中的 tar，`get_path` 为 `s3://bucket/key`
+    pub fn open_object<F>(bucket: &str, key: &str, fetch: F) -> io::Result<Arc<Mutex<TarImage>>>
+    where
+        F: Fn(&RangeRequest) -> io::Result<RangeResponse> + Send + Sync + 'static,
+    {
+        TarImage::from_backend(ObjectBackend::new(bucket, key, fetch)?, &format!("s3://{}/{}", bucket, key))
+    }
+}
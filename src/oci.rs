@@ -0,0 +1,245 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Read};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use serde_json::Value;
+use crate::base::{lock_image, try_into_tarfile, BodyReader, ImageInfo, TarFile, TarImage};
+use crate::compress::Compression;
+use crate::meta::EntryMeta;
+use crate::path::{normalize_path, sanitize_path};
+use crate::whiteout::classify;
+
+/// 镜像文件的来源：解开的目录或 `docker save` 生成的 tar
+enum Source {
+    Dir(PathBuf),
+    Tar { img: Arc<Mutex<TarImage>>, offsets: HashMap<String, u64> },
+}
+
+/// 解压后的临时层文件，随镜像一起删除
+struct TempLayer(PathBuf);
+
+impl Drop for TempLayer {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+/// 合并视图中的一个条目及其所在的层
+#[derive(Debug, Clone)]
+pub struct LayerEntry {
+    /// 层序号，0 为最底层
+    pub layer: usize,
+    pub meta: EntryMeta,
+}
+
+/// OCI / Docker 镜像：按顺序保存各层
+pub struct OciImage {
+    layers: Vec<Arc<Mutex<TarImage>>>,
+    repo_tags: Vec<String>,
+    _temps: Vec<TempLayer>,
+}
+
+fn invalid(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn parse_json(bytes: &[u8], name: &str) -> io::Result<Value> {
+    serde_json::from_slice(bytes).map_err(|e| invalid(format!("{}: {}", name, e)))
+}
+
+/// "sha256:<hex>" 对应 OCI layout 中的 blobs/sha256/<hex>
+fn blob_path(digest: &str) -> io::Result<String> {
+    let (algo, hex) = digest.split_once(':').ok_or_else(|| invalid(format!("bad digest: {}", digest)))?;
+    Ok(format!("blobs/{}/{}", algo, hex))
+}
+
+/// image index 最多嵌套的层数，超过时视为循环引用
+const MAX_INDEX_DEPTH: usize = 8;
+
+/// 目录来源中 name 对应的文件；name 来自清单，不允许含 ".." 或绝对路径
+fn dir_entry(dir: &Path, name: &str) -> io::Result<PathBuf> {
+    Ok(dir.join(sanitize_path(name)?))
+}
+
+impl Source {
+    fn open(path: &Path) -> io::Result<Self> {
+        if path.is_dir() {
+            return Ok(Source::Dir(path.to_path_buf()));
+        }
+        let img = TarImage::open(&path.to_string_lossy())?;
+        let mut offsets = HashMap::new();
+        lock_image(&img)?.for_each_entry(|file| {
+            let tar_file = try_into_tarfile(file)?;
+            offsets.insert(normalize_path(&tar_file.get_path()).to_string(), tar_file.get_offset());
+            Ok(())
+        })?;
+        Ok(Source::Tar { img, offsets })
+    }
+
+    fn entry(&self, name: &str) -> io::Result<Option<Box<TarFile>>> {
+        let Source::Tar { img, offsets } = self else { return Ok(None) };
+        match offsets.get(normalize_path(name)) {
+            Some(&offset) => Ok(Some(try_into_tarfile(lock_image(img)?.get_file_at(offset)?.0)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn exists(&self, name: &str) -> bool {
+        match self {
+            Source::Dir(dir) => dir_entry(dir, name).is_ok_and(|path| path.is_file()),
+            Source::Tar { offsets, .. } => offsets.contains_key(normalize_path(name)),
+        }
+    }
+
+    fn read(&self, name: &str) -> io::Result<Vec<u8>> {
+        match self {
+            Source::Dir(dir) => fs::read(dir_entry(dir, name)?),
+            Source::Tar { .. } => self.entry(name)?
+                .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("{} not found in image", name)))?
+                .read_to_vec(),
+        }
+    }
+
+    /// 打开一层；未压缩的层直接就地打开，压缩的层先解压到临时文件
+    fn open_layer(&self, name: &str, temps: &mut Vec<TempLayer>) -> io::Result<Arc<Mutex<TarImage>>> {
+        let mut magic = [0u8; 6];
+        match self {
+            Source::Dir(dir) => {
+                let path = dir_entry(dir, name)?;
+                let n = File::open(&path)?.read(&mut magic)?;
+                match Compression::sniff(&magic[..n]) {
+                    Compression::None => TarImage::open(&path.to_string_lossy()),
+                    compression => decompress_to_temp(File::open(&path)?, compression, temps),
+                }
+            }
+            Source::Tar { .. } => {
+                let file = self.entry(name)?
+                    .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("layer {} not found in image", name)))?;
+                match file.compression()? {
+                    Compression::None => file.open_nested(),
+                    compression => decompress_to_temp(BodyReader::new(&file), compression, temps),
+                }
+            }
+        }
+    }
+}
+
+fn decompress_to_temp<R: Read>(reader: R, compression: Compression, temps: &mut Vec<TempLayer>) -> io::Result<Arc<Mutex<TarImage>>> {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+    let path = std::env::temp_dir().join(format!("pt-layer-{}-{}.tar", std::process::id(), n));
+    let temp = TempLayer(path.clone());
+    let mut out = BufWriter::new(File::create(&path)?);
+    io::copy(&mut crate::compress::decoder(reader, compression)?, &mut out)?;
+    drop(out);
+    let img = TarImage::open(&path.to_string_lossy())?;
+    temps.push(temp);
+    Ok(img)
+}
+
+impl OciImage {
+    /// 打开 `docker save` 生成的 tar、OCI layout（目录或 tar）
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let source = Source::open(path.as_ref())?;
+        let (layer_names, repo_tags) = if source.exists("manifest.json") {
+            docker_manifest(&source)?
+        } else if source.exists("index.json") {
+            (oci_layers(&source)?, Vec::new())
+        } else {
+            return Err(invalid("neither manifest.json nor index.json found".to_string()));
+        };
+
+        let mut temps = Vec::new();
+        let mut layers = Vec::new();
+        for name in &layer_names {
+            layers.push(source.open_layer(name, &mut temps)?);
+        }
+        Ok(OciImage { layers, repo_tags, _temps: temps })
+    }
+
+    /// 各层镜像，从最底层开始
+    pub fn layers(&self) -> &[Arc<Mutex<TarImage>>] {
+        &self.layers
+    }
+
+    pub fn repo_tags(&self) -> &[String] {
+        &self.repo_tags
+    }
+
+    /// 按层顺序叠加得到最终文件系统视图，按路径排序
+    pub fn merged(&self) -> io::Result<BTreeMap<String, LayerEntry>> {
+        let mut view: BTreeMap<String, LayerEntry> = BTreeMap::new();
+        for (layer, img) in self.layers.iter().enumerate() {
+            let mut whiteouts = Vec::new();
+            let mut entries = Vec::new();
+            lock_image(img)?.for_each_entry(|file| {
+                let meta = try_into_tarfile(file)?.meta();
+                match classify(&meta.path) {
+                    Some(wh) => whiteouts.push(wh),
+                    None => entries.push(meta),
+                }
+                Ok(())
+            })?;
+
+            // 白化只作用于下层，先删除再放入本层条目
+            view.retain(|path, _| !whiteouts.iter().any(|wh| wh.hides(path)));
+            for meta in entries {
+                let path = normalize_path(&meta.path).to_string();
+                let replaces_dir = view.get(&path).is_some_and(|prev| prev.layer < layer && prev.meta.is_dir());
+                if replaces_dir && !meta.is_dir() {
+                    // 非目录覆盖下层目录时，目录下的内容一并消失
+                    let prefix = format!("{}/", path);
+                    let hidden: Vec<_> = view.range(prefix.clone()..)
+                        .take_while(|(p, _)| p.starts_with(&prefix))
+                        .filter(|(_, e)| e.layer < layer)
+                        .map(|(p, _)| p.clone())
+                        .collect();
+                    for p in hidden {
+                        view.remove(&p);
+                    }
+                }
+                view.insert(path, LayerEntry { layer, meta });
+            }
+        }
+        Ok(view)
+    }
+
+    /// 打开合并视图中的条目
+    pub fn open_entry(&self, entry: &LayerEntry) -> io::Result<Box<TarFile>> {
+        let img = self.layers.get(entry.layer)
+            .ok_or_else(|| invalid(format!("no layer {}", entry.layer)))?;
+        let file = lock_image(img)?.get_file_at(entry.meta.offset)?.0;
+        try_into_tarfile(file)
+    }
+}
+
+/// docker save：manifest.json 是数组，取第一个镜像
+fn docker_manifest(source: &Source) -> io::Result<(Vec<String>, Vec<String>)> {
+    let manifest = parse_json(&source.read("manifest.json")?, "manifest.json")?;
+    let image = manifest.get(0).ok_or_else(|| invalid("manifest.json is empty".to_string()))?;
+    let strings = |key: &str| -> Vec<String> {
+        image[key].as_array().into_iter().flatten()
+            .filter_map(|v| v.as_str().map(str::to_string))
+            .collect()
+    };
+    Ok((strings("Layers"), strings("RepoTags")))
+}
+
+/// OCI layout：index.json -> manifest blob -> layers；嵌套的 image index 取第一个，最多 `MAX_INDEX_DEPTH` 层
+fn oci_layers(source: &Source) -> io::Result<Vec<String>> {
+    let mut doc = parse_json(&source.read("index.json")?, "index.json")?;
+    for _ in 0..=MAX_INDEX_DEPTH {
+        if let Some(layers) = doc["layers"].as_array() {
+            return layers.iter()
+                .map(|l| l["digest"].as_str().ok_or_else(|| invalid("layer without digest".to_string())).and_then(blob_path))
+                .collect();
+        }
+        let digest = doc["manifests"][0]["digest"].as_str()
+            .ok_or_else(|| invalid("index without manifests".to_string()))?;
+        let name = blob_path(digest)?;
+        doc = parse_json(&source.read(&name)?, &name)?;
+    }
+    Err(invalid(format!("image index nested deeper than {} levels", MAX_INDEX_DEPTH)))
+}
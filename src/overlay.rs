@@ -0,0 +1,104 @@
+use std::collections::BTreeSet;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use crate::base::{lock_image, try_into_tarfile, ImageInfo, TarImage};
+use crate::meta::EntryMeta;
+use crate::tree::TarTree;
+
+/// 本地目录与归档的查找顺序
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverlayOrder {
+    /// 先查本地目录，找不到再查归档
+    LocalFirst,
+    /// 先查归档，找不到再查本地目录
+    ArchiveFirst,
+}
+
+/// 路径解析结果
+#[derive(Debug, Clone)]
+pub enum Resolved {
+    Local(PathBuf, fs::Metadata),
+    Archive(EntryMeta),
+}
+
+/// 把归档叠加在真实目录上的只读视图，不解包也不改写归档
+pub struct Overlay {
+    dir: PathBuf,
+    img: Arc<Mutex<TarImage>>,
+    tree: TarTree,
+    order: OverlayOrder,
+}
+
+impl Overlay {
+    pub fn new<P: AsRef<Path>>(dir: P, img: Arc<Mutex<TarImage>>, order: OverlayOrder) -> io::Result<Self> {
+        let tree = lock_image(&img)?.tree()?;
+        Ok(Overlay { dir: dir.as_ref().to_path_buf(), img, tree, order })
+    }
+
+    /// 本地目录中的对应路径；含 ".." 等越出目录的组成部分时返回 None
+    fn local_path(&self, path: &str) -> Option<PathBuf> {
+        let mut out = self.dir.clone();
+        for component in Path::new(path.trim_start_matches('/')).components() {
+            match component {
+                Component::Normal(name) => out.push(name),
+                Component::CurDir => {}
+                _ => return None,
+            }
+        }
+        Some(out)
+    }
+
+    fn local(&self, path: &str) -> Option<Resolved> {
+        let local = self.local_path(path)?;
+        let md = fs::symlink_metadata(&local).ok()?;
+        Some(Resolved::Local(local, md))
+    }
+
+    fn archive(&self, path: &str) -> Option<Resolved> {
+        let id = self.tree.lookup(path)?;
+        self.tree.node(id).meta().map(|m| Resolved::Archive(m.clone()))
+    }
+
+    /// 按设定的顺序解析路径
+    pub fn resolve(&self, path: &str) -> Option<Resolved> {
+        match self.order {
+            OverlayOrder::LocalFirst => self.local(path).or_else(|| self.archive(path)),
+            OverlayOrder::ArchiveFirst => self.archive(path).or_else(|| self.local(path)),
+        }
+    }
+
+    /// 打开文件内容，路径不存在时返回 None
+    pub fn open(&self, path: &str) -> io::Result<Option<Box<dyn Read>>> {
+        match self.resolve(path) {
+            None => Ok(None),
+            Some(Resolved::Local(local, _)) => Ok(Some(Box::new(File::open(local)?))),
+            Some(Resolved::Archive(meta)) => {
+                let file = try_into_tarfile(lock_image(&self.img)?.get_file_at(meta.offset)?.0)?;
+                Ok(Some(file))
+            }
+        }
+    }
+
+    pub fn read(&self, path: &str) -> io::Result<Option<Vec<u8>>> {
+        let Some(mut reader) = self.open(path)? else { return Ok(None) };
+        let mut out = Vec::new();
+        reader.read_to_end(&mut out)?;
+        Ok(Some(out))
+    }
+
+    /// 合并两侧的目录内容，按名称排序
+    pub fn read_dir(&self, path: &str) -> io::Result<Vec<String>> {
+        let mut names = BTreeSet::new();
+        if let Some(id) = self.tree.lookup(path) {
+            names.extend(self.tree.children(id).map(|(name, _)| name.to_string()));
+        }
+        if let Some(local) = self.local_path(path).filter(|p| p.is_dir()) {
+            for entry in fs::read_dir(local)? {
+                names.insert(entry?.file_name().to_string_lossy().into_owned());
+            }
+        }
+        Ok(names.into_iter().collect())
+    }
+}
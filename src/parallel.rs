@@ -0,0 +1,27 @@
+use std::io;
+use rayon::prelude::*;
+use crate::base::{try_into_tarfile, ImageInfo, TarFile, TarImage};
+
+impl TarImage {
+    /// 先单线程扫描出全部条目偏移，再在 rayon 线程池中并行处理条目；
+    /// 每个工作线程使用独立的文件句柄，回调的调用顺序不确定，遇到第一个错误即停止
+    pub fn par_for_each_entry<F>(&mut self, callback: F) -> io::Result<()>
+    where
+        F: Fn(Box<TarFile>) -> io::Result<()> + Send + Sync,
+    {
+        let mut offsets = Vec::new();
+        self.for_each_entry(|file| {
+            offsets.push(try_into_tarfile(file)?.get_offset());
+            Ok(())
+        })?;
+
+        let this = &*self;
+        offsets.par_iter().try_for_each_init(
+            || this.reopen(),
+            |img, &offset| {
+                let img = img.as_mut().map_err(|e| io::Error::new(e.kind(), e.to_string()))?;
+                callback(try_into_tarfile(img.get_file_at(offset)?.0)?)
+            },
+        )
+    }
+}
@@ -0,0 +1,108 @@
+/// 统一路径写法：去掉开头的 "./"、"/" 以及目录结尾的 '/'
+pub fn normalize_path(path: &str) -> &str {
+    let mut p = path;
+    while let Some(rest) = p.strip_prefix("./") {
+        p = rest;
+    }
+    p.trim_start_matches('/').trim_end_matches('/')
+}
+
+/// 与 `normalize_path` 相同，用于非 UTF-8 的原始路径字节
+pub fn normalize_path_bytes(path: &[u8]) -> &[u8] {
+    let mut p = path;
+    while let Some(rest) = p.strip_prefix(b"./") {
+        p = rest;
+    }
+    while let [b'/', rest @ ..] = p {
+        p = rest;
+    }
+    while let [rest @ .., b'/'] = p {
+        p = rest;
+    }
+    p
+}
+
+/// 去掉绝对路径前缀：开头的 '/'、'\' 以及 Windows 盘符（如 `C:`），与 GNU tar 默认行为一致
+pub fn strip_absolute(path: &[u8]) -> &[u8] {
+    let mut p = path;
+    loop {
+        if p.len() >= 2 && p[0].is_ascii_alphabetic() && p[1] == b':' {
+            p = &p[2..];
+        } else if let [b'/' | b'\\', rest @ ..] = p {
+            p = rest;
+        } else {
+            return p;
+        }
+    }
+}
+
+/// 检查条目路径能否安全地解包到目标目录下：拒绝绝对路径（含盘符）与 ".." 段（'/' 与 '\\' 都视为分隔符），
+/// 返回去掉 "." 与空段后的相对路径
+pub fn sanitize_path(path: &str) -> std::io::Result<String> {
+    let unsafe_path = || crate::error::PtError::UnsafePath { path: path.to_string() }.into();
+    if strip_absolute(path.as_bytes()).len() != path.len() {
+        return Err(unsafe_path());
+    }
+    if path.split(['/', '\\']).any(|c| c == "..") {
+        return Err(unsafe_path());
+    }
+    let parts: Vec<&str> = path.split('/').filter(|c| !c.is_empty() && *c != ".").collect();
+    Ok(parts.join("/"))
+}
+
+/// 与 `sanitize_path` 相同的检查，用于非 UTF-8 的原始路径字节
+pub fn sanitize_path_bytes(path: &[u8]) -> std::io::Result<Vec<u8>> {
+    let unsafe_path = || crate::error::PtError::UnsafePath { path: String::from_utf8_lossy(path).into_owned() }.into();
+    if strip_absolute(path).len() != path.len() {
+        return Err(unsafe_path());
+    }
+    if path.split(|&b| b == b'/' || b == b'\\').any(|c| c == b"..") {
+        return Err(unsafe_path());
+    }
+    let parts: Vec<&[u8]> = path.split(|&b| b == b'/').filter(|c| !c.is_empty() && *c != b".").collect();
+    Ok(parts.join(&b'/'))
+}
+
+/// 原始路径字节转成系统路径：Unix 上逐字节保留，其它平台上非 UTF-8 字节按替换字符转换
+pub fn os_from_bytes(bytes: &[u8]) -> std::ffi::OsString {
+    #[cfg(unix)]
+    {
+        <std::ffi::OsStr as std::os::unix::ffi::OsStrExt>::from_bytes(bytes).to_os_string()
+    }
+    #[cfg(not(unix))]
+    {
+        String::from_utf8_lossy(bytes).into_owned().into()
+    }
+}
+
+const RESERVED_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL",
+    "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8", "COM9",
+    "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// 判断单个路径段是否为 Windows 保留设备名（不区分大小写，忽略扩展名和结尾的空格/点）
+pub fn is_reserved_windows_name(component: &str) -> bool {
+    let stem = component.split('.').next().unwrap_or("").trim_end_matches([' ', '.']);
+    RESERVED_NAMES.iter().any(|r| r.eq_ignore_ascii_case(stem))
+}
+
+/// 返回路径中第一个 Windows 保留设备名
+pub fn find_reserved_windows_name(path: &str) -> Option<&str> {
+    path.split(['/', '\\']).find(|c| is_reserved_windows_name(c))
+}
+
+/// 转成 `\\?\` 扩展长度路径，绕过 MAX_PATH 限制
+#[cfg(windows)]
+pub fn to_extended_length(path: &std::path::Path) -> std::io::Result<std::path::PathBuf> {
+    use std::path::PathBuf;
+    let abs = std::path::absolute(path)?;
+    let s = abs.to_string_lossy().replace('/', "\\");
+    if s.starts_with(r"\\?\") {
+        Ok(PathBuf::from(s))
+    } else if let Some(unc) = s.strip_prefix(r"\\") {
+        Ok(PathBuf::from(format!(r"\\?\UNC\{}", unc)))
+    } else {
+        Ok(PathBuf::from(format!(r"\\?\{}", s)))
+    }
+}
@@ -0,0 +1,107 @@
+use std::collections::BTreeMap;
+use std::io;
+use std::time::Duration;
+
+/// PAX 扩展记录：键为 UTF-8 字符串，值保留原始字节（xattr 等值可能是二进制）
+pub type PaxRecords = BTreeMap<String, Vec<u8>>;
+
+/// xattr 在 PAX 记录中的键前缀
+pub const XATTR_PREFIX: &str = "SCHILY.xattr.";
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("invalid pax record: {}", msg))
+}
+
+/// 解析 PAX 扩展 header 数据区，每条记录格式为 "<长度> <键>=<值>\n"，长度包含记录自身
+pub fn parse_pax_records(data: &[u8]) -> io::Result<PaxRecords> {
+    let mut records = PaxRecords::new();
+    let mut rest = data;
+    while !rest.is_empty() {
+        // 数据区末尾的 '\0' 填充
+        if rest[0] == 0 {
+            break;
+        }
+        let space = rest.iter().position(|&b| b == b' ').ok_or_else(|| invalid("missing length"))?;
+        let len: usize = std::str::from_utf8(&rest[..space]).ok()
+            .and_then(|s| s.parse().ok())
+            .ok_or_else(|| invalid("bad length"))?;
+        if len <= space + 1 || len > rest.len() || rest[len - 1] != b'\n' {
+            return Err(invalid("length out of range"));
+        }
+        let record = &rest[space + 1..len - 1];
+        let eq = record.iter().position(|&b| b == b'=').ok_or_else(|| invalid("missing '='"))?;
+        let key = String::from_utf8_lossy(&record[..eq]).into_owned();
+        records.insert(key, record[eq + 1..].to_vec());
+        rest = &rest[len..];
+    }
+    Ok(records)
+}
+
+/// 编码一条 PAX 记录 "<长度> <键>=<值>\n"
+pub fn pax_record(key: &str, value: &[u8]) -> Vec<u8> {
+    let body_len = key.len() + value.len() + 3;
+    // 长度字段包含自身的位数，逐步逼近
+    let mut len = body_len + 1;
+    while len.to_string().len() + body_len != len {
+        len += 1;
+    }
+    let mut out = format!("{} {}=", len, key).into_bytes();
+    out.extend_from_slice(value);
+    out.push(b'\n');
+    out
+}
+
+/// 把 'g' header 的记录合并进之前生效的全局记录，空值删除同名记录
+pub fn merge_globals(globals: &mut PaxRecords, records: PaxRecords) {
+    for (key, value) in records {
+        if value.is_empty() {
+            globals.remove(&key);
+        } else {
+            globals.insert(key, value);
+        }
+    }
+}
+
+/// 取十进制数值记录（如 size）
+pub fn pax_u64(records: &PaxRecords, key: &str) -> Option<u64> {
+    records.get(key).and_then(|v| std::str::from_utf8(v).ok()).and_then(|s| s.trim().parse().ok())
+}
+
+/// 取时间记录（如 mtime），格式为 "[-]<秒>[.<小数>]"，精确到纳秒；返回 (秒, 纳秒)，
+/// 秒向下取整、纳秒总是非负，如 "-1.25" 为 (-2, 750000000)。无法解析时返回 None
+pub fn pax_timestamp(records: &PaxRecords, key: &str) -> Option<(i64, u32)> {
+    let s = std::str::from_utf8(records.get(key)?).ok()?.trim();
+    let (negative, digits) = match s.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, s),
+    };
+    let (secs, frac) = digits.split_once('.').unwrap_or((digits, ""));
+    if !secs.bytes().all(|b| b.is_ascii_digit()) || !frac.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let frac = &frac[..frac.len().min(9)];
+    let nanos = match frac {
+        "" => 0,
+        _ => frac.parse::<u32>().ok()? * 10u32.pow(9 - frac.len() as u32),
+    };
+    let secs: i64 = secs.parse().ok()?;
+    match (negative, nanos) {
+        (false, _) => Some((secs, nanos)),
+        (true, 0) => Some((-secs, 0)),
+        (true, _) => Some((-secs - 1, 1_000_000_000 - nanos)),
+    }
+}
+
+/// 与 `pax_timestamp` 相同，但以 Duration 表示；早于 1970 年时返回 None
+pub fn pax_time(records: &PaxRecords, key: &str) -> Option<Duration> {
+    let (secs, nanos) = pax_timestamp(records, key)?;
+    Some(Duration::new(u64::try_from(secs).ok()?, nanos))
+}
+
+/// 取出所有 `SCHILY.xattr.*` 记录，键为去掉前缀后的属性名
+pub fn xattrs(records: &PaxRecords) -> BTreeMap<String, Vec<u8>> {
+    records.range(XATTR_PREFIX.to_string()..)
+        .take_while(|(k, _)| k.starts_with(XATTR_PREFIX))
+        .map(|(k, v)| (k[XATTR_PREFIX.len()..].to_string(), v.clone()))
+        .collect()
+}
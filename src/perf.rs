@@ -0,0 +1,99 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// 进程级性能计数器，所有镜像共享
+struct Counters {
+    blocks_read: AtomicU64,
+    bytes_read: AtomicU64,
+    syscalls: AtomicU64,
+    allocations: AtomicU64,
+    headers_parsed: AtomicU64,
+    cache_hits: AtomicU64,
+}
+
+static COUNTERS: Counters = Counters {
+    blocks_read: AtomicU64::new(0),
+    bytes_read: AtomicU64::new(0),
+    syscalls: AtomicU64::new(0),
+    allocations: AtomicU64::new(0),
+    headers_parsed: AtomicU64::new(0),
+    cache_hits: AtomicU64::new(0),
+};
+
+/// 某一时刻的计数器快照
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PerfSnapshot {
+    /// 读取的 512 字节 header 块数
+    pub blocks_read: u64,
+    /// 从后端读取的字节数
+    pub bytes_read: u64,
+    /// 文件相关系统调用次数（dup、seek、read 等）
+    pub syscalls: u64,
+    /// 读路径上的缓冲区分配次数
+    pub allocations: u64,
+    /// 成功解析的 header 数
+    pub headers_parsed: u64,
+    /// 命中缓存、无需访问后端的读取次数
+    pub cache_hits: u64,
+}
+
+impl PerfSnapshot {
+    /// 计算自 earlier 以来的增量
+    pub fn since(&self, earlier: &PerfSnapshot) -> PerfSnapshot {
+        PerfSnapshot {
+            blocks_read: self.blocks_read - earlier.blocks_read,
+            bytes_read: self.bytes_read - earlier.bytes_read,
+            syscalls: self.syscalls - earlier.syscalls,
+            allocations: self.allocations - earlier.allocations,
+            headers_parsed: self.headers_parsed - earlier.headers_parsed,
+            cache_hits: self.cache_hits - earlier.cache_hits,
+        }
+    }
+}
+
+pub fn snapshot() -> PerfSnapshot {
+    PerfSnapshot {
+        blocks_read: COUNTERS.blocks_read.load(Ordering::Relaxed),
+        bytes_read: COUNTERS.bytes_read.load(Ordering::Relaxed),
+        syscalls: COUNTERS.syscalls.load(Ordering::Relaxed),
+        allocations: COUNTERS.allocations.load(Ordering::Relaxed),
+        headers_parsed: COUNTERS.headers_parsed.load(Ordering::Relaxed),
+        cache_hits: COUNTERS.cache_hits.load(Ordering::Relaxed),
+    }
+}
+
+pub fn reset() {
+    for c in [
+        &COUNTERS.blocks_read,
+        &COUNTERS.bytes_read,
+        &COUNTERS.syscalls,
+        &COUNTERS.allocations,
+        &COUNTERS.headers_parsed,
+        &COUNTERS.cache_hits,
+    ] {
+        c.store(0, Ordering::Relaxed);
+    }
+}
+
+pub(crate) fn add_blocks_read(n: u64) {
+    COUNTERS.blocks_read.fetch_add(n, Ordering::Relaxed);
+}
+
+pub(crate) fn add_bytes_read(n: u64) {
+    COUNTERS.bytes_read.fetch_add(n, Ordering::Relaxed);
+}
+
+pub(crate) fn add_syscalls(n: u64) {
+    COUNTERS.syscalls.fetch_add(n, Ordering::Relaxed);
+}
+
+pub(crate) fn add_allocation() {
+    COUNTERS.allocations.fetch_add(1, Ordering::Relaxed);
+}
+
+pub(crate) fn add_header_parsed() {
+    COUNTERS.headers_parsed.fetch_add(1, Ordering::Relaxed);
+}
+
+pub(crate) fn add_cache_hit() {
+    COUNTERS.cache_hits.fetch_add(1, Ordering::Relaxed);
+}
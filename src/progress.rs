@@ -0,0 +1,54 @@
+use std::io;
+use crate::base::{try_into_tarfile, FileInfo, ImageInfo, TarImage};
+
+/// 一次进度更新
+#[derive(Debug, Clone, Copy)]
+pub struct ProgressInfo<'a> {
+    /// 已处理的条目数
+    pub entries: u64,
+    /// 已处理到的镜像偏移（当前条目的结束位置）
+    pub bytes: u64,
+    /// 镜像总大小，可与 `bytes` 一起计算百分比
+    pub total_bytes: u64,
+    /// 刚处理完的条目路径
+    pub path: &'a str,
+}
+
+/// 进度回调，每处理完一个条目调用一次
+pub trait Progress {
+    fn update(&mut self, info: &ProgressInfo<'_>);
+}
+
+impl<F: FnMut(&ProgressInfo<'_>)> Progress for F {
+    fn update(&mut self, info: &ProgressInfo<'_>) {
+        self(info)
+    }
+}
+
+/// 不报告进度
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoProgress;
+
+impl Progress for NoProgress {
+    fn update(&mut self, _info: &ProgressInfo<'_>) {}
+}
+
+impl TarImage {
+    /// 与 `for_each_entry` 相同，每个条目的回调返回后报告一次进度
+    pub fn for_each_entry_with_progress<F>(&mut self, progress: &mut dyn Progress, mut callback: F) -> io::Result<()>
+    where
+        F: FnMut(Box<dyn FileInfo>) -> io::Result<()>,
+    {
+        let total_bytes = self.get_size()?;
+        let mut entries = 0;
+        self.for_each_entry(|file| {
+            let tar_file = try_into_tarfile(file)?;
+            let path = tar_file.get_path();
+            let bytes = tar_file.get_end_offset().min(total_bytes);
+            callback(tar_file)?;
+            entries += 1;
+            progress.update(&ProgressInfo { entries, bytes, total_bytes, path: &path });
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,101 @@
+use std::sync::{Arc, Mutex};
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+use crate::base::{lock_image, ImageInfo, TarFile, TarImage};
+
+/// Python 侧的 `pt.TarImage`
+#[pyclass(name = "TarImage", module = "pt")]
+pub struct PyTarImage {
+    img: Arc<Mutex<TarImage>>,
+}
+
+/// 按归档顺序遍历条目的迭代器
+#[pyclass(name = "EntryIter", module = "pt")]
+pub struct PyEntryIter {
+    img: Arc<Mutex<TarImage>>,
+    offset: u64,
+}
+
+/// Python 侧的 `pt.Entry`
+#[pyclass(name = "Entry", module = "pt")]
+pub struct PyEntry {
+    file: Box<TarFile>,
+}
+
+#[pymethods]
+impl PyTarImage {
+    #[staticmethod]
+    fn open(path: &str) -> PyResult<Self> {
+        Ok(PyTarImage { img: TarImage::open(path)? })
+    }
+
+    fn __iter__(&self) -> PyEntryIter {
+        PyEntryIter { img: self.img.clone(), offset: 0 }
+    }
+
+    /// 按路径查找条目，同名时取最后一个，找不到返回 None
+    fn find(&self, path: &str) -> PyResult<Option<PyEntry>> {
+        Ok(lock_image(&self.img)?.find_entry(path)?.map(|file| PyEntry { file }))
+    }
+}
+
+#[pymethods]
+impl PyEntryIter {
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __next__(&mut self) -> PyResult<Option<PyEntry>> {
+        let Some(file) = lock_image(&self.img)?.entry_at(self.offset)? else { return Ok(None) };
+        self.offset = file.get_end_offset();
+        Ok(Some(PyEntry { file }))
+    }
+}
+
+#[pymethods]
+impl PyEntry {
+    #[getter]
+    fn path(&self) -> String {
+        self.file.get_path()
+    }
+
+    #[getter]
+    fn size(&self) -> u64 {
+        self.file.get_size()
+    }
+
+    #[getter]
+    fn type_flag(&self) -> char {
+        self.file.get_type_flag()
+    }
+
+    #[getter]
+    fn mode(&self) -> u32 {
+        self.file.get_header().get_mode().bits()
+    }
+
+    #[getter]
+    fn mtime(&self) -> i64 {
+        self.file.get_header().get_mtime()
+    }
+
+    /// 读出整个数据区
+    fn read<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyBytes>> {
+        let data = py.detach(|| self.file.read_to_vec())?;
+        Ok(PyBytes::new(py, &data))
+    }
+
+    fn __repr__(&self) -> String {
+        format!("<pt.Entry {:?} size={}>", self.file.get_path(), self.file.get_size())
+    }
+}
+
+/// Python 模块 `pt`
+#[pymodule]
+#[pyo3(name = "pt")]
+pub fn python_module(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyTarImage>()?;
+    m.add_class::<PyEntryIter>()?;
+    m.add_class::<PyEntry>()?;
+    Ok(())
+}
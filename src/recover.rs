@@ -0,0 +1,107 @@
+use std::cell::RefCell;
+use std::io;
+use std::ops::ControlFlow;
+use crate::base::{try_into_tarfile, FileInfo, ImageInfo, TarFile, TarImage};
+use crate::error::{as_pt_error, PtError};
+use crate::tar::read_tar_header;
+
+/// 不能通过跳过当前 header 继续的错误：取消与资源上限；严格模式下格式不符的 header 可以跳过
+fn is_fatal(e: &io::Error) -> bool {
+    as_pt_error(e).is_some_and(|e| !matches!(e, PtError::MalformedHeader { .. }))
+}
+
+/// 查找下一个 header 时每次读取的块数
+const SCAN_BLOCKS: u64 = 128;
+
+/// 恢复模式下跳过的损坏区域
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SkippedRange {
+    /// 无法解析的 header 的偏移
+    pub start: u64,
+    /// 恢复读取的偏移（下一个有效 header），找不到时为镜像末尾
+    pub end: u64,
+    /// 读取失败的原因
+    pub reason: String,
+}
+
+/// 宽松遍历时无法读取的条目
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EntryError {
+    /// 出错的 header 偏移
+    pub offset: u64,
+    pub message: String,
+}
+
+/// 看起来有效的 header：magic 以 "ustar" 开头且 checksum 正确
+fn is_plausible_header(block: &[u8]) -> bool {
+    block[257..262] == *b"ustar" && unsafe { read_tar_header(block) }.is_ok_and(|hdr| hdr.crc_ok())
+}
+
+impl TarImage {
+    /// 从 offset 起按块向后查找第一个看起来有效的 header，返回其偏移
+    pub fn find_next_header(&mut self, offset: u64) -> io::Result<Option<u64>> {
+        let size = self.get_size()?;
+        let mut pos = offset.next_multiple_of(512);
+        while pos + 512 <= size {
+            let len = (SCAN_BLOCKS * 512).min((size - pos) / 512 * 512);
+            let (buf, _) = self.read_img_at(pos, len)?;
+            if let Some(i) = buf.chunks_exact(512).position(is_plausible_header) {
+                return Ok(Some(pos + i as u64 * 512));
+            }
+            pos += len;
+        }
+        Ok(None)
+    }
+
+    /// 与 `for_each_entry` 相同，但遇到无法解析的 header 时不终止：
+    /// 向后逐块查找下一个有效 header 并从那里继续，每跳过一段调用一次 on_skip。
+    /// 条目数等限制与取消仍然直接返回错误
+    pub fn for_each_entry_recover<F, S>(&mut self, mut callback: F, mut on_skip: S) -> io::Result<()>
+    where
+        F: FnMut(Box<dyn FileInfo>) -> io::Result<()>,
+        S: FnMut(&SkippedRange),
+    {
+        self.walk_entries(|file| callback(file).map(ControlFlow::Continue), |img, off, e| {
+            if is_fatal(&e) {
+                return Err(e);
+            }
+            let next = img.find_next_header(off + 512)?;
+            let end = match next {
+                Some(next) => next,
+                None => img.get_size()?,
+            };
+            on_skip(&SkippedRange { start: off, end, reason: e.to_string() });
+            Ok(next)
+        })
+    }
+
+    /// 宽松遍历：条目 header 的 checksum 错误或 size 字段无法解析时，回调收到一条 `EntryError`，
+    /// 然后从下一个 512 字节块继续；条目数等限制与取消仍然直接返回错误
+    pub fn for_each_entry_lenient<F>(&mut self, callback: F) -> io::Result<()>
+    where
+        F: FnMut(Result<Box<TarFile>, EntryError>) -> io::Result<()>,
+    {
+        // 两个闭包都需要调用回调
+        let callback = RefCell::new(callback);
+        self.walk_entries(|file| {
+            let tar_file = try_into_tarfile(file)?;
+            let size = tar_file.get_header().try_get_size();
+            match size {
+                Err(message) if !tar_file.pax_records().contains_key("size") => {
+                    // size 不可信，数据区按 0 处理，从 header 之后的块继续
+                    (callback.borrow_mut())(Err(EntryError { offset: tar_file.get_offset(), message }))?;
+                }
+                _ => (callback.borrow_mut())(Ok(tar_file))?,
+            }
+            Ok(ControlFlow::Continue(()))
+        }, |_, off, e| {
+            if is_fatal(&e) {
+                return Err(e);
+            }
+            (callback.borrow_mut())(Err(EntryError { offset: off, message: e.to_string() }))?;
+            Ok(Some(off + 512))
+        })
+    }
+}
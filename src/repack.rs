@@ -0,0 +1,272 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use crate::base::{lock_image, try_into_tarfile, ImageInfo, TarFile, TarImage};
+use crate::builder::{extension_block, gnu_long_block, truncate};
+use crate::meta::EntryMeta;
+use crate::pax::pax_record;
+use crate::tar::{block_align, write_checksum, TypeFlag};
+
+/// 单次遍历把 src 中满足 keep 的条目按原始块复制到 dst，返回保留的条目数
+///
+/// 条目附带的扩展 header（长名称、PAX、厂商记录）随条目一起原样复制；
+/// 全局 PAX header 'g' 与卷标 'V' 不属于任何文件，总是保留
+pub fn filter_copy<W, F>(src: &mut TarImage, dst: &mut W, mut keep: F) -> io::Result<u64>
+where
+    W: Write,
+    F: FnMut(&EntryMeta) -> bool,
+{
+    let mut kept = 0;
+    src.for_each_entry(|file| {
+        let tar_file = try_into_tarfile(file)?;
+        if tar_file.get_type_flag() == 'V' {
+            tar_file.copy_raw_to(dst)?;
+        } else if keep(&tar_file.meta()) {
+            tar_file.copy_raw_to(dst)?;
+            kept += 1;
+        } else {
+            tar_file.copy_globals_to(dst)?;
+        }
+        Ok(())
+    })?;
+    dst.write_all(&[0u8; 1024])?;
+    dst.flush()?;
+    Ok(kept)
+}
+
+/// 按路径打开源归档，过滤后写入新文件
+pub fn filter_copy_paths<F>(src: &str, dst: &str, keep: F) -> io::Result<u64>
+where
+    F: FnMut(&EntryMeta) -> bool,
+{
+    let img = TarImage::open(src)?;
+    let mut out = BufWriter::new(File::create(dst)?);
+    let result = filter_copy(&mut *lock_image(&img)?, &mut out, keep);
+    result
+}
+
+/// 名字改写作用的位置
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NameKind {
+    /// 条目路径
+    Path,
+    /// 硬链接目标，与条目路径同属归档内的名字
+    HardLink,
+    /// 符号链接目标，相对链接所在的目录解析
+    Symlink,
+}
+
+/// 常用的名字改写
+#[derive(Debug, Clone)]
+pub enum NameTransform {
+    /// 去掉开头的前缀（如 "./"），不以它开头的名字不变；不改动符号链接目标
+    StripPrefix(String),
+    /// 把条目放到新的根目录下；不改动符号链接目标，相对链接仍然有效
+    Prefix(String),
+    /// sed 风格的替换，作用于路径与所有链接目标
+    #[cfg(feature = "regex")]
+    Sed { re: regex::Regex, replacement: String, global: bool },
+}
+
+impl NameTransform {
+    /// 解析 `s/regex/replacement/[gi]`：分隔符取 's' 之后的字符，替换中的 `&` 与 `\1`..`\9` 引用匹配内容，
+    /// 以 '\' 转义分隔符自身；格式或正则有误时返回 InvalidInput
+    #[cfg(feature = "regex")]
+    pub fn sed(expr: &str) -> io::Result<Self> {
+        let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidInput, format!("{}: {}", msg, expr));
+        let mut chars = expr.chars();
+        let delim = match (chars.next(), chars.next()) {
+            (Some('s'), Some(d)) if d != '\\' && d != '\n' => d,
+            _ => return Err(invalid("expected s/regex/replacement/")),
+        };
+        let mut parts = vec![String::new()];
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => match chars.next() {
+                    Some(next) if next == delim => parts.last_mut().unwrap().push(next),
+                    Some(next) => parts.last_mut().unwrap().extend(['\\', next]),
+                    None => return Err(invalid("trailing backslash")),
+                },
+                c if c == delim && parts.len() < 3 => parts.push(String::new()),
+                c => parts.last_mut().unwrap().push(c),
+            }
+        }
+        let [pattern, replacement, flags] = <[String; 3]>::try_from(parts).map_err(|_| invalid("expected s/regex/replacement/"))?;
+        let (mut global, mut icase) = (false, false);
+        for flag in flags.chars() {
+            match flag {
+                'g' => global = true,
+                'i' => icase = true,
+                _ => return Err(invalid("unknown flag")),
+            }
+        }
+        let re = regex::RegexBuilder::new(&pattern)
+            .case_insensitive(icase)
+            .build()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e.to_string()))?;
+        Ok(NameTransform::Sed { re, replacement: sed_replacement(&replacement), global })
+    }
+
+    pub fn apply(&self, kind: NameKind, name: &str) -> String {
+        match self {
+            NameTransform::StripPrefix(_) | NameTransform::Prefix(_) if kind == NameKind::Symlink => name.to_string(),
+            NameTransform::StripPrefix(prefix) => name.strip_prefix(prefix.as_str()).unwrap_or(name).to_string(),
+            NameTransform::Prefix(prefix) => format!("{}/{}", prefix.trim_end_matches('/'), name),
+            #[cfg(feature = "regex")]
+            NameTransform::Sed { re, replacement, global: true } => re.replace_all(name, replacement.as_str()).into_owned(),
+            #[cfg(feature = "regex")]
+            NameTransform::Sed { re, replacement, global: false } => re.replace(name, replacement.as_str()).into_owned(),
+        }
+    }
+}
+
+/// 把 sed 的替换语法转换成 `regex` 的：`&` -> `${0}`，`\N` -> `${N}`，字面的 `$` 需要写成 `$$`
+#[cfg(feature = "regex")]
+fn sed_replacement(sed: &str) -> String {
+    let mut out = String::new();
+    let mut chars = sed.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '&' => out.push_str("${0}"),
+            '$' => out.push_str("$$"),
+            '\\' => match chars.next() {
+                Some(d) if d.is_ascii_digit() => out.push_str(&format!("${{{}}}", d)),
+                Some('$') => out.push_str("$$"),
+                Some(other) => out.push(other),
+                None => {}
+            },
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// 单次遍历把 src 复制到 dst，条目路径与链接目标经 rename 改写，返回改名的条目数
+///
+/// 名字不变的条目按原始块复制；改名的条目重写 header，原有的 'L' / 'K' 被丢弃，其余 PAX 记录与
+/// 厂商记录保留，新名字放不下时 GNU 格式的条目写成 'L' / 'K'，其余写成 PAX path / linkpath，
+/// 数据块原样复制。路径被改写为空的条目（如对 "./" 去掉 "./"）被丢弃
+pub fn transform_copy<W, F>(src: &mut TarImage, dst: &mut W, mut rename: F) -> io::Result<u64>
+where
+    W: Write,
+    F: FnMut(NameKind, &str) -> String,
+{
+    let mut renamed = 0;
+    src.for_each_entry(|file| {
+        let tar_file = try_into_tarfile(file)?;
+        if tar_file.get_type_flag() == 'V' {
+            tar_file.copy_raw_to(dst)?;
+            return Ok(());
+        }
+        let path = tar_file.get_path();
+        let new_path = rename(NameKind::Path, &path);
+        let link_kind = match tar_file.get_type_flag() {
+            '1' => Some(NameKind::HardLink),
+            '2' => Some(NameKind::Symlink),
+            _ => None,
+        };
+        let link = link_kind.map(|kind| {
+            let link = tar_file.get_link_name();
+            let new_link = rename(kind, &link);
+            (link, new_link)
+        });
+        if new_path.is_empty() {
+            tar_file.copy_globals_to(dst)?;
+            return Ok(());
+        }
+        if new_path == path && link.as_ref().is_none_or(|(link, new_link)| link == new_link) {
+            tar_file.copy_raw_to(dst)?;
+            return Ok(());
+        }
+        let new_link = link.as_ref().map(|(_, new_link)| new_link.as_str());
+        tar_file.copy_globals_to(dst)?;
+        write_renamed(&tar_file, &new_path, new_link, dst)?;
+        renamed += 1;
+        Ok(())
+    })?;
+    dst.write_all(&[0u8; 1024])?;
+    dst.flush()?;
+    Ok(renamed)
+}
+
+/// 以新的路径 / 链接目标写出条目的扩展 header、header 与数据块
+fn write_renamed<W: Write>(tar_file: &TarFile, path: &str, link: Option<&str>, dst: &mut W) -> io::Result<()> {
+    let mut hdr = *tar_file.get_header();
+    let gnu = &hdr.magic == b"ustar ";
+    let v7 = hdr.magic.iter().all(|&b| b == 0);
+    let mut records = tar_file.pax_records().clone();
+    records.remove("path");
+    records.remove("linkpath");
+
+    let mut ext = Vec::new();
+    for vendor in tar_file.vendor_records() {
+        tar_file.copy_range(vendor.offset, 512 + block_align(vendor.data.len() as u64), &mut ext)?;
+    }
+    if gnu {
+        if path.len() > 100 {
+            ext.extend(gnu_long_block(TypeFlag::GnuLongName, path)?);
+        }
+        // GNU header 的 prefix 区域保存 atime / ctime 与稀疏信息，只改 name 字段
+        let name = truncate(path, 100);
+        hdr.name.fill(0);
+        hdr.name[..name.len()].copy_from_slice(name.as_bytes());
+    } else if hdr.set_path(path).is_err() {
+        records.insert("path".to_string(), path.as_bytes().to_vec());
+        hdr.set_path(truncate(path, 100))?;
+    }
+    if let Some(link) = link {
+        if link.len() > 100 {
+            if gnu {
+                ext.extend(gnu_long_block(TypeFlag::GnuLongLink, link)?);
+            } else {
+                records.insert("linkpath".to_string(), link.as_bytes().to_vec());
+            }
+        }
+        hdr.set_link_name(truncate(link, 100))?;
+    }
+    if !records.is_empty() {
+        let data: Vec<u8> = records.iter().flat_map(|(key, value)| pax_record(key, value)).collect();
+        let base = path.trim_end_matches('/').rsplit('/').next().unwrap_or("");
+        ext.extend(extension_block(TypeFlag::PaxExtended, &format!("PaxHeaders/{}", base), &data, hdr.get_mtime(), false)?);
+    }
+
+    let mut block = hdr.to_bytes();
+    // V7 header 写回时不补 magic，除非新路径用到了 prefix
+    if v7 && hdr.prefix.iter().all(|&b| b == 0) {
+        block[257..265].fill(0);
+        write_checksum(&mut block);
+    }
+    dst.write_all(&ext)?;
+    dst.write_all(&block)?;
+    // 主 header 之后的稀疏扩展块与数据块原样复制
+    let data_offset = tar_file.get_data_offset();
+    let ext_len = tar_file.sparse_ext_len();
+    tar_file.copy_range(data_offset - ext_len, ext_len + block_align(tar_file.get_size()), dst)?;
+    Ok(())
+}
+
+/// 按路径打开源归档，依次应用 transforms 后写入新文件
+pub fn transform_copy_paths(src: &str, dst: &str, transforms: &[NameTransform]) -> io::Result<u64> {
+    let img = TarImage::open(src)?;
+    let mut out = BufWriter::new(File::create(dst)?);
+    let result = transform_copy(&mut *lock_image(&img)?, &mut out, |kind, name| {
+        transforms.iter().fold(name.to_string(), |name, t| t.apply(kind, &name))
+    });
+    result
+}
+
+impl TarImage {
+    /// 把归档复制到 output，丢弃满足 remove 的条目及其扩展 header（'L'、'K'、'x' 等），返回删除的条目数
+    pub fn remove_entries<W, F>(&mut self, mut remove: F, output: &mut W) -> io::Result<u64>
+    where
+        W: Write,
+        F: FnMut(&EntryMeta) -> bool,
+    {
+        let mut removed = 0;
+        filter_copy(self, output, |meta| {
+            let drop = remove(meta);
+            removed += drop as u64;
+            !drop
+        })?;
+        Ok(removed)
+    }
+}
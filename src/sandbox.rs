@@ -0,0 +1,211 @@
+//! 沙箱解包：目标目录只打开一次，之后的路径都相对这个目录句柄逐级用 `openat` 解析
+//!
+//! 解析时不跟随任何符号链接，也不接受 ".."，效果与 Linux `openat2` 的
+//! `RESOLVE_BENEATH | RESOLVE_NO_SYMLINKS` 相同：即使解包过程中有目录被替换成符号链接，
+//! 也只会返回错误，不会写到目标目录之外。属主、权限与时间通过文件句柄或
+//! `AT_SYMLINK_NOFOLLOW` 设置，不再按路径重新查找。
+#![cfg(unix)]
+
+use std::ffi::CString;
+use std::fs::{self, File};
+use std::io;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::Path;
+use std::time::SystemTime;
+use crate::error::PtError;
+use crate::sys::timespecs;
+
+fn cvt(rc: libc::c_int) -> io::Result<libc::c_int> {
+    if rc < 0 { Err(io::Error::last_os_error()) } else { Ok(rc) }
+}
+
+fn c_name(component: &[u8]) -> io::Result<CString> {
+    CString::new(component).map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "path contains NUL byte"))
+}
+
+/// 一个已打开的目录与其中的一个名字
+pub struct Slot {
+    dir: OwnedFd,
+    name: CString,
+}
+
+impl Slot {
+    /// 不跟随符号链接的 stat，名字不存在时返回 None
+    fn stat(&self) -> Option<libc::stat> {
+        let mut st: libc::stat = unsafe { std::mem::zeroed() };
+        let rc = unsafe { libc::fstatat(self.dir.as_raw_fd(), self.name.as_ptr(), &mut st, libc::AT_SYMLINK_NOFOLLOW) };
+        (rc == 0).then_some(st)
+    }
+
+    fn is_type(&self, kind: libc::mode_t) -> bool {
+        self.stat().is_some_and(|st| st.st_mode & libc::S_IFMT == kind)
+    }
+
+    /// 删除这个名字，目录只在为空时删除
+    pub fn remove(&self) -> io::Result<()> {
+        let flags = if self.is_type(libc::S_IFDIR) { libc::AT_REMOVEDIR } else { 0 };
+        cvt(unsafe { libc::unlinkat(self.dir.as_raw_fd(), self.name.as_ptr(), flags) }).map(drop)
+    }
+
+    /// 名字是符号链接时先删除，之后的创建不会跟随它
+    fn remove_symlink(&self) -> io::Result<()> {
+        if self.is_type(libc::S_IFLNK) {
+            self.remove()?;
+        }
+        Ok(())
+    }
+
+    fn open(&self, flags: libc::c_int, mode: libc::c_uint) -> io::Result<OwnedFd> {
+        let fd = cvt(unsafe {
+            libc::openat(self.dir.as_raw_fd(), self.name.as_ptr(), flags | libc::O_NOFOLLOW | libc::O_CLOEXEC, mode)
+        })?;
+        Ok(unsafe { OwnedFd::from_raw_fd(fd) })
+    }
+
+    /// 打开已存在的目录；名字是符号链接时失败
+    fn open_dir(&self) -> io::Result<OwnedFd> {
+        self.open(libc::O_RDONLY | libc::O_DIRECTORY, 0)
+    }
+
+    /// 设置属主，不跟随符号链接
+    pub fn lchown(&self, uid: u32, gid: u32) -> io::Result<()> {
+        cvt(unsafe { libc::fchownat(self.dir.as_raw_fd(), self.name.as_ptr(), uid, gid, libc::AT_SYMLINK_NOFOLLOW) }).map(drop)
+    }
+
+    /// 设置访问与修改时间，不跟随符号链接；atime 为 None 时保持不变
+    pub fn set_times(&self, atime: Option<SystemTime>, mtime: SystemTime) -> io::Result<()> {
+        let times = timespecs(atime, mtime);
+        cvt(unsafe { libc::utimensat(self.dir.as_raw_fd(), self.name.as_ptr(), times.as_ptr(), libc::AT_SYMLINK_NOFOLLOW) })
+            .map(drop)
+    }
+}
+
+/// 解包的目标目录
+pub struct Sandbox {
+    root: OwnedFd,
+}
+
+impl Sandbox {
+    /// 创建（如不存在）并打开 dest；dest 本身可以是符号链接，只在这里解析一次
+    pub fn open(dest: &Path) -> io::Result<Self> {
+        fs::create_dir_all(dest)?;
+        let root = fs::OpenOptions::new().read(true).custom_flags(libc::O_DIRECTORY | libc::O_CLOEXEC).open(dest)?;
+        Ok(Sandbox { root: root.into() })
+    }
+
+    /// 逐级打开 rel 的上级目录，缺失的目录按 0777（受 umask 影响）创建，返回最后一段所在的 `Slot`；
+    /// 途经符号链接或路径含 ".." 时返回 PtError::UnsafePath。rel 按原始字节解析，可以不是 UTF-8
+    pub fn slot<R: AsRef<[u8]>>(&self, rel: R) -> io::Result<Slot> {
+        let rel = rel.as_ref();
+        let unsafe_path = || io::Error::from(PtError::UnsafePath { path: String::from_utf8_lossy(rel).into_owned() });
+        let mut components: Vec<&[u8]> = rel.split(|&b| b == b'/').filter(|c| !c.is_empty() && *c != b".").collect();
+        if components.contains(&&b".."[..]) {
+            return Err(unsafe_path());
+        }
+        let leaf = components.pop().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, format!("empty entry path: {:?}", String::from_utf8_lossy(rel)))
+        })?;
+        let mut dir = self.root.try_clone()?;
+        for component in components {
+            let slot = Slot { dir, name: c_name(component)? };
+            if slot.is_type(libc::S_IFLNK) {
+                return Err(unsafe_path());
+            }
+            dir = make_dir(&slot).map_err(|e| if slot.is_type(libc::S_IFLNK) { unsafe_path() } else { e })?;
+        }
+        Ok(Slot { dir, name: c_name(leaf)? })
+    }
+
+    /// 创建目录 rel（已存在时直接打开），返回目录句柄
+    pub fn create_dir<R: AsRef<[u8]>>(&self, rel: R) -> io::Result<OwnedFd> {
+        let slot = self.slot(rel)?;
+        slot.remove_symlink()?;
+        make_dir(&slot)
+    }
+
+    /// 打开已存在的目录 rel
+    pub fn open_dir<R: AsRef<[u8]>>(&self, rel: R) -> io::Result<OwnedFd> {
+        self.slot(rel)?.open_dir()
+    }
+
+    /// 创建或截断普通文件 rel；已存在的符号链接先被删除，不会写到它指向的位置
+    pub fn create_file<R: AsRef<[u8]>>(&self, rel: R) -> io::Result<File> {
+        let slot = self.slot(rel)?;
+        slot.remove_symlink()?;
+        Ok(File::from(slot.open(libc::O_WRONLY | libc::O_CREAT | libc::O_TRUNC, 0o666)?))
+    }
+
+    /// 创建指向 target 的符号链接 rel，替换已存在的文件；target 原样写入，之后的解析从不跟随它
+    pub fn symlink<T: AsRef<[u8]>, R: AsRef<[u8]>>(&self, target: T, rel: R) -> io::Result<Slot> {
+        let slot = self.slot(rel)?;
+        let _ = slot.remove();
+        let target = c_name(target.as_ref())?;
+        cvt(unsafe { libc::symlinkat(target.as_ptr(), slot.dir.as_raw_fd(), slot.name.as_ptr()) })?;
+        Ok(slot)
+    }
+
+    /// 创建指向 source 的硬链接 rel，替换已存在的文件；source 是符号链接时链接到链接本身
+    pub fn hard_link<S: AsRef<[u8]>, R: AsRef<[u8]>>(&self, source: S, rel: R) -> io::Result<()> {
+        let source = self.slot(source)?;
+        let slot = self.slot(rel)?;
+        let _ = slot.remove();
+        cvt(unsafe {
+            libc::linkat(source.dir.as_raw_fd(), source.name.as_ptr(), slot.dir.as_raw_fd(), slot.name.as_ptr(), 0)
+        }).map(drop)
+    }
+
+    /// 创建设备节点或命名管道，kind 为 S_IFCHR / S_IFBLK / S_IFIFO，替换已存在的文件
+    pub fn mknod<R: AsRef<[u8]>>(&self, rel: R, kind: libc::mode_t, mode: u32, dev: libc::dev_t) -> io::Result<Slot> {
+        let slot = self.slot(rel)?;
+        let _ = slot.remove();
+        cvt(unsafe {
+            libc::mknodat(slot.dir.as_raw_fd(), slot.name.as_ptr(), kind | (mode & 0o7777) as libc::mode_t, dev)
+        })?;
+        Ok(slot)
+    }
+
+    /// 以非阻塞方式打开刚创建的命名管道，用于通过句柄设置权限
+    pub fn open_fifo(&self, slot: &Slot) -> io::Result<OwnedFd> {
+        slot.open(libc::O_RDONLY | libc::O_NONBLOCK, 0)
+    }
+}
+
+/// 在 slot 处创建目录（已存在时忽略）并打开
+fn make_dir(slot: &Slot) -> io::Result<OwnedFd> {
+    match cvt(unsafe { libc::mkdirat(slot.dir.as_raw_fd(), slot.name.as_ptr(), 0o777) }) {
+        Err(e) if e.kind() != io::ErrorKind::AlreadyExists => return Err(e),
+        _ => {}
+    }
+    slot.open_dir()
+}
+
+/// 通过句柄设置属主
+pub fn fchown(fd: &impl AsRawFd, uid: u32, gid: u32) -> io::Result<()> {
+    cvt(unsafe { libc::fchown(fd.as_raw_fd(), uid, gid) }).map(drop)
+}
+
+/// 通过句柄设置权限位（含 setuid/setgid/sticky）
+pub fn fchmod(fd: &impl AsRawFd, mode: u32) -> io::Result<()> {
+    cvt(unsafe { libc::fchmod(fd.as_raw_fd(), (mode & 0o7777) as libc::mode_t) }).map(drop)
+}
+
+/// 通过句柄设置访问与修改时间；atime 为 None 时保持不变
+pub fn futimens(fd: &impl AsRawFd, atime: Option<SystemTime>, mtime: SystemTime) -> io::Result<()> {
+    let times = timespecs(atime, mtime);
+    cvt(unsafe { libc::futimens(fd.as_raw_fd(), times.as_ptr()) }).map(drop)
+}
+
+/// 通过句柄设置扩展属性
+#[cfg(target_os = "linux")]
+pub fn fsetxattr(fd: &impl AsRawFd, name: &str, value: &[u8]) -> io::Result<()> {
+    let cname = c_name(name.as_bytes())?;
+    cvt(unsafe {
+        libc::fsetxattr(fd.as_raw_fd(), cname.as_ptr(), value.as_ptr() as *const libc::c_void, value.len(), 0)
+    }).map(drop)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn fsetxattr(_fd: &impl AsRawFd, _name: &str, _value: &[u8]) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "xattrs are only supported on Linux"))
+}
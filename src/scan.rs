@@ -0,0 +1,80 @@
+use std::io;
+use crate::base::{try_into_tarfile, ImageInfo, TarImage};
+use crate::meta::EntryMeta;
+use crate::tar::block_align;
+
+/// 扫描过程中发现但不影响继续遍历的问题
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ScanWarning {
+    /// 所属条目 header 的偏移
+    pub offset: u64,
+    pub path: String,
+    pub message: String,
+}
+
+/// 一次完整扫描的结构化结果，库内部不向 stdout/stderr 输出任何内容
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ScanOutcome {
+    pub entries: Vec<EntryMeta>,
+    pub warnings: Vec<ScanWarning>,
+}
+
+/// 一个条目在镜像中占用的区域，偏移均相对镜像起点
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Extent {
+    pub path: String,
+    /// 第一个 header 块（含 PAX、GNU 长名等扩展 header）的偏移
+    pub header_offset: u64,
+    /// 全部 header 块的总长
+    pub header_len: u64,
+    pub data_offset: u64,
+    /// 数据区长度，含补齐到 512 字节的填充
+    pub data_len: u64,
+    /// 数据的实际字节数，不含填充
+    pub size: u64,
+}
+
+impl Extent {
+    /// 条目结束（下一个 header 开始）的偏移
+    pub fn end_offset(&self) -> u64 {
+        self.data_offset + self.data_len
+    }
+}
+
+impl TarImage {
+    /// 遍历全部条目，收集元数据和警告
+    pub fn scan(&mut self) -> io::Result<ScanOutcome> {
+        let mut outcome = ScanOutcome::default();
+        self.for_each_entry(|file| {
+            let tar_file = try_into_tarfile(file)?;
+            let meta = tar_file.meta();
+            for message in tar_file.get_header().field_warnings() {
+                outcome.warnings.push(ScanWarning { offset: meta.offset, path: meta.path.clone(), message });
+            }
+            outcome.entries.push(meta);
+            Ok(())
+        })?;
+        Ok(outcome)
+    }
+
+    /// 按归档顺序列出每个条目的 header 与数据区，可直接按偏移寻址归档中的内容
+    pub fn extent_map(&mut self) -> io::Result<Vec<Extent>> {
+        let mut extents = Vec::new();
+        self.for_each_entry(|file| {
+            let tar_file = try_into_tarfile(file)?;
+            extents.push(Extent {
+                path: tar_file.get_path(),
+                header_offset: tar_file.get_offset(),
+                header_len: tar_file.get_data_offset() - tar_file.get_offset(),
+                data_offset: tar_file.get_data_offset(),
+                data_len: block_align(tar_file.get_size()),
+                size: tar_file.get_size(),
+            });
+            Ok(())
+        })?;
+        Ok(extents)
+    }
+}
@@ -0,0 +1,151 @@
+//! 不解包直接在镜像中搜索：按路径查找条目，或按行匹配普通文件的内容
+//!
+//! 正则表达式需要启用 `regex` feature。
+
+use std::io::{self, BufRead, BufReader};
+use crate::base::{try_into_tarfile, BodyReader, ImageInfo, TarImage};
+use crate::filter::EntryFilter;
+use crate::index::{Index, IndexKind};
+use crate::meta::EntryMeta;
+
+/// 按路径查找条目的匹配方式
+#[derive(Debug, Clone)]
+pub enum NamePattern {
+    /// `EntryFilter` 的 glob 语法，按路径段匹配：`*.so` 只匹配顶层，任意层级用 `**/*.so`
+    Glob(EntryFilter),
+    /// 在规范化后的完整路径中搜索，需要整体匹配时自行加 `^` / `$`
+    #[cfg(feature = "regex")]
+    Regex(regex::Regex),
+}
+
+impl NamePattern {
+    pub fn glob(pattern: &str) -> Self {
+        NamePattern::Glob(EntryFilter::new(&[pattern]))
+    }
+
+    /// 编译正则表达式，语法错误返回 InvalidInput
+    #[cfg(feature = "regex")]
+    pub fn regex(pattern: &str) -> io::Result<Self> {
+        regex::Regex::new(pattern)
+            .map(NamePattern::Regex)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e.to_string()))
+    }
+
+    pub fn matches(&self, path: &str) -> bool {
+        match self {
+            NamePattern::Glob(filter) => filter.matches(path),
+            #[cfg(feature = "regex")]
+            NamePattern::Regex(re) => re.is_match(crate::path::normalize_path(path)),
+        }
+    }
+}
+
+/// 内容搜索的匹配方式
+#[derive(Debug, Clone)]
+pub enum ContentPattern {
+    /// 按字节查找子串
+    Literal(Vec<u8>),
+    /// 按行匹配正则表达式（`regex::bytes`，可匹配非 UTF-8 内容）
+    #[cfg(feature = "regex")]
+    Regex(regex::bytes::Regex),
+}
+
+impl ContentPattern {
+    pub fn literal<B: AsRef<[u8]>>(needle: B) -> Self {
+        ContentPattern::Literal(needle.as_ref().to_vec())
+    }
+
+    /// 编译正则表达式，语法错误返回 InvalidInput
+    #[cfg(feature = "regex")]
+    pub fn regex(pattern: &str) -> io::Result<Self> {
+        regex::bytes::Regex::new(pattern)
+            .map(ContentPattern::Regex)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e.to_string()))
+    }
+
+    /// 第一个匹配在 line 中的起始位置
+    fn find(&self, line: &[u8]) -> Option<usize> {
+        match self {
+            ContentPattern::Literal(needle) if needle.is_empty() => Some(0),
+            ContentPattern::Literal(needle) => line.windows(needle.len()).position(|w| w == needle.as_slice()),
+            #[cfg(feature = "regex")]
+            ContentPattern::Regex(re) => re.find(line).map(|m| m.start()),
+        }
+    }
+}
+
+/// 内容搜索的一处命中，每行最多报告一次
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ContentMatch {
+    pub path: String,
+    /// 匹配起点在文件内容中的字节偏移
+    pub offset: u64,
+    /// 从 1 开始的行号
+    pub line_number: u64,
+    /// 命中的整行，不含行尾的换行符，非 UTF-8 字节按 lossy 转换
+    pub line: String,
+}
+
+impl TarImage {
+    /// 逐行搜索所有普通文件的内容，按归档顺序返回命中；文件内容流式读取，不整体载入内存
+    pub fn search_content(&mut self, pattern: &ContentPattern) -> io::Result<Vec<ContentMatch>> {
+        let mut hits = Vec::new();
+        self.for_each_entry(|file| {
+            let tar_file = try_into_tarfile(file)?;
+            if !tar_file.meta().is_file() {
+                return Ok(());
+            }
+            let path = tar_file.get_path();
+            let mut reader = BufReader::new(BodyReader::new(&tar_file));
+            let mut line = Vec::new();
+            let (mut pos, mut line_number) = (0u64, 0u64);
+            loop {
+                line.clear();
+                let n = reader.read_until(b'\n', &mut line)?;
+                if n == 0 {
+                    break;
+                }
+                line_number += 1;
+                let text = line.strip_suffix(b"\n").unwrap_or(&line);
+                let text = text.strip_suffix(b"\r").unwrap_or(text);
+                if let Some(at) = pattern.find(text) {
+                    hits.push(ContentMatch {
+                        path: path.clone(),
+                        offset: pos + at as u64,
+                        line_number,
+                        line: String::from_utf8_lossy(text).into_owned(),
+                    });
+                }
+                pos += n as u64;
+            }
+            Ok(())
+        })?;
+        Ok(hits)
+    }
+
+    /// 按归档顺序返回路径匹配 pattern 的全部条目（同名条目各出现一次），需要扫描整个归档；
+    /// 已有索引时使用 `Index::find`
+    pub fn find(&mut self, pattern: &NamePattern) -> io::Result<Vec<EntryMeta>> {
+        let mut found = Vec::new();
+        self.for_each_entry(|file| {
+            let tar_file = try_into_tarfile(file)?;
+            if pattern.matches(&tar_file.get_path()) {
+                found.push(tar_file.meta());
+            }
+            Ok(())
+        })?;
+        Ok(found)
+    }
+}
+
+impl Index {
+    /// 与 `TarImage::find` 相同；完整索引直接在内存中匹配，不读取归档，
+    /// 紧凑与侧车索引不保存路径，退回到扫描 img
+    pub fn find(&self, img: &mut TarImage, pattern: &NamePattern) -> io::Result<Vec<EntryMeta>> {
+        if self.kind() != IndexKind::Full {
+            return img.find(pattern);
+        }
+        Ok(self.entries().iter().filter(|meta| pattern.matches(&meta.path)).cloned().collect())
+    }
+}
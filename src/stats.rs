@@ -0,0 +1,68 @@
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap};
+use std::io;
+use crate::base::{try_into_tarfile, ImageInfo, TarImage};
+use crate::path::normalize_path;
+use crate::tar::block_align;
+
+/// 排行榜保留的条目数
+pub const TOP_N: usize = 10;
+
+/// 一次扫描得到的归档统计
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ArchiveStats {
+    pub entries: u64,
+    /// 按 typeflag 统计的条目数
+    pub by_type: BTreeMap<char, u64>,
+    /// 所有条目数据区的字节数
+    pub stored_bytes: u64,
+    /// header 与扩展 header（PAX、GNU 长名称等）占用的字节数
+    pub header_bytes: u64,
+    /// 数据区按 512 字节对齐补齐的字节数
+    pub padding_bytes: u64,
+    /// 最大的文件，按大小降序：(路径, 大小)
+    pub largest: Vec<(String, u64)>,
+    /// 层级最深的路径，按深度降序：(路径, 深度)
+    pub deepest: Vec<(String, usize)>,
+}
+
+/// 只保留最大的 TOP_N 项
+fn keep_top<T: Ord>(heap: &mut BinaryHeap<Reverse<T>>, item: T) {
+    heap.push(Reverse(item));
+    if heap.len() > TOP_N {
+        heap.pop();
+    }
+}
+
+fn sorted_desc<T: Ord>(heap: BinaryHeap<Reverse<T>>) -> Vec<T> {
+    // Reverse 的升序即原值的降序
+    heap.into_sorted_vec().into_iter().map(|Reverse(item)| item).collect()
+}
+
+impl TarImage {
+    /// 单次遍历统计条目数、各类型数量、数据与开销字节数，以及最大文件和最深路径
+    pub fn stats(&mut self) -> io::Result<ArchiveStats> {
+        let mut stats = ArchiveStats::default();
+        let mut largest = BinaryHeap::new();
+        let mut deepest = BinaryHeap::new();
+        self.for_each_entry(|file| {
+            let tar_file = try_into_tarfile(file)?;
+            let meta = tar_file.meta();
+            stats.entries += 1;
+            *stats.by_type.entry(meta.type_flag).or_default() += 1;
+            stats.stored_bytes += meta.size;
+            stats.header_bytes += meta.data_offset - meta.offset;
+            stats.padding_bytes += block_align(meta.size) - meta.size;
+            if meta.is_file() {
+                keep_top(&mut largest, (meta.size, Reverse(meta.path.clone())));
+            }
+            let depth = normalize_path(&meta.path).split('/').filter(|c| !c.is_empty() && *c != ".").count();
+            keep_top(&mut deepest, (depth, Reverse(meta.path)));
+            Ok(())
+        })?;
+        // 相同大小 / 深度时按路径升序
+        stats.largest = sorted_desc(largest).into_iter().map(|(size, Reverse(path))| (path, size)).collect();
+        stats.deepest = sorted_desc(deepest).into_iter().map(|(depth, Reverse(path))| (path, depth)).collect();
+        Ok(stats)
+    }
+}
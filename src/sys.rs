@@ -0,0 +1,142 @@
+//! 平台相关的文件系统操作
+#![cfg(unix)]
+
+use std::ffi::CString;
+use std::io;
+use std::path::Path;
+use std::time::SystemTime;
+use crate::meta::{unix_secs, unix_time};
+
+/// 当前进程是否以 root 身份运行
+pub fn is_root() -> bool {
+    unsafe { libc::geteuid() == 0 }
+}
+
+fn c_string(s: &str) -> Option<CString> {
+    CString::new(s).ok()
+}
+
+/// 按用户名查 uid，查不到时返回 None
+pub fn lookup_uid(name: &str) -> Option<u32> {
+    let cname = c_string(name)?;
+    let mut buf = vec![0 as libc::c_char; 4096];
+    let mut pwd: libc::passwd = unsafe { std::mem::zeroed() };
+    let mut result: *mut libc::passwd = std::ptr::null_mut();
+    let rc = unsafe { libc::getpwnam_r(cname.as_ptr(), &mut pwd, buf.as_mut_ptr(), buf.len(), &mut result) };
+    if rc == 0 && !result.is_null() {
+        Some(pwd.pw_uid)
+    } else {
+        None
+    }
+}
+
+/// 按组名查 gid，查不到时返回 None
+pub fn lookup_gid(name: &str) -> Option<u32> {
+    let cname = c_string(name)?;
+    let mut buf = vec![0 as libc::c_char; 4096];
+    let mut grp: libc::group = unsafe { std::mem::zeroed() };
+    let mut result: *mut libc::group = std::ptr::null_mut();
+    let rc = unsafe { libc::getgrnam_r(cname.as_ptr(), &mut grp, buf.as_mut_ptr(), buf.len(), &mut result) };
+    if rc == 0 && !result.is_null() {
+        Some(grp.gr_gid)
+    } else {
+        None
+    }
+}
+
+/// 按 uid 查用户名，查不到时返回 None
+pub fn user_name(uid: u32) -> Option<String> {
+    let mut buf = vec![0 as libc::c_char; 4096];
+    let mut pwd: libc::passwd = unsafe { std::mem::zeroed() };
+    let mut result: *mut libc::passwd = std::ptr::null_mut();
+    let rc = unsafe { libc::getpwuid_r(uid, &mut pwd, buf.as_mut_ptr(), buf.len(), &mut result) };
+    if rc == 0 && !result.is_null() {
+        Some(unsafe { std::ffi::CStr::from_ptr(pwd.pw_name) }.to_string_lossy().into_owned())
+    } else {
+        None
+    }
+}
+
+/// 按 gid 查组名，查不到时返回 None
+pub fn group_name(gid: u32) -> Option<String> {
+    let mut buf = vec![0 as libc::c_char; 4096];
+    let mut grp: libc::group = unsafe { std::mem::zeroed() };
+    let mut result: *mut libc::group = std::ptr::null_mut();
+    let rc = unsafe { libc::getgrgid_r(gid, &mut grp, buf.as_mut_ptr(), buf.len(), &mut result) };
+    if rc == 0 && !result.is_null() {
+        Some(unsafe { std::ffi::CStr::from_ptr(grp.gr_name) }.to_string_lossy().into_owned())
+    } else {
+        None
+    }
+}
+
+/// 设置属主，不跟随符号链接
+pub fn lchown(path: &Path, uid: u32, gid: u32) -> io::Result<()> {
+    std::os::unix::fs::lchown(path, Some(uid), Some(gid))
+}
+
+fn path_cstring(path: &Path) -> io::Result<CString> {
+    use std::os::unix::ffi::OsStrExt;
+    CString::new(path.as_os_str().as_bytes())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "path contains NUL byte"))
+}
+
+/// 设置扩展属性，不跟随符号链接
+#[cfg(target_os = "linux")]
+pub fn set_xattr(path: &Path, name: &str, value: &[u8]) -> io::Result<()> {
+    let cpath = path_cstring(path)?;
+    let cname = c_string(name).ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "xattr name contains NUL byte"))?;
+    let rc = unsafe {
+        libc::lsetxattr(cpath.as_ptr(), cname.as_ptr(), value.as_ptr() as *const libc::c_void, value.len(), 0)
+    };
+    if rc == 0 { Ok(()) } else { Err(io::Error::last_os_error()) }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn set_xattr(_path: &Path, _name: &str, _value: &[u8]) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "xattrs are only supported on Linux"))
+}
+
+/// 创建字符 / 块设备节点，需要 root 权限
+pub fn mknod(path: &Path, block: bool, mode: u32, major: u32, minor: u32) -> io::Result<()> {
+    let cpath = path_cstring(path)?;
+    let kind = if block { libc::S_IFBLK } else { libc::S_IFCHR };
+    let dev = libc::makedev(major as _, minor as _);
+    let rc = unsafe { libc::mknod(cpath.as_ptr(), kind | (mode & 0o7777) as libc::mode_t, dev) };
+    if rc == 0 { Ok(()) } else { Err(io::Error::last_os_error()) }
+}
+
+/// 创建命名管道
+pub fn mkfifo(path: &Path, mode: u32) -> io::Result<()> {
+    let cpath = path_cstring(path)?;
+    let rc = unsafe { libc::mkfifo(cpath.as_ptr(), (mode & 0o7777) as libc::mode_t) };
+    if rc == 0 { Ok(()) } else { Err(io::Error::last_os_error()) }
+}
+
+/// utimensat / futimens 的 [atime, mtime] 参数，atime 为 None 时为 UTIME_OMIT；mtime 可早于 1970 年
+pub(crate) fn timespecs(atime: Option<SystemTime>, mtime: SystemTime) -> [libc::timespec; 2] {
+    let timespec = |t: Option<(i64, u32)>| {
+        let mut ts: libc::timespec = unsafe { std::mem::zeroed() };
+        match t {
+            Some((secs, nanos)) => {
+                ts.tv_sec = secs as libc::time_t;
+                ts.tv_nsec = nanos as _;
+            }
+            None => ts.tv_nsec = libc::UTIME_OMIT,
+        }
+        ts
+    };
+    let parts = |t: SystemTime| {
+        let secs = unix_secs(t);
+        (secs, t.duration_since(unix_time(secs, 0)).map_or(0, |d| d.subsec_nanos()))
+    };
+    [timespec(atime.map(parts)), timespec(Some(parts(mtime)))]
+}
+
+/// 设置访问与修改时间（纳秒精度），不跟随符号链接；atime 为 None 时保持不变
+pub fn set_times(path: &Path, atime: Option<SystemTime>, mtime: SystemTime) -> io::Result<()> {
+    let cpath = path_cstring(path)?;
+    let times = timespecs(atime, mtime);
+    let rc = unsafe { libc::utimensat(libc::AT_FDCWD, cpath.as_ptr(), times.as_ptr(), libc::AT_SYMLINK_NOFOLLOW) };
+    if rc == 0 { Ok(()) } else { Err(io::Error::last_os_error()) }
+}
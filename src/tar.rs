@@ -1,9 +1,37 @@
 use std::mem::size_of;
 use std::ptr::read_unaligned;
 use std::io;
+use crate::mode::Mode;
 
 const T_BLOCKSIZE : usize = 512;
 
+/// 稀疏文件中保存了数据的一段：在原文件中的起始偏移与长度，各段数据在归档中依次相连
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SparseChunk {
+    pub offset: u64,
+    pub length: u64,
+}
+
+/// 解析连续的 (offset, numbytes) 字段对，遇到空字段为止；返回映射与 isextended 标志
+fn parse_sparse_entries(fields: &[u8], isextended: u8) -> (Vec<SparseChunk>, bool) {
+    let chunks = fields.chunks_exact(24)
+        .take_while(|pair| pair[0] != 0)
+        .map(|pair| SparseChunk { offset: TarHeader::parse_numeric(&pair[..12]), length: TarHeader::parse_numeric(&pair[12..]) })
+        .collect();
+    (chunks, isextended != 0)
+}
+
+/// 旧式 GNU 稀疏扩展块：21 个映射项，第 504 字节为 isextended
+pub fn parse_gnu_sparse_block(block: &[u8; T_BLOCKSIZE]) -> (Vec<SparseChunk>, bool) {
+    parse_sparse_entries(&block[..504], block[504])
+}
+
+/// 按 512 字节块向上对齐
+pub fn block_align(size: u64) -> u64 {
+    size.div_ceil(T_BLOCKSIZE as u64) * T_BLOCKSIZE as u64
+}
+
 #[repr(u32)] // 确保底层表示是 u32 类型
 pub enum TarFileType {
     Undefined = 0x00,
@@ -20,6 +48,85 @@ pub enum TarFileType {
     VirtualDirectory = 0x0b, // Virtual Directory created by TSK to hold data like orphan files
 }
 
+/// header 中 typeflag 字段的取值
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TypeFlag {
+    /// '0' 或旧格式的 '\0'
+    Regular,
+    HardLink,
+    Symlink,
+    CharDevice,
+    BlockDevice,
+    Directory,
+    Fifo,
+    Contiguous,
+    /// PAX 单条目扩展头 'x'
+    PaxExtended,
+    /// PAX 全局扩展头 'g'
+    PaxGlobal,
+    /// Solaris / star 的扩展头 'X'，记录格式与 'x' 相同
+    SolarisExtended,
+    /// GNU 长文件名 'L'
+    GnuLongName,
+    /// GNU 长链接名 'K'
+    GnuLongLink,
+    /// GNU 增量备份目录 'D'
+    GnuDumpDir,
+    /// GNU 多卷归档中跨卷文件的续接 header 'M'
+    GnuMultiVolume,
+    /// GNU 卷标 'V'
+    GnuVolumeLabel,
+    /// 其余取值，保留原始字节
+    Other(u8),
+}
+
+impl TypeFlag {
+    pub fn from_byte(b: u8) -> Self {
+        match b {
+            b'0' | 0 => TypeFlag::Regular,
+            b'1' => TypeFlag::HardLink,
+            b'2' => TypeFlag::Symlink,
+            b'3' => TypeFlag::CharDevice,
+            b'4' => TypeFlag::BlockDevice,
+            b'5' => TypeFlag::Directory,
+            b'6' => TypeFlag::Fifo,
+            b'7' => TypeFlag::Contiguous,
+            b'x' => TypeFlag::PaxExtended,
+            b'g' => TypeFlag::PaxGlobal,
+            b'X' => TypeFlag::SolarisExtended,
+            b'L' => TypeFlag::GnuLongName,
+            b'K' => TypeFlag::GnuLongLink,
+            b'D' => TypeFlag::GnuDumpDir,
+            b'M' => TypeFlag::GnuMultiVolume,
+            b'V' => TypeFlag::GnuVolumeLabel,
+            other => TypeFlag::Other(other),
+        }
+    }
+
+    /// 写入 header 时使用的字节，普通文件为 '0'
+    pub fn as_byte(self) -> u8 {
+        match self {
+            TypeFlag::Regular => b'0',
+            TypeFlag::HardLink => b'1',
+            TypeFlag::Symlink => b'2',
+            TypeFlag::CharDevice => b'3',
+            TypeFlag::BlockDevice => b'4',
+            TypeFlag::Directory => b'5',
+            TypeFlag::Fifo => b'6',
+            TypeFlag::Contiguous => b'7',
+            TypeFlag::PaxExtended => b'x',
+            TypeFlag::PaxGlobal => b'g',
+            TypeFlag::SolarisExtended => b'X',
+            TypeFlag::GnuLongName => b'L',
+            TypeFlag::GnuLongLink => b'K',
+            TypeFlag::GnuDumpDir => b'D',
+            TypeFlag::GnuMultiVolume => b'M',
+            TypeFlag::GnuVolumeLabel => b'V',
+            TypeFlag::Other(b) => b,
+        }
+    }
+}
 
 #[repr(C)]
 #[derive(Debug, Clone, Copy)]
@@ -43,6 +150,19 @@ pub struct TarHeader {
     pub padding: [u8; 12],
 }
 
+/// 取字段中第一个 '\0' 之前的原始字节
+pub fn field_bytes(field: &[u8]) -> &[u8] {
+    let end = field.iter().position(|&b| b == 0).unwrap_or(field.len());
+    &field[..end]
+}
+
+/// 字段转字符串，非 UTF-8 字节按替换字符处理，不会丢弃整个名称
+fn field_string(field: &[u8]) -> String {
+    String::from_utf8_lossy(field_bytes(field)).into_owned()
+}
+
+/// # Safety
+/// `buf` 至少需要 512 字节，且内容按 `TarHeader` 的内存布局解释
 pub unsafe fn read_tar_header(buf: &[u8]) -> io::Result<TarHeader> {
     assert!(buf.len() >= size_of::<TarHeader>());
     let ptr = buf.as_ptr() as *const TarHeader;
@@ -52,90 +172,174 @@ pub unsafe fn read_tar_header(buf: &[u8]) -> io::Result<TarHeader> {
 
 impl TarHeader {
     pub fn get_uname(&self) -> String {
-        match std::str::from_utf8(&self.uname) {
-            Ok(s) => s.trim_end_matches('\0').to_string(),
-            Err(_) => String::new(),
-        }
+        field_string(&self.uname)
     }
 
     pub fn get_gname(&self) -> String {
-        match std::str::from_utf8(&self.gname) {
-            Ok(s) => s.trim_end_matches('\0').to_string(),
-            Err(_) => String::new(),
-        }
+        field_string(&self.gname)
     }
 
     /// 从 tar header 中读取 size 字段
     pub fn get_size(&self) -> u64 {
-        let size = &self.size; // 假设 self.size 是 [u8; 12]
-
-        // 判断是不是 GNU tar binary 编码
-        if size[0] & 0x80 == 0x80 {
-            // Binary 编码
-            // 忽略前导的 0（除了首个 0x80 标志位）
-            let mut start = 1;
-            while start < 12 && size[start] == 0 {
-                start += 1;
-            }
-
-            let mut x: u64 = 0;
-            for &b in &size[start..] {
-                x = (x << 8) | (b as u64);
-            }
+        Self::parse_numeric(&self.size)
+    }
 
-            x
-        } else {
-            // Octal 编码 (以 ASCII 编码的八进制字符串)
-            Self::parse_octal(size)
+    /// 与 `get_size` 相同，但八进制 size 字段无法解析时返回错误说明而不是 0
+    pub fn try_get_size(&self) -> Result<u64, String> {
+        if self.size[0] & 0x80 != 0 {
+            return Ok(self.get_size());
         }
+        Self::try_parse_octal(&self.size).map_err(|e| format!("invalid size field {}", e))
     }
 
-    /// 从 tar header 中读取 uid 字段
+    /// 从 tar header 中读取权限 mode 字段
+    pub fn get_mode(&self) -> Mode {
+        Mode::new(Self::parse_octal(&self.mode) as u32)
+    }
+
+    /// 从 tar header 中读取 uid 字段，支持 GNU base-256 编码
     pub fn get_uid(&self) -> u64 {
-        Self::parse_octal(&self.uid)
+        Self::parse_numeric(&self.uid)
     }
 
-    /// 从 tar header 中读取 gid 字段
+    /// 从 tar header 中读取 gid 字段，支持 GNU base-256 编码
     pub fn get_gid(&self) -> u64 {
-        Self::parse_octal(&self.gid)
+        Self::parse_numeric(&self.gid)
+    }
+
+    /// 设备文件的主设备号，支持 GNU base-256 编码，超出 u32 时取 u32::MAX
+    pub fn get_devmajor(&self) -> u32 {
+        u32::try_from(Self::parse_numeric(&self.devmajor)).unwrap_or(u32::MAX)
     }
 
-    /// 从 tar header 中读取修改时间（mtime）字段
-    pub fn get_mtime(&self) -> u64 {
-        Self::parse_octal(&self.mtime)
+    /// 设备文件的次设备号，支持 GNU base-256 编码，超出 u32 时取 u32::MAX
+    pub fn get_devminor(&self) -> u32 {
+        u32::try_from(Self::parse_numeric(&self.devminor)).unwrap_or(u32::MAX)
     }
 
-    /// 公共方法：从一个 `[u8]` 八进制字段解析成 u64
+    /// 修改时间（秒），早于 1970 年为负，支持 GNU base-256 编码
+    pub fn get_mtime(&self) -> i64 {
+        Self::parse_signed_numeric(&self.mtime)
+    }
+
+    /// 带符号的数字字段：首字节为 0xff 时为 base-256 补码表示的负数，其余与 `parse_numeric` 相同
+    fn parse_signed_numeric(field: &[u8]) -> i64 {
+        match field[0] {
+            0xff => field[1..].iter().fold(-1i64, |x, &b| (x << 8) | b as i64),
+            _ => i64::try_from(Self::parse_numeric(field)).unwrap_or(i64::MAX),
+        }
+    }
+
+    /// 数字字段：首字节最高位为 1 时为 GNU base-256 编码（大端），否则为八进制；负数按 0 处理
+    fn parse_numeric(field: &[u8]) -> u64 {
+        match field[0] {
+            // 0xc0 以上是负数
+            b if b & 0xc0 == 0xc0 => 0,
+            b if b & 0x80 != 0 => field[1..].iter().fold((b & 0x3f) as u64, |x, &b| (x << 8) | b as u64),
+            _ => Self::parse_octal(field),
+        }
+    }
+
+    /// 公共方法：从一个 `[u8]` 八进制字段解析成 u64，无法解析时返回 0
     fn parse_octal(field: &[u8]) -> u64 {
-        match std::str::from_utf8(field) {
-            Ok(s) => {
-                let s = s.trim_end_matches('\0').trim();
-                match u64::from_str_radix(s.trim_matches('\0'), 8) {
-                    Ok(i) => i,
-                    Err(e) => {
-                        println!("Error parsing octal: {}", e);
-                        0
-                    },
-                }
+        Self::try_parse_octal(field).unwrap_or(0)
+    }
+
+    /// 解析八进制字段，空字段视为 0
+    fn try_parse_octal(field: &[u8]) -> Result<u64, String> {
+        let s = std::str::from_utf8(field).map_err(|e| e.to_string())?;
+        let s = s.trim_matches(|c| c == '\0' || c == ' ');
+        if s.is_empty() {
+            return Ok(0);
+        }
+        u64::from_str_radix(s, 8).map_err(|e| format!("{:?}: {}", s, e))
+    }
+
+    /// 严格检查：magic / version 必须是 POSIX 的 "ustar\0" + "00" 或 GNU 的 "ustar " + " \0"，
+    /// 数字字段必须是以 NUL 或空格结尾的八进制（除 checksum 外也可以是 GNU base-256）；
+    /// 返回第一处不符合的说明
+    pub fn validate_strict(&self) -> Result<(), String> {
+        match (&self.magic, &self.version) {
+            (b"ustar\0", b"00") | (b"ustar ", b" \0") => {}
+            (magic, version) => {
+                return Err(format!("unknown magic {:?} / version {:?}",
+                    String::from_utf8_lossy(magic), String::from_utf8_lossy(version)));
+            }
+        }
+        let fields: [(&str, &[u8], bool); 8] = [
+            ("mode", &self.mode, false),
+            ("uid", &self.uid, true),
+            ("gid", &self.gid, true),
+            ("size", &self.size, true),
+            ("mtime", &self.mtime, true),
+            ("chksum", &self.chksum, false),
+            ("devmajor", &self.devmajor, true),
+            ("devminor", &self.devminor, true),
+        ];
+        for (name, field, base256) in fields {
+            if base256 && field[0] & 0x80 != 0 {
+                continue;
+            }
+            if !is_strict_octal(field) {
+                return Err(format!("{} field {:?} is not a terminated octal number", name, String::from_utf8_lossy(field)));
             }
-            Err(_) => 0,
         }
+        Ok(())
+    }
+
+    /// 逐个检查数字字段，返回无法解析的字段说明
+    pub fn field_warnings(&self) -> Vec<String> {
+        let fields: [(&str, &[u8]); 5] = [
+            ("mode", &self.mode),
+            ("uid", &self.uid),
+            ("gid", &self.gid),
+            ("mtime", &self.mtime),
+            ("size", &self.size),
+        ];
+        fields.into_iter()
+            // GNU base-256 编码的字段不按八进制检查
+            .filter(|(_, field)| field[0] & 0x80 == 0)
+            .filter_map(|(name, field)| {
+                Self::try_parse_octal(field).err().map(|e| format!("invalid {} field {}", name, e))
+            })
+            .collect()
     }
 
     pub fn get_name(&self) -> String {
-        match std::str::from_utf8(&self.name) {
-            Ok(s) => s.trim_end_matches('\0').to_string(),
-            Err(_) => String::new(),
-        }
+        field_string(&self.name)
     }
 
     pub fn get_prefix(&self) -> String {
-        match std::str::from_utf8(&self.prefix) {
-            Ok(s) => s.trim_end_matches('\0').to_string(),
-            Err(_) => String::new(),
+        field_string(&self.prefix)
+    }
+
+    /// name 字段的原始字节
+    pub fn get_name_bytes(&self) -> Vec<u8> {
+        field_bytes(&self.name).to_vec()
+    }
+
+    /// 完整路径（prefix + name）的原始字节
+    pub fn get_full_path_bytes(&self) -> Vec<u8> {
+        let prefix = field_bytes(&self.prefix);
+        let name = field_bytes(&self.name);
+        if prefix.is_empty() {
+            name.to_vec()
+        } else {
+            [prefix, b"/", name].concat()
         }
     }
 
+    pub fn get_link_name_bytes(&self) -> Vec<u8> {
+        field_bytes(&self.linkname).to_vec()
+    }
+
+    /// 是否为全零块（归档结束标记）
+    pub fn is_zero_block(&self) -> bool {
+        let ptr = self as *const _ as *const u8;
+        let buf = unsafe { std::slice::from_raw_parts(ptr, T_BLOCKSIZE) };
+        buf.iter().all(|&b| b == 0)
+    }
+
     /// 获取完整路径（prefix + name），如果 prefix 存在
     pub fn get_full_path(&self) -> String {
         let prefix = self.get_prefix();
@@ -147,15 +351,31 @@ impl TarHeader {
         }
     }
 
+    /// GNU 'M' header 中的 offset 字段：续接部分在原文件中的起始位置
+    pub fn get_gnu_offset(&self) -> u64 {
+        Self::parse_octal(&self.prefix[24..36])
+    }
+
+    /// GNU 'M' header 中的 realsize 字段：原文件的总大小
+    pub fn get_gnu_realsize(&self) -> u64 {
+        Self::parse_numeric(&self.prefix[138..150])
+    }
+
+    /// 旧式 GNU 稀疏 header（'S'）中的稀疏映射与 isextended 标志；后者为真时映射在后续的扩展块中继续
+    pub fn get_gnu_sparse_map(&self) -> (Vec<SparseChunk>, bool) {
+        parse_sparse_entries(&self.prefix[41..137], self.prefix[137])
+    }
+
     pub fn get_type_flag(&self) -> char {
         self.typeflag as char
     }
 
+    pub fn type_flag(&self) -> TypeFlag {
+        TypeFlag::from_byte(self.typeflag)
+    }
+
     pub fn get_link_name(&self) -> String {
-        match std::str::from_utf8(&self.linkname) {
-            Ok(s) => s.trim_end_matches('\0').to_string(),
-            Err(_) => String::new(),
-        }
+        field_string(&self.linkname)
     }
 
         /// 检查 checksum 是否正确
@@ -200,3 +420,172 @@ impl TarHeader {
 
 }
 
+/// 以 '\0' 结尾的八进制字段
+fn put_octal(field: &mut [u8], value: u64) -> io::Result<()> {
+    let s = format!("{:0width$o}", value, width = field.len() - 1);
+    if s.len() >= field.len() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("value {} does not fit in header field", value)));
+    }
+    field[..s.len()].copy_from_slice(s.as_bytes());
+    field[s.len()] = 0;
+    Ok(())
+}
+
+/// 可选的前导空格、八进制数字，随后至少一个 NUL 或空格作结尾且之后只有 NUL 或空格；
+/// 全空的字段也接受（如非设备条目的 devmajor）
+fn is_strict_octal(field: &[u8]) -> bool {
+    let start = field.iter().take_while(|&&b| b == b' ').count();
+    let digits = field[start..].iter().take_while(|b| (b'0'..=b'7').contains(b)).count();
+    let rest = &field[start + digits..];
+    if digits == 0 {
+        return field.iter().all(|&b| b == 0 || b == b' ');
+    }
+    !rest.is_empty() && rest.iter().all(|&b| b == 0 || b == b' ')
+}
+
+/// 数字字段：优先写八进制，放不下时写 GNU base-256（首字节 0x80，其余为大端数值）
+fn put_numeric(field: &mut [u8], value: u64) -> io::Result<()> {
+    if put_octal(field, value).is_ok() {
+        return Ok(());
+    }
+    let bytes = value.to_be_bytes();
+    let skip = bytes.iter().take_while(|&&b| b == 0).count();
+    if bytes.len() - skip >= field.len() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("value {} does not fit in header field", value)));
+    }
+    field.fill(0);
+    field[0] = 0x80;
+    let start = field.len() - (bytes.len() - skip);
+    field[start..].copy_from_slice(&bytes[skip..]);
+    Ok(())
+}
+
+/// 写入字符串字段，不足部分补 '\0'；恰好占满时没有结尾的 '\0'
+fn put_str(field: &mut [u8], value: &str) -> io::Result<()> {
+    if value.len() > field.len() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("name too long for header field: {}", value)));
+    }
+    field.fill(0);
+    field[..value.len()].copy_from_slice(value.as_bytes());
+    Ok(())
+}
+
+/// 重新计算并写入块的 checksum，计算时 chksum 字段按空格计
+pub(crate) fn write_checksum(b: &mut [u8; T_BLOCKSIZE]) {
+    b[148..156].copy_from_slice(b"        ");
+    let sum: u32 = b.iter().map(|&x| x as u32).sum();
+    let chk = format!("{:06o}\0 ", sum);
+    b[148..156].copy_from_slice(chk.as_bytes());
+}
+
+/// 写入侧：各字段按 ustar 格式编码，`to_bytes` 时补全 magic 并计算 checksum
+impl Default for TarHeader {
+    fn default() -> Self {
+        let mut hdr = unsafe { read_tar_header(&[0u8; T_BLOCKSIZE]) }.expect("zero block");
+        hdr.magic = *b"ustar\0";
+        hdr.version = *b"00";
+        hdr
+    }
+}
+
+impl TarHeader {
+    pub fn new(type_flag: TypeFlag) -> Self {
+        let mut hdr = TarHeader::default();
+        hdr.set_type_flag(type_flag);
+        hdr
+    }
+
+    /// 从 512 字节块解析
+    pub fn from_bytes(block: &[u8; T_BLOCKSIZE]) -> Self {
+        unsafe { read_tar_header(block) }.expect("block is 512 bytes")
+    }
+
+    /// 超过 100 字节的路径在 '/' 处拆分到 155 字节的 prefix 字段，仍放不下时返回 InvalidInput
+    pub fn set_path(&mut self, path: &str) -> io::Result<()> {
+        if path.len() <= 100 {
+            self.prefix.fill(0);
+            return put_str(&mut self.name, path);
+        }
+        let split = crate::builder::truncate(path, 156).rfind('/')
+            .filter(|&i| path.len() - i - 1 <= 100 && i > 0)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, format!("path too long: {}", path)))?;
+        put_str(&mut self.prefix, &path[..split])?;
+        put_str(&mut self.name, &path[split + 1..])
+    }
+
+    pub fn set_link_name(&mut self, link: &str) -> io::Result<()> {
+        put_str(&mut self.linkname, link)
+    }
+
+    pub fn set_type_flag(&mut self, type_flag: TypeFlag) {
+        self.typeflag = type_flag.as_byte();
+    }
+
+    pub fn set_mode(&mut self, mode: u32) -> io::Result<()> {
+        put_octal(&mut self.mode, mode as u64)
+    }
+
+    /// 八进制放不下（>= 2097152）时改用 GNU base-256 编码
+    pub fn set_uid(&mut self, uid: u64) -> io::Result<()> {
+        put_numeric(&mut self.uid, uid)
+    }
+
+    /// 八进制放不下时改用 GNU base-256 编码
+    pub fn set_gid(&mut self, gid: u64) -> io::Result<()> {
+        put_numeric(&mut self.gid, gid)
+    }
+
+    /// 八进制放不下（>= 8 GiB）时改用 GNU base-256 编码
+    pub fn set_size(&mut self, size: u64) -> io::Result<()> {
+        put_numeric(&mut self.size, size)
+    }
+
+    /// 负数（早于 1970 年）与八进制放不下的值改用 GNU base-256 编码
+    pub fn set_mtime(&mut self, mtime: i64) -> io::Result<()> {
+        match u64::try_from(mtime) {
+            Ok(mtime) => put_numeric(&mut self.mtime, mtime),
+            Err(_) => {
+                // base-256 补码：首字节 0xff，其余为大端的低位字节
+                self.mtime.fill(0xff);
+                let start = self.mtime.len() - 8;
+                self.mtime[start..].copy_from_slice(&mtime.to_be_bytes());
+                Ok(())
+            }
+        }
+    }
+
+    pub fn set_uname(&mut self, uname: &str) -> io::Result<()> {
+        put_str(&mut self.uname, uname)
+    }
+
+    pub fn set_gname(&mut self, gname: &str) -> io::Result<()> {
+        put_str(&mut self.gname, gname)
+    }
+
+    /// 八进制放不下时改用 GNU base-256 编码
+    pub fn set_device(&mut self, major: u32, minor: u32) -> io::Result<()> {
+        put_numeric(&mut self.devmajor, major as u64)?;
+        put_numeric(&mut self.devminor, minor as u64)
+    }
+
+    /// 写入 GNU 'M' header 的 offset 与 realsize 字段，与 prefix 共用同一区域
+    pub fn set_gnu_multivolume(&mut self, offset: u64, realsize: u64) -> io::Result<()> {
+        self.prefix.fill(0);
+        put_octal(&mut self.prefix[24..36], offset)?;
+        put_octal(&mut self.prefix[138..150], realsize)
+    }
+
+    /// 输出 512 字节块：magic 为空时填入 ustar，并重新计算 checksum
+    pub fn to_bytes(&self) -> [u8; T_BLOCKSIZE] {
+        let mut b = [0u8; T_BLOCKSIZE];
+        // 所有字段都是字节数组，repr(C) 下正好 512 字节且没有填充
+        unsafe { std::ptr::copy_nonoverlapping(self as *const _ as *const u8, b.as_mut_ptr(), T_BLOCKSIZE) };
+        if b[257..265].iter().all(|&x| x == 0) {
+            b[257..263].copy_from_slice(b"ustar\0");
+            b[263..265].copy_from_slice(b"00");
+        }
+        write_checksum(&mut b);
+        b
+    }
+}
+
@@ -0,0 +1,157 @@
+//! 归档外层的流变换（加密、签名等）
+//!
+//! 写出时 `TarBuilder` 写入变换后的 writer，读取时变换后的 reader 交给 `unpack_stream`，
+//! 其余 API 不变。启用 `age` feature 后可用 `Age` 按口令或 X25519 收件人加密备份，
+//! 其它变换由调用方实现 `Transform`。
+
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use crate::builder::TarBuilder;
+use crate::extract::{unpack_stream, ExtractOptions};
+
+/// 变换后的 writer；`finish` 写出尾部（如认证标签）并刷新，只 drop 不会得到完整的输出
+pub trait TransformWriter: Write {
+    fn finish(self: Box<Self>) -> io::Result<()>;
+}
+
+/// 一对互逆的流变换
+pub trait Transform {
+    /// 包装写出端，写入的明文变换后写到 inner
+    fn writer<'a>(&self, inner: Box<dyn Write + 'a>) -> io::Result<Box<dyn TransformWriter + 'a>>;
+
+    /// 包装读取端，从 inner 读到的数据还原为明文
+    fn reader<'a>(&self, inner: Box<dyn Read + 'a>) -> io::Result<Box<dyn Read + 'a>>;
+}
+
+/// 不做任何变换
+pub struct Identity;
+
+/// `Identity` 的写出端
+struct Plain<W>(W);
+
+impl<W: Write> Write for Plain<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+impl<W: Write> TransformWriter for Plain<W> {
+    fn finish(mut self: Box<Self>) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+impl Transform for Identity {
+    fn writer<'a>(&self, inner: Box<dyn Write + 'a>) -> io::Result<Box<dyn TransformWriter + 'a>> {
+        Ok(Box::new(Plain(inner)))
+    }
+
+    fn reader<'a>(&self, inner: Box<dyn Read + 'a>) -> io::Result<Box<dyn Read + 'a>> {
+        Ok(inner)
+    }
+}
+
+/// 创建经 transform 写出的归档；`TarBuilder::finish` 之后还需调用返回 writer 的 `finish`
+pub fn create_with(
+    path: &Path,
+    transform: &dyn Transform,
+) -> io::Result<TarBuilder<Box<dyn TransformWriter>>> {
+    let file = BufWriter::new(File::create(path)?);
+    Ok(TarBuilder::new(transform.writer(Box::new(file))?))
+}
+
+/// 读取经 transform 写出的归档并解包到 dest
+pub fn unpack_with(path: &Path, transform: &dyn Transform, dest: &Path, options: &ExtractOptions) -> io::Result<()> {
+    let file = BufReader::new(File::open(path)?);
+    unpack_stream(transform.reader(Box::new(file))?, dest, options)
+}
+
+/// age 格式的加密（需要 `age` feature）：写出时加密给口令或收件人，读取时用口令或私钥解密，
+/// 内容经 ChaCha20-Poly1305 认证，截断或篡改在读取时返回 InvalidData
+#[cfg(feature = "age")]
+pub struct Age {
+    passphrase: Option<age::secrecy::SecretString>,
+    recipients: Vec<age::x25519::Recipient>,
+    identities: Vec<age::x25519::Identity>,
+}
+
+#[cfg(feature = "age")]
+fn age_error<E: std::fmt::Display>(e: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("age: {}", e))
+}
+
+#[cfg(feature = "age")]
+impl Age {
+    /// 口令加密（scrypt），读写使用同一口令
+    pub fn passphrase(passphrase: &str) -> Self {
+        Age { passphrase: Some(passphrase.to_string().into()), recipients: Vec::new(), identities: Vec::new() }
+    }
+
+    /// 加密给 `age1...` 收件人，只能写出；格式错误返回 InvalidInput
+    pub fn recipients(recipients: &[&str]) -> io::Result<Self> {
+        let recipients = recipients.iter()
+            .map(|r| r.parse().map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, format!("age recipient: {}", e))))
+            .collect::<io::Result<_>>()?;
+        Ok(Age { passphrase: None, recipients, identities: Vec::new() })
+    }
+
+    /// 用 `AGE-SECRET-KEY-1...` 私钥解密，写出时加密给这些私钥对应的公钥；格式错误返回 InvalidInput
+    pub fn identities(identities: &[&str]) -> io::Result<Self> {
+        let identities: Vec<age::x25519::Identity> = identities.iter()
+            .map(|i| i.parse().map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, format!("age identity: {}", e))))
+            .collect::<io::Result<_>>()?;
+        let recipients = identities.iter().map(|i| i.to_public()).collect();
+        Ok(Age { passphrase: None, recipients, identities })
+    }
+}
+
+/// `Age` 的写出端
+#[cfg(feature = "age")]
+struct AgeWriter<W: Write>(age::stream::StreamWriter<W>);
+
+#[cfg(feature = "age")]
+impl<W: Write> Write for AgeWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+#[cfg(feature = "age")]
+impl<W: Write> TransformWriter for AgeWriter<W> {
+    fn finish(self: Box<Self>) -> io::Result<()> {
+        self.0.finish()?.flush()
+    }
+}
+
+#[cfg(feature = "age")]
+impl Transform for Age {
+    fn writer<'a>(&self, inner: Box<dyn Write + 'a>) -> io::Result<Box<dyn TransformWriter + 'a>> {
+        let encryptor = match &self.passphrase {
+            Some(passphrase) => age::Encryptor::with_user_passphrase(passphrase.clone()),
+            None => age::Encryptor::with_recipients(self.recipients.iter().map(|r| r as &dyn age::Recipient))
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, format!("age: {}", e)))?,
+        };
+        Ok(Box::new(AgeWriter(encryptor.wrap_output(inner)?)))
+    }
+
+    fn reader<'a>(&self, inner: Box<dyn Read + 'a>) -> io::Result<Box<dyn Read + 'a>> {
+        let decryptor = age::Decryptor::new(inner).map_err(age_error)?;
+        let reader = match &self.passphrase {
+            Some(passphrase) => {
+                let identity = age::scrypt::Identity::new(passphrase.clone());
+                decryptor.decrypt(std::iter::once(&identity as &dyn age::Identity))
+            }
+            None => decryptor.decrypt(self.identities.iter().map(|i| i as &dyn age::Identity)),
+        };
+        Ok(Box::new(reader.map_err(age_error)?))
+    }
+}
@@ -0,0 +1,211 @@
+use std::collections::BTreeMap;
+use std::io;
+use crate::base::{try_into_tarfile, ImageInfo, TarImage};
+use crate::meta::EntryMeta;
+use crate::path::normalize_path;
+
+/// 树中节点的编号
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NodeId(usize);
+
+impl NodeId {
+    /// 节点编号，根为 0，按插入顺序递增
+    pub fn index(self) -> usize {
+        self.0
+    }
+}
+
+/// 目录树节点
+#[derive(Debug, Clone)]
+pub struct TreeNode {
+    name: String,
+    parent: Option<NodeId>,
+    children: BTreeMap<String, NodeId>,
+    meta: Option<EntryMeta>,
+    /// meta 由 `fill_implicit_dirs` 合成
+    implicit: bool,
+}
+
+impl TreeNode {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// 节点对应的条目；归档中没有显式记录的中间目录为 None
+    pub fn meta(&self) -> Option<&EntryMeta> {
+        self.meta.as_ref()
+    }
+
+    pub fn parent(&self) -> Option<NodeId> {
+        self.parent
+    }
+
+    /// 元数据是 `TarTree::fill_implicit_dirs` 合成的
+    pub fn is_implicit(&self) -> bool {
+        self.implicit
+    }
+
+    pub fn is_dir(&self) -> bool {
+        !self.children.is_empty() || self.meta.as_ref().is_none_or(|m| m.is_dir())
+    }
+}
+
+/// `list_dir` 返回的一项
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DirEntry {
+    pub name: String,
+    /// 从根开始的完整路径
+    pub path: String,
+    pub is_dir: bool,
+    /// 子节点数，非目录为 0
+    pub children: usize,
+    /// 归档中的条目；没有显式记录的中间目录为 None
+    pub meta: Option<EntryMeta>,
+}
+
+/// 一次扫描建立的目录树，同名条目以最后出现的为准
+#[derive(Debug, Clone)]
+pub struct TarTree {
+    nodes: Vec<TreeNode>,
+}
+
+impl Default for TarTree {
+    fn default() -> Self {
+        TarTree { nodes: vec![TreeNode { name: String::new(), parent: None, children: BTreeMap::new(), meta: None, implicit: false }] }
+    }
+}
+
+impl TarTree {
+    pub fn build(img: &mut TarImage) -> io::Result<Self> {
+        let mut tree = TarTree::default();
+        img.for_each_entry(|file| {
+            tree.insert(try_into_tarfile(file)?.meta());
+            Ok(())
+        })?;
+        Ok(tree)
+    }
+
+    fn insert(&mut self, meta: EntryMeta) {
+        let mut current = self.root();
+        for name in normalize_path(&meta.path).split('/').filter(|c| !c.is_empty() && *c != ".") {
+            current = match self.nodes[current.0].children.get(name) {
+                Some(&child) => child,
+                None => {
+                    let child = NodeId(self.nodes.len());
+                    self.nodes.push(TreeNode { name: name.to_string(), parent: Some(current), children: BTreeMap::new(), meta: None, implicit: false });
+                    self.nodes[current.0].children.insert(name.to_string(), child);
+                    child
+                }
+            };
+        }
+        if current != self.root() {
+            self.nodes[current.0].meta = Some(meta);
+            self.nodes[current.0].implicit = false;
+        }
+    }
+
+    /// 为没有显式记录的中间目录合成元数据（见 `EntryMeta::implicit_dir`），
+    /// 属主与时间取自最早插入的子节点；之后 `meta` 对所有非根节点都不为 None
+    pub fn fill_implicit_dirs(&mut self) {
+        // 子节点总在父节点之后插入，倒序处理保证子节点已经有元数据
+        for i in (1..self.nodes.len()).rev() {
+            if self.nodes[i].meta.is_some() {
+                continue;
+            }
+            let Some(&first) = self.nodes[i].children.values().min_by_key(|c| c.0) else { continue };
+            let Some(child) = self.nodes[first.0].meta.clone() else { continue };
+            self.nodes[i].meta = Some(EntryMeta::implicit_dir(&self.path_of(NodeId(i)), &child));
+            self.nodes[i].implicit = true;
+        }
+    }
+
+    pub fn root(&self) -> NodeId {
+        NodeId(0)
+    }
+
+    pub fn node(&self, id: NodeId) -> &TreeNode {
+        &self.nodes[id.0]
+    }
+
+    /// 由编号取回节点 id，编号越界时返回 None
+    pub fn id(&self, index: usize) -> Option<NodeId> {
+        (index < self.nodes.len()).then_some(NodeId(index))
+    }
+
+    /// 节点总数（不含根）
+    pub fn len(&self) -> usize {
+        self.nodes.len() - 1
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.len() == 1
+    }
+
+    /// 按路径查找节点，开头的 '/' 与 "./" 可有可无
+    pub fn lookup(&self, path: &str) -> Option<NodeId> {
+        let mut current = self.root();
+        for name in normalize_path(path).split('/').filter(|c| !c.is_empty() && *c != ".") {
+            current = if name == ".." {
+                self.node(current).parent.unwrap_or(current)
+            } else {
+                *self.node(current).children.get(name)?
+            };
+        }
+        Some(current)
+    }
+
+    /// 按名称排序的子节点
+    pub fn children(&self, id: NodeId) -> impl Iterator<Item = (&str, NodeId)> {
+        self.node(id).children.iter().map(|(name, &child)| (name.as_str(), child))
+    }
+
+    /// 列出目录内容；路径不存在时返回 None
+    pub fn readdir(&self, path: &str) -> Option<Vec<(&str, NodeId)>> {
+        Some(self.children(self.lookup(path)?).collect())
+    }
+
+    /// 列出目录的直接子项（按名称排序）及其元数据；路径不存在时返回 NotFound，不是目录时返回 NotADirectory
+    pub fn list_dir(&self, path: &str) -> io::Result<Vec<DirEntry>> {
+        let id = self.lookup(path)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("{} not found in archive", path)))?;
+        if !self.node(id).is_dir() {
+            return Err(io::Error::new(io::ErrorKind::NotADirectory, format!("{} is not a directory", path)));
+        }
+        Ok(self.children(id).map(|(name, child)| {
+            let node = self.node(child);
+            DirEntry {
+                name: name.to_string(),
+                path: self.path_of(child),
+                is_dir: node.is_dir(),
+                children: node.children.len(),
+                meta: node.meta.clone(),
+            }
+        }).collect())
+    }
+
+    /// 从根开始的路径（不带开头的 '/'），根节点为空串
+    pub fn path_of(&self, id: NodeId) -> String {
+        let mut names = Vec::new();
+        let mut current = Some(id);
+        while let Some(node) = current.map(|c| self.node(c)) {
+            if node.parent.is_some() {
+                names.push(node.name.as_str());
+            }
+            current = node.parent;
+        }
+        names.reverse();
+        names.join("/")
+    }
+}
+
+impl TarImage {
+    pub fn tree(&mut self) -> io::Result<TarTree> {
+        TarTree::build(self)
+    }
+
+    /// 列出一个目录的直接子项；每次调用都会扫描一遍，逐级浏览时请保留 `tree` 的结果
+    pub fn list_dir(&mut self, path: &str) -> io::Result<Vec<DirEntry>> {
+        self.tree()?.list_dir(path)
+    }
+}
@@ -0,0 +1,76 @@
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+use std::path::Path;
+use crate::base::{lock_image, try_into_tarfile, ImageInfo, TarImage};
+use crate::builder::TarBuilder;
+use crate::meta::unix_secs;
+
+/// 归档中每个路径最后一个条目的 mtime，以及最后一个条目的结束偏移
+fn archive_state(archive: &str) -> io::Result<(HashMap<String, i64>, u64)> {
+    let img = TarImage::open(archive)?;
+    let mut mtimes = HashMap::new();
+    let mut end = 0;
+    lock_image(&img)?.for_each_entry(|file| {
+        let tar_file = try_into_tarfile(file)?;
+        let meta = tar_file.meta();
+        // 同名条目以最后一个为准
+        mtimes.insert(meta.path.trim_end_matches('/').to_string(), meta.mtime);
+        end = tar_file.get_end_offset();
+        Ok(())
+    })?;
+    Ok((mtimes, end))
+}
+
+/// 磁盘上的修改时间（秒）
+fn disk_mtime(path: &Path) -> io::Result<i64> {
+    let md = fs::symlink_metadata(path)?;
+    Ok(md.modified().map_or(0, unix_secs))
+}
+
+fn update_recursive<W: Write>(
+    builder: &mut TarBuilder<W>,
+    mtimes: &HashMap<String, i64>,
+    name: &str,
+    path: &Path,
+) -> io::Result<u64> {
+    let mut appended = 0;
+    let key = name.trim_end_matches('/');
+    let newer = match mtimes.get(key) {
+        Some(&mtime) => disk_mtime(path)? > mtime,
+        None => true,
+    };
+    if newer {
+        builder.append_path(name, path)?;
+        appended += 1;
+    }
+    if fs::symlink_metadata(path)?.is_dir() {
+        let mut children: Vec<_> = fs::read_dir(path)?.collect::<io::Result<_>>()?;
+        children.sort_by_key(|c| c.file_name());
+        for child in children {
+            let child_name = format!("{}/{}", key, child.file_name().to_string_lossy());
+            appended += update_recursive(builder, mtimes, &child_name, &child.path())?;
+        }
+    }
+    Ok(appended)
+}
+
+/// 相当于 `tar -u`：把磁盘上比归档中同名条目更新（或归档中没有）的文件追加到归档末尾，返回追加的条目数
+///
+/// inputs 为 (归档内路径, 磁盘路径)，目录会递归处理；旧条目保留在原处，
+/// 按同名条目以最后一个为准的规则，读取时看到的是新追加的版本
+pub fn update_archive(archive: &str, inputs: &[(&str, &Path)]) -> io::Result<u64> {
+    let (mtimes, end) = archive_state(archive)?;
+    let mut file = OpenOptions::new().write(true).open(archive)?;
+    file.seek(SeekFrom::Start(end))?;
+    let mut builder = TarBuilder::new(BufWriter::new(file));
+    let mut appended = 0;
+    for (name, path) in inputs {
+        appended += update_recursive(&mut builder, &mtimes, name, path)?;
+    }
+    let mut file = builder.finish()?.into_inner().map_err(|e| e.into_error())?;
+    // 去掉原结束标记之后残留的数据
+    let len = file.stream_position()?;
+    file.set_len(len)?;
+    Ok(appended)
+}
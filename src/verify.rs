@@ -0,0 +1,54 @@
+use std::io;
+use crate::base::{try_into_tarfile, ImageInfo, TarImage};
+use crate::checksum::IntegrityStatus;
+use crate::progress::{NoProgress, Progress};
+
+/// 校验结果汇总
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct VerifyReport {
+    pub entries: u64,
+    pub data_bytes: u64,
+    /// 数据区超出镜像末尾的条目路径
+    pub truncated: Vec<String>,
+    /// 通过 PAX 摘要记录（crc32 / sha256）校验的条目数
+    pub checksummed: u64,
+    /// 内容与摘要记录不一致的条目：(路径, 记录键)
+    pub checksum_mismatches: Vec<(String, String)>,
+}
+
+impl VerifyReport {
+    pub fn is_ok(&self) -> bool {
+        self.truncated.is_empty() && self.checksum_mismatches.is_empty()
+    }
+}
+
+/// 遍历整个镜像，校验每个 header 的 checksum、数据区是否完整，以及 PAX 摘要记录
+pub fn verify(img: &mut TarImage) -> io::Result<VerifyReport> {
+    verify_with_progress(img, &mut NoProgress)
+}
+
+/// 与 `verify` 相同，每校验完一个条目报告一次进度
+pub fn verify_with_progress(img: &mut TarImage, progress: &mut dyn Progress) -> io::Result<VerifyReport> {
+    let image_size = img.get_size()?;
+    let mut report = VerifyReport::default();
+    img.for_each_entry_with_progress(progress, |file| {
+        let tar_file = try_into_tarfile(file)?;
+        report.entries += 1;
+        report.data_bytes += tar_file.get_size();
+        if tar_file.get_data_offset() + tar_file.get_size() > image_size {
+            report.truncated.push(tar_file.get_path());
+            return Ok(());
+        }
+        match tar_file.verify_body()? {
+            IntegrityStatus::Unchecked => {}
+            IntegrityStatus::Ok => report.checksummed += 1,
+            IntegrityStatus::Mismatch(key) => {
+                report.checksummed += 1;
+                report.checksum_mismatches.push((tar_file.get_path(), key));
+            }
+        }
+        Ok(())
+    })?;
+    Ok(report)
+}
@@ -0,0 +1,284 @@
+//! GNU 多卷归档
+//!
+//! 跨卷的文件在前一卷中只写出前半部分，header 中的 size 仍为完整大小；
+//! 后一卷以类型为 'M' 的续接 header 开头，其 size 为剩余大小，offset 字段记录已写出的字节数。
+
+use std::io::{self, Read, Write};
+use std::sync::{Arc, Mutex};
+use crate::base::{lock_image, try_into_tarfile, ImageInfo, TarFile, TarImage};
+use crate::builder::{gnu_long_block, truncate};
+use crate::tar::{block_align, TarHeader, TypeFlag};
+
+/// 每卷至少能容纳 header、续接 header 与一个数据块
+const MIN_VOLUME_SIZE: u64 = 3 * 512;
+
+/// 按顺序排列的各卷
+pub struct Volumes {
+    images: Vec<Arc<Mutex<TarImage>>>,
+}
+
+/// 合并各卷后的一个条目，跨卷文件由多段组成
+pub struct VolumeEntry {
+    /// (条目, 该段在所在卷中的数据长度)
+    parts: Vec<(Box<TarFile>, u64)>,
+}
+
+/// 条目在所在卷中实际保存的数据长度，卷末被截断时小于 size
+fn part_len(file: &TarFile, volume_size: u64) -> u64 {
+    file.get_size().min(volume_size.saturating_sub(file.get_data_offset()))
+}
+
+impl Volumes {
+    /// 按卷号顺序打开各卷
+    pub fn open<P: AsRef<str>>(paths: &[P]) -> io::Result<Self> {
+        let images = paths.iter().map(|p| TarImage::open(p.as_ref())).collect::<io::Result<_>>()?;
+        Ok(Volumes { images })
+    }
+
+    pub fn from_images(images: Vec<Arc<Mutex<TarImage>>>) -> Self {
+        Volumes { images }
+    }
+
+    pub fn len(&self) -> usize {
+        self.images.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.images.is_empty()
+    }
+
+    /// 按归档顺序遍历条目，跨卷文件合并成一个条目；卷标 'V' 被跳过
+    pub fn for_each_entry<F>(&self, mut callback: F) -> io::Result<()>
+    where
+        F: FnMut(VolumeEntry) -> io::Result<()>,
+    {
+        let mut pending: Option<VolumeEntry> = None;
+        for (index, img) in self.images.iter().enumerate() {
+            let mut img = lock_image(img)?;
+            let volume_size = img.get_size()?;
+            img.for_each_entry(|file| {
+                let tar_file = try_into_tarfile(file)?;
+                let len = part_len(&tar_file, volume_size);
+                let entry = match tar_file.type_flag() {
+                    TypeFlag::GnuVolumeLabel => return Ok(()),
+                    TypeFlag::GnuMultiVolume => {
+                        let Some(mut entry) = pending.take() else {
+                            return Err(io::Error::new(io::ErrorKind::InvalidData, format!(
+                                "volume {} starts with a continuation of {} but no entry was split", index + 1, tar_file.get_path())));
+                        };
+                        if tar_file.get_path() != entry.get_path() || tar_file.get_header().get_gnu_offset() != entry.stored() {
+                            return Err(io::Error::new(io::ErrorKind::InvalidData, format!(
+                                "volume {} does not continue {} at offset {}", index + 1, entry.get_path(), entry.stored())));
+                        }
+                        entry.parts.push((tar_file, len));
+                        entry
+                    }
+                    _ => {
+                        if let Some(entry) = pending.take() {
+                            return Err(io::Error::new(io::ErrorKind::InvalidData, format!(
+                                "volume {} does not continue {}", index + 1, entry.get_path())));
+                        }
+                        VolumeEntry { parts: vec![(tar_file, len)] }
+                    }
+                };
+                if entry.stored() < entry.get_size() {
+                    pending = Some(entry);
+                    Ok(())
+                } else {
+                    callback(entry)
+                }
+            })?;
+        }
+        match pending {
+            Some(entry) => Err(io::Error::new(io::ErrorKind::UnexpectedEof, format!(
+                "{} continues past the last volume", entry.get_path()))),
+            None => Ok(()),
+        }
+    }
+
+    /// 收集所有条目
+    pub fn entries(&self) -> io::Result<Vec<VolumeEntry>> {
+        let mut out = Vec::new();
+        self.for_each_entry(|entry| {
+            out.push(entry);
+            Ok(())
+        })?;
+        Ok(out)
+    }
+}
+
+impl VolumeEntry {
+    /// 第一段的条目，header 与元数据以它为准
+    pub fn file(&self) -> &TarFile {
+        &self.parts[0].0
+    }
+
+    pub fn get_path(&self) -> String {
+        self.file().get_path()
+    }
+
+    /// 完整的数据大小
+    pub fn get_size(&self) -> u64 {
+        self.file().get_size()
+    }
+
+    /// 条目分布在几卷中
+    pub fn volume_count(&self) -> usize {
+        self.parts.len()
+    }
+
+    /// 各段已保存的数据总长
+    fn stored(&self) -> u64 {
+        self.parts.iter().map(|(_, len)| len).sum()
+    }
+
+    /// 依次读取各段数据的 reader
+    pub fn reader(&self) -> VolumeReader<'_> {
+        VolumeReader { entry: self, part: 0, pos: 0 }
+    }
+
+    /// 读出完整数据
+    pub fn read_to_vec(&self) -> io::Result<Vec<u8>> {
+        let mut out = Vec::with_capacity(self.get_size() as usize);
+        self.reader().read_to_end(&mut out)?;
+        Ok(out)
+    }
+}
+
+/// `VolumeEntry::reader` 返回的 reader
+pub struct VolumeReader<'a> {
+    entry: &'a VolumeEntry,
+    part: usize,
+    pos: u64,
+}
+
+impl Read for VolumeReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while let Some((file, len)) = self.entry.parts.get(self.part) {
+            if self.pos >= *len {
+                self.part += 1;
+                self.pos = 0;
+                continue;
+            }
+            let max = (buf.len() as u64).min(len - self.pos) as usize;
+            let n = file.read_body_at(self.pos, &mut buf[..max])?;
+            self.pos += n as u64;
+            return Ok(n);
+        }
+        Ok(0)
+    }
+}
+
+/// 按卷切换的输出端
+struct VolumeSink<W, F> {
+    open: F,
+    writer: Option<W>,
+    count: usize,
+    used: u64,
+    max: u64,
+}
+
+impl<W: Write, F: FnMut(usize) -> io::Result<W>> VolumeSink<W, F> {
+    fn remaining(&self) -> u64 {
+        self.max - self.used
+    }
+
+    fn next_volume(&mut self) -> io::Result<()> {
+        if let Some(mut writer) = self.writer.take() {
+            writer.flush()?;
+        }
+        self.writer = Some((self.open)(self.count)?);
+        self.count += 1;
+        self.used = 0;
+        Ok(())
+    }
+
+    fn writer(&mut self) -> &mut W {
+        self.writer.as_mut().expect("volume opened")
+    }
+
+    fn write(&mut self, data: &[u8]) -> io::Result<()> {
+        self.writer().write_all(data)?;
+        self.used += data.len() as u64;
+        Ok(())
+    }
+}
+
+/// 跨卷文件在新卷开头的续接 header，已写出 done 字节
+fn continuation_header(file: &TarFile, done: u64) -> io::Result<Vec<u8>> {
+    let path = file.get_path();
+    let src = file.get_header();
+    let mut out = Vec::new();
+    if path.len() > 100 {
+        out.extend(gnu_long_block(TypeFlag::GnuLongName, &path)?);
+    }
+    let mut hdr = TarHeader::new(TypeFlag::GnuMultiVolume);
+    hdr.set_path(truncate(&path, 100))?;
+    hdr.set_mode(src.get_mode().bits())?;
+    hdr.set_uid(src.get_uid())?;
+    hdr.set_gid(src.get_gid())?;
+    hdr.set_mtime(src.get_mtime())?;
+    hdr.set_size(file.get_size() - done)?;
+    hdr.set_gnu_multivolume(done, file.get_size())?;
+    hdr.magic = *b"ustar ";
+    hdr.version = *b" \0";
+    out.extend_from_slice(&hdr.to_bytes());
+    Ok(out)
+}
+
+/// 把 img 按每卷最多 max_volume_size 字节写成 GNU 多卷归档，返回卷数
+///
+/// open 按卷号（从 0 开始）创建各卷的输出；header 不跨卷，放不下时整体移到下一卷，
+/// 数据区在卷满时切分，下一卷以 'M' 续接 header 开头；归档结束标记写在最后一卷。
+/// max_volume_size 必须是 512 的倍数且不小于 1536
+pub fn write_volumes<W, F>(img: &mut TarImage, max_volume_size: u64, open: F) -> io::Result<usize>
+where
+    W: Write,
+    F: FnMut(usize) -> io::Result<W>,
+{
+    if !max_volume_size.is_multiple_of(512) || max_volume_size < MIN_VOLUME_SIZE {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, format!(
+            "volume size must be a multiple of 512 and at least {}: {}", MIN_VOLUME_SIZE, max_volume_size)));
+    }
+    let mut sink = VolumeSink { open, writer: None, count: 0, used: 0, max: max_volume_size };
+    sink.next_volume()?;
+    let mut offset = 0;
+    while let Some(file) = img.entry_at(offset)? {
+        offset = file.get_end_offset();
+        let header_len = file.get_data_offset() - file.get_offset();
+        let body_len = block_align(file.get_size());
+        // 至少带上一个数据块，避免在卷末留下没有数据的 header
+        let need = header_len + body_len.min(512);
+        if need > sink.remaining() {
+            if need > max_volume_size {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, format!(
+                    "header of {} does not fit in a volume of {} bytes", file.get_path(), max_volume_size)));
+            }
+            sink.next_volume()?;
+        }
+        let (header, _) = img.read_img_at(file.get_offset(), header_len)?;
+        sink.write(&header)?;
+        let mut done = 0;
+        while done < body_len {
+            if sink.remaining() == 0 {
+                sink.next_volume()?;
+                let header = continuation_header(&file, done)?;
+                if header.len() as u64 >= max_volume_size {
+                    return Err(io::Error::new(io::ErrorKind::InvalidInput, format!(
+                        "continuation header of {} does not fit in a volume of {} bytes", file.get_path(), max_volume_size)));
+                }
+                sink.write(&header)?;
+            }
+            let n = sink.remaining().min(body_len - done);
+            img.copy_range_to(file.get_data_offset() + done, n, sink.writer())?;
+            sink.used += n;
+            done += n;
+        }
+    }
+    if sink.remaining() < 1024 {
+        sink.next_volume()?;
+    }
+    sink.write(&[0u8; 1024])?;
+    sink.writer().flush()?;
+    Ok(sink.count)
+}
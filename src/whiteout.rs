@@ -0,0 +1,57 @@
+use crate::base::TarFile;
+use crate::path::normalize_path;
+
+/// overlayfs 白化文件名前缀
+pub const WHITEOUT_PREFIX: &str = ".wh.";
+/// 不透明目录标记：隐藏下层同一目录中的全部内容
+pub const OPAQUE_MARKER: &str = ".wh..wh..opq";
+
+/// 容器镜像层中的删除标记
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Whiteout {
+    /// `.wh.<name>`：删除下层的 `<name>`（及其子项）
+    Remove(String),
+    /// `.wh..wh..opq`：清空下层的该目录，目录本身保留；根目录为空串
+    Opaque(String),
+}
+
+impl Whiteout {
+    /// 判断下层的 `path` 是否被该标记隐藏
+    pub fn hides(&self, path: &str) -> bool {
+        let path = normalize_path(path);
+        match self {
+            Whiteout::Remove(target) => path == target || is_under(path, target),
+            Whiteout::Opaque(dir) => is_under(path, dir),
+        }
+    }
+}
+
+fn is_under(path: &str, dir: &str) -> bool {
+    if dir.is_empty() {
+        return !path.is_empty();
+    }
+    path.len() > dir.len() && path.starts_with(dir) && path.as_bytes()[dir.len()] == b'/'
+}
+
+/// 识别白化路径，不是白化文件时返回 None
+pub fn classify(path: &str) -> Option<Whiteout> {
+    let path = normalize_path(path);
+    let (dir, name) = match path.rfind('/') {
+        Some(i) => (&path[..i], &path[i + 1..]),
+        None => ("", path),
+    };
+    if name == OPAQUE_MARKER {
+        return Some(Whiteout::Opaque(dir.to_string()));
+    }
+    let target = name.strip_prefix(WHITEOUT_PREFIX)?;
+    if target.is_empty() {
+        return None;
+    }
+    Some(Whiteout::Remove(if dir.is_empty() { target.to_string() } else { format!("{}/{}", dir, target) }))
+}
+
+impl TarFile {
+    pub fn whiteout(&self) -> Option<Whiteout> {
+        classify(&self.get_path())
+    }
+}
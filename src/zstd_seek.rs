@@ -0,0 +1,257 @@
+//! zstd 可寻址格式（seekable format）的 .tar.zst
+//!
+//! 文件由多个独立的 zstd 帧组成，末尾的可跳过帧保存每帧的压缩与解压大小。读取时只解压
+//! 覆盖目标范围的帧；配合 `Index` 按偏移打开条目，查找条目不必从头解压。
+//! 格式见 zstd 仓库 contrib/seekable_format/zstd_seekable_compression_format.md。
+
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use crate::backend::{Backend, BlockCache, FileBackend};
+use crate::base::TarImage;
+
+/// 可跳过帧的魔数
+const SKIPPABLE_MAGIC: u32 = 0x184D2A5E;
+/// seek table 尾部的魔数
+const SEEKABLE_MAGIC: u32 = 0x8F92EAB1;
+/// 尾部：帧数 u32、描述符 u8、魔数 u32
+const FOOTER_SIZE: u64 = 9;
+/// 描述符中表示每项带校验和的位
+const CHECKSUM_FLAG: u8 = 0x80;
+
+/// `open_zstd` 缓存解压结果的块大小与总容量
+const CACHE_BLOCK: usize = 256 * 1024;
+const CACHE_CAPACITY: usize = 32 * 1024 * 1024;
+
+/// seek table 中的一帧
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Frame {
+    pub compressed_offset: u64,
+    pub uncompressed_offset: u64,
+    pub compressed_size: u32,
+    pub decompressed_size: u32,
+}
+
+/// 帧表
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SeekTable {
+    frames: Vec<Frame>,
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("invalid zstd seek table: {}", msg))
+}
+
+fn read_exact_at(backend: &dyn Backend, buf: &mut [u8], offset: u64) -> io::Result<()> {
+    let mut done = 0;
+    while done < buf.len() {
+        match backend.read_at(&mut buf[done..], offset + done as u64) {
+            Ok(0) => return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "zstd frame past end of file")),
+            Ok(n) => done += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
+fn le32(b: &[u8]) -> u32 {
+    u32::from_le_bytes(b[..4].try_into().unwrap())
+}
+
+impl SeekTable {
+    /// 从文件末尾读取帧表；不是可寻址格式时返回 InvalidData
+    pub fn read(backend: &dyn Backend) -> io::Result<Self> {
+        let len = backend.len()?;
+        if len < FOOTER_SIZE + 8 {
+            return Err(invalid("file too short"));
+        }
+        let mut footer = [0u8; FOOTER_SIZE as usize];
+        read_exact_at(backend, &mut footer, len - FOOTER_SIZE)?;
+        if le32(&footer[5..]) != SEEKABLE_MAGIC {
+            return Err(invalid("no seekable footer"));
+        }
+        let count = le32(&footer) as u64;
+        let descriptor = footer[4];
+        let entry_size: u64 = if descriptor & CHECKSUM_FLAG != 0 { 12 } else { 8 };
+        let table_size = count * entry_size + FOOTER_SIZE;
+        let frame_start = len.checked_sub(table_size + 8).ok_or_else(|| invalid("frame count too large"))?;
+        let mut table = vec![0u8; (table_size + 8) as usize];
+        read_exact_at(backend, &mut table, frame_start)?;
+        if le32(&table) != SKIPPABLE_MAGIC || le32(&table[4..]) as u64 != table_size {
+            return Err(invalid("bad skippable frame header"));
+        }
+
+        let mut frames = Vec::with_capacity(count as usize);
+        let (mut compressed, mut uncompressed) = (0u64, 0u64);
+        for entry in table[8..].chunks_exact(entry_size as usize).take(count as usize) {
+            let frame = Frame {
+                compressed_offset: compressed,
+                uncompressed_offset: uncompressed,
+                compressed_size: le32(entry),
+                decompressed_size: le32(&entry[4..]),
+            };
+            compressed += frame.compressed_size as u64;
+            uncompressed += frame.decompressed_size as u64;
+            frames.push(frame);
+        }
+        if compressed != frame_start {
+            return Err(invalid("frame sizes do not add up to the file size"));
+        }
+        Ok(SeekTable { frames })
+    }
+
+    pub fn frames(&self) -> &[Frame] {
+        &self.frames
+    }
+
+    /// 解压后的总长
+    pub fn uncompressed_len(&self) -> u64 {
+        self.frames.last().map_or(0, |f| f.uncompressed_offset + f.decompressed_size as u64)
+    }
+
+    /// 包含 offset 的帧
+    fn frame_for(&self, offset: u64) -> Option<&Frame> {
+        let i = self.frames.partition_point(|f| f.uncompressed_offset + f.decompressed_size as u64 <= offset);
+        self.frames.get(i)
+    }
+
+    /// 序列化为可跳过帧，追加在最后一帧之后
+    fn to_bytes(&self) -> Vec<u8> {
+        let table_size = self.frames.len() as u32 * 8 + FOOTER_SIZE as u32;
+        let mut out = Vec::with_capacity(table_size as usize + 8);
+        out.extend_from_slice(&SKIPPABLE_MAGIC.to_le_bytes());
+        out.extend_from_slice(&table_size.to_le_bytes());
+        for frame in &self.frames {
+            out.extend_from_slice(&frame.compressed_size.to_le_bytes());
+            out.extend_from_slice(&frame.decompressed_size.to_le_bytes());
+        }
+        out.extend_from_slice(&(self.frames.len() as u32).to_le_bytes());
+        out.push(0);
+        out.extend_from_slice(&SEEKABLE_MAGIC.to_le_bytes());
+        out
+    }
+}
+
+/// 按帧表随机读取解压后的内容；每次读取解压覆盖范围内的帧，通常再套一层 `BlockCache`
+pub struct ZstdSeekableBackend<B: Backend> {
+    inner: B,
+    table: SeekTable,
+}
+
+impl<B: Backend> ZstdSeekableBackend<B> {
+    pub fn new(inner: B) -> io::Result<Self> {
+        let table = SeekTable::read(&inner)?;
+        Ok(ZstdSeekableBackend { inner, table })
+    }
+
+    pub fn table(&self) -> &SeekTable {
+        &self.table
+    }
+
+    fn decompress(&self, frame: &Frame) -> io::Result<Vec<u8>> {
+        let mut compressed = vec![0u8; frame.compressed_size as usize];
+        read_exact_at(&self.inner, &mut compressed, frame.compressed_offset)?;
+        let data = zstd::bulk::decompress(&compressed, frame.decompressed_size as usize)?;
+        if data.len() != frame.decompressed_size as usize {
+            return Err(invalid("frame size does not match the seek table"));
+        }
+        Ok(data)
+    }
+}
+
+impl<B: Backend> Backend for ZstdSeekableBackend<B> {
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        let mut done = 0;
+        while done < buf.len() {
+            let pos = offset + done as u64;
+            let Some(frame) = self.table.frame_for(pos) else { break };
+            let data = self.decompress(frame)?;
+            let from = (pos - frame.uncompressed_offset) as usize;
+            let n = (data.len() - from).min(buf.len() - done);
+            buf[done..done + n].copy_from_slice(&data[from..from + n]);
+            done += n;
+        }
+        Ok(done)
+    }
+
+    fn len(&self) -> io::Result<u64> {
+        Ok(self.table.uncompressed_len())
+    }
+}
+
+/// 写出可寻址格式：每 span 字节明文压缩成一个独立帧，`finish` 时追加帧表
+pub struct ZstdSeekableWriter<W: Write> {
+    inner: W,
+    level: i32,
+    span: usize,
+    pending: Vec<u8>,
+    table: SeekTable,
+    compressed: u64,
+    uncompressed: u64,
+}
+
+impl<W: Write> ZstdSeekableWriter<W> {
+    /// span 为每帧的明文大小，不超过 u32 范围
+    pub fn new(inner: W, span: usize, level: i32) -> Self {
+        let span = span.clamp(1, u32::MAX as usize);
+        ZstdSeekableWriter {
+            inner, level, span,
+            pending: Vec::new(),
+            table: SeekTable::default(),
+            compressed: 0,
+            uncompressed: 0,
+        }
+    }
+
+    fn flush_frame(&mut self) -> io::Result<()> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        let frame = zstd::bulk::compress(&self.pending, self.level)?;
+        self.inner.write_all(&frame)?;
+        self.table.frames.push(Frame {
+            compressed_offset: self.compressed,
+            uncompressed_offset: self.uncompressed,
+            compressed_size: frame.len() as u32,
+            decompressed_size: self.pending.len() as u32,
+        });
+        self.compressed += frame.len() as u64;
+        self.uncompressed += self.pending.len() as u64;
+        self.pending.clear();
+        Ok(())
+    }
+
+    /// 写出剩余数据与帧表，返回内层 writer
+    pub fn finish(mut self) -> io::Result<W> {
+        self.flush_frame()?;
+        self.inner.write_all(&self.table.to_bytes())?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> Write for ZstdSeekableWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = (self.span - self.pending.len()).min(buf.len());
+        self.pending.extend_from_slice(&buf[..n]);
+        if self.pending.len() == self.span {
+            self.flush_frame()?;
+        }
+        Ok(n)
+    }
+
+    /// 只刷新内层 writer；未满一帧的数据要到帧满或 `finish` 时才写出
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl TarImage {
+    /// 打开可寻址格式的 .tar.zst 并随机读取
+    pub fn open_zstd<P: AsRef<Path>>(path: P) -> io::Result<Arc<Mutex<TarImage>>> {
+        let path = path.as_ref();
+        let backend = ZstdSeekableBackend::new(FileBackend::open(path)?)?;
+        TarImage::from_backend(BlockCache::new(backend, CACHE_BLOCK, CACHE_CAPACITY), &path.to_string_lossy())
+    }
+}
@@ -0,0 +1,19 @@
+#![cfg(feature = "tokio")]
+
+mod common;
+
+use common::{temp_dir, write_tar, Fixture};
+use pt::base::{lock_image, ImageInfo, TarImage};
+
+#[tokio::test]
+async fn test_async_read_streams_body() {
+    let body: Vec<u8> = (0..200_000u32).map(|i| (i % 251) as u8).collect();
+    let dir = temp_dir("async_read");
+    let path = write_tar(&dir, "a.tar", &[Fixture::file("big", &body), Fixture::file("next", b"n")]);
+    let img = TarImage::open(&path).unwrap();
+    let file = lock_image(&img).unwrap().find_entry("big").unwrap().unwrap();
+
+    let mut out = Vec::new();
+    tokio::io::copy(&mut file.into_async_read(), &mut out).await.unwrap();
+    assert_eq!(out, body);
+}
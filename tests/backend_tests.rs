@@ -0,0 +1,159 @@
+mod common;
+
+use std::io;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use common::{build_tar, Fixture};
+use pt::backend::{Backend, BlockCache, MemoryBackend};
+use pt::base::{lock_image, TarImage};
+use pt::RetryPolicy;
+
+/// 每次最多返回 100 字节的 backend，模拟网络读取
+struct Chunky(MemoryBackend);
+
+impl Backend for Chunky {
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        let len = buf.len().min(100);
+        self.0.read_at(&mut buf[..len], offset)
+    }
+
+    fn len(&self) -> io::Result<u64> {
+        self.0.len()
+    }
+}
+
+/// 记录读取次数的 backend
+struct Counting(MemoryBackend, Arc<AtomicUsize>);
+
+impl Backend for Counting {
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        self.1.fetch_add(1, Ordering::SeqCst);
+        self.0.read_at(buf, offset)
+    }
+
+    fn len(&self) -> io::Result<u64> {
+        self.0.len()
+    }
+}
+
+/// 先以 kind 失败 failures 次再正常读取的 backend，记录调用次数
+struct Flaky {
+    inner: MemoryBackend,
+    failures: Arc<AtomicUsize>,
+    kind: Arc<std::sync::Mutex<io::ErrorKind>>,
+    calls: Arc<AtomicUsize>,
+}
+
+impl Backend for Flaky {
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        if self.failures.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1)).is_ok() {
+            return Err(io::Error::new(*self.kind.lock().unwrap(), "flaky"));
+        }
+        self.inner.read_at(buf, offset)
+    }
+
+    fn len(&self) -> io::Result<u64> {
+        self.inner.len()
+    }
+}
+
+#[test]
+fn test_image_over_custom_backends() {
+    let tar = build_tar(&[
+        Fixture::dir("etc/"),
+        Fixture::file("etc/hostname", b"box\n"),
+        Fixture::file(&format!("{}/long", "x".repeat(150)), b"long name"),
+    ]);
+
+    let img = TarImage::from_backend(MemoryBackend::new(tar.clone()), "memory").unwrap();
+    let mut img = lock_image(&img).unwrap();
+    let paths: Vec<String> = img.scan().unwrap().entries.into_iter().map(|m| m.path).collect();
+    assert_eq!(paths, ["etc/", "etc/hostname", &format!("{}/long", "x".repeat(150))]);
+    assert_eq!(img.find_entry("etc/hostname").unwrap().unwrap().read_to_string().unwrap(), "box\n");
+    // 不是本地文件，不能原地修改 header
+    let err = img.edit_header(0, |_| Ok(())).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::Unsupported);
+    drop(img);
+
+    let img = TarImage::from_backend(Chunky(MemoryBackend::new(tar)), "chunky").unwrap();
+    let file = lock_image(&img).unwrap().find_entry(&format!("{}/long", "x".repeat(150))).unwrap().unwrap();
+    assert_eq!(file.read_to_vec().unwrap(), b"long name");
+}
+
+/// 关闭预读后扫描并读取一个条目
+fn scan_counted<B: Backend + 'static>(backend: B) {
+    let img = TarImage::from_backend(backend, "counted").unwrap();
+    let mut img = lock_image(&img).unwrap();
+    img.set_readahead(0);
+    assert_eq!(img.scan().unwrap().entries.len(), 32);
+    let file = img.find_entry("f07").unwrap().unwrap();
+    assert_eq!(file.read_to_vec().unwrap(), vec![b'h'; 700]);
+}
+
+#[test]
+fn test_block_cache_coalesces_small_reads() {
+    let names: Vec<String> = (0..32).map(|i| format!("f{:02}", i)).collect();
+    let bodies: Vec<Vec<u8>> = (0..32).map(|i| vec![b'a' + i as u8 % 26; 700]).collect();
+    let fixtures: Vec<Fixture> = names.iter().zip(&bodies).map(|(name, body)| Fixture::file(name, body)).collect();
+    let tar = build_tar(&fixtures);
+    let direct = Arc::new(AtomicUsize::new(0));
+    scan_counted(Counting(MemoryBackend::new(tar.clone()), direct.clone()));
+    let cached = Arc::new(AtomicUsize::new(0));
+    let inner = Counting(MemoryBackend::new(tar.clone()), cached.clone());
+    scan_counted(BlockCache::new(inner, 16 * 1024, 1 << 20));
+    // 整个归档不到 64 KiB，缓存后只需几次块读取
+    assert!(cached.load(Ordering::SeqCst) <= 4, "{} reads", cached.load(Ordering::SeqCst));
+    assert!(direct.load(Ordering::SeqCst) > 32);
+
+    // 容量只有一块时照样能读对
+    let cache = BlockCache::new(MemoryBackend::new(tar.clone()), 512, 0);
+    let mut buf = vec![0u8; tar.len()];
+    let mut done = 0;
+    while done < buf.len() {
+        done += cache.read_at(&mut buf[done..], done as u64).unwrap();
+    }
+    assert_eq!(buf, tar);
+    let (hits, misses) = cache.stats();
+    assert_eq!(hits, 0);
+    assert_eq!(misses as usize, tar.len() / 512);
+}
+
+#[test]
+fn test_retry_policy_on_flaky_backend() {
+    let tar = build_tar(&[Fixture::file("data", b"payload")]);
+    let failures = Arc::new(AtomicUsize::new(0));
+    let kind = Arc::new(std::sync::Mutex::new(io::ErrorKind::WouldBlock));
+    let calls = Arc::new(AtomicUsize::new(0));
+    let backend = Flaky { inner: MemoryBackend::new(tar), failures: failures.clone(), kind: kind.clone(), calls: calls.clone() };
+    let img = TarImage::from_backend(backend, "flaky").unwrap();
+    let mut guard = lock_image(&img).unwrap();
+    guard.set_readahead(0);
+    guard.set_retry_policy(RetryPolicy { max_retries: 3, initial_backoff: Duration::ZERO, max_backoff: Duration::ZERO });
+    let file = guard.find_entry("data").unwrap().unwrap();
+    drop(guard);
+
+    // 预算之内的暂时性错误被重试掉
+    for transient in [io::ErrorKind::WouldBlock, io::ErrorKind::TimedOut] {
+        *kind.lock().unwrap() = transient;
+        failures.store(3, Ordering::SeqCst);
+        calls.store(0, Ordering::SeqCst);
+        assert_eq!(file.read_to_vec().unwrap(), b"payload");
+        assert_eq!(calls.load(Ordering::SeqCst), 4);
+    }
+
+    // 超出预算后返回最后一次的错误
+    *kind.lock().unwrap() = io::ErrorKind::TimedOut;
+    failures.store(4, Ordering::SeqCst);
+    calls.store(0, Ordering::SeqCst);
+    assert_eq!(file.read_to_vec().unwrap_err().kind(), io::ErrorKind::TimedOut);
+    assert_eq!(calls.load(Ordering::SeqCst), 4);
+
+    // 非暂时性错误不重试
+    *kind.lock().unwrap() = io::ErrorKind::PermissionDenied;
+    failures.store(1, Ordering::SeqCst);
+    calls.store(0, Ordering::SeqCst);
+    assert_eq!(file.read_to_vec().unwrap_err().kind(), io::ErrorKind::PermissionDenied);
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+}
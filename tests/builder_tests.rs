@@ -0,0 +1,412 @@
+mod common;
+
+use common::temp_dir;
+use pt::base::{lock_image, try_into_tarfile, ImageInfo, TarImage};
+use pt::builder::TarBuilder;
+
+#[test]
+fn test_builder_roundtrip() {
+    let dir = temp_dir("builder");
+    let path = dir.join("out.tar");
+    let mut builder = TarBuilder::new(std::fs::File::create(&path).unwrap());
+    builder.append_dir("etc", 0o755, 1).unwrap();
+    builder.append_data("etc/os-release", 0o644, 2, b"ID=test\n").unwrap();
+    let long = format!("{}/file", "d".repeat(120));
+    builder.append_data(&long, 0o600, 3, b"x").unwrap();
+    builder.append_symlink("etc/link", "os-release", 4).unwrap();
+    builder.finish().unwrap();
+
+    let img = TarImage::open(path.to_str().unwrap()).unwrap();
+    let mut seen = Vec::new();
+    lock_image(&img).unwrap().for_each_entry(|f| {
+        let f = try_into_tarfile(f)?;
+        seen.push((f.get_path(), f.get_type_flag(), f.get_size(), f.get_link_name()));
+        Ok(())
+    }).unwrap();
+    assert_eq!(seen, [
+        ("etc/".to_string(), '5', 0, String::new()),
+        ("etc/os-release".to_string(), '0', 8, String::new()),
+        (long, '0', 1, String::new()),
+        ("etc/link".to_string(), '2', 0, "os-release".to_string()),
+    ]);
+}
+
+#[test]
+fn test_entry_builder_composes_archive() {
+    use pt::builder::EntryBuilder;
+
+    let entry = EntryBuilder::file("a.txt", b"hello").mode(0o600).owner(1000, 100).mtime(7);
+    let bytes = entry.build().unwrap();
+    assert_eq!(bytes.len(), 1024);
+    assert_eq!(&bytes[512..517], b"hello");
+
+    let path = temp_dir("entry_builder").join("out.tar");
+    let mut builder = TarBuilder::new(std::fs::File::create(&path).unwrap());
+    builder.append(&entry).unwrap();
+    builder.append(&EntryBuilder::symlink("b", "a.txt")).unwrap();
+    builder.finish().unwrap();
+
+    let img = TarImage::open(path.to_str().unwrap()).unwrap();
+    let (file, _) = lock_image(&img).unwrap().get_file_at(0).unwrap();
+    let meta = try_into_tarfile(file).unwrap().meta();
+    assert_eq!((meta.path.as_str(), meta.size, meta.mode, meta.uid, meta.gid, meta.mtime), ("a.txt", 5, 0o600, 1000, 100, 7));
+}
+
+#[test]
+fn test_tar_header_typed_mode() {
+    use pt::mode::{Mode, Perms};
+    use pt::tar::{TarHeader, TypeFlag};
+
+    let mut hdr = TarHeader::new(TypeFlag::Regular);
+    hdr.set_mode(0o4751).unwrap();
+    let mode = TarHeader::from_bytes(&hdr.to_bytes()).get_mode();
+    assert_eq!(mode, Mode::new(0o4751));
+    assert!(mode.is_setuid() && !mode.is_setgid() && !mode.is_sticky());
+    assert!(mode.is_executable());
+    assert_eq!(mode.owner(), Perms { read: true, write: true, execute: true });
+    assert_eq!(mode.group(), Perms { read: true, write: false, execute: true });
+    assert_eq!(mode.other(), Perms { read: false, write: false, execute: true });
+
+    // 带文件类型位的 mode 保留原值，权限部分单独取出
+    let mode = Mode::from(0o100644);
+    assert_eq!(mode.permissions(), 0o644);
+    assert!(!mode.is_executable());
+    assert_eq!(u32::from(mode), 0o100644);
+}
+
+#[test]
+fn test_tar_header_to_bytes() {
+    use pt::tar::{TarHeader, TypeFlag};
+
+    let long = format!("{}/{}", "d".repeat(120), "file.txt");
+    let mut hdr = TarHeader::new(TypeFlag::Regular);
+    hdr.set_path(&long).unwrap();
+    hdr.set_mode(0o640).unwrap();
+    hdr.set_uid(1000).unwrap();
+    hdr.set_size(9 << 30).unwrap();
+    hdr.set_mtime(1_700_000_000).unwrap();
+    hdr.set_uname("alice").unwrap();
+
+    let block = hdr.to_bytes();
+    assert_eq!(&block[257..265], b"ustar\x0000");
+    let parsed = TarHeader::from_bytes(&block);
+    assert!(parsed.crc_ok());
+    assert_eq!(parsed.get_full_path(), long);
+    assert_eq!(parsed.get_mode(), 0o640);
+    assert_eq!(parsed.get_uid(), 1000);
+    assert_eq!(parsed.get_size(), 9 << 30);
+    assert_eq!(parsed.get_mtime(), 1_700_000_000);
+    assert_eq!(parsed.get_uname(), "alice");
+    assert_eq!(parsed.type_flag(), TypeFlag::Regular);
+
+    assert!(hdr.set_path(&"x".repeat(300)).is_err());
+    assert!(hdr.set_mode(0o77777777).is_err());
+}
+
+#[test]
+fn test_header_formats_fall_back_for_long_names() {
+    use pt::builder::{EntryBuilder, HeaderFormat};
+
+    let long_path = format!("{}/{}", "a".repeat(150), "b".repeat(120));
+    let long_link = "t".repeat(130);
+    let read_back = |format: HeaderFormat, uid: u64| {
+        let path = temp_dir(&format!("format_{:?}", format)).join("out.tar");
+        let mut builder = TarBuilder::with_format(std::fs::File::create(&path).unwrap(), format);
+        builder.append(&EntryBuilder::symlink(&long_path, &long_link).owner(uid, 0)).unwrap();
+        builder.finish().unwrap();
+        let img = TarImage::open(path.to_str().unwrap()).unwrap();
+        let file = try_into_tarfile(lock_image(&img).unwrap().get_file_at(0).unwrap().0).unwrap();
+        (file.get_path(), file.get_link_name(), file.get_header().typeflag, std::fs::read(&path).unwrap())
+    };
+
+    let (path, link, _, raw) = read_back(HeaderFormat::Gnu, 1000);
+    assert_eq!((path.as_str(), link.as_str()), (long_path.as_str(), long_link.as_str()));
+    assert_eq!(raw[156], b'L');
+    assert_eq!(&raw[257..265], b"ustar  \0");
+
+    let (path, link, _, raw) = read_back(HeaderFormat::Pax, 1 << 22);
+    assert_eq!((path.as_str(), link.as_str()), (long_path.as_str(), long_link.as_str()));
+    assert_eq!(raw[156], b'x');
+    assert_eq!(pt::pax::pax_u64(&pt::pax::parse_pax_records(&raw[512..1024]).unwrap(), "uid"), Some(1 << 22));
+
+    let entry = EntryBuilder::file(&long_path, b"x");
+    assert!(entry.build_as(HeaderFormat::Ustar).is_err());
+    assert!(entry.build_as(HeaderFormat::V7).is_err());
+    let v7 = EntryBuilder::file("short", b"x").build_as(HeaderFormat::V7).unwrap();
+    assert!(v7[257..265].iter().all(|&b| b == 0));
+    assert!(pt::tar::TarHeader::from_bytes(v7[..512].try_into().unwrap()).crc_ok());
+}
+
+#[test]
+fn test_large_uid_gid_roundtrip() {
+    use pt::builder::{EntryBuilder, HeaderFormat};
+
+    let (uid, gid) = (4_000_000_000u64, 1u64 << 40);
+    for format in [HeaderFormat::Gnu, HeaderFormat::Pax] {
+        let path = temp_dir(&format!("large_uid_{:?}", format)).join("out.tar");
+        let mut builder = TarBuilder::with_format(std::fs::File::create(&path).unwrap(), format);
+        builder.append(&EntryBuilder::file("f", b"x").owner(uid, gid)).unwrap();
+        builder.finish().unwrap();
+        let img = TarImage::open(path.to_str().unwrap()).unwrap();
+        let meta = lock_image(&img).unwrap().scan().unwrap();
+        assert!(meta.warnings.is_empty(), "{:?}", meta.warnings);
+        assert_eq!((meta.entries[0].uid, meta.entries[0].gid), (uid, gid), "{:?}", format);
+    }
+
+    // GNU 输出直接在 header 中使用 base-256
+    let raw = EntryBuilder::file("f", b"").owner(uid, 0).build_as(HeaderFormat::Gnu).unwrap();
+    assert_eq!(raw[108], 0x80);
+    assert_eq!(pt::tar::TarHeader::from_bytes(raw[..512].try_into().unwrap()).get_uid(), uid);
+    assert!(EntryBuilder::file("f", b"").owner(uid, 0).build_as(HeaderFormat::Ustar).is_err());
+}
+
+#[test]
+fn test_negative_mtime_roundtrip() {
+    use pt::builder::{EntryBuilder, HeaderFormat};
+    use std::time::{Duration, UNIX_EPOCH};
+
+    // 1960 年：PAX 写 mtime 记录，GNU 用 base-256，ustar 表示不了
+    let past = -315_619_200;
+    for format in [HeaderFormat::Pax, HeaderFormat::Gnu] {
+        let mut builder = TarBuilder::with_format(Vec::new(), format);
+        builder.append(&EntryBuilder::file("old", b"x").mtime(past)).unwrap();
+        builder.append_dir("dir/", 0o755, -1).unwrap();
+        let bytes = builder.finish().unwrap();
+        let path = temp_dir("negative_mtime").join("a.tar");
+        std::fs::write(&path, bytes).unwrap();
+        let img = TarImage::open(path.to_str().unwrap()).unwrap();
+        let entries = lock_image(&img).unwrap().scan().unwrap().entries;
+        let times: Vec<_> = entries.iter().map(|m| (m.path.as_str(), m.mtime)).collect();
+        assert_eq!(times, [("old", past), ("dir/", -1)], "{:?}", format);
+        assert_eq!(entries[0].modified(), UNIX_EPOCH - Duration::from_secs(315_619_200));
+    }
+    assert!(EntryBuilder::file("old", b"").mtime(past).build_as(HeaderFormat::Ustar).is_err());
+}
+
+#[test]
+fn test_deterministic_builds_are_identical() {
+    use pt::builder::{EntryBuilder, RECORD_SIZE};
+
+    let build = |uid: u64, mtime: i64| {
+        let mut builder = TarBuilder::new(Vec::new()).deterministic_at(1_700_000_000);
+        builder.append(&EntryBuilder::file("a", b"same").owner(uid, uid).mtime(mtime)).unwrap();
+        builder.append_dir("d", 0o755, mtime).unwrap();
+        builder.finish().unwrap()
+    };
+    let first = build(1000, 1_800_000_000);
+    assert_eq!(first, build(0, 1_900_000_000));
+    assert_eq!(first.len() as u64, RECORD_SIZE);
+    let hdr = pt::tar::TarHeader::from_bytes(first[..512].try_into().unwrap());
+    assert_eq!((hdr.get_uid(), hdr.get_mtime()), (0, 1_700_000_000));
+    // 早于上限的 mtime 保持不变
+    assert_ne!(first, build(0, 1_600_000_000));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_deterministic_tree_ignores_owner_and_creation_order() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = temp_dir("deterministic_tree");
+    let make = |name: &str, names: &[&str]| {
+        let root = dir.join(name);
+        std::fs::create_dir_all(&root).unwrap();
+        for n in names {
+            std::fs::write(root.join(n), n.as_bytes()).unwrap();
+            std::fs::set_permissions(root.join(n), std::fs::Permissions::from_mode(0o644)).unwrap();
+        }
+        std::fs::set_permissions(&root, std::fs::Permissions::from_mode(0o755)).unwrap();
+        root
+    };
+    let first = make("first", &["a", "b", "c", "d"]);
+    let second = make("second", &["d", "c", "b", "a"]);
+    // 以 root 运行时再给第二棵树换一个属主
+    if pt::sys::is_root() {
+        for n in ["a", "c"] {
+            std::os::unix::fs::lchown(second.join(n), Some(1234), Some(1234)).unwrap();
+        }
+    }
+    let build = |src: &std::path::Path| {
+        let mut builder = TarBuilder::new(Vec::new()).deterministic_at(1_000_000);
+        builder.append_dir_all(src, "tree").unwrap();
+        builder.finish().unwrap()
+    };
+    let bytes = build(&first);
+    assert_eq!(bytes, build(&second));
+    let hdr = pt::tar::TarHeader::from_bytes(bytes[512..1024].try_into().unwrap());
+    assert_eq!((hdr.get_uid(), hdr.get_gid(), hdr.get_uname(), hdr.get_gname()), (0, 0, String::new(), String::new()));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_append_dir_all_preserves_tree() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = temp_dir("append_dir_all");
+    let src = dir.join("src");
+    std::fs::create_dir_all(src.join("empty")).unwrap();
+    std::fs::create_dir_all(src.join("sub")).unwrap();
+    std::fs::write(src.join("sub/run.sh"), b"#!/bin/sh").unwrap();
+    std::fs::set_permissions(src.join("sub/run.sh"), std::fs::Permissions::from_mode(0o750)).unwrap();
+    std::os::unix::fs::symlink("sub/run.sh", src.join("link")).unwrap();
+    std::os::unix::net::UnixListener::bind(src.join("sock")).unwrap();
+    let fifo = std::ffi::CString::new(src.join("pipe").to_str().unwrap()).unwrap();
+    assert_eq!(unsafe { libc::mkfifo(fifo.as_ptr(), 0o644) }, 0);
+
+    let out = dir.join("out.tar");
+    let mut builder = TarBuilder::new(std::fs::File::create(&out).unwrap());
+    builder.append_dir_all(&src, "root").unwrap();
+    builder.finish().unwrap();
+
+    let img = TarImage::open(out.to_str().unwrap()).unwrap();
+    let entries = lock_image(&img).unwrap().scan().unwrap().entries;
+    let listing: Vec<(char, &str)> = entries.iter().map(|m| (m.type_flag, m.path.as_str())).collect();
+    assert_eq!(listing, [
+        ('5', "root/"),
+        ('5', "root/empty/"),
+        ('2', "root/link"),
+        ('6', "root/pipe"),
+        ('5', "root/sub/"),
+        ('0', "root/sub/run.sh"),
+    ]);
+    assert_eq!(entries[2].link_name, "sub/run.sh");
+    assert_eq!(entries[5].mode, 0o750);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_append_path_records_owner() {
+    use std::os::unix::fs::MetadataExt;
+
+    let dir = temp_dir("append_path_owner");
+    let src = dir.join("file.txt");
+    std::fs::write(&src, b"owned").unwrap();
+    std::fs::create_dir_all(dir.join("sub")).unwrap();
+    let mut builder = TarBuilder::new(Vec::new());
+    builder.append_path("file.txt", &src).unwrap();
+    builder.append_path("sub", &dir.join("sub")).unwrap();
+    let bytes = builder.finish().unwrap();
+
+    let md = std::fs::metadata(&src).unwrap();
+    let uname = pt::sys::user_name(md.uid()).unwrap_or_default();
+    let gname = pt::sys::group_name(md.gid()).unwrap_or_default();
+    let path = dir.join("out.tar");
+    std::fs::write(&path, bytes).unwrap();
+    let img = TarImage::open(path.to_str().unwrap()).unwrap();
+    for meta in lock_image(&img).unwrap().scan().unwrap().entries {
+        assert_eq!((meta.uid, meta.gid), (md.uid() as u64, md.gid() as u64), "{}", meta.path);
+        assert_eq!((meta.uname.as_str(), meta.gname.as_str()), (uname.as_str(), gname.as_str()));
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn test_append_dir_all_symlink_policies() {
+    use pt::builder::SymlinkPolicy;
+
+    let dir = temp_dir("symlink_policies");
+    let src = dir.join("src");
+    std::fs::create_dir_all(src.join("data")).unwrap();
+    std::fs::write(src.join("data/file.txt"), b"payload").unwrap();
+    std::os::unix::fs::symlink("data/file.txt", src.join("file_link")).unwrap();
+    std::os::unix::fs::symlink("data", src.join("dir_link")).unwrap();
+
+    let listing = |policy: SymlinkPolicy| -> std::io::Result<Vec<(char, String)>> {
+        let out = dir.join("out.tar");
+        let mut builder = TarBuilder::new(std::fs::File::create(&out)?).symlinks(policy);
+        builder.append_dir_all(&src, "root")?;
+        builder.finish()?;
+        let img = TarImage::open(out.to_str().unwrap())?;
+        let entries = lock_image(&img)?.scan()?.entries;
+        Ok(entries.into_iter().map(|m| (m.type_flag, m.path)).collect())
+    };
+    let entry = |flag: char, path: &str| (flag, path.to_string());
+
+    assert_eq!(listing(SymlinkPolicy::Preserve).unwrap(), [
+        entry('5', "root/"),
+        entry('5', "root/data/"),
+        entry('0', "root/data/file.txt"),
+        entry('2', "root/dir_link"),
+        entry('2', "root/file_link"),
+    ]);
+    assert_eq!(listing(SymlinkPolicy::Follow).unwrap(), [
+        entry('5', "root/"),
+        entry('5', "root/data/"),
+        entry('0', "root/data/file.txt"),
+        entry('5', "root/dir_link/"),
+        entry('0', "root/dir_link/file.txt"),
+        entry('0', "root/file_link"),
+    ]);
+    assert!(listing(SymlinkPolicy::DenyDangling).is_ok());
+
+    // 悬空链接：Follow 时按链接保存，DenyDangling 报错
+    std::os::unix::fs::symlink("missing", src.join("zz_dangling")).unwrap();
+    assert_eq!(listing(SymlinkPolicy::Follow).unwrap().last(), Some(&entry('2', "root/zz_dangling")));
+    let err = listing(SymlinkPolicy::DenyDangling).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+
+    // 指向上层目录的链接在跟随时视为循环
+    std::fs::remove_file(src.join("zz_dangling")).unwrap();
+    std::os::unix::fs::symlink("..", src.join("data/up")).unwrap();
+    assert_eq!(listing(SymlinkPolicy::Follow).unwrap_err().kind(), std::io::ErrorKind::InvalidData);
+    assert!(listing(SymlinkPolicy::Preserve).is_ok());
+}
+
+#[test]
+fn test_append_dir_all_exclude_and_tarignore() {
+    let dir = temp_dir("exclude_tarignore");
+    let src = dir.join("src");
+    for sub in ["target/debug", ".git", "src/gen", "docs"] {
+        std::fs::create_dir_all(src.join(sub)).unwrap();
+    }
+    for file in ["Cargo.toml", "target/debug/app", ".git/HEAD", "src/main.rs", "src/main.o", "src/gen/out.rs", "docs/target"] {
+        std::fs::write(src.join(file), b"x").unwrap();
+    }
+    std::fs::write(src.join("src/.tarignore"), b"# generated\n\ngen/\n").unwrap();
+
+    let listing = |builder: TarBuilder<std::fs::File>| -> Vec<String> {
+        let mut builder = builder;
+        builder.append_dir_all(&src, "").unwrap();
+        builder.finish().unwrap();
+        let img = TarImage::open(dir.join("out.tar").to_str().unwrap()).unwrap();
+        let entries = lock_image(&img).unwrap().scan().unwrap().entries;
+        entries.into_iter().map(|m| m.path).collect()
+    };
+    let create = || TarBuilder::new(std::fs::File::create(dir.join("out.tar")).unwrap());
+
+    // `target/` 只匹配目录，docs/target 是普通文件因此保留
+    let paths = listing(create().exclude(&["target/", ".git", "*.o"]));
+    assert_eq!(paths, ["Cargo.toml", "docs/", "docs/target", "src/", "src/.tarignore", "src/gen/", "src/gen/out.rs", "src/main.rs"]);
+
+    let paths = listing(create().exclude(&["target/", ".git", "*.o"]).tarignore(true));
+    assert_eq!(paths, ["Cargo.toml", "docs/", "docs/target", "src/", "src/.tarignore", "src/main.rs"]);
+
+    // 含 '/' 的模式相对 src_dir 匹配
+    let paths = listing(create().exclude(&["src/*.rs", "target", ".git", "docs"]));
+    assert_eq!(paths, ["Cargo.toml", "src/", "src/.tarignore", "src/gen/", "src/gen/out.rs", "src/main.o"]);
+}
+
+#[test]
+fn test_long_multibyte_path_does_not_split_inside_char() {
+    use pt::builder::{EntryBuilder, HeaderFormat};
+
+    // 第 156 字节落在 "中" 的中间
+    let flat = format!("a{}", "中".repeat(70));
+    assert!(TarBuilder::new(Vec::new()).append_data(&flat, 0o644, 0, b"x").is_ok());
+    let nested = format!("{}/{}", "中".repeat(40), "文".repeat(20));
+    for format in [HeaderFormat::Ustar, HeaderFormat::Pax] {
+        // 在 '/' 处拆到 prefix 即可放下，不需要扩展 header
+        let data = EntryBuilder::file(&nested, b"x").build_as(format).unwrap();
+        assert_eq!(data[156], b'0');
+    }
+    assert!(EntryBuilder::file(&flat, b"x").build_as(HeaderFormat::Ustar).is_err());
+    for format in [HeaderFormat::Pax, HeaderFormat::Gnu] {
+        let path = temp_dir(&format!("cjk_{:?}", format)).join("out.tar");
+        let mut builder = TarBuilder::with_format(std::fs::File::create(&path).unwrap(), format);
+        builder.append(&EntryBuilder::file(&flat, b"x")).unwrap();
+        builder.finish().unwrap();
+        let img = TarImage::open(path.to_str().unwrap()).unwrap();
+        let file = try_into_tarfile(lock_image(&img).unwrap().get_file_at(0).unwrap().0).unwrap();
+        assert_eq!(file.get_path(), flat);
+    }
+}
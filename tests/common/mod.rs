@@ -0,0 +1,92 @@
+#![allow(dead_code)]
+
+use std::path::PathBuf;
+use pt::builder::EntryBuilder;
+
+/// 测试用的条目描述
+pub struct Fixture<'a> {
+    pub name: &'a str,
+    pub type_flag: u8,
+    pub mode: u32,
+    pub mtime: i64,
+    pub uid: u64,
+    pub gid: u64,
+    pub link: &'a str,
+    pub body: &'a [u8],
+}
+
+impl<'a> Fixture<'a> {
+    pub fn file(name: &'a str, body: &'a [u8]) -> Self {
+        Fixture { name, type_flag: b'0', mode: 0o644, mtime: 1_600_000_000, uid: 0, gid: 0, link: "", body }
+    }
+
+    pub fn dir(name: &'a str) -> Self {
+        Fixture { name, type_flag: b'5', mode: 0o755, mtime: 1_600_000_000, uid: 0, gid: 0, link: "", body: b"" }
+    }
+
+    pub fn symlink(name: &'a str, link: &'a str) -> Self {
+        Fixture { name, type_flag: b'2', mode: 0o777, mtime: 1_600_000_000, uid: 0, gid: 0, link, body: b"" }
+    }
+}
+
+impl Fixture<'_> {
+    fn entry(&self) -> EntryBuilder {
+        EntryBuilder::new(self.name, self.type_flag)
+            .mode(self.mode)
+            .owner(self.uid, self.gid)
+            .mtime(self.mtime)
+            .link_name(self.link)
+            .data(self.body)
+    }
+}
+
+/// 生成单个 ustar header 块
+pub fn header_block(f: &Fixture) -> [u8; 512] {
+    f.entry().header().unwrap()
+}
+
+/// 修改 header 字节后重新计算 checksum
+pub fn fix_checksum(b: &mut [u8]) {
+    b[148..156].copy_from_slice(b"        ");
+    let sum: u32 = b[..512].iter().map(|&x| x as u32).sum();
+    let chk = format!("{:06o}\0 ", sum);
+    b[148..156].copy_from_slice(chk.as_bytes());
+}
+
+/// 按条目生成完整的 tar 字节流（含结尾的两个全零块）
+pub fn build_tar(entries: &[Fixture]) -> Vec<u8> {
+    let mut out = Vec::new();
+    for f in entries {
+        out.extend_from_slice(&f.entry().build().unwrap());
+    }
+    out.extend(std::iter::repeat_n(0u8, 1024));
+    out
+}
+
+/// 测试专用的临时目录，按测试名区分
+pub fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("pt_test_{}_{}", std::process::id(), name));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// 把 tar 写入临时目录并返回路径
+pub fn write_tar(dir: &std::path::Path, file_name: &str, entries: &[Fixture]) -> String {
+    let path = dir.join(file_name);
+    std::fs::write(&path, build_tar(entries)).unwrap();
+    path.to_string_lossy().into_owned()
+}
+
+/// 生成一条 PAX 记录 "<长度> <键>=<值>\n"
+pub fn pax_record(key: &str, value: &[u8]) -> Vec<u8> {
+    let body_len = key.len() + value.len() + 3;
+    let mut len = body_len + 1;
+    while format!("{}", len).len() + body_len != len {
+        len += 1;
+    }
+    let mut out = format!("{} {}=", len, key).into_bytes();
+    out.extend_from_slice(value);
+    out.push(b'\n');
+    out
+}
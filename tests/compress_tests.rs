@@ -0,0 +1,33 @@
+#![cfg(feature = "gzip")]
+mod common;
+
+use std::io::{Read, Write};
+use common::{temp_dir, write_tar, Fixture};
+use pt::base::{lock_image, try_into_tarfile, ImageInfo, TarImage};
+use pt::compress::Compression;
+
+#[test]
+fn test_decompressed_reader_gzip_member() {
+    let mut enc = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    enc.write_all(b"log line 1\nlog line 2\n").unwrap();
+    let gz = enc.finish().unwrap();
+
+    let dir = temp_dir("compress");
+    let path = write_tar(&dir, "a.tar", &[
+        Fixture::file("var/log/app.log.gz", &gz),
+        Fixture::file("plain.txt", b"plain"),
+    ]);
+    let img = TarImage::open(&path).unwrap();
+    let mut out = Vec::new();
+    lock_image(&img).unwrap().for_each_entry(|file| {
+        let f = try_into_tarfile(file)?;
+        let mut text = String::new();
+        f.decompressed_reader()?.read_to_string(&mut text)?;
+        out.push((f.compression()?, text));
+        Ok(())
+    }).unwrap();
+    assert_eq!(out, [
+        (Compression::Gzip, "log line 1\nlog line 2\n".to_string()),
+        (Compression::None, "plain".to_string()),
+    ]);
+}
@@ -0,0 +1,73 @@
+mod common;
+
+use common::{temp_dir, write_tar, Fixture};
+use pt::diff::{diff_paths, Change};
+
+#[test]
+fn test_diff_reports_added_removed_modified() {
+    let dir = temp_dir("diff");
+    let a = write_tar(&dir, "a.tar", &[
+        Fixture::dir("etc/"),
+        Fixture::file("etc/hosts", b"127.0.0.1 localhost\n"),
+        Fixture::file("etc/removed", b"bye"),
+        Fixture::file("etc/same", b"same"),
+    ]);
+    let mut changed = Fixture::file("etc/hosts", b"127.0.0.2 localhost\n");
+    changed.mode = 0o600;
+    let b = write_tar(&dir, "b.tar", &[
+        Fixture::dir("etc/"),
+        changed,
+        Fixture::file("etc/same", b"same"),
+        Fixture::file("etc/added", b"hi"),
+    ]);
+
+    let d = diff_paths(&a, &b).unwrap();
+    assert_eq!(d.added.iter().map(|m| m.path.as_str()).collect::<Vec<_>>(), ["etc/added"]);
+    assert_eq!(d.removed.iter().map(|m| m.path.as_str()).collect::<Vec<_>>(), ["etc/removed"]);
+    assert_eq!(d.modified.len(), 1);
+    assert_eq!(d.modified[0].path, "etc/hosts");
+    assert_eq!(d.modified[0].changes, [Change::Mode, Change::Content]);
+
+    assert!(diff_paths(&a, &a).unwrap().is_empty());
+}
+
+#[test]
+fn test_compare_with_filesystem() {
+    use pt::base::{lock_image, ImageInfo, TarImage};
+    use pt::extract::{extract_all_with, ExtractOptions};
+
+    let dir = temp_dir("compare_with");
+    let path = write_tar(&dir, "a.tar", &[
+        Fixture::dir("etc/"),
+        Fixture::file("etc/hosts", b"127.0.0.1 localhost\n"),
+        Fixture::file("etc/motd", b"hello"),
+        Fixture::file("etc/gone", b"bye"),
+        Fixture::symlink("etc/link", "hosts"),
+    ]);
+    let img = TarImage::open(&path).unwrap();
+    let mut img = lock_image(&img).unwrap();
+    let out = dir.join("out");
+    let options = ExtractOptions { preserve_times: true, ..Default::default() };
+    extract_all_with(&mut img, &out, &options).unwrap();
+    assert!(img.compare_with(&out).unwrap().is_empty());
+
+    // 同样大小的不同内容只有计算哈希时才能发现
+    std::fs::write(out.join("etc/hosts"), b"127.0.0.2 localhost\n").unwrap();
+    std::fs::write(out.join("etc/motd"), b"hello, world").unwrap();
+    std::fs::remove_file(out.join("etc/gone")).unwrap();
+    std::fs::remove_file(out.join("etc/link")).unwrap();
+    std::fs::write(out.join("etc/link"), b"").unwrap();
+
+    let report = img.compare_with(&out).unwrap();
+    assert_eq!(report.missing, ["etc/gone"]);
+    let changes: Vec<(&str, &[Change])> = report.differences.iter().map(|d| (d.path.as_str(), &d.changes[..])).collect();
+    assert_eq!(changes, [
+        ("etc/", &[Change::Mtime][..]),
+        ("etc/hosts", &[Change::Mtime, Change::Content][..]),
+        ("etc/link", &[Change::Type][..]),
+        ("etc/motd", &[Change::Size, Change::Mtime][..]),
+    ]);
+
+    let report = img.compare_with_options(&out, false).unwrap();
+    assert_eq!(report.differences[1].changes, [Change::Mtime]);
+}
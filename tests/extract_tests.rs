@@ -0,0 +1,575 @@
+mod common;
+
+use common::{temp_dir, write_tar, Fixture};
+use pt::base::{lock_image, ImageInfo, TarImage};
+use pt::extract::{extract_file, extract_file_to};
+
+#[test]
+fn test_extract_single_file() {
+    let dir = temp_dir("extract_file");
+    let mut exe = Fixture::file("./usr/bin/tool", b"#!/bin/sh\n");
+    exe.mode = 0o750;
+    exe.mtime = 1_000_000;
+    let path = write_tar(&dir, "a.tar", &[
+        Fixture::file("etc/os-release", b"ID=old\n"),
+        exe,
+        Fixture::file("etc/os-release", b"ID=new\n"),
+    ]);
+    let img = TarImage::open(&path).unwrap();
+    let mut img = lock_image(&img).unwrap();
+
+    let mut buf = Vec::new();
+    extract_file_to(&mut img, "/etc/os-release", &mut buf).unwrap();
+    assert_eq!(buf, b"ID=new\n");
+
+    let target = dir.join("out/tool");
+    let meta = extract_file(&mut img, "usr/bin/tool", &target).unwrap();
+    assert_eq!(meta.size, 10);
+    let md = std::fs::metadata(&target).unwrap();
+    assert_eq!(md.modified().unwrap(), std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_000_000));
+    #[cfg(unix)]
+    assert_eq!(std::os::unix::fs::PermissionsExt::mode(&md.permissions()) & 0o777, 0o750);
+
+    assert_eq!(extract_file(&mut img, "missing", &target).unwrap_err().kind(), std::io::ErrorKind::NotFound);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_extract_all_preserves_permissions() {
+    use std::os::unix::fs::PermissionsExt;
+    use pt::extract::{extract_all_with, ExtractOptions};
+
+    let dir = temp_dir("extract_perms");
+    let mut ro = Fixture::dir("ro/");
+    ro.mode = 0o555;
+    let mut suid = Fixture::file("ro/su", b"x");
+    suid.mode = 0o4755;
+    let path = write_tar(&dir, "a.tar", &[ro, suid]);
+    let img = TarImage::open(&path).unwrap();
+    let mode = |p: &str| std::fs::metadata(p).unwrap().permissions().mode() & 0o7777;
+
+    let out = dir.join("kept");
+    extract_all_with(&mut lock_image(&img).unwrap(), &out, &ExtractOptions::default()).unwrap();
+    assert_eq!(mode(out.join("ro").to_str().unwrap()), 0o555);
+    assert_eq!(mode(out.join("ro/su").to_str().unwrap()), 0o4755);
+
+    let out = dir.join("plain");
+    let options = ExtractOptions { preserve_permissions: false, ..Default::default() };
+    extract_all_with(&mut lock_image(&img).unwrap(), &out, &options).unwrap();
+    assert_eq!(mode(out.join("ro/su").to_str().unwrap()) & 0o4000, 0);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_extract_all_preserves_ownership_as_root() {
+    use std::os::unix::fs::MetadataExt;
+    use pt::extract::{extract_all_with, ExtractOptions};
+
+    if !pt::sys::is_root() {
+        return;
+    }
+    let dir = temp_dir("extract_owner");
+    let path = write_tar(&dir, "a.tar", &[Fixture { uid: 1234, gid: 4321, ..Fixture::file("f", b"x") }]);
+    let img = TarImage::open(&path).unwrap();
+    let out = dir.join("out");
+    let options = ExtractOptions { preserve_ownership: true, ..Default::default() };
+    extract_all_with(&mut lock_image(&img).unwrap(), &out, &options).unwrap();
+    let md = std::fs::metadata(out.join("f")).unwrap();
+    assert_eq!((md.uid(), md.gid()), (1234, 4321));
+}
+
+#[test]
+fn test_case_insensitive_collisions() {
+    use pt::collision::CollisionPolicy;
+    use pt::extract::{extract_all_with, ExtractOptions};
+
+    let dir = temp_dir("collisions");
+    let path = write_tar(&dir, "a.tar", &[
+        Fixture::dir("Docs/"),
+        Fixture::file("Docs/readme", b"lower"),
+        Fixture::dir("DOCS/"),
+        Fixture::file("DOCS/README", b"upper"),
+    ]);
+    let img = TarImage::open(&path).unwrap();
+
+    let options = ExtractOptions { case_insensitive: true, ..Default::default() };
+    let err = extract_all_with(&mut lock_image(&img).unwrap(), &dir.join("err"), &options).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::AlreadyExists);
+
+    let out = dir.join("renamed");
+    let options = ExtractOptions { case_insensitive: true, collision_policy: CollisionPolicy::Rename, ..Default::default() };
+    extract_all_with(&mut lock_image(&img).unwrap(), &out, &options).unwrap();
+    assert_eq!(std::fs::read(out.join("Docs/readme")).unwrap(), b"lower");
+    assert_eq!(std::fs::read(out.join("DOCS~1/README")).unwrap(), b"upper");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_extract_fifo() {
+    use std::os::unix::fs::FileTypeExt;
+    use pt::extract::extract_all;
+
+    let dir = temp_dir("extract_fifo");
+    // size 字段非零也不应有数据块
+    let mut bytes = common::build_tar(&[
+        Fixture { type_flag: b'6', ..Fixture::file("run/pipe", b"") },
+        Fixture::file("after", b"ok"),
+    ]);
+    bytes[124..136].copy_from_slice(b"00000001000\0");
+    common::fix_checksum(&mut bytes[..512]);
+    let path = dir.join("a.tar");
+    std::fs::write(&path, bytes).unwrap();
+
+    let img = TarImage::open(path.to_str().unwrap()).unwrap();
+    let out = dir.join("out");
+    extract_all(&mut lock_image(&img).unwrap(), &out).unwrap();
+    assert!(std::fs::symlink_metadata(out.join("run/pipe")).unwrap().file_type().is_fifo());
+    assert_eq!(std::fs::read(out.join("after")).unwrap(), b"ok");
+}
+
+#[test]
+fn test_extract_reports_progress() {
+    use pt::extract::{extract_all_with_progress, ExtractOptions};
+    use pt::progress::ProgressInfo;
+
+    let dir = temp_dir("extract_progress");
+    let path = write_tar(&dir, "a.tar", &[Fixture::dir("d/"), Fixture::file("d/a", &[7u8; 1000]), Fixture::file("d/b", b"b")]);
+    let img = TarImage::open(&path).unwrap();
+    let mut seen = Vec::new();
+    let mut progress = |info: &ProgressInfo<'_>| seen.push((info.entries, info.bytes, info.total_bytes, info.path.to_string()));
+    extract_all_with_progress(&mut lock_image(&img).unwrap(), &dir.join("out"), &ExtractOptions::default(), &mut progress).unwrap();
+
+    let total = std::fs::metadata(&path).unwrap().len();
+    assert_eq!(seen, vec![
+        (1, 512, total, "d/".to_string()),
+        (2, 512 + 512 + 1024, total, "d/a".to_string()),
+        (3, 512 + 512 + 1024 + 1024, total, "d/b".to_string()),
+    ]);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_extract_all_rejects_path_traversal() {
+    use pt::error::{as_pt_error, PtError};
+    use pt::extract::{extract_all, extract_all_with, ExtractOptions};
+
+    let dir = temp_dir("extract_slip");
+    let outside = dir.join("outside");
+    std::fs::create_dir_all(&outside).unwrap();
+
+    let path = write_tar(&dir, "dotdot.tar", &[Fixture::file("ok", b"ok"), Fixture::file("../outside/a", b"x")]);
+    let img = TarImage::open(&path).unwrap();
+    let err = extract_all(&mut lock_image(&img).unwrap(), &dir.join("out1")).unwrap_err();
+    assert!(matches!(as_pt_error(&err), Some(PtError::UnsafePath { .. })));
+    assert!(!outside.join("a").exists());
+
+    // 先放一个指向目录外的符号链接，再经由它写文件
+    let link = Fixture::symlink("evil", outside.to_str().unwrap());
+    let path = write_tar(&dir, "symlink.tar", &[link, Fixture::file("evil/b", b"x")]);
+    let img = TarImage::open(&path).unwrap();
+    let err = extract_all(&mut lock_image(&img).unwrap(), &dir.join("out2")).unwrap_err();
+    assert!(matches!(as_pt_error(&err), Some(PtError::UnsafePath { .. })));
+    assert!(!outside.join("b").exists());
+
+    // 指向目录内的链接照常使用
+    let path = write_tar(&dir, "inner.tar", &[Fixture::dir("real/"), Fixture::symlink("alias", "real"), Fixture::file("alias/c", b"c")]);
+    let img = TarImage::open(&path).unwrap();
+    extract_all(&mut lock_image(&img).unwrap(), &dir.join("out3")).unwrap();
+    assert_eq!(std::fs::read(dir.join("out3/real/c")).unwrap(), b"c");
+
+    let options = ExtractOptions { allow_unsafe_paths: true, ..Default::default() };
+    extract_all_with(&mut lock_image(&img).unwrap(), &dir.join("out4"), &options).unwrap();
+    let img = TarImage::open(&dir.join("symlink.tar").to_string_lossy()).unwrap();
+    extract_all_with(&mut lock_image(&img).unwrap(), &dir.join("out5"), &options).unwrap();
+    assert_eq!(std::fs::read(outside.join("b")).unwrap(), b"x");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_extract_restores_nanosecond_times() {
+    use std::time::{Duration, UNIX_EPOCH};
+    use pt::extract::{extract_all_with, ExtractOptions};
+
+    let mut records = common::pax_record("mtime", b"1600000000.123456789");
+    records.extend(common::pax_record("atime", b"1500000000.5"));
+    records.extend(common::pax_record("ctime", b"1600000001"));
+    let dir = temp_dir("extract_times");
+    let path = write_tar(&dir, "a.tar", &[
+        Fixture::dir("d/"),
+        Fixture { type_flag: b'x', ..Fixture::file("PaxHeaders/f", &records) },
+        Fixture::file("d/f", b"x"),
+    ]);
+    let img = TarImage::open(&path).unwrap();
+    let meta = lock_image(&img).unwrap().find_entry("d/f").unwrap().unwrap().meta();
+    assert_eq!(meta.mtime_precise(), Duration::new(1_600_000_000, 123_456_789));
+    assert_eq!(meta.atime, Some((1_500_000_000, 500_000_000)));
+    assert_eq!(meta.changed(), Some(UNIX_EPOCH + Duration::from_secs(1_600_000_001)));
+
+    let out = dir.join("out");
+    let options = ExtractOptions { preserve_times: true, ..Default::default() };
+    extract_all_with(&mut lock_image(&img).unwrap(), &out, &options).unwrap();
+    let md = std::fs::metadata(out.join("d/f")).unwrap();
+    assert_eq!(md.modified().unwrap(), meta.modified());
+    assert_eq!(md.accessed().unwrap(), meta.accessed().unwrap());
+    // 目录的 mtime 在内容写完后设置
+    assert_eq!(std::fs::metadata(out.join("d")).unwrap().modified().unwrap(), UNIX_EPOCH + Duration::from_secs(1_600_000_000));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_extract_remaps_ownership_with_id_map() {
+    use std::os::unix::fs::MetadataExt;
+    use pt::extract::{extract_all_with, ExtractOptions};
+    use pt::idmap::{IdMap, IdRange};
+
+    let map = IdMap::parse("0 100000 65536\n", "0 200000 1000\n1000 300000 10").unwrap();
+    assert_eq!(map.map_uid(1234).unwrap(), 101234);
+    assert_eq!(map.map_gid(1005).unwrap(), 300005);
+    assert!(map.map_gid(1010).is_err());
+    assert_eq!(IdMap::shift(100000, 65536).uids, [IdRange { inner: 0, outer: 100000, count: 65536 }]);
+    assert!(IdMap::parse("0 100000", "").is_err());
+
+    if !pt::sys::is_root() {
+        return;
+    }
+    let dir = temp_dir("extract_id_map");
+    let path = write_tar(&dir, "a.tar", &[
+        Fixture { uid: 0, gid: 0, ..Fixture::dir("d/") },
+        Fixture { uid: 1234, gid: 1005, ..Fixture::file("d/f", b"x") },
+    ]);
+    let img = TarImage::open(&path).unwrap();
+    let out = dir.join("out");
+    let options = ExtractOptions { preserve_ownership: true, id_map: Some(map.clone()), ..Default::default() };
+    extract_all_with(&mut lock_image(&img).unwrap(), &out, &options).unwrap();
+    let owner = |p: &str| {
+        let md = std::fs::symlink_metadata(out.join(p)).unwrap();
+        (md.uid(), md.gid())
+    };
+    assert_eq!(owner("d"), (100000, 200000));
+    assert_eq!(owner("d/f"), (101234, 300005));
+
+    let path = write_tar(&dir, "b.tar", &[Fixture { uid: 0, gid: 2000, ..Fixture::file("g", b"x") }]);
+    let img = TarImage::open(&path).unwrap();
+    let err = extract_all_with(&mut lock_image(&img).unwrap(), &dir.join("out2"), &options).unwrap_err();
+    assert!(err.to_string().contains("gid 2000 is not mapped"), "{}", err);
+}
+
+/// 只实现 Read、每次最多返回 7 字节的 reader，模拟管道
+struct Trickle<'a>(&'a [u8]);
+
+impl std::io::Read for Trickle<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = buf.len().min(7).min(self.0.len());
+        buf[..n].copy_from_slice(&self.0[..n]);
+        self.0 = &self.0[n..];
+        Ok(n)
+    }
+}
+
+#[test]
+fn test_unpack_stream_without_seeking() {
+    use common::build_tar;
+    use pt::extract::{unpack_stream, ExtractOptions};
+
+    let long_name = format!("deep/{}/file.txt", "d".repeat(120));
+    let mut tar = build_tar(&[
+        Fixture::dir("deep/"),
+        Fixture::file(&long_name, b"long"),
+        Fixture { mode: 0o600, ..Fixture::file("deep/small", &[7u8; 1500]) },
+        Fixture::symlink("deep/link", "small"),
+    ]);
+    // 结束标记之后的数据被读掉并忽略
+    tar.extend_from_slice(b"trailing garbage");
+
+    let dir = temp_dir("unpack_stream");
+    let out = dir.join("out");
+    unpack_stream(Trickle(&tar), &out, &ExtractOptions::default()).unwrap();
+    assert_eq!(std::fs::read(out.join(&long_name)).unwrap(), b"long");
+    assert_eq!(std::fs::read(out.join("deep/small")).unwrap(), [7u8; 1500]);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        assert_eq!(std::fs::read_link(out.join("deep/link")).unwrap(), std::path::Path::new("small"));
+        assert_eq!(std::fs::metadata(out.join("deep/small")).unwrap().permissions().mode() & 0o777, 0o600);
+    }
+
+    // 数据区中途结束的流
+    let truncated = &tar[..512 * 4];
+    let err = unpack_stream(Trickle(truncated), &dir.join("truncated"), &ExtractOptions::default()).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_extract_synthesizes_missing_parent_dirs() {
+    use std::os::unix::fs::PermissionsExt;
+    use pt::extract::{extract_all_with, ExtractOptions};
+
+    let dir = temp_dir("extract_implicit_dirs");
+    let mut explicit = Fixture::dir("top/");
+    explicit.mode = 0o700;
+    let path = write_tar(&dir, "a.tar", &[Fixture::file("top/sub/deep/f", b"f"), explicit]);
+    let img = TarImage::open(&path).unwrap();
+    let options = ExtractOptions { implicit_dirs: true, ..Default::default() };
+    let out = dir.join("out");
+    std::fs::create_dir(&out).unwrap();
+    // 已存在的目录不受影响
+    std::fs::create_dir(out.join("top")).unwrap();
+    std::fs::set_permissions(out.join("top"), std::fs::Permissions::from_mode(0o777)).unwrap();
+    extract_all_with(&mut lock_image(&img).unwrap(), &out, &options).unwrap();
+
+    let mode = |p: &str| std::fs::metadata(out.join(p)).unwrap().permissions().mode() & 0o7777;
+    assert_eq!(mode("top/sub"), 0o755);
+    assert_eq!(mode("top/sub/deep"), 0o755);
+    // 之后出现的显式条目仍然生效
+    assert_eq!(mode("top"), 0o700);
+    assert_eq!(std::fs::read(out.join("top/sub/deep/f")).unwrap(), b"f");
+}
+
+#[test]
+fn test_extract_overwrite_policy_and_dry_run() {
+    use pt::extract::{extract_all_report, ExtractAction, ExtractOptions};
+    use pt::OverwritePolicy;
+
+    let dir = temp_dir("extract_overwrite");
+    let path = write_tar(&dir, "a.tar", &[Fixture::dir("d/"), Fixture::file("d/a", b"new"), Fixture::file("b", b"new")]);
+    let img = TarImage::open(&path).unwrap();
+    let mut img = lock_image(&img).unwrap();
+    let out = dir.join("out");
+    std::fs::create_dir_all(out.join("d")).unwrap();
+    std::fs::write(out.join("d/a"), b"old").unwrap();
+
+    let actions = |options: &ExtractOptions, img: &mut TarImage| -> Vec<(String, ExtractAction)> {
+        extract_all_report(img, &out, options).unwrap().into_iter().map(|r| (r.path, r.action)).collect()
+    };
+    let dry = ExtractOptions { dry_run: true, ..Default::default() };
+    assert_eq!(actions(&dry, &mut img), [
+        ("d".to_string(), ExtractAction::Overwrite),
+        ("d/a".to_string(), ExtractAction::Overwrite),
+        ("b".to_string(), ExtractAction::Create),
+    ]);
+    assert!(!out.join("b").exists());
+    // dry run 不创建 dest
+    assert_eq!(extract_all_report(&mut img, &dir.join("missing"), &dry).unwrap().len(), 3);
+    assert!(!dir.join("missing").exists());
+
+    let error = ExtractOptions { overwrite: OverwritePolicy::Error, ..Default::default() };
+    let err = extract_all_report(&mut img, &out, &error).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::AlreadyExists);
+
+    let skip = ExtractOptions { overwrite: OverwritePolicy::Skip, ..Default::default() };
+    assert_eq!(actions(&skip, &mut img)[1], ("d/a".to_string(), ExtractAction::Skip));
+    assert_eq!(std::fs::read(out.join("d/a")).unwrap(), b"old");
+    assert_eq!(std::fs::read(out.join("b")).unwrap(), b"new");
+
+    // 先删除再写，不会写穿指向旧文件的硬链接
+    std::fs::hard_link(out.join("d/a"), dir.join("linked")).unwrap();
+    let unlink = ExtractOptions { unlink_first: true, ..Default::default() };
+    extract_all_report(&mut img, &out, &unlink).unwrap();
+    assert_eq!(std::fs::read(out.join("d/a")).unwrap(), b"new");
+    assert_eq!(std::fs::read(dir.join("linked")).unwrap(), b"old");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_sandboxed_extraction() {
+    use std::os::unix::fs::PermissionsExt;
+    use pt::error::{as_pt_error, PtError};
+    use pt::extract::{extract_all_with, ExtractOptions};
+
+    let dir = temp_dir("extract_sandboxed");
+    let mut exe = Fixture::file("d/tool", b"#!/bin/sh\n");
+    exe.mode = 0o750;
+    let path = write_tar(&dir, "a.tar", &[
+        Fixture::dir("d/"),
+        exe,
+        Fixture::symlink("d/self", "tool"),
+        Fixture { type_flag: b'1', link: "d/tool", ..Fixture::file("hard", b"") },
+    ]);
+    let img = TarImage::open(&path).unwrap();
+    let options = ExtractOptions { sandboxed: true, ..Default::default() };
+    let out = dir.join("out");
+    extract_all_with(&mut lock_image(&img).unwrap(), &out, &options).unwrap();
+    assert_eq!(std::fs::read(out.join("hard")).unwrap(), b"#!/bin/sh\n");
+    assert_eq!(std::fs::metadata(out.join("d/tool")).unwrap().permissions().mode() & 0o7777, 0o750);
+    assert_eq!(std::fs::read_link(out.join("d/self")).unwrap().to_str(), Some("tool"));
+
+    // 即使符号链接指向 dest 之内，也不能作为上级目录
+    let path = write_tar(&dir, "b.tar", &[Fixture::symlink("up", "d"), Fixture::file("up/f", b"x")]);
+    let img = TarImage::open(&path).unwrap();
+    let err = extract_all_with(&mut lock_image(&img).unwrap(), &out, &options).unwrap_err();
+    assert!(matches!(as_pt_error(&err), Some(PtError::UnsafePath { .. })));
+    assert!(!out.join("d/f").exists());
+
+    // dest 中已有的符号链接同样不会被穿过
+    std::fs::create_dir(dir.join("outside")).unwrap();
+    std::os::unix::fs::symlink(dir.join("outside"), out.join("escape")).unwrap();
+    let path = write_tar(&dir, "c.tar", &[Fixture::file("escape/f", b"x")]);
+    let img = TarImage::open(&path).unwrap();
+    let options = ExtractOptions { allow_unsafe_paths: true, ..options };
+    assert!(extract_all_with(&mut lock_image(&img).unwrap(), &out, &options).is_err());
+    assert!(!dir.join("outside/f").exists());
+}
+
+#[test]
+fn test_old_gnu_sparse_with_extension_block() {
+    // 6 段数据：header 中放 4 段，其余在 isextended 指向的扩展块中
+    let chunks: Vec<(u64, Vec<u8>)> = (0..6u64).map(|i| (i * 8192 + 512, vec![b'a' + i as u8; 512])).collect();
+    let stored: Vec<u8> = chunks.iter().flat_map(|(_, data)| data.clone()).collect();
+    let real_size = 6 * 8192;
+    let put = |b: &mut [u8], at: usize, field: &str| b[at..at + field.len()].copy_from_slice(field.as_bytes());
+
+    let mut header = common::header_block(&Fixture { type_flag: b'S', ..Fixture::file("disk.img", &stored) });
+    header[257..265].copy_from_slice(b"ustar  \0");
+    for (i, (offset, data)) in chunks[..4].iter().enumerate() {
+        put(&mut header, 386 + i * 24, &format!("{:011o}\0{:011o}\0", offset, data.len()));
+    }
+    header[482] = 1;
+    put(&mut header, 483, &format!("{:011o}\0", real_size));
+    common::fix_checksum(&mut header);
+    let mut ext = [0u8; 512];
+    for (i, (offset, data)) in chunks[4..].iter().enumerate() {
+        put(&mut ext, i * 24, &format!("{:011o}\0{:011o}\0", offset, data.len()));
+    }
+    // 与 GNU tar 相同，以 (realsize, 0) 结束映射
+    put(&mut ext, 2 * 24, &format!("{:011o}\0{:011o}\0", real_size, 0));
+
+    let mut tar = header.to_vec();
+    tar.extend_from_slice(&ext);
+    tar.extend_from_slice(&stored);
+    tar.resize(tar.len().next_multiple_of(512), 0);
+    tar.extend(common::build_tar(&[Fixture::file("after.txt", b"after")]));
+    let dir = temp_dir("old_gnu_sparse");
+    let path = dir.join("sparse.tar");
+    std::fs::write(&path, &tar).unwrap();
+
+    let img = TarImage::open(path.to_str().unwrap()).unwrap();
+    let mut img = lock_image(&img).unwrap();
+    let (file, _) = img.get_file_at(0).unwrap();
+    let file = pt::base::try_into_tarfile(file).unwrap();
+    assert_eq!(file.sparse_map().len(), 7);
+    assert_eq!(file.sparse_map()[5].offset, 5 * 8192 + 512);
+    assert_eq!(file.real_size(), real_size);
+    // 数据区在扩展块之后
+    assert_eq!(file.get_data_offset(), 1024);
+    assert_eq!(file.read_to_vec().unwrap(), stored);
+    let paths: Vec<_> = img.scan().unwrap().entries.into_iter().map(|m| m.path).collect();
+    assert_eq!(paths, ["disk.img", "after.txt"]);
+
+    let out = dir.join("out");
+    pt::extract::extract_all(&mut img, &out).unwrap();
+    let disk = std::fs::read(out.join("disk.img")).unwrap();
+    assert_eq!(disk.len() as u64, real_size);
+    for (offset, data) in &chunks {
+        assert_eq!(&disk[*offset as usize..*offset as usize + data.len()], data.as_slice());
+    }
+    assert!(disk[..512].iter().all(|&b| b == 0));
+    assert_eq!(std::fs::read(out.join("after.txt")).unwrap(), b"after");
+
+    let streamed = dir.join("streamed");
+    pt::extract::unpack_stream(tar.as_slice(), &streamed, &Default::default()).unwrap();
+    assert_eq!(std::fs::read(streamed.join("disk.img")).unwrap(), disk);
+    assert_eq!(std::fs::read(streamed.join("after.txt")).unwrap(), b"after");
+
+    // 就地修改的是主 header，扩展块保持不变
+    img.edit_header(0, |h| h.set_uid(5)).unwrap();
+    drop(img);
+    let edited = std::fs::read(&path).unwrap();
+    assert_eq!(&edited[512..1024], &ext[..]);
+    let img = TarImage::open(path.to_str().unwrap()).unwrap();
+    let file = lock_image(&img).unwrap().entry_at(0).unwrap().unwrap();
+    assert_eq!(file.uid(), 5);
+    assert_eq!(file.sparse_map().len(), 7);
+}
+
+#[test]
+fn test_old_gnu_sparse_base256_fields() {
+    let build = |extra: Option<&[u8]>| {
+        let mut header = common::header_block(&Fixture { type_flag: b'S', ..Fixture::file("big.img", b"data") });
+        header[257..265].copy_from_slice(b"ustar  \0");
+        header[386..398].copy_from_slice(b"00000000000\0");
+        header[398..410].copy_from_slice(b"00000000004\0");
+        if let Some(length) = extra {
+            header[410..422].copy_from_slice(b"00000000010\0");
+            header[422..434].copy_from_slice(length);
+        }
+        // realsize 为 base-256 编码的 8 GiB
+        let mut realsize = [0u8; 12];
+        realsize[0] = 0x80;
+        realsize[4..].copy_from_slice(&(8u64 << 30).to_be_bytes());
+        header[483..495].copy_from_slice(&realsize);
+        common::fix_checksum(&mut header);
+        let mut tar = header.to_vec();
+        tar.extend_from_slice(&[b"data".as_slice(), &[0u8; 508]].concat());
+        tar.extend_from_slice(&[0u8; 1024]);
+        tar
+    };
+    let dir = temp_dir("old_gnu_sparse_base256");
+    let path = dir.join("sparse.tar");
+    std::fs::write(&path, build(None)).unwrap();
+    let img = TarImage::open(path.to_str().unwrap()).unwrap();
+    let file = lock_image(&img).unwrap().entry_at(0).unwrap().unwrap();
+    assert_eq!(file.real_size(), 8 << 30);
+
+    // 接近 u64::MAX 的段长度不会溢出
+    let mut huge = [0xffu8; 12];
+    huge[..4].copy_from_slice(&[0x80, 0, 0, 0]);
+    std::fs::write(&path, build(Some(&huge))).unwrap();
+    let img = TarImage::open(path.to_str().unwrap()).unwrap();
+    let err = pt::extract::extract_all(&mut lock_image(&img).unwrap(), &dir.join("out")).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn test_unpack_stream_enforces_limits_and_cancel() {
+    use common::build_tar;
+    use pt::error::{as_pt_error, PtError};
+    use pt::extract::{unpack_stream, ExtractOptions};
+    use pt::{CancelToken, Limits};
+
+    let tar = build_tar(&[
+        Fixture::file("a", b"a"),
+        Fixture::file("b", b"b"),
+        Fixture::file("big", &vec![1u8; 300 * 1024]),
+    ]);
+    let dir = temp_dir("unpack_stream_limits");
+    let limit_of = |err: &std::io::Error| match as_pt_error(err) {
+        Some(PtError::LimitExceeded { limit, .. }) => *limit,
+        other => panic!("unexpected error: {:?}", other),
+    };
+
+    let options = ExtractOptions { limits: Limits { max_entries: Some(2), ..Limits::default() }, ..Default::default() };
+    let err = unpack_stream(&tar[..], &dir.join("count"), &options).unwrap_err();
+    assert_eq!(limit_of(&err), "entry count");
+    assert!(dir.join("count/b").exists());
+    assert!(!dir.join("count/big").exists());
+
+    let options = ExtractOptions { limits: Limits { max_entry_size: Some(1024), ..Limits::default() }, ..Default::default() };
+    let err = unpack_stream(&tar[..], &dir.join("size"), &options).unwrap_err();
+    assert_eq!(limit_of(&err), "entry size");
+    assert!(!dir.join("size/big").exists());
+
+    // 读到大文件数据区中途时取消
+    struct CancelAfter<'a> {
+        data: &'a [u8],
+        pos: usize,
+        token: CancelToken,
+    }
+    impl std::io::Read for CancelAfter<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            if self.pos > 4 * 1024 {
+                self.token.cancel();
+            }
+            let n = buf.len().min(self.data.len() - self.pos);
+            buf[..n].copy_from_slice(&self.data[self.pos..self.pos + n]);
+            self.pos += n;
+            Ok(n)
+        }
+    }
+    let token = CancelToken::new();
+    let options = ExtractOptions { cancel: Some(token.clone()), ..Default::default() };
+    let reader = CancelAfter { data: &tar, pos: 0, token };
+    let err = unpack_stream(reader, &dir.join("cancel"), &options).unwrap_err();
+    assert_eq!(as_pt_error(&err), Some(&PtError::Cancelled));
+    assert!(!dir.join("cancel/big").exists());
+}
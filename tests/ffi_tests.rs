@@ -0,0 +1,39 @@
+#![cfg(feature = "ffi")]
+
+mod common;
+
+use std::ffi::{CStr, CString};
+use common::{temp_dir, write_tar, Fixture};
+use pt::ffi::*;
+
+#[test]
+fn test_ffi_iterate_and_read() {
+    let dir = temp_dir("ffi");
+    let path = write_tar(&dir, "a.tar", &[Fixture::dir("d/"), Fixture::file("d/f", b"hello ffi")]);
+    let c_path = CString::new(path).unwrap();
+
+    unsafe {
+        let img = pt_open(c_path.as_ptr());
+        assert!(!img.is_null());
+        let mut seen = Vec::new();
+        loop {
+            let entry = pt_next_entry(img);
+            if entry.is_null() {
+                break;
+            }
+            let name = CStr::from_ptr(pt_entry_path(entry)).to_str().unwrap().to_string();
+            let mut body = vec![0u8; pt_entry_size(entry) as usize + 8];
+            let n = pt_entry_read(entry, body.as_mut_ptr(), body.len());
+            body.truncate(n as usize);
+            seen.push((name, pt_entry_type(entry) as u8, body));
+            pt_entry_free(entry);
+        }
+        assert!(pt_last_error().is_null());
+        pt_close(img);
+        assert_eq!(seen, vec![("d/".to_string(), b'5', Vec::new()), ("d/f".to_string(), b'0', b"hello ffi".to_vec())]);
+
+        let missing = CString::new(dir.join("missing.tar").to_str().unwrap()).unwrap();
+        assert!(pt_open(missing.as_ptr()).is_null());
+        assert!(!pt_last_error().is_null());
+    }
+}
@@ -0,0 +1,31 @@
+mod common;
+
+use common::{temp_dir, write_tar, Fixture};
+use pt::base::{lock_image, try_into_tarfile, TarImage, ImageInfo};
+use pt::filter::EntryFilter;
+
+#[test]
+fn test_glob_filter() {
+    let f = EntryFilter::new(&["usr/lib/**/*.so", "etc/[hp]*"]);
+    assert!(f.matches("usr/lib/libc.so"));
+    assert!(f.matches("./usr/lib/x86_64/deep/libm.so"));
+    assert!(!f.matches("usr/lib/libc.so.6"));
+    assert!(f.matches("etc/hosts"));
+    assert!(f.matches("etc/passwd"));
+    assert!(!f.matches("etc/group"));
+    assert!(!f.matches("etc/sub/hosts"));
+
+    let dir = temp_dir("filter");
+    let path = write_tar(&dir, "a.tar", &[
+        Fixture::dir("usr/lib/"),
+        Fixture::file("usr/lib/a.so", b"a"),
+        Fixture::file("usr/lib/a.txt", b"t"),
+    ]);
+    let img = TarImage::open(&path).unwrap();
+    let mut hits = Vec::new();
+    lock_image(&img).unwrap().for_each_entry_matching(&["**/*.so"], |file| {
+        hits.push(try_into_tarfile(file)?.get_path());
+        Ok(())
+    }).unwrap();
+    assert_eq!(hits, ["usr/lib/a.so"]);
+}
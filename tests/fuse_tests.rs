@@ -0,0 +1,28 @@
+#![cfg(all(unix, feature = "fuse"))]
+
+mod common;
+
+use common::{temp_dir, write_tar, Fixture};
+use pt::base::{ImageInfo, TarImage};
+
+#[test]
+fn test_mount_serves_entries() {
+    let dir = temp_dir("fuse");
+    let path = write_tar(&dir, "a.tar", &[
+        Fixture::file("etc/hosts", b"127.0.0.1 localhost\n"),
+        Fixture::symlink("etc/link", "hosts"),
+    ]);
+    let mnt = dir.join("mnt");
+    std::fs::create_dir(&mnt).unwrap();
+    let Ok(session) = pt::fuse::spawn_mount(TarImage::open(&path).unwrap(), &mnt) else {
+        // 没有 FUSE 设备或权限时跳过
+        return;
+    };
+    assert_eq!(std::fs::read(mnt.join("etc/hosts")).unwrap(), b"127.0.0.1 localhost\n");
+    assert_eq!(std::fs::read_link(mnt.join("etc/link")).unwrap(), std::path::Path::new("hosts"));
+    let mut names: Vec<_> = std::fs::read_dir(mnt.join("etc")).unwrap().map(|e| e.unwrap().file_name()).collect();
+    names.sort();
+    assert_eq!(names, ["hosts", "link"]);
+    assert!(std::fs::write(mnt.join("etc/new"), b"x").is_err());
+    drop(session);
+}
@@ -0,0 +1,52 @@
+#![cfg(feature = "gzip")]
+mod common;
+
+use std::io::{Read, Write};
+use common::{build_tar, temp_dir, Fixture};
+use pt::backend::{Backend, MemoryBackend};
+use pt::base::{lock_image, TarImage};
+use pt::gzindex::{GzipBackend, GzipIndex, GzipIndexWriter};
+
+#[test]
+fn test_gzip_members_allow_random_access() {
+    let names: Vec<String> = (0..40).map(|i| format!("data/{:02}.bin", i)).collect();
+    let bodies: Vec<Vec<u8>> = (0..40).map(|i| (0..3000u32).map(|j| (i * 7 + j % 251) as u8).collect()).collect();
+    let fixtures: Vec<Fixture> = names.iter().zip(&bodies).map(|(name, body)| Fixture::file(name, body)).collect();
+    let tar = build_tar(&fixtures);
+
+    let mut writer = GzipIndexWriter::new(Vec::new(), 16 * 1024, flate2::Compression::default());
+    writer.write_all(&tar).unwrap();
+    let (gz, index) = writer.finish().unwrap();
+    assert_eq!(index.uncompressed_len(), tar.len() as u64);
+    assert!(index.checkpoints().len() > 5);
+    // 输出仍是普通的多成员 gzip
+    let mut plain = Vec::new();
+    flate2::read::MultiGzDecoder::new(&gz[..]).read_to_end(&mut plain).unwrap();
+    assert_eq!(plain, tar);
+    assert_eq!(GzipIndex::build(&gz[..]).unwrap(), index);
+
+    let backend = GzipBackend::new(MemoryBackend::new(gz.clone()), index.clone());
+    let mut buf = vec![0u8; 5000];
+    let offset = tar.len() as u64 - 9000;
+    assert_eq!(backend.read_at(&mut buf, offset).unwrap(), 5000);
+    assert_eq!(buf, tar[offset as usize..offset as usize + 5000]);
+
+    let dir = temp_dir("gzindex");
+    let path = dir.join("a.tar.gz");
+    std::fs::write(&path, &gz).unwrap();
+    index.save(dir.join("a.tar.gz.gzi")).unwrap();
+    assert_eq!(GzipIndex::load(dir.join("a.tar.gz.gzi"), &MemoryBackend::new(gz)).unwrap(), index);
+    let img = TarImage::open_gzip(&path).unwrap();
+    let file = lock_image(&img).unwrap().find_entry("data/33.bin").unwrap().unwrap();
+    assert_eq!(file.read_to_vec().unwrap(), bodies[33]);
+
+    // 单成员 gzip 也能读取，只是每次都从头解压
+    let mut enc = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+    enc.write_all(&tar).unwrap();
+    let single = dir.join("single.tar.gz");
+    std::fs::write(&single, enc.finish().unwrap()).unwrap();
+    let img = TarImage::open_gzip(&single).unwrap();
+    let mut img = lock_image(&img).unwrap();
+    assert_eq!(img.scan().unwrap().entries.len(), 40);
+    assert_eq!(img.find_entry("data/05.bin").unwrap().unwrap().read_to_vec().unwrap(), bodies[5]);
+}
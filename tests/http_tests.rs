@@ -0,0 +1,59 @@
+#![cfg(feature = "http")]
+
+mod common;
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use common::{build_tar, Fixture};
+use pt::base::{lock_image, TarImage};
+
+/// 只支持 `Range: bytes=a-b` 的最小 HTTP 服务器，返回 (地址, 已服务的请求数)
+fn serve(data: Vec<u8>) -> (String, Arc<AtomicUsize>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+    let requests = Arc::new(AtomicUsize::new(0));
+    let counter = requests.clone();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut range = None;
+            let mut line = String::new();
+            while reader.read_line(&mut line).unwrap() > 0 && line != "\r\n" {
+                if let Some(spec) = line.trim_end().strip_prefix("Range: bytes=") {
+                    let (a, b) = spec.split_once('-').unwrap();
+                    range = Some((a.parse::<usize>().unwrap(), b.parse::<usize>().unwrap()));
+                }
+                line.clear();
+            }
+            counter.fetch_add(1, Ordering::Relaxed);
+            let (a, b) = range.unwrap();
+            let body = &data[a..=b.min(data.len() - 1)];
+            write!(stream, "HTTP/1.1 206 Partial Content\r\nContent-Length: {}\r\nContent-Range: bytes {}-{}/{}\r\n\r\n",
+                body.len(), a, a + body.len() - 1, data.len()).unwrap();
+            stream.write_all(body).unwrap();
+        }
+    });
+    (addr, requests)
+}
+
+#[test]
+fn test_open_url_reads_ranges() {
+    let big = vec![b'z'; 3 * 1024 * 1024];
+    let tar = build_tar(&[
+        Fixture::file("big.bin", &big),
+        Fixture::file("etc/hostname", b"remote\n"),
+    ]);
+    let (addr, requests) = serve(tar);
+
+    let img = TarImage::open_url(&format!("http://{}/images/a.tar", addr)).unwrap();
+    let file = lock_image(&img).unwrap().find_entry("etc/hostname").unwrap().unwrap();
+    assert_eq!(file.read_to_string().unwrap(), "remote\n");
+    // 只请求了 header 与小文件所在的范围，没有下载 3 MiB 的数据区
+    assert!(requests.load(Ordering::Relaxed) <= 4, "{} requests", requests.load(Ordering::Relaxed));
+
+    let err = TarImage::open_url("https://example.com/a.tar").err().unwrap();
+    assert_eq!(err.kind(), std::io::ErrorKind::Unsupported);
+}
@@ -0,0 +1,93 @@
+mod common;
+
+use common::{temp_dir, write_tar, Fixture};
+use pt::incremental::restore_chain;
+
+#[test]
+fn test_restore_chain_applies_deletions() {
+    let dir = temp_dir("restore_chain");
+    let full = write_tar(&dir, "full.tar", &[
+        Fixture { type_flag: b'D', body: b"Yx\0Yy\0\0", ..Fixture::dir("a/") },
+        Fixture::file("a/x", b"x0"),
+        Fixture::file("a/y", b"y0"),
+    ]);
+    let inc = write_tar(&dir, "inc1.tar", &[
+        Fixture { type_flag: b'D', body: b"Nx\0Yz\0\0", ..Fixture::dir("a/") },
+        Fixture::file("a/z", b"z1"),
+    ]);
+
+    let dest = dir.join("out");
+    restore_chain(&full, &[&inc], &dest).unwrap();
+    assert_eq!(std::fs::read(dest.join("a/x")).unwrap(), b"x0");
+    assert!(!dest.join("a/y").exists());
+    assert_eq!(std::fs::read(dest.join("a/z")).unwrap(), b"z1");
+}
+
+#[test]
+fn test_restore_chain_rejects_escaping_dumpdir() {
+    let dir = temp_dir("restore_chain_escape");
+    let victim = dir.join("victim");
+    std::fs::create_dir_all(&victim).unwrap();
+    std::fs::write(victim.join("keep.txt"), b"keep").unwrap();
+    let full = write_tar(&dir, "full.tar", &[Fixture::file("a", b"a")]);
+    let inc = write_tar(&dir, "inc1.tar", &[
+        Fixture { type_flag: b'D', body: b"\0", ..Fixture::dir("../victim/") },
+    ]);
+
+    let err = restore_chain(&full, &[&inc], &dir.join("out")).unwrap_err();
+    assert!(matches!(pt::error::as_pt_error(&err), Some(pt::error::PtError::UnsafePath { .. })), "{}", err);
+    assert_eq!(std::fs::read(victim.join("keep.txt")).unwrap(), b"keep");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_restore_chain_rejects_symlinked_dumpdir() {
+    let dir = temp_dir("restore_chain_symlink");
+    let victim = dir.join("victim");
+    std::fs::create_dir_all(&victim).unwrap();
+    std::fs::write(victim.join("keep.txt"), b"keep").unwrap();
+    let full = write_tar(&dir, "full.tar", &[Fixture::dir("a/")]);
+    let inc = write_tar(&dir, "inc1.tar", &[
+        Fixture { type_flag: b'D', body: b"\0", ..Fixture::dir("a/link/") },
+    ]);
+
+    let dest = dir.join("out");
+    restore_chain(&full, &[], &dest).unwrap();
+    std::os::unix::fs::symlink(&victim, dest.join("a/link")).unwrap();
+    let err = restore_chain(&full, &[&inc], &dest).unwrap_err();
+    assert!(matches!(pt::error::as_pt_error(&err), Some(pt::error::PtError::UnsafePath { .. })), "{}", err);
+    assert_eq!(std::fs::read(victim.join("keep.txt")).unwrap(), b"keep");
+}
+
+#[test]
+fn test_listed_incremental_backup_and_restore() {
+    use pt::incremental::{create_incremental, Snapshot};
+
+    let dir = temp_dir("listed_incremental");
+    let src = dir.join("src");
+    std::fs::create_dir_all(src.join("sub")).unwrap();
+    std::fs::write(src.join("keep"), b"k").unwrap();
+    std::fs::write(src.join("gone"), b"g").unwrap();
+    std::fs::write(src.join("sub/old"), b"o").unwrap();
+    let snapshot = dir.join("backup.snar");
+    let full = dir.join("level0.tar");
+    assert_eq!(create_incremental(std::fs::File::create(&full).unwrap(), &[("data", &src)], &snapshot).unwrap(), 5);
+    let level0 = Snapshot::load(&snapshot).unwrap();
+    assert_eq!(level0.dirs.keys().collect::<Vec<_>>(), ["data", "data/sub"]);
+    assert_eq!(Snapshot::parse(&level0.to_bytes()).unwrap(), level0);
+
+    // 文件时间戳的精度可能低于快照时间
+    std::thread::sleep(std::time::Duration::from_millis(50));
+    std::fs::remove_file(src.join("gone")).unwrap();
+    std::fs::write(src.join("sub/new"), b"n").unwrap();
+    let inc = dir.join("level1.tar");
+    // 两个 'D' 条目加 sub/new
+    assert_eq!(create_incremental(std::fs::File::create(&inc).unwrap(), &[("data", &src)], &snapshot).unwrap(), 3);
+
+    let dest = dir.join("out");
+    restore_chain(full.to_str().unwrap(), &[inc.to_str().unwrap()], &dest).unwrap();
+    assert!(dest.join("data/keep").exists());
+    assert!(!dest.join("data/gone").exists());
+    assert_eq!(std::fs::read(dest.join("data/sub/new")).unwrap(), b"n");
+    assert_eq!(std::fs::read(dest.join("data/sub/old")).unwrap(), b"o");
+}
@@ -0,0 +1,206 @@
+mod common;
+
+use common::{temp_dir, write_tar, Fixture};
+use pt::base::{lock_image, ImageInfo, TarImage};
+use pt::index::{Index, IndexBudget, IndexKind};
+
+fn sample(name: &str) -> String {
+    let names: Vec<_> = (0..50).map(|i| format!("dir/file{i}")).collect();
+    let fixtures: Vec<_> = names.iter().map(|n| Fixture::file(n, b"data")).collect();
+    write_tar(&temp_dir(name), "a.tar", &fixtures)
+}
+
+#[test]
+fn test_index_within_budget_stays_full() {
+    let img = TarImage::open(&sample("index_full")).unwrap();
+    let mut img = lock_image(&img).unwrap();
+    let full = Index::build(&mut img).unwrap();
+    let budget = IndexBudget { max_bytes: usize::MAX, sidecar_dir: None };
+    let index = Index::build_with_budget(&mut img, &budget).unwrap();
+    assert_eq!(index.kind(), IndexKind::Full);
+    assert_eq!(index.len(), 50);
+    assert!(index.memory_usage() > 0);
+    assert_eq!(index.memory_usage(), full.memory_usage());
+}
+
+#[test]
+fn test_index_over_budget_falls_back() {
+    let dir = temp_dir("index_fallback");
+    let img = TarImage::open(&sample("index_fallback_src")).unwrap();
+    let mut img = lock_image(&img).unwrap();
+
+    let compact = Index::build_with_budget(&mut img, &IndexBudget { max_bytes: 1024, sidecar_dir: None }).unwrap();
+    let sidecar = Index::build_with_budget(&mut img, &IndexBudget { max_bytes: 64, sidecar_dir: Some(dir.clone()) }).unwrap();
+    assert_eq!(compact.kind(), IndexKind::Compact);
+    assert_eq!(sidecar.kind(), IndexKind::Sidecar);
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+
+    for index in [&compact, &sidecar] {
+        assert_eq!(index.len(), 50);
+        assert!(index.get("dir/file7").is_none());
+        assert_eq!(index.stat(&mut img, "dir/file7").unwrap().unwrap().path, "dir/file7");
+        assert!(index.open_entry(&mut img, "dir/missing").unwrap().is_none());
+    }
+    drop(sidecar);
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+}
+
+#[test]
+fn test_stat_many() {
+    let img = TarImage::open(&sample("index_stat_many")).unwrap();
+    let mut img = lock_image(&img).unwrap();
+    let paths = ["dir/file3", "missing", "./dir/file49", "dir/file3"];
+
+    let scanned = img.stat_many(&paths).unwrap();
+    let found: Vec<_> = scanned.iter().map(|m| m.as_ref().map(|m| m.path.as_str())).collect();
+    assert_eq!(found, [Some("dir/file3"), None, Some("dir/file49"), Some("dir/file3")]);
+
+    let budget = IndexBudget { max_bytes: 1024, sidecar_dir: None };
+    for index in [Index::build(&mut img).unwrap(), Index::build_with_budget(&mut img, &budget).unwrap()] {
+        let indexed = index.stat_many(&mut img, &paths).unwrap();
+        let offsets: Vec<_> = indexed.iter().map(|m| m.as_ref().map(|m| m.offset)).collect();
+        assert_eq!(offsets, scanned.iter().map(|m| m.as_ref().map(|m| m.offset)).collect::<Vec<_>>());
+    }
+}
+
+#[test]
+fn test_for_each_entry_ordered() {
+    use pt::index::EntryOrder;
+
+    let dir = temp_dir("index_ordered");
+    let mut second = Fixture::file("b", b"2");
+    second.mtime += 1;
+    let path = write_tar(&dir, "a.tar", &[
+        Fixture::file("c", b""),
+        Fixture::file("b", b"1"),
+        Fixture::file("a/z", b""),
+        second,
+        Fixture::file("./a", b""),
+    ]);
+    let img = TarImage::open(&path).unwrap();
+    let mut img = lock_image(&img).unwrap();
+    let mut collect = |order| {
+        let mut out = Vec::new();
+        img.for_each_entry_ordered(order, |f| {
+            out.push(format!("{}@{}", f.get_path(), f.get_header().get_mtime()));
+            Ok(())
+        }).unwrap();
+        out
+    };
+    assert_eq!(collect(EntryOrder::Archive), ["c@1600000000", "b@1600000000", "a/z@1600000000", "b@1600000001", "./a@1600000000"]);
+    assert_eq!(collect(EntryOrder::Path), ["./a@1600000000", "a/z@1600000000", "b@1600000000", "b@1600000001", "c@1600000000"]);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_index_serde_round_trip() {
+    let path = sample("index_serde");
+    let img = TarImage::open(&path).unwrap();
+    let mut img = lock_image(&img).unwrap();
+
+    let full = Index::build(&mut img).unwrap();
+    let json = serde_json::to_string(&full).unwrap();
+    let back: Index = serde_json::from_str(&json).unwrap();
+    assert_eq!(back.kind(), IndexKind::Full);
+    assert_eq!(back.entries(), full.entries());
+    assert_eq!(serde_json::to_value(&full.entries()[0]).unwrap()["path"], "dir/file0");
+
+    let sidecar = Index::build_with_budget(&mut img, &IndexBudget { max_bytes: 0, sidecar_dir: None }).unwrap();
+    assert_eq!(sidecar.kind(), IndexKind::Sidecar);
+    let back: Index = serde_json::from_str(&serde_json::to_string(&sidecar).unwrap()).unwrap();
+    assert_eq!(back.kind(), IndexKind::Compact);
+    assert_eq!(back.len(), 50);
+    assert_eq!(back.stat(&mut img, "dir/file7").unwrap().unwrap().path, "dir/file7");
+}
+
+#[test]
+fn test_index_save_and_load() {
+    let dir = temp_dir("index_toc");
+    let path = sample("index_toc_src");
+    let img = TarImage::open(&path).unwrap();
+    let mut img = lock_image(&img).unwrap();
+
+    let full = Index::build(&mut img).unwrap();
+    full.save(dir.join("full.toc")).unwrap();
+    let back = Index::load(dir.join("full.toc"), &mut img).unwrap();
+    assert_eq!(back.kind(), IndexKind::Full);
+    assert_eq!(back.entries(), full.entries());
+    assert_eq!(back.get("dir/file7").unwrap().offset, full.get("dir/file7").unwrap().offset);
+
+    let budget = IndexBudget { max_bytes: 0, sidecar_dir: Some(dir.clone()) };
+    Index::build_with_budget(&mut img, &budget).unwrap().save(dir.join("compact.toc")).unwrap();
+    let back = Index::load(dir.join("compact.toc"), &mut img).unwrap();
+    assert_eq!(back.kind(), IndexKind::Compact);
+    assert_eq!(back.len(), 50);
+    assert_eq!(back.stat(&mut img, "dir/file7").unwrap().unwrap().path, "dir/file7");
+    drop(img);
+
+    // 归档被替换后索引过期
+    let names: Vec<_> = (0..50).map(|i| format!("dir/other{i}")).collect();
+    let fixtures: Vec<_> = names.iter().map(|n| Fixture::file(n, b"data")).collect();
+    let other = write_tar(&temp_dir("index_toc_other"), "a.tar", &fixtures);
+    std::fs::copy(other, &path).unwrap();
+    let img = TarImage::open(&path).unwrap();
+    let err = Index::load(dir.join("full.toc"), &mut lock_image(&img).unwrap()).err().unwrap();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+    std::fs::write(dir.join("bad.toc"), b"not an index").unwrap();
+    assert!(Index::load(dir.join("bad.toc"), &mut lock_image(&img).unwrap()).is_err());
+    assert!(Index::default().save(dir.join("empty.toc")).is_err());
+}
+
+#[test]
+fn test_index_keys_on_raw_name_bytes() {
+    use common::{build_tar, fix_checksum};
+
+    // 两个名字只差在非 UTF-8 字节上，转成 String 后相同
+    let mut tar = build_tar(&[Fixture::file("caf?", b"e9"), Fixture::file("caf?", b"ff!")]);
+    for (block, byte) in [(0, 0xe9), (1024, 0xff)] {
+        tar[block + 3] = byte;
+        fix_checksum(&mut tar[block..block + 512]);
+    }
+    let dir = temp_dir("index_raw_names");
+    let path = dir.join("a.tar");
+    std::fs::write(&path, &tar).unwrap();
+    let img = TarImage::open(path.to_str().unwrap()).unwrap();
+    let mut img = lock_image(&img).unwrap();
+
+    let full = Index::build(&mut img).unwrap();
+    assert_eq!(full.get_bytes(b"caf\xe9").unwrap().size, 2);
+    assert_eq!(full.get_bytes(b"./caf\xff").unwrap().size, 3);
+    assert!(full.get("caf\u{fffd}").is_none());
+
+    let sidecar = Index::build_with_budget(&mut img, &IndexBudget { max_bytes: 0, sidecar_dir: Some(dir.clone()) }).unwrap();
+    assert_eq!(sidecar.kind(), IndexKind::Sidecar);
+    sidecar.save(dir.join("a.toc")).unwrap();
+    let back = Index::load(dir.join("a.toc"), &mut img).unwrap();
+    for index in [&sidecar, &back] {
+        assert_eq!(index.open_entry_bytes(&mut img, b"caf\xe9").unwrap().unwrap().get_size(), 2);
+        assert_eq!(index.open_entry_bytes(&mut img, b"caf\xff").unwrap().unwrap().get_size(), 3);
+        assert!(index.open_entry_bytes(&mut img, b"caf\xfe").unwrap().is_none());
+    }
+}
+
+#[test]
+fn test_sidecar_index_concurrent_lookups() {
+    let path = sample("index_sidecar_threads");
+    let img = TarImage::open(&path).unwrap();
+    let budget = IndexBudget { max_bytes: 0, sidecar_dir: None };
+    let index = Index::build_with_budget(&mut lock_image(&img).unwrap(), &budget).unwrap();
+    assert_eq!(index.kind(), IndexKind::Sidecar);
+
+    std::thread::scope(|scope| {
+        for t in 0..8 {
+            let (index, path) = (&index, &path);
+            scope.spawn(move || {
+                let img = TarImage::open(path).unwrap();
+                let mut img = lock_image(&img).unwrap();
+                for round in 0..200 {
+                    let name = format!("dir/file{}", (t * 7 + round) % 50);
+                    let meta = index.stat(&mut img, &name).unwrap().unwrap();
+                    assert_eq!(meta.path, name);
+                }
+            });
+        }
+    });
+}
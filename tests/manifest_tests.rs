@@ -0,0 +1,68 @@
+mod common;
+
+use common::{write_tar, temp_dir, Fixture};
+use pt::base::{lock_image, ImageInfo, TarImage};
+use pt::checksum::DigestAlgorithm;
+use pt::manifest::Manifest;
+
+#[test]
+fn test_manifest_roundtrip_and_verify() {
+    let dir = temp_dir("manifest");
+    let good = write_tar(&dir, "good.tar", &[
+        Fixture::dir("etc/"),
+        Fixture::file("etc/passwd", b"root:x:0:0"),
+        Fixture::symlink("etc/localtime", "/usr/share/zoneinfo/UTC"),
+    ]);
+    let img = TarImage::open(&good).unwrap();
+    let manifest = lock_image(&img).unwrap().manifest(DigestAlgorithm::Sha256).unwrap();
+    let entry = &manifest.entries["etc/passwd"];
+    assert_eq!((entry.kind.as_str(), entry.size), ("file", 10));
+    assert_eq!(entry.digest.as_ref().unwrap().len(), 64);
+    assert_eq!(manifest.entries["etc/localtime"].digest, None);
+
+    let bytes = manifest.to_bytes();
+    let parsed = Manifest::parse(&bytes).unwrap();
+    assert_eq!(parsed, manifest);
+    assert_eq!(parsed.to_bytes(), bytes);
+    assert!(lock_image(&img).unwrap().verify_manifest(&parsed).unwrap().is_ok());
+
+    let tampered = write_tar(&dir, "tampered.tar", &[
+        Fixture::dir("etc/"),
+        Fixture::file("etc/passwd", b"root:x:0:1"),
+        Fixture::file("etc/shadow", b""),
+    ]);
+    let img = TarImage::open(&tampered).unwrap();
+    let report = lock_image(&img).unwrap().verify_manifest(&parsed).unwrap();
+    assert_eq!(report.missing, ["etc/localtime"]);
+    assert_eq!(report.unexpected, ["etc/shadow"]);
+    assert_eq!(report.mismatched, [("etc/passwd".to_string(), "digest".to_string())]);
+
+    assert!(Manifest::parse(b"{\"version\":2}").is_err());
+}
+
+#[cfg(feature = "sign")]
+#[test]
+fn test_signed_manifest() {
+    use ed25519_dalek::SigningKey;
+
+    let dir = temp_dir("manifest_signed");
+    let path = write_tar(&dir, "a.tar", &[Fixture::file("etc/passwd", b"root:x:0:0")]);
+    let img = TarImage::open(&path).unwrap();
+    let manifest = lock_image(&img).unwrap().manifest(DigestAlgorithm::Sha256).unwrap();
+    let key = SigningKey::from_bytes(&[7; 32]);
+    let signature = manifest.sign(&key);
+    let bytes = manifest.to_bytes();
+    assert_eq!(Manifest::parse_signed(&bytes, &signature, &key.verifying_key()).unwrap(), manifest);
+
+    // 改动清单内容、签名或换用其它公钥都无法通过校验
+    let forged = String::from_utf8(bytes.clone()).unwrap().replace("\"size\":10", "\"size\":11");
+    assert_ne!(forged.as_bytes(), bytes.as_slice());
+    let err = Manifest::parse_signed(forged.as_bytes(), &signature, &key.verifying_key()).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    let mut bad = signature;
+    bad[0] ^= 1;
+    assert!(Manifest::parse_signed(&bytes, &bad, &key.verifying_key()).is_err());
+    let other = SigningKey::from_bytes(&[8; 32]).verifying_key();
+    assert!(Manifest::parse_signed(&bytes, &signature, &other).is_err());
+    assert!(Manifest::parse_signed(&bytes, &signature[..63], &key.verifying_key()).is_err());
+}
@@ -0,0 +1,112 @@
+mod common;
+
+use common::{pax_record, temp_dir, write_tar, Fixture};
+use pt::base::{lock_image, try_into_tarfile, ImageInfo, TarImage};
+use pt::merge::{merge_paths, ConflictPolicy};
+
+fn names(path: &str) -> Vec<(String, u64)> {
+    let img = TarImage::open(path).unwrap();
+    let mut out = Vec::new();
+    lock_image(&img).unwrap().for_each_entry(|f| {
+        let f = try_into_tarfile(f)?;
+        out.push((f.get_path(), f.get_size()));
+        Ok(())
+    }).unwrap();
+    out
+}
+
+#[test]
+fn test_merge_policies() {
+    let dir = temp_dir("merge");
+    let a = write_tar(&dir, "a.tar", &[
+        Fixture::file("bin/tool", b"old"),
+        Fixture::file("lib/a.so", b"aaaa"),
+    ]);
+    let mut newer = Fixture::file("bin/tool", b"newer");
+    newer.mtime += 10;
+    let b = write_tar(&dir, "b.tar", &[newer, Fixture::file("lib/b.so", b"b")]);
+
+    let out = dir.join("out.tar").to_string_lossy().into_owned();
+    merge_paths(&a, &b, &out, ConflictPolicy::PreferA).unwrap();
+    assert_eq!(names(&out), [("bin/tool".into(), 3), ("lib/a.so".into(), 4), ("lib/b.so".into(), 1)]);
+
+    merge_paths(&a, &b, &out, ConflictPolicy::NewerMtime).unwrap();
+    assert_eq!(names(&out)[0], ("bin/tool".into(), 5));
+
+    assert!(merge_paths(&a, &b, &out, ConflictPolicy::Error).is_err());
+    merge_paths(&a, &a, &out, ConflictPolicy::Error).unwrap();
+}
+
+#[test]
+fn test_merge_keeps_globals_of_dropped_duplicates() {
+    let (uid_a, uid_b) = (pax_record("uid", b"4242"), pax_record("uid", b"99"));
+    fn global(records: &[u8]) -> Fixture<'_> {
+        Fixture { type_flag: b'g', ..Fixture::file("pax_global_header", records) }
+    }
+    let dir = temp_dir("merge_globals");
+    let a = write_tar(&dir, "a.tar", &[
+        global(&uid_a),
+        Fixture::file("dup", b"old"),
+        Fixture::file("a_only", b"a"),
+        Fixture::file("dup", b"oldest"),
+    ]);
+    let mut newer = Fixture::file("dup", b"newer");
+    newer.mtime += 10;
+    let b = write_tar(&dir, "b.tar", &[global(&uid_b), newer, Fixture::file("b_only", b"b")]);
+
+    let owners = |path: &str| -> Vec<(String, u64)> {
+        let img = TarImage::open(path).unwrap();
+        let entries = lock_image(&img).unwrap().scan().unwrap().entries;
+        entries.into_iter().map(|m| (m.path, m.uid)).collect()
+    };
+    let out = dir.join("out.tar").to_string_lossy().into_owned();
+    // a 中的 dup 被 b 替换，b 中的 dup 被丢弃，两者前面的 'g' 都要保留
+    merge_paths(&a, &b, &out, ConflictPolicy::PreferB).unwrap();
+    assert_eq!(owners(&out), [("dup".into(), 4242), ("a_only".into(), 4242), ("b_only".into(), 99)]);
+    assert_eq!(names(&out)[0], ("dup".into(), 5));
+    merge_paths(&a, &b, &out, ConflictPolicy::PreferA).unwrap();
+    assert_eq!(owners(&out), [("dup".into(), 4242), ("a_only".into(), 4242), ("b_only".into(), 99)]);
+    // a 内部的同名条目以最后一个为准
+    assert_eq!(names(&out)[0], ("dup".into(), 6));
+}
+
+#[test]
+fn test_merge_layers_applies_whiteouts() {
+    use pt::merge::merge_layers;
+    use pt::tar::TarFileType;
+    use pt::whiteout::{classify, Whiteout};
+
+    assert_eq!(classify("etc/.wh.passwd"), Some(Whiteout::Remove("etc/passwd".into())));
+    assert_eq!(classify("var/cache/.wh..wh..opq"), Some(Whiteout::Opaque("var/cache".into())));
+    assert_eq!(classify("etc/passwd"), None);
+
+    let dir = temp_dir("merge_layers");
+    let lower = write_tar(&dir, "lower.tar", &[
+        Fixture::file("etc/passwd", b"root"),
+        Fixture::file("etc/hosts", b"localhost"),
+        Fixture::file("var/cache/a", b"a"),
+        Fixture::file("var/cache2", b"keep"),
+    ]);
+    let upper = write_tar(&dir, "upper.tar", &[
+        Fixture::file("etc/.wh.passwd", b""),
+        Fixture::file("var/cache/.wh..wh..opq", b""),
+        Fixture::file("var/cache/b", b"bb"),
+        Fixture::file("etc/hosts", b"example"),
+    ]);
+
+    let img = TarImage::open(&upper).unwrap();
+    let (file, _) = lock_image(&img).unwrap().get_file_at(0).unwrap();
+    assert_eq!(try_into_tarfile(file).unwrap().get_file_type(), TarFileType::Whiteout as i32);
+
+    let out = dir.join("out.tar");
+    let lower = TarImage::open(&lower).unwrap();
+    let upper = TarImage::open(&upper).unwrap();
+    let mut buf = Vec::new();
+    merge_layers(&mut lock_image(&lower).unwrap(), &mut lock_image(&upper).unwrap(), &mut buf).unwrap();
+    std::fs::write(&out, buf).unwrap();
+    assert_eq!(names(out.to_str().unwrap()), [
+        ("var/cache2".into(), 4),
+        ("var/cache/b".into(), 2),
+        ("etc/hosts".into(), 7),
+    ]);
+}
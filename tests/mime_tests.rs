@@ -0,0 +1,20 @@
+use pt::mime::sniff_mime;
+
+#[test]
+fn test_sniff_mime() {
+    assert_eq!(sniff_mime(b"\x7fELF\x02\x01\x01"), "application/x-executable");
+    assert_eq!(sniff_mime(b"\x89PNG\r\n\x1a\n...."), "image/png");
+    assert_eq!(sniff_mime(b"#!/bin/sh\necho hi\n"), "text/x-shellscript");
+    assert_eq!(sniff_mime(b"  {\"a\": 1}"), "application/json");
+    assert_eq!(sniff_mime("NAME=\"Ubuntu\"\n".as_bytes()), "text/plain");
+    assert_eq!(sniff_mime(b"\x00\x01\x02\x03"), "application/octet-stream");
+    assert_eq!(sniff_mime(b""), "application/x-empty");
+    assert_eq!(sniff_mime(b"<!DOCTYPE html><html>"), "text/html");
+}
+
+#[test]
+fn test_sniff_mime_multibyte_text() {
+    // 第 14 字节落在多字节字符中间
+    assert_eq!(sniff_mime("€€€€€€ price list".as_bytes()), "text/plain");
+    assert_eq!(sniff_mime("<!doctype htm€".as_bytes()), "text/plain");
+}
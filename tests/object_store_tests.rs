@@ -0,0 +1,45 @@
+#![cfg(feature = "s3")]
+
+mod common;
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use common::{build_tar, Fixture};
+use pt::base::lock_image;
+use pt::extract::extract_file_to;
+use pt::object_store::{parse_s3_url, RangeRequest, RangeResponse};
+use pt::TarImage;
+
+#[test]
+fn test_index_and_extract_from_object_store() {
+    let big = vec![0u8; 2 * 1024 * 1024];
+    let tar = Arc::new(build_tar(&[
+        Fixture::file("layer/blob", &big),
+        Fixture::file("layer/config.json", b"{}"),
+    ]));
+    let fetched = Arc::new(AtomicU64::new(0));
+
+    let (bucket, key) = parse_s3_url("s3://images/app/layer.tar").unwrap();
+    let (data, counter) = (tar.clone(), fetched.clone());
+    let img = TarImage::open_object(&bucket, &key, move |req: &RangeRequest| {
+        assert_eq!((req.bucket, req.key), ("images", "app/layer.tar"));
+        let end = (req.end as usize).min(data.len() - 1);
+        let body = data[req.start as usize..=end].to_vec();
+        counter.fetch_add(body.len() as u64, Ordering::Relaxed);
+        Ok(RangeResponse { body, total_len: data.len() as u64 })
+    }).unwrap();
+    let mut img = lock_image(&img).unwrap();
+    assert_eq!(img.get_path(), "s3://images/app/layer.tar");
+    // 远程对象上用较小的预读块，扫描 header 时少下载无关数据
+    img.set_readahead(64 * 1024);
+
+    let index = img.build_index().unwrap();
+    assert_eq!(index.len(), 2);
+    let mut out = Vec::new();
+    extract_file_to(&mut img, "layer/config.json", &mut out).unwrap();
+    assert_eq!(out, b"{}");
+    // 大文件的数据区没有被下载
+    assert!(fetched.load(Ordering::Relaxed) < 512 * 1024, "fetched {} bytes", fetched.load(Ordering::Relaxed));
+
+    assert!(parse_s3_url("s3://bucket-only").is_err());
+}
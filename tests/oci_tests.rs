@@ -0,0 +1,98 @@
+mod common;
+
+use common::{build_tar, temp_dir, write_tar, Fixture};
+use pt::oci::OciImage;
+
+fn layers() -> (Vec<u8>, Vec<u8>) {
+    let lower = build_tar(&[
+        Fixture::dir("etc/"),
+        Fixture::file("etc/passwd", b"root"),
+        Fixture::file("etc/hosts", b"localhost"),
+        Fixture::dir("opt/"),
+        Fixture::file("opt/app", b"old"),
+    ]);
+    let upper = build_tar(&[
+        Fixture::file("etc/.wh.passwd", b""),
+        Fixture::file("etc/hosts", b"example"),
+        Fixture::file("opt", b"now a file"),
+    ]);
+    (lower, upper)
+}
+
+fn merged_paths(image: &OciImage) -> Vec<(String, usize)> {
+    image.merged().unwrap().into_iter().map(|(p, e)| (p, e.layer)).collect()
+}
+
+#[test]
+fn test_docker_save_tarball() {
+    let (lower, upper) = layers();
+    let manifest = br#"[{"Config":"c.json","RepoTags":["app:latest"],"Layers":["l1/layer.tar","l2/layer.tar"]}]"#;
+    let dir = temp_dir("oci_docker");
+    let path = write_tar(&dir, "image.tar", &[
+        Fixture::file("l1/layer.tar", &lower),
+        Fixture::file("l2/layer.tar", &upper),
+        Fixture::file("manifest.json", manifest),
+    ]);
+
+    let image = OciImage::open(&path).unwrap();
+    assert_eq!(image.layers().len(), 2);
+    assert_eq!(image.repo_tags(), ["app:latest"]);
+    assert_eq!(merged_paths(&image), [("etc".into(), 0), ("etc/hosts".into(), 1), ("opt".into(), 1)]);
+
+    let merged = image.merged().unwrap();
+    let file = image.open_entry(&merged["etc/hosts"]).unwrap();
+    let mut body = Vec::new();
+    std::io::Read::read_to_end(&mut pt::base::BodyReader::new(&file), &mut body).unwrap();
+    assert_eq!(body, b"example");
+}
+
+#[test]
+fn test_oci_layout_directory() {
+    let (lower, upper) = layers();
+    let dir = temp_dir("oci_layout");
+    let blobs = dir.join("blobs/sha256");
+    std::fs::create_dir_all(&blobs).unwrap();
+    std::fs::write(blobs.join("aaa"), lower).unwrap();
+    std::fs::write(blobs.join("bbb"), upper).unwrap();
+    std::fs::write(blobs.join("mmm"), br#"{"layers":[{"digest":"sha256:aaa"},{"digest":"sha256:bbb"}]}"#).unwrap();
+    std::fs::write(dir.join("index.json"), br#"{"manifests":[{"digest":"sha256:mmm"}]}"#).unwrap();
+
+    let image = OciImage::open(&dir).unwrap();
+    assert_eq!(merged_paths(&image), [("etc".into(), 0), ("etc/hosts".into(), 1), ("opt".into(), 1)]);
+}
+
+#[test]
+fn test_oci_index_cycle_is_rejected() {
+    let dir = temp_dir("oci_cycle");
+    let blobs = dir.join("blobs/sha256");
+    std::fs::create_dir_all(&blobs).unwrap();
+    let index = br#"{"manifests":[{"digest":"sha256:aa"}]}"#;
+    std::fs::write(blobs.join("aa"), index).unwrap();
+    std::fs::write(dir.join("index.json"), index).unwrap();
+
+    let err = OciImage::open(&dir).err().unwrap();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn test_oci_directory_rejects_escaping_names() {
+    let (lower, _) = layers();
+    let dir = temp_dir("oci_escape");
+    let image = dir.join("image");
+    std::fs::create_dir_all(image.join("blobs/sha256")).unwrap();
+    std::fs::write(dir.join("outside.tar"), &lower).unwrap();
+    let rejected = || {
+        let err = OciImage::open(&image).err().unwrap();
+        matches!(pt::error::as_pt_error(&err), Some(pt::error::PtError::UnsafePath { .. }))
+    };
+
+    std::fs::write(image.join("manifest.json"), br#"[{"Layers":["../outside.tar"]}]"#).unwrap();
+    assert!(rejected());
+    let absolute = format!(r#"[{{"Layers":[{:?}]}}]"#, dir.join("outside.tar").to_string_lossy());
+    std::fs::write(image.join("manifest.json"), absolute).unwrap();
+    assert!(rejected());
+
+    std::fs::remove_file(image.join("manifest.json")).unwrap();
+    std::fs::write(image.join("index.json"), br#"{"manifests":[{"digest":"sha256:../../../outside.tar"}]}"#).unwrap();
+    assert!(rejected());
+}
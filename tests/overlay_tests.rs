@@ -0,0 +1,31 @@
+mod common;
+
+use common::{temp_dir, write_tar, Fixture};
+use pt::base::{ImageInfo, TarImage};
+use pt::overlay::{Overlay, OverlayOrder, Resolved};
+
+#[test]
+fn test_overlay_prefers_configured_side() {
+    let dir = temp_dir("overlay");
+    let path = write_tar(&dir, "a.tar", &[
+        Fixture::file("etc/hosts", b"archive hosts"),
+        Fixture::file("etc/passwd", b"archive passwd"),
+    ]);
+    let local = dir.join("patch");
+    std::fs::create_dir_all(local.join("etc")).unwrap();
+    std::fs::write(local.join("etc/hosts"), b"patched hosts").unwrap();
+    std::fs::write(local.join("etc/extra"), b"extra").unwrap();
+    std::fs::write(dir.join("secret"), b"outside").unwrap();
+
+    let overlay = Overlay::new(&local, TarImage::open(&path).unwrap(), OverlayOrder::LocalFirst).unwrap();
+    assert_eq!(overlay.read("/etc/hosts").unwrap().unwrap(), b"patched hosts");
+    assert_eq!(overlay.read("etc/passwd").unwrap().unwrap(), b"archive passwd");
+    assert!(overlay.read("etc/missing").unwrap().is_none());
+    assert!(overlay.read("../secret").unwrap().is_none());
+    assert_eq!(overlay.read_dir("etc").unwrap(), ["extra", "hosts", "passwd"]);
+
+    let overlay = Overlay::new(&local, TarImage::open(&path).unwrap(), OverlayOrder::ArchiveFirst).unwrap();
+    assert!(matches!(overlay.resolve("etc/hosts"), Some(Resolved::Archive(_))));
+    assert!(matches!(overlay.resolve("etc/extra"), Some(Resolved::Local(..))));
+    assert_eq!(overlay.read("etc/hosts").unwrap().unwrap(), b"archive hosts");
+}
@@ -0,0 +1,35 @@
+#![cfg(feature = "rayon")]
+
+mod common;
+
+use std::io::Read;
+use std::sync::Mutex;
+use common::{temp_dir, write_tar, Fixture};
+use pt::base::{lock_image, BodyReader, ImageInfo, TarImage};
+
+#[test]
+fn test_par_for_each_entry_reads_every_body() {
+    let names: Vec<_> = (0..200).map(|i| format!("f{i}")).collect();
+    let fixtures: Vec<_> = names.iter().map(|n| Fixture::file(n, n.as_bytes())).collect();
+    let path = write_tar(&temp_dir("parallel"), "a.tar", &fixtures);
+
+    let img = TarImage::open(&path).unwrap();
+    let seen = Mutex::new(Vec::new());
+    lock_image(&img).unwrap().par_for_each_entry(|file| {
+        let mut body = String::new();
+        BodyReader::new(&file).read_to_string(&mut body)?;
+        assert_eq!(body, file.get_path());
+        seen.lock().unwrap().push(body);
+        Ok(())
+    }).unwrap();
+    let mut seen = seen.into_inner().unwrap();
+    seen.sort();
+    let mut expected = names.clone();
+    expected.sort();
+    assert_eq!(seen, expected);
+
+    let err = lock_image(&img).unwrap().par_for_each_entry(|file| {
+        if file.get_path() == "f7" { Err(std::io::Error::other("stop")) } else { Ok(()) }
+    });
+    assert!(err.is_err());
+}
@@ -0,0 +1,28 @@
+use pt::path::{find_reserved_windows_name, is_reserved_windows_name, sanitize_path, strip_absolute};
+
+#[test]
+fn test_windows_reserved_names() {
+    assert!(is_reserved_windows_name("CON"));
+    assert!(is_reserved_windows_name("nul.txt"));
+    assert!(is_reserved_windows_name("Com1 "));
+    assert!(!is_reserved_windows_name("CONSOLE"));
+    assert!(!is_reserved_windows_name("COM0"));
+    assert_eq!(find_reserved_windows_name("a/b/aux.h"), Some("aux.h"));
+    assert_eq!(find_reserved_windows_name("a/b/c.h"), None);
+}
+
+#[test]
+fn test_strip_absolute() {
+    assert_eq!(strip_absolute(b"/etc/passwd"), b"etc/passwd");
+    assert_eq!(strip_absolute(b"C:\\Windows"), b"Windows");
+    assert_eq!(strip_absolute(b"rel/path"), b"rel/path");
+}
+
+#[test]
+fn test_sanitize_path() {
+    assert_eq!(sanitize_path("./usr//bin/./sh").unwrap(), "usr/bin/sh");
+    assert_eq!(sanitize_path("a..b/c..").unwrap(), "a..b/c..");
+    for bad in ["../etc/passwd", "a/../../b", "/etc/passwd", "C:\\Windows", "a\\..\\..\\b"] {
+        assert!(sanitize_path(bad).is_err(), "{}", bad);
+    }
+}
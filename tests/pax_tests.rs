@@ -0,0 +1,370 @@
+mod common;
+
+use common::{pax_record, temp_dir, write_tar, Fixture};
+use pt::base::{lock_image, ImageInfo, TarImage};
+use pt::pax::parse_pax_records;
+
+#[test]
+fn test_pax_path_and_xattrs() {
+    let long = format!("{}/file", "p".repeat(200));
+    let mut records = pax_record("path", long.as_bytes());
+    records.extend(pax_record("SCHILY.xattr.security.capability", b"\x01\x00\x00\x02"));
+    records.extend(pax_record("SCHILY.xattr.user.k", b"v"));
+    assert_eq!(parse_pax_records(&records).unwrap().len(), 3);
+
+    let dir = temp_dir("pax");
+    let path = write_tar(&dir, "a.tar", &[
+        Fixture { type_flag: b'x', ..Fixture::file("PaxHeaders/file", &records) },
+        Fixture::file("short", b"data"),
+        Fixture::file("plain", b""),
+    ]);
+    let img = TarImage::open(&path).unwrap();
+    let entries = lock_image(&img).unwrap().scan().unwrap().entries;
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].path, long);
+    assert_eq!(entries[0].xattrs.get("security.capability").unwrap(), b"\x01\x00\x00\x02");
+    assert_eq!(entries[0].xattrs.get("user.k").unwrap(), b"v");
+    assert!(entries[1].xattrs.is_empty());
+}
+
+#[test]
+fn test_parse_acl_and_linux_encoding() {
+    use pt::acl::{parse_acl, AclTag};
+
+    let acl = parse_acl("user::rwx,user:alice:r-x:1000,group::r--,mask::r-x,other::---").unwrap();
+    assert_eq!(acl.entries.len(), 5);
+    assert_eq!(acl.entries[1].tag, AclTag::User { name: "alice".into(), id: Some(1000) });
+    assert_eq!(acl.entries[1].perms, 5);
+    assert_eq!(acl.to_string(), "user::rwx,user:alice:r-x,group::r--,mask::r-x,other::---");
+
+    let raw = acl.to_linux_xattr(|_| None, |_| None).unwrap();
+    assert_eq!(raw.len(), 4 + 5 * 8);
+    assert_eq!(&raw[..4], &2u32.to_le_bytes());
+    // 第二条为命名用户 (tag 0x02, id 1000)
+    assert_eq!(&raw[12..20], &[0x02, 0, 5, 0, 0xe8, 0x03, 0, 0]);
+
+    assert!(parse_acl("user:bob:rwz").is_err());
+    assert!(parse_acl("user:nobody-here:rwx").unwrap().to_linux_xattr(|_| None, |_| None).is_err());
+}
+
+#[test]
+fn test_pax_body_checksums() {
+    use pt::base::try_into_tarfile;
+    use pt::checksum::IntegrityStatus;
+    use std::io::Read;
+
+    // "hello" 的 CRC-32 为 3610a686
+    let good = pax_record("SCHILY.crc32", b"3610a686");
+    let mut bad = pax_record("SCHILY.crc32", b"3610a686");
+    bad.extend(pax_record("VENDOR.sha256", "00".repeat(32).as_bytes()));
+
+    let dir = temp_dir("pax_checksum");
+    let path = write_tar(&dir, "a.tar", &[
+        Fixture { type_flag: b'x', ..Fixture::file("PaxHeaders/good", &good) },
+        Fixture::file("good", b"hello"),
+        Fixture { type_flag: b'x', ..Fixture::file("PaxHeaders/bad", &bad) },
+        Fixture::file("bad", b"hello"),
+        Fixture::file("plain", b"hello"),
+    ]);
+    let img = TarImage::open(&path).unwrap();
+    let mut img = lock_image(&img).unwrap();
+
+    let report = pt::verify::verify(&mut img).unwrap();
+    assert_eq!(report.checksummed, 2);
+    assert_eq!(report.checksum_mismatches, [("bad".to_string(), "VENDOR.sha256".to_string())]);
+    assert!(!report.is_ok());
+
+    let (file, _) = img.get_file_at(0).unwrap();
+    let file = try_into_tarfile(file).unwrap();
+    assert_eq!(file.verify_body().unwrap(), IntegrityStatus::Ok);
+    let (file, _) = img.get_file_at(2048).unwrap();
+    let file = try_into_tarfile(file).unwrap();
+    let mut body = Vec::new();
+    assert!(file.verified_reader().read_to_end(&mut body).is_err());
+}
+
+#[test]
+fn test_digest_manifest() {
+    use pt::checksum::{to_hex, DigestAlgorithm};
+
+    let dir = temp_dir("digest_manifest");
+    let path = write_tar(&dir, "a.tar", &[
+        Fixture::file("a", b"hello"),
+        Fixture::dir("d/"),
+        Fixture::file("b", b""),
+    ]);
+    let img = TarImage::open(&path).unwrap();
+    let manifest = lock_image(&img).unwrap().digest_manifest(DigestAlgorithm::Sha256).unwrap();
+    assert_eq!(manifest.len(), 2);
+    assert_eq!(to_hex(&manifest["a"]), "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824");
+    assert_eq!(to_hex(&manifest["b"]), "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855");
+
+    let sha512 = lock_image(&img).unwrap().digest_manifest(DigestAlgorithm::Sha512).unwrap();
+    assert_eq!(sha512["a"].len(), 64);
+}
+
+#[test]
+fn test_limits_reject_oversized_archives() {
+    use pt::error::{as_pt_error, PtError};
+    use pt::limits::Limits;
+
+    let records = pax_record("comment", &[b'c'; 600]);
+    let dir = temp_dir("limits");
+    let path = write_tar(&dir, "a.tar", &[
+        Fixture::file("a", &[1u8; 100]),
+        Fixture { type_flag: b'x', ..Fixture::file("PaxHeaders/b", &records) },
+        Fixture::file("b", &[2u8; 100]),
+        Fixture::file("c", &[3u8; 100]),
+    ]);
+    let img = TarImage::open(&path).unwrap();
+    let exceeded = |limits: Limits| {
+        let mut img = lock_image(&img).unwrap();
+        img.set_limits(limits);
+        let err = img.for_each_entry(|_| Ok(())).unwrap_err();
+        match as_pt_error(&err) {
+            Some(PtError::LimitExceeded { limit, .. }) => *limit,
+            other => panic!("unexpected error {:?}", other),
+        }
+    };
+
+    assert_eq!(exceeded(Limits { max_entries: Some(2), ..Default::default() }), "entry count");
+    assert_eq!(exceeded(Limits { max_entry_size: Some(99), ..Default::default() }), "entry size");
+    assert_eq!(exceeded(Limits { max_total_size: Some(250), ..Default::default() }), "total size");
+    assert_eq!(exceeded(Limits { max_pax_size: Some(512), ..Default::default() }), "extension header size");
+
+    let mut img = lock_image(&img).unwrap();
+    img.set_limits(Limits::untrusted());
+    img.for_each_entry(|_| Ok(())).unwrap();
+}
+
+#[test]
+fn test_huge_extension_size_is_rejected() {
+    use common::{fix_checksum, header_block};
+
+    // 默认 Limits 下，声称 2^60 字节的扩展 header 在分配之前就被拒绝
+    for flag in [b'x', b'g', b'L', b'K'] {
+        let mut tar = header_block(&Fixture { type_flag: flag, ..Fixture::file("././@LongLink", b"") }).to_vec();
+        tar[124..136].fill(0);
+        tar[124] = 0x80;
+        tar[128..136].copy_from_slice(&(1u64 << 60).to_be_bytes());
+        fix_checksum(&mut tar);
+        tar.extend(common::build_tar(&[Fixture::file("a", b"a")]));
+        let dir = temp_dir("huge_extension");
+        let path = dir.join("a.tar");
+        std::fs::write(&path, &tar).unwrap();
+        let img = TarImage::open(path.to_str().unwrap()).unwrap();
+        let err = lock_image(&img).unwrap().for_each_entry(|_| Ok(())).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData, "{}", flag as char);
+    }
+}
+
+#[test]
+fn test_pax_global_defaults() {
+    let mut globals = pax_record("comment", b"nightly build");
+    globals.extend(pax_record("uid", b"4242"));
+    globals.extend(pax_record("mtime", b"1700000000"));
+    let mut reset = pax_record("uid", b"");
+    reset.extend(pax_record("comment", b"release"));
+    let dir = temp_dir("pax_global");
+    let path = write_tar(&dir, "a.tar", &[
+        Fixture { type_flag: b'g', ..Fixture::file("pax_global_header", &globals) },
+        Fixture::file("a", b"a"),
+        Fixture { type_flag: b'x', ..Fixture::file("PaxHeaders/b", &pax_record("uid", b"7")) },
+        Fixture::file("b", b"b"),
+        Fixture { type_flag: b'g', ..Fixture::file("pax_global_header", &reset) },
+        Fixture::file("c", b"c"),
+    ]);
+    let img = TarImage::open(&path).unwrap();
+    let mut img = lock_image(&img).unwrap();
+    // 'g' header 不作为条目出现
+    let entries = img.scan().unwrap().entries;
+    let owners: Vec<_> = entries.iter().map(|m| (m.path.as_str(), m.uid, m.mtime)).collect();
+    // 条目自身的记录优先；空值删除之前的全局记录，未被覆盖的 mtime 继续生效
+    assert_eq!(owners, [("a", 4242, 1_700_000_000), ("b", 7, 1_700_000_000), ("c", 0, 1_700_000_000)]);
+
+    let globals = img.pax_globals().unwrap();
+    assert_eq!(globals.get("comment").unwrap(), b"release");
+    assert!(!globals.contains_key("uid"));
+    // 尚未遍历的镜像也能直接取得
+    let fresh = TarImage::open(&path).unwrap();
+    assert_eq!(lock_image(&fresh).unwrap().pax_globals().unwrap(), globals);
+
+    // 删除 'g' 之后的条目时 'g' 仍然保留
+    let filtered = dir.join("filtered.tar");
+    let mut out = std::fs::File::create(&filtered).unwrap();
+    assert_eq!(pt::repack::filter_copy(&mut img, &mut out, |m| m.path != "a" && m.path != "c").unwrap(), 1);
+    let img = TarImage::open(filtered.to_str().unwrap()).unwrap();
+    let entries = lock_image(&img).unwrap().scan().unwrap().entries;
+    assert_eq!(entries.iter().map(|m| (m.path.as_str(), m.mtime)).collect::<Vec<_>>(), [("b", 1_700_000_000)]);
+    assert_eq!(lock_image(&img).unwrap().pax_globals().unwrap(), globals);
+
+    // 流式解包时全局记录同样作用于之后的每个条目
+    let out = dir.join("streamed");
+    let options = pt::extract::ExtractOptions { preserve_times: true, ..Default::default() };
+    pt::extract::unpack_stream(std::fs::File::open(&path).unwrap(), &out, &options).unwrap();
+    for name in ["a", "b", "c"] {
+        let modified = std::fs::metadata(out.join(name)).unwrap().modified().unwrap();
+        assert_eq!(modified, std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_700_000_000), "{}", name);
+    }
+    assert!(!out.join("pax_global_header").exists());
+}
+
+#[test]
+fn test_pax_global_defaults_random_access() {
+    use pt::index::{Index, IndexBudget};
+
+    let mut globals = pax_record("uid", b"4242");
+    globals.extend(pax_record("mtime", b"1700000000"));
+    let dir = temp_dir("pax_global_random");
+    let path = write_tar(&dir, "a.tar", &[
+        Fixture { type_flag: b'g', ..Fixture::file("pax_global_header", &globals) },
+        Fixture::file("a", b"a"),
+        Fixture { type_flag: b'g', ..Fixture::file("pax_global_header", &pax_record("uid", b"")) },
+        Fixture::file("b", b"b"),
+    ]);
+    let img = TarImage::open(&path).unwrap();
+    let scanned = lock_image(&img).unwrap().scan().unwrap().entries;
+    let owners = |m: &pt::meta::EntryMeta| (m.path.clone(), m.uid, m.mtime);
+    assert_eq!(owners(&scanned[1]), ("b".to_string(), 0, 1_700_000_000));
+
+    // 未遍历过的镜像上直接按偏移打开，结果与 scan 相同
+    for meta in &scanned {
+        let fresh = TarImage::open(&path).unwrap();
+        let file = lock_image(&fresh).unwrap().entry_at(meta.offset).unwrap().unwrap();
+        assert_eq!(owners(&file.meta()), owners(meta));
+        let fresh = TarImage::open(&path).unwrap();
+        let file = lock_image(&fresh).unwrap().get_file_at(meta.offset).unwrap().0;
+        let file = file.as_any().downcast_ref::<pt::base::TarFile>().unwrap();
+        assert_eq!(owners(&file.meta()), owners(meta));
+    }
+
+    // 索引文件保存全局记录，读回后在新镜像上 stat 也一致
+    let budget = IndexBudget { max_bytes: 0, sidecar_dir: Some(dir.clone()) };
+    let mut img = lock_image(&img).unwrap();
+    for index in [Index::build(&mut img).unwrap(), Index::build_with_budget(&mut img, &budget).unwrap()] {
+        index.save(dir.join("a.toc")).unwrap();
+        let fresh = TarImage::open(&path).unwrap();
+        let mut fresh = lock_image(&fresh).unwrap();
+        let back = Index::load(dir.join("a.toc"), &mut fresh).unwrap();
+        for meta in &scanned {
+            assert_eq!(owners(&back.stat(&mut fresh, &meta.path).unwrap().unwrap()), owners(meta));
+        }
+    }
+}
+
+#[test]
+fn test_signed_and_far_future_mtime() {
+    use std::time::{Duration, UNIX_EPOCH};
+    use pt::tar::{TarHeader, TypeFlag};
+
+    // 1960 年与 2500 年：分别需要 base-256 负数与超过 11 位八进制的 base-256
+    let header = |name: &str, mtime: i64| {
+        let mut hdr = TarHeader::new(TypeFlag::Regular);
+        hdr.set_path(name).unwrap();
+        hdr.set_mode(0o644).unwrap();
+        hdr.set_mtime(mtime).unwrap();
+        hdr.to_bytes()
+    };
+    let (past, future) = (-315_619_200, 16_725_225_600);
+    assert_eq!(TarHeader::from_bytes(&header("x", past)).get_mtime(), past);
+    let mut tar = header("past", past).to_vec();
+    tar.extend(header("future", future));
+    let mut records = pax_record("mtime", b"-1.25");
+    records.extend(pax_record("atime", b"-10.5"));
+    records.extend(pax_record("ctime", b"-3"));
+    tar.extend(common::build_tar(&[
+        Fixture { type_flag: b'x', ..Fixture::file("PaxHeaders/frac", &records) },
+        Fixture::file("frac", b""),
+    ]));
+    let dir = temp_dir("signed_mtime");
+    let path = dir.join("a.tar");
+    std::fs::write(&path, &tar).unwrap();
+
+    let img = TarImage::open(path.to_str().unwrap()).unwrap();
+    let entries = lock_image(&img).unwrap().scan().unwrap().entries;
+    let times: Vec<_> = entries.iter().map(|m| (m.path.as_str(), m.mtime, m.mtime_nsec)).collect();
+    assert_eq!(times, [("past", past, 0), ("future", future, 0), ("frac", -2, 750_000_000)]);
+    assert_eq!(entries[2].modified(), UNIX_EPOCH - Duration::from_millis(1250));
+    assert_eq!(entries[2].atime, Some((-11, 500_000_000)));
+    assert_eq!(entries[2].accessed(), Some(UNIX_EPOCH - Duration::from_millis(10_500)));
+    assert_eq!(entries[2].changed(), Some(UNIX_EPOCH - Duration::from_secs(3)));
+
+    #[cfg(unix)]
+    {
+        use pt::extract::{extract_all_with, ExtractOptions};
+        let out = dir.join("out");
+        let options = ExtractOptions { preserve_times: true, ..Default::default() };
+        extract_all_with(&mut lock_image(&img).unwrap(), &out, &options).unwrap();
+        let modified = std::fs::metadata(out.join("past")).unwrap().modified().unwrap();
+        assert_eq!(modified, UNIX_EPOCH - Duration::from_secs(315_619_200));
+    }
+}
+
+#[test]
+fn test_hdrcharset_binary_names() {
+    let name: &[u8] = b"dir/caf\xe9.txt";
+    let mut records = pax_record("hdrcharset", b"BINARY");
+    records.extend(pax_record("path", name));
+    let mut link = pax_record("hdrcharset", b"BINARY");
+    link.extend(pax_record("linkpath", b"caf\xe9.txt"));
+    let dir = temp_dir("pax_binary");
+    let path = write_tar(&dir, "a.tar", &[
+        Fixture::dir("dir/"),
+        Fixture { type_flag: b'x', ..Fixture::file("PaxHeaders/file", &records) },
+        Fixture::file("placeholder", b"latin-1"),
+        Fixture { type_flag: b'x', ..Fixture::file("PaxHeaders/link", &link) },
+        Fixture::symlink("dir/link", "placeholder"),
+    ]);
+    let img = TarImage::open(&path).unwrap();
+    let mut img = lock_image(&img).unwrap();
+    let file = img.find_entry_bytes(b"./dir/caf\xe9.txt").unwrap().unwrap();
+    assert_eq!(file.get_path_bytes(), name);
+    assert!(img.find_entry("dir/caf\u{fffd}.txt").unwrap().is_none());
+
+    let entries: Vec<_> = img.scan().unwrap().entries.into_iter().filter(|m| m.type_flag != 'x').collect();
+    assert_eq!(entries[1].path_bytes(), name);
+    assert_eq!(entries[1].path, "dir/caf\u{fffd}.txt");
+    assert_eq!(entries[2].raw_path, None);
+    assert!(entries[2].long_format_bytes().ends_with(b"dir/link -> caf\xe9.txt"));
+
+    #[cfg(unix)]
+    for sandboxed in [false, true] {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+        use pt::extract::{extract_all_with, ExtractOptions};
+        let out = dir.join(if sandboxed { "sandboxed" } else { "plain" });
+        extract_all_with(&mut img, &out, &ExtractOptions { sandboxed, ..Default::default() }).unwrap();
+        let target = out.join(OsStr::from_bytes(name));
+        assert_eq!(std::fs::read(&target).unwrap(), b"latin-1");
+        assert_eq!(std::fs::read_link(out.join("dir/link")).unwrap().as_os_str().as_bytes(), b"caf\xe9.txt");
+        assert_eq!(std::fs::read(out.join("dir/link")).unwrap(), b"latin-1");
+    }
+}
+
+#[test]
+fn test_solaris_extended_header() {
+    let long = format!("{}/file", "s".repeat(150));
+    let mut records = pax_record("path", long.as_bytes());
+    records.extend(pax_record("mtime", b"1234567890.5"));
+    records.extend(pax_record("SCHILY.xattr.user.k", b"v"));
+    let dir = temp_dir("pax_solaris");
+    let path = write_tar(&dir, "a.tar", &[
+        Fixture { type_flag: b'X', ..Fixture::file("X/file", &records) },
+        Fixture::file("short", b"data"),
+        Fixture::file("plain", b""),
+    ]);
+    let img = TarImage::open(&path).unwrap();
+    let mut img = lock_image(&img).unwrap();
+    let entries = img.scan().unwrap().entries;
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].path, long);
+    assert_eq!((entries[0].mtime, entries[0].mtime_nsec), (1_234_567_890, 500_000_000));
+    assert_eq!(entries[0].xattrs.get("user.k").unwrap(), b"v");
+    let file = img.find_entry(&long).unwrap().unwrap();
+    assert!(file.vendor_records().is_empty());
+    assert_eq!(entries[1].path, "plain");
+
+    let out = dir.join("out");
+    pt::extract::unpack_stream(std::fs::File::open(&path).unwrap(), &out, &Default::default()).unwrap();
+    assert_eq!(std::fs::read(out.join(&long)).unwrap(), b"data");
+}
@@ -1,21 +1,594 @@
-use pt::base::{try_into_tarfile, ImageInfo, TarImage};
+mod common;
+
+use common::{build_tar, fix_checksum, temp_dir, Fixture};
+use pt::base::{lock_image, try_into_tarfile, ImageInfo, TarImage};
 
 #[test]
 fn test_pt_tar() {
     let path = r#"C:\Program Files\Docker\Docker\resources\wsl\wsl-bootstrap.tar"#;
-    let img = match TarImage::open(path) {
-        Ok(img) => img,
-        Err(e) => {
-            println!("Error opening tar file: {}", e);
-            return;
-        }
+    let Ok(img) = TarImage::open(path) else {
+        // 本机没有该镜像时跳过
+        return;
     };
-    match img.try_lock().unwrap().for_each_entry(|file| {
-        let tarfile = try_into_tarfile(file).unwrap();
-        println!("{} : {} {} {}", tarfile.get_offset(),tarfile.get_name(), tarfile.get_size(), tarfile.get_type_flag());
+    let outcome = lock_image(&img).unwrap().scan().unwrap();
+    assert!(!outcome.entries.is_empty());
+    for meta in &outcome.entries {
+        assert!(meta.data_offset >= meta.offset + 512);
+    }
+}
+
+#[test]
+fn test_non_utf8_names_do_not_end_iteration() {
+    let mut bytes = build_tar(&[Fixture::file("caf?.txt", b"latin1"), Fixture::file("next", b"n")]);
+    bytes[3] = 0xe9;
+    fix_checksum(&mut bytes[..512]);
+    let path = temp_dir("non_utf8").join("a.tar");
+    std::fs::write(&path, bytes).unwrap();
+
+    let img = TarImage::open(path.to_str().unwrap()).unwrap();
+    let mut seen = Vec::new();
+    lock_image(&img).unwrap().for_each_entry(|f| {
+        let f = try_into_tarfile(f)?;
+        seen.push((f.get_path(), f.get_path_bytes()));
+        Ok(())
+    }).unwrap();
+    assert_eq!(seen.len(), 2);
+    assert_eq!(seen[0].0, "caf\u{fffd}.txt");
+    assert_eq!(seen[0].1, b"caf\xe9.txt");
+    assert_eq!(seen[1].0, "next");
+}
+
+#[test]
+fn test_absolute_paths_are_stripped_by_default() {
+    use pt::index::Index;
+
+    let dir = temp_dir("absolute");
+    let path = common::write_tar(&dir, "a.tar", &[Fixture::file("/etc/passwd", b"root"), Fixture::file("C:/boot.ini", b"x")]);
+    let img = TarImage::open(&path).unwrap();
+    let mut img = lock_image(&img).unwrap();
+    let paths: Vec<_> = img.scan().unwrap().entries.into_iter().map(|m| m.path).collect();
+    assert_eq!(paths, ["etc/passwd", "boot.ini"]);
+    assert!(Index::build(&mut img).unwrap().get("etc/passwd").is_some());
+
+    img.set_keep_absolute_paths(true);
+    let paths: Vec<_> = img.scan().unwrap().entries.into_iter().map(|m| m.path).collect();
+    assert_eq!(paths, ["/etc/passwd", "C:/boot.ini"]);
+}
+
+#[test]
+fn test_perf_counters_track_scans() {
+    let dir = temp_dir("perf");
+    let path = common::write_tar(&dir, "a.tar", &[Fixture::file("a", b"1"), Fixture::file("b", b"2")]);
+    let img = TarImage::open(&path).unwrap();
+    let before = pt::perf::snapshot();
+    lock_image(&img).unwrap().scan().unwrap();
+    // 其它测试可能并发运行，只检查下界
+    let delta = pt::perf::snapshot().since(&before);
+    assert!(delta.headers_parsed >= 2);
+    assert!(delta.blocks_read >= 4);
+    assert!(delta.bytes_read >= 4 * 512);
+}
+
+#[test]
+fn test_device_entries() {
+    use pt::tar::TarFileType;
+
+    let mut bytes = build_tar(&[Fixture { type_flag: b'3', ..Fixture::file("dev/null", b"") }]);
+    bytes[329..337].copy_from_slice(b"0000001\0");
+    bytes[337..345].copy_from_slice(b"0000003\0");
+    fix_checksum(&mut bytes[..512]);
+    let path = temp_dir("devices").join("a.tar");
+    std::fs::write(&path, bytes).unwrap();
+
+    let img = TarImage::open(path.to_str().unwrap()).unwrap();
+    let (file, _) = lock_image(&img).unwrap().get_file_at(0).unwrap();
+    let file = try_into_tarfile(file).unwrap();
+    assert_eq!(file.get_file_type(), TarFileType::CharacterDevice as i32);
+    assert_eq!((file.get_header().get_devmajor(), file.get_header().get_devminor()), (1, 3));
+    let meta = file.meta();
+    assert_eq!((meta.devmajor, meta.devminor), (1, 3));
+    assert!(meta.long_format().starts_with("crw-r--r-- 0/0             1,3 "));
+    assert_eq!(meta.to_json()["devminor"], 3);
+
+    // GNU base-256 编码的设备号
+    let mut hdr = pt::tar::TarHeader::new(pt::tar::TypeFlag::BlockDevice);
+    hdr.set_device(259, 0x1234_5678).unwrap();
+    let block = hdr.to_bytes();
+    assert_eq!(block[337], 0x80);
+    let parsed = pt::tar::TarHeader::from_bytes(&block);
+    assert_eq!((parsed.get_devmajor(), parsed.get_devminor()), (259, 0x1234_5678));
+}
+
+#[test]
+fn test_readahead_serves_header_scans() {
+    let names: Vec<_> = (0..50).map(|i| format!("f{i}")).collect();
+    let fixtures: Vec<_> = names.iter().map(|n| Fixture::file(n, b"body")).collect();
+    let path = common::write_tar(&temp_dir("readahead"), "a.tar", &fixtures);
+    let img = TarImage::open(&path).unwrap();
+    let mut img = lock_image(&img).unwrap();
+    assert_eq!(img.readahead(), pt::base::DEFAULT_READAHEAD);
+
+    let before = pt::perf::snapshot();
+    assert_eq!(img.scan().unwrap().entries.len(), 50);
+    // 整个归档只有一个预读块，之后的 header 都命中缓冲
+    assert!(pt::perf::snapshot().since(&before).cache_hits >= 49);
+
+    img.set_readahead(0);
+    assert_eq!(img.scan().unwrap().entries.len(), 50);
+}
+
+#[test]
+fn test_prelude_type_flags() {
+    use pt::prelude::*;
+
+    let dir = temp_dir("prelude");
+    let path = common::write_tar(&dir, "a.tar", &[Fixture::dir("d/"), Fixture::file("d/f", b"x"), Fixture::symlink("l", "d/f")]);
+    let img = TarImage::open(&path).unwrap();
+    let mut flags = Vec::new();
+    lock_image(&img).unwrap().for_each_entry(|f| {
+        flags.push(try_into_tarfile(f)?.type_flag());
         Ok(())
-    }) {
-        Ok(_) => println!("Successfully iterated through tar entries."),
-        Err(e) => println!("Error iterating through tar entries: {}", e),
+    }).unwrap();
+    assert_eq!(flags, vec![TypeFlag::Directory, TypeFlag::Regular, TypeFlag::Symlink]);
+    assert_eq!(TypeFlag::from_byte(b'S'), TypeFlag::Other(b'S'));
+    assert_eq!(TypeFlag::from_byte(0).as_byte(), b'0');
+}
+
+#[test]
+fn test_cancel_token_stops_scans() {
+    use pt::cancel::CancelToken;
+    use pt::error::{as_pt_error, PtError};
+    use pt::index::Index;
+
+    let dir = temp_dir("cancel");
+    let path = common::write_tar(&dir, "a.tar", &[Fixture::file("a", b"a"), Fixture::file("b", b"b"), Fixture::file("c", b"c")]);
+    let img = TarImage::open(&path).unwrap();
+    let token = CancelToken::new();
+    lock_image(&img).unwrap().set_cancel_token(token.clone());
+
+    let mut seen = 0;
+    let err = lock_image(&img).unwrap().for_each_entry(|_| {
+        seen += 1;
+        if seen == 2 {
+            token.cancel();
+        }
+        Ok(())
+    }).unwrap_err();
+    assert_eq!(seen, 2);
+    assert_eq!(as_pt_error(&err), Some(&PtError::Cancelled));
+
+    let err = Index::build(&mut lock_image(&img).unwrap()).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::Interrupted);
+}
+
+#[test]
+fn test_traversal_can_skip_and_stop() {
+    use std::ops::ControlFlow;
+    use pt::base::Action;
+
+    let tar = build_tar(&[
+        Fixture::dir("a/"),
+        Fixture::file("a/1", b"1"),
+        Fixture::dir("a/sub/"),
+        Fixture::file("a/sub/x", b"x"),
+        Fixture::file("a/2", b"2"),
+        Fixture::dir("b/"),
+        Fixture::file("b/1", b"1"),
+        Fixture::file("c", b"c"),
+        Fixture::file("d", b"d"),
+    ]);
+    let path = temp_dir("traversal_ctrl").join("a.tar");
+    std::fs::write(&path, tar).unwrap();
+    let img = TarImage::open(path.to_str().unwrap()).unwrap();
+    let mut img = lock_image(&img).unwrap();
+
+    let mut seen = Vec::new();
+    img.for_each_entry_ctrl(|file| {
+        let path = file.get_path();
+        seen.push(path.clone());
+        Ok(match path.as_str() {
+            // 跳过 a/ 中余下的条目，包括子目录
+            "a/1" => ControlFlow::Continue(Action::SkipSiblings),
+            "c" => ControlFlow::Break(()),
+            _ => ControlFlow::Continue(Action::Continue),
+        })
+    }).unwrap();
+    assert_eq!(seen, ["a/", "a/1", "b/", "b/1", "c"]);
+
+    // 顶层条目跳过同级即跳过其余全部
+    let mut seen = 0;
+    img.for_each_entry_ctrl(|_| {
+        seen += 1;
+        Ok(ControlFlow::Continue(Action::SkipSiblings))
+    }).unwrap();
+    assert_eq!(seen, 1);
+}
+
+#[test]
+fn test_strict_mode_validates_magic_and_numbers() {
+    use pt::error::{as_pt_error, PtError};
+
+    let open = |name: &str, patch: &dyn Fn(&mut [u8])| {
+        let mut bytes = build_tar(&[Fixture::file("a", b"a"), Fixture::file("b", b"b")]);
+        patch(&mut bytes[1024..1536]);
+        fix_checksum(&mut bytes[1024..1536]);
+        let path = temp_dir(name).join("a.tar");
+        std::fs::write(&path, bytes).unwrap();
+        TarImage::open(path.to_str().unwrap()).unwrap()
     };
-}
\ No newline at end of file
+    let strict_scan = |img: &std::sync::Arc<std::sync::Mutex<TarImage>>| {
+        let mut img = lock_image(img).unwrap();
+        img.set_strict(true);
+        img.scan().map(|o| o.entries.len())
+    };
+
+    // GNU 的 "ustar  " 与 POSIX 的 "ustar\000" 都接受
+    let gnu = open("strict_gnu", &|h| h[257..265].copy_from_slice(b"ustar  \0"));
+    assert_eq!(strict_scan(&gnu).unwrap(), 2);
+
+    // 非严格模式照常读取，严格模式报告第二个 header 的偏移
+    let v7 = open("strict_v7", &|h| h[257..265].fill(0));
+    assert_eq!(lock_image(&v7).unwrap().scan().unwrap().entries.len(), 2);
+    let err = strict_scan(&v7).unwrap_err();
+    assert!(matches!(as_pt_error(&err), Some(PtError::MalformedHeader { offset: 1024, .. })), "{}", err);
+
+    let garbage = open("strict_mode_field", &|h| h[100..108].copy_from_slice(b"0006x4\0\0"));
+    let err = strict_scan(&garbage).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    assert!(err.to_string().contains("mode field"), "{}", err);
+
+    // 恢复模式跳过不合格的 header 继续
+    let mut img = lock_image(&garbage).unwrap();
+    img.set_strict(true);
+    let mut skipped = 0;
+    img.for_each_entry_recover(|_| Ok(()), |_| skipped += 1).unwrap();
+    assert_eq!(skipped, 1);
+}
+
+#[test]
+fn test_tar_file_read_is_bounded_to_body() {
+    use std::io::{Read, Seek, SeekFrom};
+
+    let body: Vec<u8> = (0..700u32).map(|i| i as u8).collect();
+    let dir = temp_dir("tarfile_read");
+    let path = common::write_tar(&dir, "a.tar", &[Fixture::file("pad", b"p"), Fixture::file("f", &body)]);
+    let img = TarImage::open(&path).unwrap();
+    let mut file = lock_image(&img).unwrap().find_entry("f").unwrap().unwrap();
+
+    let mut out = Vec::new();
+    std::io::copy(&mut file, &mut out).unwrap();
+    assert_eq!(out, body);
+
+    assert_eq!(file.seek(SeekFrom::End(-10)).unwrap(), 690);
+    let mut tail = Vec::new();
+    file.read_to_end(&mut tail).unwrap();
+    assert_eq!(tail, &body[690..]);
+    file.seek(SeekFrom::Start(100)).unwrap();
+    file.seek(SeekFrom::Current(-50)).unwrap();
+    let mut buf = [0u8; 4];
+    file.read_exact(&mut buf).unwrap();
+    assert_eq!(buf, [50, 51, 52, 53]);
+}
+
+#[test]
+fn test_tar_image_read_keeps_position() {
+    use std::io::{Read, Seek, SeekFrom};
+
+    let dir = temp_dir("image_read_position");
+    let path = common::write_tar(&dir, "a.tar", &[Fixture::file("a", b"first"), Fixture::file("b", b"second")]);
+    let raw = std::fs::read(&path).unwrap();
+    let img = TarImage::open(&path).unwrap();
+    let mut img = lock_image(&img).unwrap();
+
+    let mut buf = [0u8; 100];
+    img.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf[..], &raw[..100]);
+    // 遍历条目使用定位读取，不影响 Read 的位置
+    img.for_each_entry(|_| Ok(())).unwrap();
+    img.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf[..], &raw[100..200]);
+    assert_eq!(img.stream_position().unwrap(), 200);
+
+    assert_eq!(img.seek(SeekFrom::End(-24)).unwrap(), raw.len() as u64 - 24);
+    let mut tail = Vec::new();
+    img.read_to_end(&mut tail).unwrap();
+    assert_eq!(tail, &raw[raw.len() - 24..]);
+    assert!(img.seek(SeekFrom::Current(-(raw.len() as i64) - 1)).is_err());
+}
+
+#[test]
+fn test_entries_can_be_read_from_many_threads() {
+    use std::io::Read;
+
+    let bodies: Vec<Vec<u8>> = (0..4u8).map(|i| vec![i; 200_000]).collect();
+    let names = ["a", "b", "c", "d"];
+    let fixtures: Vec<Fixture> = names.iter().zip(&bodies).map(|(n, b)| Fixture::file(n, b)).collect();
+    let dir = temp_dir("concurrent_reads");
+    let path = common::write_tar(&dir, "a.tar", &fixtures);
+    let img = TarImage::open(&path).unwrap();
+    let files: Vec<_> = names.iter().map(|n| lock_image(&img).unwrap().find_entry(n).unwrap().unwrap()).collect();
+
+    // 同一条目的多个克隆与不同条目同时读取
+    std::thread::scope(|scope| {
+        for (i, file) in files.iter().enumerate() {
+            for _ in 0..4 {
+                let mut reader = file.clone();
+                let want = &bodies[i];
+                scope.spawn(move || {
+                    for _ in 0..10 {
+                        assert_eq!(&file.read_to_vec().unwrap(), want);
+                    }
+                    let mut out = Vec::new();
+                    reader.read_to_end(&mut out).unwrap();
+                    assert_eq!(&out, want);
+                });
+            }
+        }
+    });
+}
+
+#[test]
+fn test_read_to_vec_and_string() {
+    let dir = temp_dir("read_to_vec");
+    let path = common::write_tar(&dir, "a.tar", &[Fixture::file("etc/os-release", b"ID=alpine\n"), Fixture::file("bin", b"\xff\xfe")]);
+    let img = TarImage::open(&path).unwrap();
+    let mut img = lock_image(&img).unwrap();
+    let release = img.find_entry("etc/os-release").unwrap().unwrap();
+    assert_eq!(release.read_to_string().unwrap(), "ID=alpine\n");
+    let bin = img.find_entry("bin").unwrap().unwrap();
+    assert_eq!(bin.read_to_vec().unwrap(), b"\xff\xfe");
+    assert_eq!(bin.read_to_string().unwrap_err().kind(), std::io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn test_list_json() {
+    let dir = temp_dir("list_json");
+    let mut exe = Fixture::file("bin/tool", b"#!");
+    exe.mode = 0o755;
+    let path = common::write_tar(&dir, "a.tar", &[Fixture::dir("bin/"), exe, Fixture::symlink("tool", "bin/tool")]);
+    let img = TarImage::open(&path).unwrap();
+    let json: serde_json::Value = serde_json::from_str(&lock_image(&img).unwrap().list_json().unwrap()).unwrap();
+
+    let entries = json.as_array().unwrap();
+    assert_eq!(entries.len(), 3);
+    assert_eq!(entries[0]["type"], "dir");
+    assert_eq!(entries[1], serde_json::json!({
+        "path": "bin/tool", "size": 2, "type": "file", "mode": "0755",
+        "mtime": 1_600_000_000, "link_target": null, "offset": 512,
+    }));
+    assert_eq!(entries[2]["type"], "symlink");
+    assert_eq!(entries[2]["link_target"], "bin/tool");
+}
+
+#[test]
+fn test_long_listing_format() {
+    let dir = temp_dir("long_format");
+    let mut exe = Fixture::file("bin/tool", b"#!");
+    exe.mode = 0o4755;
+    exe.uid = 1000;
+    let mut tmp = Fixture::dir("tmp/");
+    tmp.mode = 0o1777;
+    let path = common::write_tar(&dir, "a.tar", &[exe, tmp, Fixture::symlink("tool", "bin/tool")]);
+    let img = TarImage::open(&path).unwrap();
+    let lines: Vec<String> = lock_image(&img).unwrap().scan().unwrap().entries.iter().map(|m| m.long_format()).collect();
+    assert_eq!(lines, [
+        "-rwsr-xr-x 1000/0            2 2020-09-13 12:26 bin/tool",
+        "drwxrwxrwt 0/0               0 2020-09-13 12:26 tmp/",
+        "lrwxrwxrwx 0/0               0 2020-09-13 12:26 tool -> bin/tool",
+    ]);
+    assert_eq!(pt::Mode::new(0o2644).symbolic(), "rw-r-Sr--");
+}
+
+#[test]
+fn test_archive_stats() {
+    let dir = temp_dir("stats");
+    let big = vec![0u8; 1500];
+    let path = common::write_tar(&dir, "a.tar", &[
+        Fixture::dir("a/"),
+        Fixture::file("a/b/c/deep", b"x"),
+        Fixture::file("a/big", &big),
+        Fixture::symlink("link", "a/big"),
+    ]);
+    let img = TarImage::open(&path).unwrap();
+    let stats = lock_image(&img).unwrap().stats().unwrap();
+
+    assert_eq!(stats.entries, 4);
+    assert_eq!(stats.by_type.get(&'0'), Some(&2));
+    assert_eq!(stats.by_type.get(&'5'), Some(&1));
+    assert_eq!(stats.stored_bytes, 1501);
+    assert_eq!(stats.header_bytes, 4 * 512);
+    assert_eq!(stats.padding_bytes, 511 + 36);
+    assert_eq!(stats.largest, vec![("a/big".to_string(), 1500), ("a/b/c/deep".to_string(), 1)]);
+    assert_eq!(stats.deepest[0], ("a/b/c/deep".to_string(), 4));
+    assert_eq!(stats.deepest.len(), 4);
+}
+
+#[test]
+fn test_recover_skips_corrupt_header() {
+    let mut data = build_tar(&[
+        Fixture::file("a", b"1"),
+        Fixture::file("b", &[b'x'; 700]),
+        Fixture::file("c", b"3"),
+    ]);
+    // 破坏 b 的 header，不修正 checksum
+    data[1024] = b'?';
+    let dir = temp_dir("recover");
+    let path = dir.join("bad.tar");
+    std::fs::write(&path, &data).unwrap();
+    let img = TarImage::open(path.to_str().unwrap()).unwrap();
+    let mut img = lock_image(&img).unwrap();
+    assert!(img.for_each_entry(|_| Ok(())).is_err());
+
+    let mut names = Vec::new();
+    let mut skipped = Vec::new();
+    img.for_each_entry_recover(|file| {
+        names.push(try_into_tarfile(file)?.get_path());
+        Ok(())
+    }, |range| skipped.push(range.clone())).unwrap();
+    assert_eq!(names, ["a", "c"]);
+    assert_eq!(skipped.len(), 1);
+    assert_eq!((skipped[0].start, skipped[0].end), (1024, 1024 + 512 * 3));
+    assert!(skipped[0].reason.contains("checksum"), "{}", skipped[0].reason);
+}
+
+#[test]
+fn test_lenient_iteration_reports_bad_entries() {
+    let mut data = build_tar(&[
+        Fixture::file("a", b""),
+        Fixture::file("b", b""),
+        Fixture::file("c", b""),
+        Fixture::file("d", b"4"),
+    ]);
+    // b 的 checksum 错误；c 的 size 字段无法解析
+    data[512] = b'?';
+    data[1024 + 124..1024 + 128].copy_from_slice(b"zzzz");
+    fix_checksum(&mut data[1024..]);
+    let dir = temp_dir("lenient");
+    let path = dir.join("bad.tar");
+    std::fs::write(&path, &data).unwrap();
+    let img = TarImage::open(path.to_str().unwrap()).unwrap();
+
+    let mut names = Vec::new();
+    let mut errors = Vec::new();
+    lock_image(&img).unwrap().for_each_entry_lenient(|entry| {
+        match entry {
+            Ok(file) => names.push(file.get_path()),
+            Err(e) => errors.push(e),
+        }
+        Ok(())
+    }).unwrap();
+    assert_eq!(names, ["a", "d"]);
+    assert_eq!(errors.iter().map(|e| e.offset).collect::<Vec<_>>(), [512, 1024]);
+    assert!(errors[1].message.contains("invalid size field"), "{}", errors[1].message);
+}
+
+#[test]
+fn test_edit_header_in_place() {
+    let dir = temp_dir("edit_header");
+    let path = dir.join("a.tar");
+    std::fs::write(&path, build_tar(&[
+        Fixture { uid: 1000, gid: 1000, ..Fixture::file("a", b"body") },
+        Fixture::file("b", b"x"),
+    ])).unwrap();
+    let img = TarImage::open(path.to_str().unwrap()).unwrap();
+    let mut img = lock_image(&img).unwrap();
+    let hdr = img.edit_header(0, |hdr| {
+        hdr.set_uid(0)?;
+        hdr.set_gid(0)?;
+        hdr.set_mode(0o600)
+    }).unwrap();
+    assert!(hdr.crc_ok());
+    assert!(img.edit_header(0, |hdr| hdr.set_size(1)).is_err());
+
+    let reopened = TarImage::open(path.to_str().unwrap()).unwrap();
+    let entries = lock_image(&reopened).unwrap().scan().unwrap().entries;
+    assert_eq!((entries[0].uid, entries[0].gid, entries[0].mode), (0, 0, 0o600));
+    assert_eq!(entries[1].path, "b");
+    assert_eq!(std::fs::metadata(&path).unwrap().len(), 512 * 6);
+}
+
+#[test]
+fn test_repair_checksum_in_place() {
+    let dir = temp_dir("repair_checksum");
+    let path = dir.join("a.tar");
+    let mut data = build_tar(&[Fixture::file("a", b"body"), Fixture::file("b", b"x")]);
+    // 模拟位翻转：改动第二个条目的 mtime，checksum 不再匹配
+    data[1024 + 136] ^= 0x01;
+    std::fs::write(&path, &data).unwrap();
+    let img = TarImage::open(path.to_str().unwrap()).unwrap();
+    let mut img = lock_image(&img).unwrap();
+    assert!(img.scan().is_err());
+    assert!(!img.repair_checksum(0).unwrap());
+    assert!(img.repair_checksum(1024).unwrap());
+    assert!(img.repair_checksum(2048).is_err());
+    assert!(img.repair_checksum(100).is_err());
+
+    let reopened = TarImage::open(path.to_str().unwrap()).unwrap();
+    let entries = lock_image(&reopened).unwrap().scan().unwrap().entries;
+    assert_eq!(entries.iter().map(|e| e.path.as_str()).collect::<Vec<_>>(), ["a", "b"]);
+}
+
+#[test]
+fn test_extent_map() {
+    let records = common::pax_record("mtime", b"1600000000.5");
+    let dir = temp_dir("extent_map");
+    let path = common::write_tar(&dir, "a.tar", &[
+        Fixture::file("a", &[7u8; 600]),
+        Fixture { type_flag: b'x', ..Fixture::file("PaxHeaders/b", &records) },
+        Fixture::file("b", b"x"),
+        Fixture::dir("d/"),
+    ]);
+    let img = TarImage::open(&path).unwrap();
+    let extents = lock_image(&img).unwrap().extent_map().unwrap();
+    let spans: Vec<_> = extents.iter()
+        .map(|e| (e.path.as_str(), e.header_offset, e.header_len, e.data_offset, e.data_len, e.size))
+        .collect();
+    assert_eq!(spans, [
+        ("a", 0, 512, 512, 1024, 600),
+        ("b", 1536, 1536, 3072, 512, 1),
+        ("d/", 3584, 512, 4096, 0, 0),
+    ]);
+    assert_eq!(extents[0].end_offset(), extents[1].header_offset);
+}
+
+#[test]
+fn test_copy_to_writer() {
+    use std::io::{self, Read};
+
+    let body: Vec<u8> = (0..3000u32).map(|i| i as u8).collect();
+    let dir = temp_dir("copy_to");
+    let path = dir.join("a.tar");
+    let data = build_tar(&[Fixture::file("a", &body), Fixture::file("b", b"x")]);
+    std::fs::write(&path, &data).unwrap();
+    let img = TarImage::open(path.to_str().unwrap()).unwrap();
+    let mut file = lock_image(&img).unwrap().entry_at(0).unwrap().unwrap();
+    let mut sink = [0u8; 10];
+    file.read_exact(&mut sink).unwrap();
+    let mut out = Vec::new();
+    assert_eq!(file.copy_to(&mut out).unwrap(), 3000);
+    assert_eq!(out, body);
+
+    // 数据区中途截断
+    std::fs::write(&path, &data[..512 + 1024]).unwrap();
+    let img = TarImage::open(path.to_str().unwrap()).unwrap();
+    let file = lock_image(&img).unwrap().entry_at(0).unwrap().unwrap();
+    let err = file.copy_to(&mut io::sink()).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+}
+
+#[cfg(feature = "gzip")]
+#[test]
+fn test_for_each_entry_recursive() {
+    use std::io::Write;
+
+    let innermost = build_tar(&[Fixture::file("etc/passwd", b"root:x:0:0")]);
+    let inner = build_tar(&[Fixture::file("inner.tar", &innermost), Fixture::file("readme", b"hi")]);
+    let mut gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    gz.write_all(&build_tar(&[Fixture::file("x", b"x")])).unwrap();
+    let gz = gz.finish().unwrap();
+    let dir = temp_dir("recursive");
+    let path = common::write_tar(&dir, "a.tar", &[
+        Fixture::file("outer.tar", &inner),
+        // 没有 .tar 后缀，按魔数识别
+        Fixture::file("blob", &innermost),
+        Fixture::file("layer.gz", &gz),
+        Fixture::file("plain.txt", b"not a tar"),
+    ]);
+    let img = TarImage::open(&path).unwrap();
+    let mut paths = Vec::new();
+    lock_image(&img).unwrap().for_each_entry_recursive(|path, _| {
+        paths.push(path.to_string());
+        Ok(())
+    }).unwrap();
+    assert_eq!(paths, [
+        "outer.tar",
+        "outer.tar!inner.tar",
+        "outer.tar!inner.tar!etc/passwd",
+        "outer.tar!readme",
+        "blob",
+        "blob!etc/passwd",
+        "layer.gz",
+        "layer.gz!x",
+        "plain.txt",
+    ]);
+}
@@ -0,0 +1,33 @@
+#![cfg(feature = "python")]
+
+mod common;
+
+use common::{temp_dir, write_tar, Fixture};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+#[test]
+fn test_python_iterate_and_read() {
+    let dir = temp_dir("python");
+    let path = write_tar(&dir, "a.tar", &[Fixture::dir("etc/"), Fixture::file("etc/os-release", b"ID=alpine\n")]);
+
+    Python::initialize();
+    Python::attach(|py| {
+        let module = pyo3::wrap_pymodule!(pt::python::python_module)(py);
+        let locals = PyDict::new(py);
+        locals.set_item("pt", module).unwrap();
+        locals.set_item("path", &path).unwrap();
+        py.run(c"
+img = pt.TarImage.open(path)
+names = [(e.path, e.type_flag, e.size) for e in img]
+body = img.find('etc/os-release').read()
+missing = img.find('nope')
+", None, Some(&locals)).unwrap();
+
+        let names: Vec<(String, char, u64)> = locals.get_item("names").unwrap().unwrap().extract().unwrap();
+        assert_eq!(names, vec![("etc/".to_string(), '5', 0), ("etc/os-release".to_string(), '0', 10)]);
+        let body: Vec<u8> = locals.get_item("body").unwrap().unwrap().extract().unwrap();
+        assert_eq!(body, b"ID=alpine\n");
+        assert!(locals.get_item("missing").unwrap().unwrap().is_none());
+    });
+}
@@ -0,0 +1,123 @@
+mod common;
+
+use common::{temp_dir, write_tar, Fixture};
+use pt::base::{lock_image, ImageInfo, TarImage};
+use pt::repack::{filter_copy_paths, transform_copy_paths, NameTransform};
+
+#[test]
+fn test_filter_copy_strips_debug_files() {
+    let dir = temp_dir("filter_copy");
+    let long = format!("{}/big.debug\0", "x".repeat(150));
+    let src = write_tar(&dir, "src.tar", &[
+        Fixture::file("bin/app", b"app"),
+        Fixture::file("bin/app.debug", b"symbols"),
+        Fixture { type_flag: b'L', ..Fixture::file("././@LongLink", long.as_bytes()) },
+        Fixture::file("xxxx", b"symbols"),
+        Fixture::file("lib/x.so", b"so"),
+    ]);
+    let dst = dir.join("dst.tar").to_string_lossy().into_owned();
+    let kept = filter_copy_paths(&src, &dst, |m| !m.path.ends_with(".debug")).unwrap();
+    assert_eq!(kept, 2);
+
+    let img = TarImage::open(&dst).unwrap();
+    let paths: Vec<_> = lock_image(&img).unwrap().scan().unwrap().entries.into_iter().map(|m| m.path).collect();
+    assert_eq!(paths, ["bin/app", "lib/x.so"]);
+}
+
+#[test]
+fn test_filter_copy_keeps_vendor_records() {
+    let dir = temp_dir("filter_copy_vendor");
+    let src = write_tar(&dir, "src.tar", &[
+        Fixture { type_flag: b'V', ..Fixture::file("backup volume 1", b"") },
+        Fixture { type_flag: b'Q', ..Fixture::file("vendor", b"opaque vendor data") },
+        Fixture::file("keep.txt", b"k"),
+        Fixture { type_flag: b'Q', ..Fixture::file("vendor", b"dropped with entry") },
+        Fixture::file("drop.txt", b"d"),
+    ]);
+    let dst = dir.join("dst.tar").to_string_lossy().into_owned();
+    assert_eq!(filter_copy_paths(&src, &dst, |m| m.path == "keep.txt").unwrap(), 1);
+
+    let (src_bytes, dst_bytes) = (std::fs::read(&src).unwrap(), std::fs::read(&dst).unwrap());
+    // 卷标 + 厂商记录 + keep.txt 原样保留
+    assert!(dst_bytes[..5 * 512] == src_bytes[..5 * 512]);
+    assert_eq!(dst_bytes.len(), 5 * 512 + 1024);
+
+    let img = TarImage::open(&dst).unwrap();
+    let (file, _) = lock_image(&img).unwrap().get_file_at(512).unwrap();
+    let file = pt::base::try_into_tarfile(file).unwrap();
+    assert_eq!(file.get_path(), "keep.txt");
+    assert_eq!(file.vendor_records()[0].type_flag, 'Q');
+    assert_eq!(file.vendor_records()[0].data, b"opaque vendor data");
+}
+
+#[test]
+fn test_remove_entries_drops_meta_headers() {
+    let dir = temp_dir("remove_entries");
+    let long = format!("{}/id_rsa", "s".repeat(150));
+    let src = write_tar(&dir, "src.tar", &[
+        Fixture::file("etc/hosts", b"127.0.0.1"),
+        Fixture { type_flag: b'x', ..Fixture::file("PaxHeaders/id_rsa", &common::pax_record("path", long.as_bytes())) },
+        Fixture::file("id_rsa", b"secret"),
+        Fixture::file("var/cache/x", b"cache"),
+    ]);
+    let img = TarImage::open(&src).unwrap();
+    let mut out = Vec::new();
+    let removed = lock_image(&img).unwrap()
+        .remove_entries(|m| m.path.ends_with("id_rsa") || m.path.starts_with("var/cache/"), &mut out)
+        .unwrap();
+    assert_eq!(removed, 2);
+    // 只剩 etc/hosts 与结束标记，PAX header 一并删除
+    assert_eq!(out.len(), 2 * 512 + 1024);
+    assert_eq!(&out[..9], b"etc/hosts");
+}
+
+#[test]
+fn test_transform_copy_strips_and_reroots() {
+    let dir = temp_dir("transform_copy");
+    let src = write_tar(&dir, "src.tar", &[
+        Fixture::dir("./"),
+        Fixture::file("./etc/hosts", b"127.0.0.1"),
+        Fixture { type_flag: b'1', link: "./etc/hosts", ..Fixture::file("./etc/hosts.bak", b"") },
+        Fixture::symlink("./etc/localhost", "hosts"),
+    ]);
+    let dst = dir.join("dst.tar").to_string_lossy().into_owned();
+    // 没有 '/' 可拆分的长前缀，改名后需要 PAX path / linkpath
+    let root = "r".repeat(120);
+    let transforms = [NameTransform::StripPrefix("./".into()), NameTransform::Prefix(root.clone())];
+    assert_eq!(transform_copy_paths(&src, &dst, &transforms).unwrap(), 4);
+
+    let img = TarImage::open(&dst).unwrap();
+    let mut entries = Vec::new();
+    lock_image(&img).unwrap().for_each_entry(|file| {
+        let file = pt::base::try_into_tarfile(file)?;
+        let mut body = Vec::new();
+        file.copy_to(&mut body)?;
+        entries.push((file.get_path(), file.get_link_name(), body));
+        Ok(())
+    }).unwrap();
+    assert_eq!(entries, [
+        (format!("{}/", root), String::new(), Vec::new()),
+        (format!("{}/etc/hosts", root), String::new(), b"127.0.0.1".to_vec()),
+        (format!("{}/etc/hosts.bak", root), format!("{}/etc/hosts", root), Vec::new()),
+        (format!("{}/etc/localhost", root), "hosts".to_string(), Vec::new()),
+    ]);
+
+    // 只去掉 "./" 时根目录条目的名字变为空，被丢弃
+    assert_eq!(transform_copy_paths(&src, &dst, &transforms[..1]).unwrap(), 3);
+    let img = TarImage::open(&dst).unwrap();
+    let paths: Vec<_> = lock_image(&img).unwrap().scan().unwrap().entries.into_iter().map(|m| m.path).collect();
+    assert_eq!(paths, ["etc/hosts", "etc/hosts.bak", "etc/localhost"]);
+}
+
+#[cfg(feature = "regex")]
+#[test]
+fn test_transform_sed_expression() {
+    use pt::repack::NameKind;
+    let t = NameTransform::sed(r"s,^usr/(lib|bin)/,opt/\1/,").unwrap();
+    assert_eq!(t.apply(NameKind::Path, "usr/lib/libc.so"), "opt/lib/libc.so");
+    assert_eq!(t.apply(NameKind::Symlink, "usr/bin/sh"), "opt/bin/sh");
+    let t = NameTransform::sed("s/a/[&]/g").unwrap();
+    assert_eq!(t.apply(NameKind::Path, "banana"), "b[a]n[a]n[a]");
+    assert!(NameTransform::sed("s/a/b").is_err());
+    assert!(NameTransform::sed("s/a/b/x").is_err());
+}
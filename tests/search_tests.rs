@@ -0,0 +1,62 @@
+mod common;
+
+use common::{temp_dir, write_tar, Fixture};
+use pt::base::{lock_image, ImageInfo, TarImage};
+use pt::search::{ContentPattern, NamePattern};
+use pt::{Index, IndexBudget};
+
+fn fixture_image(name: &str) -> String {
+    let dir = temp_dir(name);
+    write_tar(&dir, "a.tar", &[
+        Fixture::file("etc/hosts", b"127.0.0.1 localhost\r\n::1 localhost ip6-localhost\n"),
+        Fixture::dir("etc/"),
+        Fixture::symlink("etc/localhost", "hosts"),
+        Fixture::file("notes.txt", b"no match here\nlast line localhost"),
+    ])
+}
+
+#[test]
+fn test_search_content_literal() {
+    let img = TarImage::open(&fixture_image("search_literal")).unwrap();
+    let hits = lock_image(&img).unwrap().search_content(&ContentPattern::literal("localhost")).unwrap();
+    let got: Vec<_> = hits.iter().map(|h| (h.path.as_str(), h.offset, h.line_number, h.line.as_str())).collect();
+    assert_eq!(got, [
+        ("etc/hosts", 10, 1, "127.0.0.1 localhost"),
+        ("etc/hosts", 25, 2, "::1 localhost ip6-localhost"),
+        ("notes.txt", 24, 2, "last line localhost"),
+    ]);
+}
+
+#[cfg(feature = "regex")]
+#[test]
+fn test_search_content_regex() {
+    let img = TarImage::open(&fixture_image("search_regex")).unwrap();
+    let pattern = ContentPattern::regex(r"^::\d\s").unwrap();
+    let hits = lock_image(&img).unwrap().search_content(&pattern).unwrap();
+    assert_eq!(hits.len(), 1);
+    assert_eq!((hits[0].offset, hits[0].line_number), (21, 2));
+    assert!(ContentPattern::regex("(").is_err());
+}
+
+#[test]
+fn test_find_by_glob_with_and_without_index() {
+    let img = TarImage::open(&fixture_image("find_glob")).unwrap();
+    let mut img = lock_image(&img).unwrap();
+    let pattern = NamePattern::glob("**/host*");
+    let paths = |found: Vec<pt::EntryMeta>| found.into_iter().map(|m| m.path).collect::<Vec<_>>();
+    assert_eq!(paths(img.find(&pattern).unwrap()), ["etc/hosts"]);
+    let index = Index::build(&mut img).unwrap();
+    assert_eq!(paths(index.find(&mut img, &NamePattern::glob("etc/*")).unwrap()), ["etc/hosts", "etc/localhost"]);
+    let sidecar = Index::build_with_budget(&mut img, &IndexBudget { max_bytes: 0, sidecar_dir: None }).unwrap();
+    assert_ne!(sidecar.kind(), pt::index::IndexKind::Full);
+    assert_eq!(paths(sidecar.find(&mut img, &pattern).unwrap()), ["etc/hosts"]);
+}
+
+#[cfg(feature = "regex")]
+#[test]
+fn test_find_by_regex() {
+    let img = TarImage::open(&fixture_image("find_regex")).unwrap();
+    let found = lock_image(&img).unwrap().find(&NamePattern::regex(r"\.txt$").unwrap()).unwrap();
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].path, "notes.txt");
+}
@@ -0,0 +1,132 @@
+mod common;
+
+use std::io::{self, Read, Write};
+use common::temp_dir;
+use pt::extract::ExtractOptions;
+use pt::transform::{create_with, unpack_with, Identity, Transform, TransformWriter};
+
+/// 按密钥逐字节异或，结尾追加 4 字节长度作为“认证标签”，读取时校验
+struct Xor(u8);
+
+struct XorWriter<'a> {
+    inner: Box<dyn Write + 'a>,
+    key: u8,
+    written: u32,
+}
+
+impl Write for XorWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let data: Vec<u8> = buf.iter().map(|b| b ^ self.key).collect();
+        self.inner.write_all(&data)?;
+        self.written += buf.len() as u32;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl TransformWriter for XorWriter<'_> {
+    fn finish(mut self: Box<Self>) -> io::Result<()> {
+        let tag = self.written.to_le_bytes();
+        self.inner.write_all(&tag)?;
+        self.inner.flush()
+    }
+}
+
+impl Transform for Xor {
+    fn writer<'a>(&self, inner: Box<dyn Write + 'a>) -> io::Result<Box<dyn TransformWriter + 'a>> {
+        Ok(Box::new(XorWriter { inner, key: self.0, written: 0 }))
+    }
+
+    fn reader<'a>(&self, mut inner: Box<dyn Read + 'a>) -> io::Result<Box<dyn Read + 'a>> {
+        let mut data = Vec::new();
+        inner.read_to_end(&mut data)?;
+        let Some(split) = data.len().checked_sub(4) else {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "missing tag"));
+        };
+        let (body, tag) = data.split_at(split);
+        if u32::from_le_bytes(tag.try_into().unwrap()) as usize != body.len() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "bad tag"));
+        }
+        let plain: Vec<u8> = body.iter().map(|b| b ^ self.0).collect();
+        Ok(Box::new(io::Cursor::new(plain)))
+    }
+}
+
+#[test]
+fn test_archive_roundtrip_through_transform() {
+    let dir = temp_dir("transform_roundtrip");
+    let archive = dir.join("backup.tar.xor");
+
+    let mut builder = create_with(&archive, &Xor(0x5a)).unwrap();
+    builder.append_dir("etc/", 0o755, 0).unwrap();
+    builder.append_data("etc/secret", 0o600, 0, b"hunter2").unwrap();
+    builder.finish().unwrap().finish().unwrap();
+
+    let raw = std::fs::read(&archive).unwrap();
+    assert!(!raw.windows(7).any(|w| w == b"hunter2"));
+
+    unpack_with(&archive, &Xor(0x5a), &dir.join("out"), &ExtractOptions::default()).unwrap();
+    assert_eq!(std::fs::read(dir.join("out/etc/secret")).unwrap(), b"hunter2");
+
+    // 截断后标签校验失败
+    std::fs::write(&archive, &raw[..raw.len() - 1]).unwrap();
+    let err = unpack_with(&archive, &Xor(0x5a), &dir.join("bad"), &ExtractOptions::default()).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+    let plain = dir.join("plain.tar");
+    let mut builder = create_with(&plain, &Identity).unwrap();
+    builder.append_data("a", 0o644, 0, b"x").unwrap();
+    builder.finish().unwrap().finish().unwrap();
+    unpack_with(&plain, &Identity, &dir.join("plain"), &ExtractOptions::default()).unwrap();
+    assert_eq!(std::fs::read(dir.join("plain/a")).unwrap(), b"x");
+}
+
+#[cfg(feature = "age")]
+#[test]
+fn test_age_encrypted_archive() {
+    use age::secrecy::ExposeSecret;
+    use pt::transform::Age;
+
+    let dir = temp_dir("transform_age");
+    let key = age::x25519::Identity::generate();
+    let secret = key.to_string();
+    let public = key.to_public().to_string();
+    let write = |path: &std::path::Path, transform: &dyn Transform| {
+        let mut builder = create_with(path, transform).unwrap();
+        builder.append_data("etc/secret", 0o600, 0, b"hunter2").unwrap();
+        builder.finish().unwrap().finish().unwrap();
+        let raw = std::fs::read(path).unwrap();
+        assert!(raw.starts_with(b"age-encryption.org/v1\n"));
+        assert!(!raw.windows(7).any(|w| w == b"hunter2"));
+        raw
+    };
+
+    // 只有公钥也能写出，解密需要私钥
+    let archive = dir.join("backup.tar.age");
+    let raw = write(&archive, &Age::recipients(&[&public]).unwrap());
+    let identity = Age::identities(&[secret.expose_secret()]).unwrap();
+    unpack_with(&archive, &identity, &dir.join("out"), &ExtractOptions::default()).unwrap();
+    assert_eq!(std::fs::read(dir.join("out/etc/secret")).unwrap(), b"hunter2");
+
+    let other = Age::identities(&[age::x25519::Identity::generate().to_string().expose_secret()]).unwrap();
+    let err = unpack_with(&archive, &other, &dir.join("wrong"), &ExtractOptions::default()).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+    // 篡改密文后认证失败
+    let mut tampered = raw.clone();
+    let last = tampered.len() - 20;
+    tampered[last] ^= 1;
+    std::fs::write(&archive, &tampered).unwrap();
+    let err = unpack_with(&archive, &identity, &dir.join("tampered"), &ExtractOptions::default()).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+    let archive = dir.join("passphrase.tar.age");
+    write(&archive, &Age::passphrase("correct horse"));
+    unpack_with(&archive, &Age::passphrase("correct horse"), &dir.join("pass"), &ExtractOptions::default()).unwrap();
+    assert_eq!(std::fs::read(dir.join("pass/etc/secret")).unwrap(), b"hunter2");
+    assert!(unpack_with(&archive, &Age::passphrase("wrong"), &dir.join("bad"), &ExtractOptions::default()).is_err());
+    assert!(Age::recipients(&["not-a-key"]).is_err());
+}
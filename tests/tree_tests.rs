@@ -0,0 +1,98 @@
+mod common;
+
+use common::{temp_dir, write_tar, Fixture};
+use pt::base::{lock_image, ImageInfo, TarImage};
+
+#[test]
+fn test_tree_lookup_and_readdir() {
+    let dir = temp_dir("tree");
+    let path = write_tar(&dir, "a.tar", &[
+        Fixture::dir("usr/"),
+        Fixture::file("usr/bin/ls", b"ls"),
+        Fixture::file("usr/bin/cat", b"cat"),
+        Fixture::file("./etc/hosts", b"h"),
+        Fixture::file("usr/bin/ls", b"newer"),
+    ]);
+    let img = TarImage::open(&path).unwrap();
+    let tree = lock_image(&img).unwrap().tree().unwrap();
+    assert_eq!(tree.len(), 6);
+
+    let ls = tree.lookup("/usr/bin/ls").unwrap();
+    assert_eq!(tree.node(ls).meta().unwrap().size, 5);
+    assert_eq!(tree.path_of(ls), "usr/bin/ls");
+    assert!(!tree.node(ls).is_dir());
+
+    let bin = tree.node(ls).parent().unwrap();
+    assert!(tree.node(bin).meta().is_none());
+    assert!(tree.node(bin).is_dir());
+    assert_eq!(tree.lookup("usr/bin/../bin"), Some(bin));
+
+    let names: Vec<_> = tree.readdir("/usr/bin").unwrap().into_iter().map(|(n, _)| n).collect();
+    assert_eq!(names, ["cat", "ls"]);
+    let names: Vec<_> = tree.readdir("/").unwrap().into_iter().map(|(n, _)| n).collect();
+    assert_eq!(names, ["etc", "usr"]);
+    assert!(tree.readdir("/missing").is_none());
+}
+
+#[test]
+fn test_list_dir_returns_direct_children() {
+    let dir = temp_dir("tree_list_dir");
+    let path = write_tar(&dir, "a.tar", &[
+        Fixture::dir("usr/"),
+        Fixture::file("usr/bin/ls", b"ls"),
+        Fixture::file("usr/bin/cat", b"cat"),
+        Fixture::file("usr/lib/libc.so", b"elf"),
+        Fixture::file("usr/README", b"readme"),
+    ]);
+    let img = TarImage::open(&path).unwrap();
+    let mut img = lock_image(&img).unwrap();
+
+    let usr = img.list_dir("usr").unwrap();
+    let summary: Vec<_> = usr.iter().map(|e| (e.name.as_str(), e.is_dir, e.children)).collect();
+    assert_eq!(summary, [("README", false, 0), ("bin", true, 2), ("lib", true, 1)]);
+    assert_eq!(usr[0].path, "usr/README");
+    assert_eq!(usr[0].meta.as_ref().unwrap().size, 6);
+    assert!(usr[1].meta.is_none());
+
+    let bin = img.list_dir("/usr/bin/").unwrap();
+    assert_eq!(bin.iter().map(|e| e.path.as_str()).collect::<Vec<_>>(), ["usr/bin/cat", "usr/bin/ls"]);
+    assert_eq!(img.list_dir("").unwrap().len(), 1);
+    assert_eq!(img.list_dir("usr/README").unwrap_err().kind(), std::io::ErrorKind::NotADirectory);
+    assert_eq!(img.list_dir("opt").unwrap_err().kind(), std::io::ErrorKind::NotFound);
+}
+
+#[test]
+fn test_implicit_directories_are_synthesized() {
+    let dir = temp_dir("tree_implicit");
+    let mut file = Fixture::file("a/b/c.txt", b"c");
+    file.uid = 1000;
+    file.mtime = 1_234_567;
+    let path = write_tar(&dir, "a.tar", &[file, Fixture::dir("a/"), Fixture::file("a/d", b"d")]);
+    let img = TarImage::open(&path).unwrap();
+    let mut img = lock_image(&img).unwrap();
+
+    let mut tree = img.tree().unwrap();
+    let b = tree.lookup("a/b").unwrap();
+    assert!(tree.node(b).meta().is_none());
+    tree.fill_implicit_dirs();
+    let meta = tree.node(b).meta().unwrap();
+    assert!(tree.node(b).is_implicit());
+    assert_eq!((meta.path.as_str(), meta.type_flag, meta.mode, meta.uid, meta.mtime), ("a/b/", '5', 0o755, 1000, 1_234_567));
+    // 显式记录的目录保持原样
+    let a = tree.lookup("a").unwrap();
+    assert!(!tree.node(a).is_implicit());
+    assert_eq!(img.tree().unwrap().list_dir("a").unwrap()[0].meta, None);
+
+    let listing = img.listing_with(true).unwrap();
+    let rows: Vec<_> = listing.as_array().unwrap().iter()
+        .map(|v| (v["path"].as_str().unwrap().to_string(), v.get("implicit").is_some()))
+        .collect();
+    assert_eq!(rows, [
+        ("a/".to_string(), true),
+        ("a/b/".to_string(), true),
+        ("a/b/c.txt".to_string(), false),
+        ("a/".to_string(), false),
+        ("a/d".to_string(), false),
+    ]);
+    assert_eq!(img.listing().unwrap().as_array().unwrap().len(), 3);
+}
@@ -0,0 +1,33 @@
+mod common;
+
+use common::{temp_dir, write_tar, Fixture};
+use pt::base::{lock_image, ImageInfo, TarImage};
+use pt::update::update_archive;
+
+#[test]
+fn test_update_appends_newer_files() {
+    let dir = temp_dir("update");
+    let src = dir.join("data");
+    std::fs::create_dir(&src).unwrap();
+    for (name, body) in [("a", "new a"), ("b", "disk b"), ("c", "new c")] {
+        std::fs::write(src.join(name), body).unwrap();
+    }
+    let future = 4_000_000_000;
+    let archive = write_tar(&dir, "a.tar", &[
+        Fixture { mtime: future, ..Fixture::dir("data/") },
+        Fixture { mtime: 0, ..Fixture::file("data/a", b"old a") },
+        Fixture { mtime: future, ..Fixture::file("data/b", b"kept b") },
+    ]);
+
+    assert_eq!(update_archive(&archive, &[("data", &src)]).unwrap(), 2);
+    let img = TarImage::open(&archive).unwrap();
+    let mut img = lock_image(&img).unwrap();
+    let paths: Vec<String> = img.scan().unwrap().entries.into_iter().map(|m| m.path).collect();
+    assert_eq!(paths, ["data/", "data/a", "data/b", "data/a", "data/c"]);
+    assert_eq!(img.find_entry("data/a").unwrap().unwrap().read_to_string().unwrap(), "new a");
+    assert_eq!(img.find_entry("data/b").unwrap().unwrap().read_to_string().unwrap(), "kept b");
+
+    // 再次更新时没有需要追加的文件
+    assert_eq!(update_archive(&archive, &[("data", &src)]).unwrap(), 0);
+    assert_eq!(std::fs::metadata(&archive).unwrap().len(), 512 * 11);
+}
@@ -0,0 +1,41 @@
+mod common;
+
+use common::{temp_dir, write_tar, Fixture};
+use pt::base::{lock_image, ImageInfo, TarImage};
+use pt::volume::{write_volumes, Volumes};
+
+#[test]
+fn test_multi_volume_roundtrip() {
+    let dir = temp_dir("volumes");
+    let long = format!("{}/big.bin", "d".repeat(120));
+    let big: Vec<u8> = (0..5000u32).map(|i| (i % 251) as u8).collect();
+    let src = write_tar(&dir, "src.tar", &[
+        Fixture::file("a.txt", b"hello"),
+        Fixture::file(&long, &big),
+        Fixture::file("z.txt", b"tail"),
+    ]);
+
+    let img = TarImage::open(&src).unwrap();
+    let paths: Vec<String> = (0..10).map(|i| dir.join(format!("vol{}.tar", i)).to_string_lossy().into_owned()).collect();
+    let count = write_volumes(&mut lock_image(&img).unwrap(), 4096, |i| std::fs::File::create(&paths[i])).unwrap();
+    assert_eq!(count, 3);
+    for path in &paths[..count] {
+        assert!(std::fs::metadata(path).unwrap().len() <= 4096);
+    }
+    // 第二卷以长名称 header 和 'M' 续接 header 开头
+    let vol1 = std::fs::read(&paths[1]).unwrap();
+    assert_eq!((vol1[156], vol1[512 + 512 + 156]), (b'L', b'M'));
+
+    let volumes = Volumes::open(&paths[..count]).unwrap();
+    let entries = volumes.entries().unwrap();
+    let names: Vec<String> = entries.iter().map(|e| e.get_path()).collect();
+    assert_eq!(names, ["a.txt", long.as_str(), "z.txt"]);
+    assert_eq!(entries[1].volume_count(), 2);
+    assert_eq!(entries[1].read_to_vec().unwrap(), big);
+    assert_eq!(entries[2].read_to_vec().unwrap(), b"tail");
+
+    // 缺少后续卷时报错
+    let err = Volumes::open(&paths[..1]).unwrap().entries().err().unwrap();
+    assert!(err.to_string().contains("continues past the last volume"), "{}", err);
+    assert!(write_volumes(&mut lock_image(&img).unwrap(), 1000, |_| Ok(std::io::sink())).is_err());
+}
@@ -0,0 +1,70 @@
+#![cfg(feature = "zstd")]
+mod common;
+
+use std::io::{self, Read, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use common::{build_tar, temp_dir, Fixture};
+use pt::backend::{Backend, MemoryBackend};
+use pt::base::{lock_image, TarImage};
+use pt::index::Index;
+use pt::zstd_seek::{ZstdSeekableBackend, ZstdSeekableWriter};
+
+/// 记录读取字节数的 backend
+struct Counting(MemoryBackend, Arc<AtomicU64>);
+
+impl Backend for Counting {
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        let n = self.0.read_at(buf, offset)?;
+        self.1.fetch_add(n as u64, Ordering::SeqCst);
+        Ok(n)
+    }
+
+    fn len(&self) -> io::Result<u64> {
+        self.0.len()
+    }
+}
+
+#[test]
+fn test_seekable_zstd_random_access() {
+    let names: Vec<String> = (0..40).map(|i| format!("data/{:02}.bin", i)).collect();
+    let bodies: Vec<Vec<u8>> = (0..40u32).map(|i| (0..3000u32).map(|j| (i.wrapping_mul(2654435761) ^ j.wrapping_mul(40503)) as u8).collect()).collect();
+    let fixtures: Vec<Fixture> = names.iter().zip(&bodies).map(|(name, body)| Fixture::file(name, body)).collect();
+    let tar = build_tar(&fixtures);
+
+    let mut writer = ZstdSeekableWriter::new(Vec::new(), 8 * 1024, 3);
+    writer.write_all(&tar).unwrap();
+    let zst = writer.finish().unwrap();
+    // 普通的 zstd 解码器跳过帧表，得到原始内容
+    let mut plain = Vec::new();
+    zstd::stream::read::Decoder::new(&zst[..]).unwrap().read_to_end(&mut plain).unwrap();
+    assert_eq!(plain, tar);
+
+    let read = Arc::new(AtomicU64::new(0));
+    let backend = ZstdSeekableBackend::new(Counting(MemoryBackend::new(zst.clone()), read.clone())).unwrap();
+    assert_eq!(backend.table().frames().len(), tar.len().div_ceil(8 * 1024));
+    assert_eq!(backend.len().unwrap(), tar.len() as u64);
+
+    let img = TarImage::from_backend(backend, "seekable").unwrap();
+    let mut img = lock_image(&img).unwrap();
+    img.set_readahead(0);
+    let index = Index::build(&mut img).unwrap();
+    // 按索引打开条目只解压它所在的帧
+    let before = read.load(Ordering::SeqCst);
+    let file = index.open_entry(&mut img, "data/31.bin").unwrap().unwrap();
+    assert_eq!(file.read_to_vec().unwrap(), bodies[31]);
+    let used = read.load(Ordering::SeqCst) - before;
+    assert!(used < zst.len() as u64 / 4, "read {} of {} compressed bytes", used, zst.len());
+    drop(img);
+
+    let dir = temp_dir("zstd_seek");
+    let path = dir.join("a.tar.zst");
+    std::fs::write(&path, &zst).unwrap();
+    let img = TarImage::open_zstd(&path).unwrap();
+    assert_eq!(lock_image(&img).unwrap().find_entry("data/07.bin").unwrap().unwrap().read_to_vec().unwrap(), bodies[7]);
+
+    // 没有帧表的普通 zstd 不能随机读取
+    std::fs::write(&path, zstd::bulk::compress(&tar, 3).unwrap()).unwrap();
+    let err = TarImage::open_zstd(&path).err().unwrap();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+}